@@ -0,0 +1,16 @@
+//! Feeds arbitrary diffs and files to the patch engine, which must report
+//! malformed input as errors instead of panicking.
+//!
+//! The input is the original file and the diff separated by a NUL byte.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(data) = std::str::from_utf8(data) else {
+        return;
+    };
+    let (original, diff) = data.split_once('\0').unwrap_or(("", data));
+    let _ = cargo_patch::apply_diff(diff, original);
+});
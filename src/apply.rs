@@ -0,0 +1,81 @@
+//! Ad-hoc application of diffs without patch metadata.
+
+use crate::{
+    interrupt,
+    long_path::long_path,
+    orchestrator::{
+        apply_patch_data, copy_package, copy_root, get_config, get_id,
+        load_workspace, lock_package_cache, resolve_ws, setup_gctx, Options,
+        PatchTarget, VersionFilter,
+    },
+    overlap::History,
+    status, Error, FinalNewline, PatchSource,
+};
+use anyhow::Result;
+use semver::VersionReq;
+use std::{fs, path::Path};
+
+/// Applies `diff` to the patched copy of the dependency `name`.
+///
+/// No patch metadata is required. The copy is created again from the
+/// pristine sources, replacing an existing copy, so the result doesn't
+/// depend on earlier runs. An interrupted run removes the incomplete copy.
+pub fn apply(
+    name: &str,
+    version: Option<&str>,
+    source: &PatchSource,
+    diff: &str,
+    options: &Options,
+) -> Result<(), Error> {
+    interrupt::check()?;
+    let version = version
+        .map(VersionReq::parse)
+        .transpose()
+        .map_err(anyhow::Error::from)?
+        .map(VersionFilter::Req);
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+    interrupt::check()?;
+
+    let id = get_id(name, &version, None, &resolve).ok_or_else(|| {
+        Error::PackageNotFound {
+            name: name.to_owned(),
+        }
+    })?;
+    let package = pkg_set.get_one(id)?;
+    let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+    interrupt::check()?;
+    let root = long_path(&copy_root(package, &config))?;
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+    let result = copy_package(package, &config, false).and_then(|path| {
+        interrupt::check()?;
+        let target = PatchTarget {
+            name,
+            path: &path,
+            use_orig_manifest: false,
+            final_newline: FinalNewline::default(),
+        };
+        let mut history = History::default();
+        apply_patch_data(
+            &target,
+            Path::new("-"),
+            diff,
+            source,
+            &mut history,
+            options,
+        )
+    });
+    if result.is_err() && interrupt::interrupted() {
+        if root.exists() {
+            fs::remove_dir_all(&root)?;
+            status::status("Removed", format!("incomplete copy of {name}"));
+        }
+        return Err(Error::Interrupted);
+    }
+    result?;
+    Ok(())
+}
@@ -0,0 +1,271 @@
+//! External tools which can apply patches instead of the internal engine.
+
+use crate::{
+    event::{self, Event},
+    porcelain, status, PatchSource,
+};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::{
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Engine applying the patches of an entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// The pure Rust engine of cargo-patch.
+    #[default]
+    Internal,
+    /// `git apply`, which understands all git diff extensions.
+    GitApply,
+    /// GNU `patch`, which tolerates offsets and fuzz.
+    GnuPatch,
+}
+
+impl Backend {
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "internal" => Self::Internal,
+            "git-apply" => Self::GitApply,
+            "gnu-patch" => Self::GnuPatch,
+            &_ => {
+                eprintln!("Unknown patch backend: {s}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Returns the external tool of the backend, `None` for the internal
+    /// engine.
+    pub const fn external(self) -> Option<Tool> {
+        match self {
+            Self::Internal => None,
+            Self::GitApply => Some(Tool::GitApply),
+            Self::GnuPatch => Some(Tool::GnuPatch),
+        }
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.external() {
+            Some(tool) => tool.fmt(f),
+            None => f.write_str("internal"),
+        }
+    }
+}
+
+/// External tool applying patches, see [`Backend::external`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tool {
+    GitApply,
+    GnuPatch,
+}
+
+impl Tool {
+    const fn program(self) -> &'static str {
+        match self {
+            Self::GitApply => "git",
+            Self::GnuPatch => "patch",
+        }
+    }
+
+    fn command(self, path: &Path, source: &PatchSource, strict: bool) -> Command {
+        let strip = match source {
+            PatchSource::Default => "-p0",
+            PatchSource::GithubPrDiff | PatchSource::GitDiff => "-p1",
+        };
+        let mut command = Command::new(self.program());
+        let _ = match self {
+            Self::GitApply => command
+                .args(["apply", strip, "--verbose"])
+                // keep git from treating a surrounding repository as the root
+                .env(
+                    "GIT_CEILING_DIRECTORIES",
+                    path.parent().unwrap_or(path),
+                ),
+            Self::GnuPatch => {
+                let _ = command.args([strip, "--batch", "--forward"]);
+                if strict {
+                    let _ = command.arg("--fuzz=0");
+                }
+                &mut command
+            }
+        };
+        let _ = command.current_dir(path);
+        command
+    }
+}
+
+impl std::fmt::Display for Tool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::GitApply => "git apply",
+            Self::GnuPatch => "patch",
+        })
+    }
+}
+
+/// Returns the lines of the tool output reporting hunks which were applied
+/// with an offset or fuzz.
+fn inexact_hunks(output: &str) -> Vec<&str> {
+    output
+        .lines()
+        .filter(|line| line.contains("(offset ") || line.contains("with fuzz "))
+        .map(str::trim)
+        .collect()
+}
+
+/// Returns events for the hunks of the tool output applied with an offset or
+/// fuzz. The file is taken from the preceding `patching file` (GNU patch) or
+/// `Checking patch` (git apply) line.
+fn inexact_hunk_events(name: &str, output: &str) -> Vec<Event> {
+    let hunk = Regex::new(
+        r"Hunk #\d+ succeeded at (\d+)(?: with fuzz (\d+))?(?: \(offset (-?\d+) lines?\))?",
+    )
+    .expect("valid hunk regex");
+    let mut file = "";
+    let mut events = vec![];
+    for line in output.lines().map(str::trim) {
+        if let Some(path) = line
+            .strip_prefix("patching file ")
+            .or_else(|| line.strip_prefix("Checking patch "))
+        {
+            file = path.trim_end_matches("...").trim_matches('\'');
+            continue;
+        }
+        let Some(captures) = hunk.captures(line) else {
+            continue;
+        };
+        let number = |index| {
+            captures
+                .get(index)
+                .and_then(|value| value.as_str().parse::<i64>().ok())
+                .unwrap_or_default()
+        };
+        let offset = number(3);
+        let fuzz = u64::try_from(number(2)).unwrap_or_default();
+        if offset == 0 && fuzz == 0 {
+            continue;
+        }
+        events.push(Event::HunkApplied {
+            name: name.to_owned(),
+            file: PathBuf::from(file),
+            line: u64::try_from(number(1)).unwrap_or_default(),
+            offset,
+            fuzz,
+        });
+    }
+    events
+}
+
+/// Applies `data` to the dependency at `path` using an external tool. With
+/// `strict` set, hunks applied with an offset or fuzz are an error.
+///
+/// The tool writes the files itself, so the `final-newline` policy and
+/// `--show-diff` of the internal engine can't be honoured and are rejected
+/// by the caller. An interrupt takes effect once the tool has finished.
+#[allow(clippy::wildcard_enum_match_arm)]
+pub fn apply_external(
+    tool: Tool,
+    name: &str,
+    file: &Path,
+    data: &str,
+    source: &PatchSource,
+    path: &Path,
+    strict: bool,
+) -> Result<()> {
+    let mut child = tool
+        .command(path, source, strict)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => anyhow!(
+                "The {tool} backend requires `{}` which was not found",
+                tool.program()
+            ),
+            _ => err.into(),
+        })?;
+    // the tool may print more than a pipe buffer while it still reads the
+    // patch, so stdin is written while its output is collected
+    let stdin = child.stdin.take();
+    let (written, output) = thread::scope(|scope| {
+        let writer = scope.spawn(move || {
+            stdin.map_or(Ok(()), |mut stdin| stdin.write_all(data.as_bytes()))
+        });
+        let output = child.wait_with_output();
+        (writer.join(), output)
+    });
+    let output = output?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let combined = format!("{stdout}{stderr}");
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{tool} failed to apply {}: {}",
+            file.display(),
+            combined.trim()
+        ));
+    }
+    match written {
+        Ok(Ok(())) => {}
+        // the tool exited successfully without reading everything
+        Ok(Err(err)) if err.kind() == ErrorKind::BrokenPipe => {}
+        Ok(Err(err)) => return Err(err.into()),
+        Err(_) => {
+            return Err(anyhow!("Unable to write {} to {tool}", file.display()))
+        }
+    }
+    let inexact = inexact_hunks(&combined);
+    if strict && !inexact.is_empty() {
+        return Err(anyhow!(
+            "{tool} applied {} inexactly, regenerate it against the current sources:\n    {}",
+            file.display(),
+            inexact.join("\n    ")
+        ));
+    }
+    for hunk in inexact_hunk_events(name, &combined) {
+        event::emit(|| hunk);
+    }
+    status::status(
+        "Patched",
+        format!("{name}: {} using {tool}", file.display()),
+    );
+    let strip = !matches!(source, PatchSource::Default);
+    for (file, hunks) in porcelain::diff_files(data, strip) {
+        porcelain::patched(name, &file, hunks);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::inexact_hunk_events;
+    use crate::Event;
+    use std::path::PathBuf;
+
+    #[test]
+    fn inexact_hunks_of_gnu_patch() {
+        let output = "patching file src/lib.rs\n\
+                      Hunk #1 succeeded at 3 (offset 2 lines).\n\
+                      Hunk #2 succeeded at 10.\n\
+                      patching file src/main.rs\n\
+                      Hunk #1 succeeded at 7 with fuzz 1 (offset -1 line).\n";
+        let hunk = |file: &str, line, offset, fuzz| Event::HunkApplied {
+            name: "dep".to_owned(),
+            file: PathBuf::from(file),
+            line,
+            offset,
+            fuzz,
+        };
+        assert_eq!(
+            inexact_hunk_events("dep", output),
+            [hunk("src/lib.rs", 3, 2, 0), hunk("src/main.rs", 7, -1, 1)]
+        );
+    }
+}
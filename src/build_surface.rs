@@ -0,0 +1,266 @@
+//! Detection of patches that change what a dependency can do at build time.
+
+use anyhow::{anyhow, Result};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use syn::{
+    visit::{self, Visit},
+    Expr, ExprLit, ItemMod, Lit, LitStr, Macro, Meta,
+};
+use toml::{Table, Value};
+
+/// Directory of helper files of build scripts, by convention.
+const BUILD_DIR: &str = "build";
+
+/// The parts of a package which run code or link libraries at build time.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BuildSurface {
+    /// The build script, the files it reaches with `mod` and the `include!`
+    /// macros, and the files in [`BUILD_DIR`], relative to the package.
+    build_files: BTreeMap<PathBuf, Vec<u8>>,
+    links: Option<String>,
+    proc_macro: bool,
+}
+
+/// Collects the files a Rust source file pulls in.
+struct References {
+    /// Directory the file's `mod` declarations are resolved in.
+    module_dir: PathBuf,
+    /// Directory containing the file, which `include!` paths are relative
+    /// to.
+    file_dir: PathBuf,
+    /// Referenced files, and whether they own the directory they are in
+    /// like a `mod.rs`.
+    files: Vec<(PathBuf, bool)>,
+}
+
+impl<'ast> Visit<'ast> for References {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        let name = item.ident.to_string();
+        if item.content.is_some() {
+            self.module_dir.push(&name);
+            visit::visit_item_mod(self, item);
+            let _ = self.module_dir.pop();
+            return;
+        }
+        let path = item.attrs.iter().find_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("path") => match &meta.value
+            {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(path),
+                    ..
+                }) => Some(path.value()),
+                _ => None,
+            },
+            _ => None,
+        });
+        if let Some(path) = path {
+            self.files.push((self.file_dir.join(path), true));
+            return;
+        }
+        let file = self.module_dir.join(format!("{name}.rs"));
+        if file.exists() {
+            self.files.push((file, false));
+        } else {
+            self.files
+                .push((self.module_dir.join(name).join("mod.rs"), true));
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        let is_include = mac.path.segments.last().is_some_and(|segment| {
+            ["include", "include_str", "include_bytes"]
+                .iter()
+                .any(|name| segment.ident == name)
+        });
+        if let Some(path) = is_include
+            .then(|| mac.parse_body::<LitStr>().ok())
+            .flatten()
+        {
+            self.files.push((self.file_dir.join(path.value()), true));
+        }
+        visit::visit_macro(self, mac);
+    }
+}
+
+/// Adds every file below `dir` to `files`.
+fn add_dir(dir: &Path, files: &mut BTreeMap<PathBuf, Vec<u8>>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            add_dir(&path, files)?;
+        } else {
+            let data = fs::read(&path)?;
+            let _ = files.insert(path, data);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the build script `script` of the package at `path` and every file
+/// it reaches, plus the files in [`BUILD_DIR`].
+fn build_files(
+    path: &Path,
+    script: Option<&str>,
+) -> Result<BTreeMap<PathBuf, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut pending = script
+        .map(|script| (path.join(script), true))
+        .into_iter()
+        .collect::<Vec<_>>();
+    while let Some((file, owns_dir)) = pending.pop() {
+        if files.contains_key(&file) {
+            continue;
+        }
+        // missing files break the build, which is reported by cargo
+        let Ok(data) = fs::read(&file) else {
+            continue;
+        };
+        let source = syn::parse_file(&String::from_utf8_lossy(&data));
+        if let (Ok(source), Some(file_dir)) = (source, file.parent()) {
+            let module_dir = match file.file_stem() {
+                Some(stem) if !owns_dir => file_dir.join(stem),
+                _ => file_dir.to_path_buf(),
+            };
+            let mut references = References {
+                module_dir,
+                file_dir: file_dir.to_path_buf(),
+                files: vec![],
+            };
+            references.visit_file(&source);
+            pending.extend(references.files);
+        }
+        let _ = files.insert(file, data);
+    }
+    if path.join(BUILD_DIR).is_dir() {
+        add_dir(&path.join(BUILD_DIR), &mut files)?;
+    }
+    Ok(files
+        .into_iter()
+        .map(|(file, data)| match file.strip_prefix(path) {
+            Ok(relative) => (relative.to_path_buf(), data),
+            Err(_) => (file, data),
+        })
+        .collect())
+}
+
+impl BuildSurface {
+    /// Reads the build surface of the package at `path`.
+    pub fn read(path: &Path) -> Result<Self> {
+        let manifest = fs::read_to_string(path.join("Cargo.toml"))?
+            .parse::<Table>()
+            .map_err(|err| anyhow!("Unable to parse dependency manifest: {err}"))?;
+        let package = manifest.get("package").and_then(Value::as_table);
+        let lib = manifest.get("lib").and_then(Value::as_table);
+
+        let build_script = match package.and_then(|package| package.get("build")) {
+            Some(Value::Boolean(false)) => None,
+            Some(Value::String(script)) => Some(script.as_str()),
+            _ => path.join("build.rs").exists().then_some("build.rs"),
+        };
+        let links = package
+            .and_then(|package| package.get("links"))
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+        let proc_macro = lib.is_some_and(|lib| {
+            lib.get("proc-macro").and_then(Value::as_bool) == Some(true)
+                || lib.get("crate-type").and_then(Value::as_array).is_some_and(
+                    |types| types.iter().any(|ty| ty.as_str() == Some("proc-macro")),
+                )
+        });
+
+        Ok(Self {
+            build_files: build_files(path, build_script)?,
+            links,
+            proc_macro,
+        })
+    }
+
+    /// The native library the package links to.
+    pub fn links(&self) -> Option<&str> {
+        self.links.as_deref()
+    }
+
+    /// Describes how `self` differs from the `patched` build surface.
+    pub fn changes(&self, patched: &Self) -> Vec<&'static str> {
+        let mut changes = vec![];
+        if self.build_files != patched.build_files {
+            changes.push("build script");
+        }
+        if self.links != patched.links {
+            changes.push("`links` key");
+        }
+        if self.proc_macro != patched.proc_macro {
+            changes.push("proc-macro crate type");
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BuildSurface;
+    use std::fs;
+
+    #[test]
+    fn build_surface_changes() {
+        let dir = tempfile::tempdir().expect("Unable to create package");
+        let dir = dir.path();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+        )
+        .expect("Unable to write manifest");
+        let pristine = BuildSurface::read(dir).expect("Unable to read package");
+
+        fs::write(dir.join("build.rs"), "fn main() {}").expect("Unable to write");
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\nlinks = \"foo\"\n",
+        )
+        .expect("Unable to write manifest");
+        let patched = BuildSurface::read(dir).expect("Unable to read package");
+
+        assert_eq!(
+            pristine.changes(&patched),
+            vec!["build script", "`links` key"]
+        );
+        assert!(patched.changes(&patched).is_empty());
+    }
+
+    #[test]
+    fn build_surface_follows_modules_and_includes() {
+        let dir = tempfile::tempdir().expect("Unable to create package");
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("gen")).expect("Unable to create module");
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+        )
+        .expect("Unable to write manifest");
+        fs::write(
+            dir.join("build.rs"),
+            "mod gen;\nfn main() { gen::run(); }\n",
+        )
+        .expect("Unable to write");
+        fs::write(
+            dir.join("gen/mod.rs"),
+            "pub fn run() { include!(\"../rules.in\"); }\n",
+        )
+        .expect("Unable to write");
+        fs::write(dir.join("rules.in"), "let _ = 1;").expect("Unable to write");
+        let pristine = BuildSurface::read(dir).expect("Unable to read package");
+
+        fs::write(dir.join("rules.in"), "let _ = 2;").expect("Unable to write");
+        let included = BuildSurface::read(dir).expect("Unable to read package");
+        assert_eq!(pristine.changes(&included), vec!["build script"]);
+
+        fs::create_dir_all(dir.join("build")).expect("Unable to create dir");
+        fs::write(dir.join("build/helper.rs"), "").expect("Unable to write");
+        let helper = BuildSurface::read(dir).expect("Unable to read package");
+        assert_eq!(included.changes(&helper), vec!["build script"]);
+    }
+}
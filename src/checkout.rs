@@ -0,0 +1,69 @@
+//! Packages checked out from a git repository containing several packages.
+
+use anyhow::Result;
+use cargo::core::package::Package;
+use fs_extra::dir::{copy, CopyOptions};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A package living in a subdirectory of its git repository.
+#[derive(Debug, Clone)]
+pub struct Member {
+    /// Root of the checked out repository.
+    pub root: PathBuf,
+    /// Path of the package relative to [`Member::root`].
+    pub path: PathBuf,
+}
+
+impl Member {
+    /// Returns the repository layout of a git dependency which isn't located
+    /// at the root of its repository.
+    pub fn of(pkg: &Package) -> Option<Self> {
+        if !pkg.package_id().source_id().is_git() {
+            return None;
+        }
+        let root = pkg
+            .root()
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())?;
+        let path = pkg.root().strip_prefix(root).ok()?;
+        if path.as_os_str().is_empty() {
+            return None;
+        }
+        Some(Self {
+            root: root.to_owned(),
+            path: path.to_owned(),
+        })
+    }
+
+    /// Copies the member to `dest`, keeping its path inside the repository.
+    /// Files at the repository root, like the workspace manifest or shared
+    /// licenses, are copied as well, so workspace inheritance keeps working.
+    /// With `whole_root` every other directory is copied, too.
+    pub fn copy(&self, dest: &Path, whole_root: bool) -> Result<()> {
+        fs::create_dir_all(dest)?;
+        let options = CopyOptions::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_file() {
+                let _ = fs::copy(entry.path(), dest.join(entry.file_name()))?;
+            } else if file_type.is_dir() && whole_root {
+                let _ = copy(entry.path(), dest, &options)?;
+            }
+        }
+        if !whole_root {
+            let member = dest.join(&self.path);
+            if let Some(parent) = member.parent() {
+                fs::create_dir_all(parent)?;
+                let _ = copy(self.root.join(&self.path), parent, &options)?;
+            }
+        }
+        Ok(())
+    }
+}
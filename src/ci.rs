@@ -0,0 +1,161 @@
+//! Summary and problem matcher output of `--ci` runs.
+
+use crate::{permissions, status, Error};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// File in the target directory the summary of a run is written to.
+const SUMMARY_FILE: &str = "cargo-patch-summary.json";
+
+#[derive(Debug, Default)]
+struct Summary {
+    patched: Vec<Value>,
+    failed: Vec<Value>,
+    warnings: Vec<String>,
+    /// Target directory of the workspace, once it is loaded.
+    target_dir: Option<PathBuf>,
+}
+
+thread_local! {
+    /// Summary of the current run, if running in CI mode.
+    static SUMMARY: RefCell<Option<Summary>> = const { RefCell::new(None) };
+}
+
+/// Starts collecting the summary of the run.
+pub fn start() {
+    SUMMARY.set(Some(Summary::default()));
+}
+
+/// Sets the target directory of the workspace the summary is written to.
+/// Without it, e.g. if the workspace failed to load, `target` in the
+/// current directory is used.
+pub fn set_target_dir(dir: &Path) {
+    SUMMARY.with_borrow_mut(|summary| {
+        if let Some(summary) = summary {
+            summary.target_dir = Some(dir.to_path_buf());
+        }
+    });
+}
+
+/// Records a warning printed during the run.
+pub fn warning(message: &str) {
+    SUMMARY.with_borrow_mut(|summary| {
+        if let Some(summary) = summary {
+            summary.warnings.push(message.to_owned());
+        }
+    });
+}
+
+/// Records a package which was patched to `path`.
+pub fn patched(name: &str, version: impl ToString, path: &Path) {
+    SUMMARY.with_borrow_mut(|summary| {
+        if let Some(summary) = summary {
+            summary.patched.push(json!({
+                "name": name,
+                "version": version.to_string(),
+                "path": path,
+            }));
+        }
+    });
+}
+
+/// Returns the ranges of a hunk header like `@@ -8,9 +8,7 @@ fn serialize`
+/// with omitted line counts filled in, so `@@ -1 +1 @@` equals
+/// `@@ -1,1 +1,1 @@`.
+fn hunk_ranges(header: &str) -> Option<Vec<String>> {
+    let (ranges, _) = header.strip_prefix("@@ ")?.split_once(" @@")?;
+    Some(
+        ranges
+            .split_whitespace()
+            .map(|range| {
+                if range.contains(',') {
+                    range.to_owned()
+                } else {
+                    format!("{range},1")
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Returns the line of the hunk with `header` in the patch file, starting
+/// at 1.
+fn hunk_line(patch: &Path, header: &str) -> Option<usize> {
+    let ranges = hunk_ranges(header)?;
+    let data = fs::read_to_string(patch).ok()?;
+    data.lines()
+        .position(|line| hunk_ranges(line).as_ref() == Some(&ranges))
+        .map(|index| index + 1)
+}
+
+/// Records a package which failed to patch. Failures pointing to a patch
+/// file are printed as `file:line: error: message` for problem matchers.
+pub fn failed(name: &str, version: impl ToString, err: &anyhow::Error) {
+    let location = err.chain().find_map(|cause| match cause.downcast_ref()? {
+        Error::HunkFailed { patch, header, .. } => {
+            Some((patch.clone(), hunk_line(patch, header)))
+        }
+        Error::PatchParse { file } => Some((file.clone(), None)),
+        _ => None,
+    });
+    let mut failure = json!({
+        "name": name,
+        "version": version.to_string(),
+        "error": format!("{err:#}"),
+    });
+    if let Some((patch, line)) = &location {
+        failure["patch"] = json!(patch);
+        failure["line"] = json!(line);
+        let position = line.map_or_else(String::new, |line| format!(":{line}"));
+        eprintln!("{}{position}: error: {err:#}", patch.display());
+    }
+    SUMMARY.with_borrow_mut(|summary| {
+        if let Some(summary) = summary {
+            summary.failed.push(failure);
+        }
+    });
+}
+
+/// Stops collecting and writes the summary including the outcome of the
+/// run. Returns the path of the summary or `None` if not running in CI
+/// mode.
+pub fn finish(result: &Result<(), Error>) -> Result<Option<PathBuf>> {
+    let Some(summary) = SUMMARY.with_borrow_mut(Option::take) else {
+        return Ok(None);
+    };
+    let contents = json!({
+        "success": result.is_ok(),
+        "error": result.as_ref().err().map(ToString::to_string),
+        "patched": summary.patched,
+        "failed": summary.failed,
+        "warnings": summary.warnings,
+    });
+    let dir = summary
+        .target_dir
+        .unwrap_or_else(|| PathBuf::from("target"));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(SUMMARY_FILE);
+    permissions::write(&path, serde_json::to_string_pretty(&contents)?)?;
+    status::status("Summary", format!("saved to {}", path.display()));
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hunk_ranges;
+
+    #[test]
+    fn hunk_ranges_default_count() {
+        assert_eq!(hunk_ranges("@@ -1 +1 @@"), hunk_ranges("@@ -1,1 +1,1 @@"));
+        assert_eq!(
+            hunk_ranges("@@ -8,9 +8,7 @@ fn serialize"),
+            Some(vec!["-8,9".to_owned(), "+8,7".to_owned()])
+        );
+        assert_eq!(hunk_ranges("+@@ -1 +1 @@"), None);
+    }
+}
@@ -0,0 +1,50 @@
+//! Removing files matched by a gitignore-style file from patched copies.
+
+use anyhow::{anyhow, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{fs, path::Path};
+
+/// Reads the gitignore-style file `ignore_file` with patterns relative to
+/// `root`.
+fn load(root: &Path, ignore_file: &Path) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(err) = builder.add(ignore_file) {
+        return Err(anyhow!(
+            "Unable to read copy-ignore file {}: {err}",
+            ignore_file.display()
+        ));
+    }
+    Ok(builder.build()?)
+}
+
+/// Removes the entries below `dir` which `ignore` matches and returns how
+/// many were removed. Like git, ignored directories are removed as a whole
+/// without looking at negated patterns for files inside of them.
+fn remove_matched(root: &Path, dir: &Path, ignore: &Gitignore) -> Result<usize> {
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_dir = entry.file_type()?.is_dir();
+        let relative = path.strip_prefix(root)?;
+        if ignore.matched(relative, is_dir).is_ignore() {
+            if is_dir {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+            removed += 1;
+        } else if is_dir {
+            removed += remove_matched(root, &path, ignore)?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Removes everything matched by the patterns in `ignore_file` from the copy
+/// at `root`, before any patch is applied. Returns the number of removed
+/// files and directories.
+pub fn prune(root: &Path, ignore_file: &Path) -> Result<usize> {
+    let ignore = load(root, ignore_file)?;
+    remove_matched(root, root, &ignore)
+}
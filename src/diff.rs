@@ -0,0 +1,127 @@
+//! Rendering of unified diffs.
+
+use similar::{ChangeTag, TextDiff};
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Returns a unified diff between `old` and `new`.
+pub fn unified_diff(
+    old_label: &str,
+    new_label: &str,
+    old: &str,
+    new: &str,
+) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(old_label, new_label)
+        .to_string()
+}
+
+/// Returns a unified diff of every file which differs between the
+/// directories `old` and `new`, e.g. a pristine and a modified copy of a
+/// dependency.
+///
+/// Paths are relative to the compared directories and use `/dev/null` for
+/// created and deleted files, so the result can be used as patch file with
+/// the default patch source. Empty files which are created or deleted have
+/// no lines to diff and are listed as `diff --git` blocks with a
+/// `new file mode` or `deleted file mode` line after the other files. Files
+/// which aren't valid UTF-8 and `.git` directories are skipped.
+pub fn diff_dirs(old: &Path, new: &Path) -> String {
+    let mut files = BTreeSet::new();
+    collect_files(old, Path::new(""), &mut files);
+    collect_files(new, Path::new(""), &mut files);
+    let mut out = String::new();
+    // a `diff --git` block extends to the next one, so these go last
+    let mut empty = String::new();
+    for file in files {
+        let label = file
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let (old_path, new_path) = (old.join(&file), new.join(&file));
+        let (old_data, old_label) = match fs::read_to_string(&old_path).ok() {
+            Some(data) => (data, label.as_str()),
+            None if old_path.exists() => continue,
+            None => (String::new(), "/dev/null"),
+        };
+        let (new_data, new_label) = match fs::read_to_string(&new_path).ok() {
+            Some(data) => (data, label.as_str()),
+            None if new_path.exists() => continue,
+            None => (String::new(), "/dev/null"),
+        };
+        if old_data != new_data {
+            out.push_str(&unified_diff(old_label, new_label, &old_data, &new_data));
+        } else if old_label != new_label {
+            let mode = if old_label == "/dev/null" {
+                "new"
+            } else {
+                "deleted"
+            };
+            empty.push_str(&format!(
+                "diff --git {label} {label}\n{mode} file mode 100644\n"
+            ));
+        }
+    }
+    out.push_str(&empty);
+    out
+}
+
+/// Adds the paths of all files below `dir` relative to the compared root.
+fn collect_files(root: &Path, dir: &Path, files: &mut BTreeSet<PathBuf>) {
+    let Ok(entries) = fs::read_dir(root.join(dir)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = dir.join(entry.file_name());
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => {
+                if entry.file_name() != ".git" {
+                    collect_files(root, &path, files);
+                }
+            }
+            Ok(_) => {
+                let _ = files.insert(path);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// Prints a unified diff between `old` and `new` to stdout, colored if
+/// stdout is a terminal.
+pub fn print_diff(old_label: &str, new_label: &str, old: &str, new: &str) {
+    if !std::io::stdout().is_terminal() {
+        print!("{}", unified_diff(old_label, new_label, old, new));
+        return;
+    }
+
+    println!("--- {old_label}");
+    println!("+++ {new_label}");
+    let diff = TextDiff::from_lines(old, new);
+    for hunk in diff.unified_diff().iter_hunks() {
+        println!("{CYAN}{}{RESET}", hunk.header());
+        for change in hunk.iter_changes() {
+            let (sign, color) = match change.tag() {
+                ChangeTag::Delete => ("-", RED),
+                ChangeTag::Insert => ("+", GREEN),
+                ChangeTag::Equal => (" ", ""),
+            };
+            let reset = if color.is_empty() { "" } else { RESET };
+            print!("{color}{sign}{change}{reset}");
+            if change.missing_newline() {
+                println!();
+            }
+        }
+    }
+}
@@ -0,0 +1,749 @@
+//! Parsing and application of diffs, independent of cargo.
+//!
+//! This is all that is built without the default `orchestrator` feature,
+//! for tools which apply patch files themselves and don't need dependencies
+//! to be resolved and copied.
+
+use crate::{
+    diff::print_diff, git_header, patched_file::PatchedFile, permissions, Error,
+};
+use anyhow::{anyhow, Result};
+use patch::{FileMetadata, Hunk, Line, Patch};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    fs, panic,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Where a patch file comes from, which determines how its file paths are
+/// mapped into the dependency.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum PatchSource {
+    /// Paths are relative to the dependency root.
+    #[default]
+    Default,
+    /// Paths carry the `a/` and `b/` prefixes of GitHub pull request diffs.
+    GithubPrDiff,
+    /// Paths carry the `a/` and `b/` prefixes of `git diff`.
+    GitDiff,
+}
+
+/// Whether files written by a patch end with a newline.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FinalNewline {
+    /// As given by the `\ No newline at end of file` markers of the patch,
+    /// or as in the original file where the patch doesn't reach its end.
+    #[default]
+    Preserve,
+    /// Always end non-empty files with a newline.
+    Always,
+    /// Never end files with a newline.
+    Never,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum PatchType {
+    Modify,
+    Create,
+    Delete,
+}
+
+impl FinalNewline {
+    /// Applies the policy to the content of a patched file.
+    fn apply(self, mut data: String) -> String {
+        match self {
+            Self::Always if !data.is_empty() && !data.ends_with('\n') => {
+                data.push('\n');
+            }
+            Self::Never if data.ends_with('\n') => {
+                let _ = data.pop();
+                if data.ends_with('\r') {
+                    let _ = data.pop();
+                }
+            }
+            Self::Preserve | Self::Always | Self::Never => {}
+        }
+        data
+    }
+}
+
+/// Returns the header of a hunk including the function context git adds
+/// after the ranges, e.g. `@@ -8,9 +8,7 @@ fn serialize`.
+pub fn hunk_header(hunk: &Hunk<'_>) -> String {
+    let header = format!("@@ -{} +{} @@", hunk.old_range, hunk.new_range);
+    match hunk.hint().map(str::trim_end) {
+        Some(hint) if !hint.is_empty() => format!("{header} {hint}"),
+        _ => header,
+    }
+}
+
+/// Applies a single file diff. With `preview` set to the old and new file
+/// label, the effective changes are printed before anything is written.
+pub fn do_patch(
+    file: &Path,
+    diff: Patch<'_>,
+    old_path: Option<PathBuf>,
+    new_path: Option<PathBuf>,
+    preview: Option<(&str, &str)>,
+    final_newline: FinalNewline,
+) -> Result<PatchType> {
+    // delete
+    if new_path.is_none() {
+        if let Some(old) = old_path {
+            if let Some((old_label, new_label)) = preview {
+                let old_data = fs::read_to_string(&old)?;
+                print_diff(old_label, new_label, &old_data, "");
+            }
+            fs::remove_file(old)?;
+            return Ok(PatchType::Delete);
+        }
+        return Err(anyhow!("Both old and new file are all empty."));
+    }
+    let new_path = new_path.unwrap();
+
+    let (old_data, patch_type) = if let Some(old) = old_path {
+        // modify
+        (fs::read_to_string(old)?, PatchType::Modify)
+    } else {
+        // create
+        ("".to_string(), PatchType::Create)
+    };
+
+    let headers = diff.hunks.iter().map(hunk_header).collect::<Vec<_>>();
+    let hunk_failed =
+        |mismatch: HunkMismatch| Error::HunkFailed {
+            file: PathBuf::from(new_path.to_owned().file_name().map_or_else(
+                || "".to_string(),
+                |it| it.to_string_lossy().to_string(),
+            )),
+            line: mismatch.old_line + 1,
+            new_line: mismatch.new_line + 1,
+            hunk: mismatch.hunk + 1,
+            header: headers[mismatch.hunk].clone(),
+            patch: file.to_path_buf(),
+            changed_by: None,
+        };
+
+    if patch_type == PatchType::Create {
+        if let Some(parent) = new_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    if let Some((old_label, new_label)) = preview {
+        let data = apply_patch(diff, &old_data).map_err(hunk_failed)?;
+        let data = final_newline.apply(data);
+        print_diff(old_label, new_label, &old_data, &data);
+        permissions::write(&new_path, data)?;
+    } else {
+        // streamed as generated files may be huge
+        let mut out = PatchedFile::create(&new_path)?;
+        let newline = apply_patch_lines(diff, &old_data, |line| out.line(line))
+            .map_err(hunk_failed)?;
+        out.finish(newline, final_newline)?;
+    }
+
+    Ok(patch_type)
+}
+
+/// Returns whether the file diff applies to the file at `path`.
+#[cfg(feature = "orchestrator")]
+pub fn applies_to(diff: &Patch<'_>, path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|old| apply_patch(diff.clone(), &old).is_ok())
+}
+
+/// Whether a file diff deletes the file using the convention of GNU
+/// `diff -N`, which compares against an empty file dated to the epoch
+/// instead of `/dev/null`.
+fn is_deleted(patch: &Patch<'_>) -> bool {
+    let epoch = matches!(
+        &patch.new.meta,
+        Some(FileMetadata::DateTime(time)) if time.timestamp() == 0
+    );
+    epoch && patch.hunks.iter().all(|hunk| hunk.new_range.count == 0)
+}
+
+/// `diff -r old new` prefixes the paths with the compared directories. They
+/// are stripped if they are the only difference between both paths.
+fn strip_dir_prefixes<'p>(old: &'p str, new: &'p str) -> (&'p str, &'p str) {
+    match (old.split_once('/'), new.split_once('/')) {
+        (Some((old_dir, old_rest)), Some((new_dir, new_rest)))
+            if !old_dir.is_empty() && old_dir != new_dir && old_rest == new_rest =>
+        {
+            (old_rest, new_rest)
+        }
+        _ => (old, new),
+    }
+}
+
+/// Returns the old and new file path of a patch relative to the dependency
+/// root, stripping prefixes specific to the patch source.
+pub fn patch_paths<'p>(
+    patch: &'p Patch<'_>,
+    source: &PatchSource,
+) -> (&'p str, &'p str) {
+    match source {
+        PatchSource::Default => strip_dir_prefixes(&patch.old.path, &patch.new.path),
+        PatchSource::GithubPrDiff | PatchSource::GitDiff => (
+            patch
+                .old
+                .path
+                .strip_prefix("a/")
+                .unwrap_or_else(|| patch.old.path.as_ref()),
+            patch
+                .new
+                .path
+                .strip_prefix("b/")
+                .unwrap_or_else(|| patch.new.path.as_ref()),
+        ),
+    }
+}
+
+/// Returns `path` below `base`, failing with [`Error::PathEscape`] if it
+/// leads outside of it.
+pub fn check_path<P: AsRef<Path>>(
+    base: &Path,
+    path: P,
+    loc: &str,
+) -> Result<PathBuf> {
+    let escape = || Error::PathEscape {
+        path: path.as_ref().to_owned(),
+        location: loc.to_owned(),
+    };
+    let joined = base.join(path.as_ref());
+    match joined.canonicalize() {
+        // a file created by the patch, its parent must not be left with `..`
+        Err(_) if joined.to_string_lossy().contains("..") => Err(escape().into()),
+        Err(_) => Ok(joined),
+        Ok(canonical) if canonical.strip_prefix(base).is_err() => {
+            Err(escape().into())
+        }
+        Ok(_) => Ok(joined),
+    }
+}
+
+/// Returns the files below `base` a file diff reads and writes, `None` for
+/// the old file of a created and the new file of a deleted one.
+pub fn file_paths(
+    base: &Path,
+    patch: &Patch<'_>,
+    (old_path, new_path): (&str, &str),
+    loc: &str,
+) -> Result<(Option<PathBuf>, Option<PathBuf>)> {
+    let new_file_path = check_path(base, new_path, loc);
+    let old_file_path = check_path(base, old_path, loc);
+
+    let new_file_path = if patch.new.path == "/dev/null" || is_deleted(patch) {
+        None
+    } else {
+        Some(new_file_path?)
+    };
+    let old_missing = old_file_path.as_ref().is_ok_and(|old| !old.exists());
+    let old_file_path = if patch.old.path == "/dev/null"
+        || (old_missing && patch.hunks.iter().all(|hunk| hunk.old_range.count == 0))
+    {
+        None
+    } else {
+        Some(old_file_path?)
+    };
+    Ok((old_file_path, new_file_path))
+}
+
+/// Applies the diff of a single file in `data` to `original`. `file` names
+/// the patch in errors.
+pub fn apply_single(
+    data: &str,
+    original: &str,
+    file: &Path,
+) -> Result<String, Error> {
+    let (data, _) = git_header::extract(data, &PatchSource::Default);
+    let data = mark_missing_newlines(&data);
+    let mut diffs = parse_patches(&data).ok_or_else(|| Error::PatchParse {
+        file: file.to_owned(),
+    })?;
+    if diffs.len() != 1 {
+        return Err(
+            anyhow!("Expected a diff of one file, found {}", diffs.len()).into(),
+        );
+    }
+    let diff = diffs.remove(0);
+    let headers = diff.hunks.iter().map(hunk_header).collect::<Vec<_>>();
+    apply_patch(diff, original).map_err(|mismatch| {
+        anyhow!(
+            "hunk {} `{}` failed on line {}",
+            mismatch.hunk + 1,
+            headers.get(mismatch.hunk).map_or("", String::as_str),
+            mismatch.old_line.saturating_add(1)
+        )
+        .into()
+    })
+}
+
+/// Applies the diff of a single file to `original` in memory, like the
+/// fixtures of `cargo patch selftest` are applied.
+///
+/// Malformed diffs, e.g. with overlapping hunks or ranges beyond the end of
+/// the file, are reported as errors. This never panics, whatever the input.
+pub fn apply_diff(diff: &str, original: &str) -> Result<String, Error> {
+    apply_single(diff, original, Path::new("-"))
+}
+
+/// A patch file applied by [`apply_to_dir`].
+#[derive(Debug, Clone)]
+pub struct PatchSpec {
+    pub path: PathBuf,
+    pub source: PatchSource,
+    pub final_newline: FinalNewline,
+}
+
+impl PatchSpec {
+    /// A patch file with paths relative to the directory.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            source: PatchSource::Default,
+            final_newline: FinalNewline::Preserve,
+        }
+    }
+}
+
+/// Outcome of [`apply_to_dir`].
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Report {
+    /// The created, modified and deleted files relative to the directory, in
+    /// the order they were patched.
+    pub files: Vec<PathBuf>,
+}
+
+/// Applies the patch files to an arbitrary directory in place, e.g. vendored
+/// C sources in a build script.
+///
+/// Nothing is resolved, copied or printed. Paths are mapped according to the
+/// `source` of each patch and checked like for dependencies, so the patches
+/// can't modify files outside of `dir`. Git extended headers like renames
+/// and mode changes are applied as well.
+pub fn apply_to_dir(dir: &Path, patches: &[PatchSpec]) -> Result<Report, Error> {
+    let base = dir.canonicalize()?;
+    let name = base
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut report = Report::default();
+    for patch in patches {
+        let data = fs::read_to_string(&patch.path)?;
+        report.files.extend(
+            apply_to_base(&base, &name, patch, &data)?
+                .into_iter()
+                .map(|patched| patched.file),
+        );
+    }
+    Ok(report)
+}
+
+/// A file changed by [`apply_to_base`].
+pub struct Patched {
+    /// The file relative to the patched directory.
+    pub file: PathBuf,
+    /// The name of the directory and the old and new path of the file diff,
+    /// e.g. `serde: src/lib.rs -> src/lib.rs`, printed by the metadata
+    /// backend.
+    #[cfg_attr(not(feature = "metadata-backend"), allow(dead_code))]
+    pub location: String,
+}
+
+/// Applies every file diff of the patch file `spec` with the content `data`
+/// to `base`, which is called `name` in the locations of the changed files.
+pub fn apply_to_base(
+    base: &Path,
+    name: &str,
+    spec: &PatchSpec,
+    data: &str,
+) -> Result<Vec<Patched>, Error> {
+    let (data, operations) = git_header::extract(data, &spec.source);
+    let data = mark_missing_newlines(&data);
+    let patches = if data.trim().is_empty() && !operations.is_empty() {
+        vec![]
+    } else {
+        parse_patches(&data).ok_or_else(|| Error::PatchParse {
+            file: spec.path.clone(),
+        })?
+    };
+    let mut changed = vec![];
+    for patch in patches {
+        let paths = patch_paths(&patch, &spec.source);
+        let (old_file, new_file) = (paths.0.to_owned(), paths.1.to_owned());
+        let loc = format!("{name}: {old_file} -> {new_file}");
+        let (old_file_path, new_file_path) = file_paths(base, &patch, paths, &loc)?;
+        let patch_type = do_patch(
+            &spec.path,
+            patch,
+            old_file_path,
+            new_file_path,
+            None,
+            spec.final_newline,
+        )?;
+        let file = match patch_type {
+            PatchType::Modify | PatchType::Create => &new_file,
+            PatchType::Delete => &old_file,
+        };
+        changed.push(Patched {
+            file: PathBuf::from(file),
+            location: loc,
+        });
+    }
+    for operation in operations {
+        let location = operation.perform(name, base)?;
+        changed.push(Patched {
+            file: PathBuf::from(operation.file()),
+            location,
+        });
+    }
+    Ok(changed)
+}
+
+/// Marks the last line of a side of the diff which doesn't end with a
+/// newline. Replaces the `\ No newline at end of file` lines as the patch
+/// parser only accepts them after the last hunk of a file.
+const NO_NEWLINE: char = '\0';
+
+/// Moves every `\ No newline at end of file` line into the line before it
+/// using [`NO_NEWLINE`] so that patches changing the end of file newline
+/// can be parsed.
+pub fn mark_missing_newlines(data: &str) -> Cow<'_, str> {
+    if !data.contains("\n\\ ") {
+        return Cow::Borrowed(data);
+    }
+    let mut out = String::with_capacity(data.len());
+    for line in data.split_inclusive('\n') {
+        if line.starts_with("\\ ") && out.ends_with('\n') {
+            let _ = out.pop();
+            if out.ends_with('\r') {
+                let _ = out.pop();
+            }
+            out.push(NO_NEWLINE);
+            out.push('\n');
+        } else {
+            out.push_str(line);
+        }
+    }
+    Cow::Owned(out)
+}
+
+thread_local! {
+    /// Whether the patch parser runs on this thread, see [`parse_patches`].
+    static PARSING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Parses the diffs of several files. The parser panics on some malformed
+/// input, e.g. line numbers beyond `u64` or lines it can't make sense of
+/// after the last hunk, so its panics are caught and reported as `None`.
+///
+/// On first use, a panic hook is installed which stays silent for the panics
+/// of the parser and passes all others on to the hook set before. It is never
+/// removed, so hooks set later by the application replace it as usual.
+pub fn parse_patches(data: &str) -> Option<Vec<Patch<'_>>> {
+    static HOOK: OnceLock<()> = OnceLock::new();
+    HOOK.get_or_init(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !PARSING.get() {
+                previous(info);
+            }
+        }));
+    });
+    PARSING.set(true);
+    let patches = panic::catch_unwind(|| Patch::from_multiple(data));
+    PARSING.set(false);
+    patches.ok()?.ok()
+}
+
+/// Location a hunk failed to apply at, all counting from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HunkMismatch {
+    hunk: usize,
+    /// Line of the original file.
+    old_line: u64,
+    /// Line of the patched file according to the hunk header.
+    new_line: u64,
+}
+
+/// Apply a patch to the given text.
+/// If the apply fails (i.e. due to mismatch in context lines), returns an Err with the hunk and
+/// line number it failed on (both 0-based). Malformed hunks, e.g. overlapping
+/// ones or ones beyond the end of the text, fail the same way.
+pub fn apply_patch(diff: Patch<'_>, old: &str) -> Result<String, HunkMismatch> {
+    let mut out = vec![];
+    if apply_patch_lines(diff, old, |line| out.push(line))? {
+        out.push("");
+    }
+    Ok(out.join("\n"))
+}
+
+/// Applies a patch to the given text like [`apply_patch`], but passes the
+/// lines of the result to `emit` one by one instead of collecting them.
+/// Returns whether the result ends with a newline.
+fn apply_patch_lines<'a>(
+    diff: Patch<'a>,
+    old: &'a str,
+    mut emit: impl FnMut(&'a str),
+) -> Result<bool, HunkMismatch> {
+    let line_count = u64::try_from(old.lines().count()).unwrap_or(u64::MAX);
+    let mut old_lines = old.lines().peekable();
+    // whether the output so far has to end with a newline
+    let mut newline = false;
+    let mut old_line = 0;
+    for (index, hunk) in diff.hunks.into_iter().enumerate() {
+        // a hunk without old lines inserts after its start line
+        let start = if hunk.old_range.count == 0 {
+            hunk.old_range.start
+        } else {
+            hunk.old_range.start.saturating_sub(1)
+        };
+        let mut new_line = if hunk.new_range.count == 0 {
+            hunk.new_range.start
+        } else {
+            hunk.new_range.start.saturating_sub(1)
+        };
+        let mismatch = |old_line, new_line| HunkMismatch {
+            hunk: index,
+            old_line,
+            new_line,
+        };
+        // overlapping hunks or hunks starting beyond the end of the file
+        if start < old_line || start > line_count {
+            return Err(mismatch(old_line, new_line));
+        }
+        while old_line < start {
+            old_lines.next().into_iter().for_each(&mut emit);
+            old_line += 1;
+            newline = true;
+        }
+        for line in hunk.lines {
+            match line {
+                Line::Context(line) => {
+                    let (line, missing) = split_no_newline(line);
+                    if old_lines.next_if_eq(&line).is_none() {
+                        return Err(mismatch(old_line, new_line));
+                    }
+                    emit(line);
+                    old_line += 1;
+                    new_line = new_line.saturating_add(1);
+                    newline = !missing;
+                }
+                Line::Add(line) => {
+                    let (line, missing) = split_no_newline(line);
+                    emit(line);
+                    new_line = new_line.saturating_add(1);
+                    newline = !missing;
+                }
+                Line::Remove(line) => {
+                    let (line, _) = split_no_newline(line);
+                    if old_lines.next_if_eq(&line).is_none() {
+                        return Err(mismatch(old_line, new_line));
+                    }
+                    old_line += 1;
+                }
+            }
+        }
+    }
+    if old_lines.peek().is_some() {
+        old_lines.for_each(emit);
+        newline = old.ends_with('\n');
+    }
+    Ok(newline)
+}
+
+/// Splits the [`NO_NEWLINE`] marker off a patch line.
+pub fn split_no_newline(line: &str) -> (&str, bool) {
+    line.strip_suffix(NO_NEWLINE)
+        .map_or((line, false), |line| (line, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_patch, mark_missing_newlines, HunkMismatch};
+    use patch::Patch;
+
+    #[test]
+    fn apply_patch_simply() {
+        let patch = r#"--- test	2020-05-21 08:50:06.629765310 +0200
++++ test	2020-05-21 08:50:19.689878523 +0200
+@@ -1,6 +1,6 @@
+ This is the first line
+ 
+-This is the second line
++This is the patched line
+ 
+ This is the third line
+"#;
+        let content = r#"This is the first line
+
+This is the second line
+
+This is the third line
+"#;
+        let patched = r#"This is the first line
+
+This is the patched line
+
+This is the third line
+"#;
+        let patch = Patch::from_single(patch).expect("Unable to parse patch");
+        let test_patched =
+            apply_patch(patch, content).expect("Failed to apply patch");
+        assert_eq!(patched, test_patched, "Patched content does not match");
+    }
+
+    #[test]
+    fn apply_patch_middle() {
+        let patch = r#"--- test1	2020-05-22 17:30:38.119170176 +0200
++++ test2	2020-05-22 17:30:48.905935473 +0200
+@@ -2,8 +2,7 @@
+ adipiscing elit, sed do eiusmod tempor 
+ incididunt ut labore et dolore magna 
+ aliqua. Ut enim ad minim veniam, quis 
+-nostrud exercitation ullamco laboris 
+-nisi ut aliquip ex ea commodo consequat. 
++PATCHED
+ Duis aute irure dolor in reprehenderit 
+ in voluptate velit esse cillum dolore 
+ eu fugiat nulla pariatur. Excepteur sint 
+"#;
+        let content = r#"Lorem ipsum dolor sit amet, consectetur 
+adipiscing elit, sed do eiusmod tempor 
+incididunt ut labore et dolore magna 
+aliqua. Ut enim ad minim veniam, quis 
+nostrud exercitation ullamco laboris 
+nisi ut aliquip ex ea commodo consequat. 
+Duis aute irure dolor in reprehenderit 
+in voluptate velit esse cillum dolore 
+eu fugiat nulla pariatur. Excepteur sint 
+occaecat cupidatat non proident, sunt in 
+culpa qui officia deserunt mollit anim 
+id est laborum.
+"#;
+        let patched = r#"Lorem ipsum dolor sit amet, consectetur 
+adipiscing elit, sed do eiusmod tempor 
+incididunt ut labore et dolore magna 
+aliqua. Ut enim ad minim veniam, quis 
+PATCHED
+Duis aute irure dolor in reprehenderit 
+in voluptate velit esse cillum dolore 
+eu fugiat nulla pariatur. Excepteur sint 
+occaecat cupidatat non proident, sunt in 
+culpa qui officia deserunt mollit anim 
+id est laborum.
+"#;
+        let patch = Patch::from_single(patch).expect("Unable to parse patch");
+        let test_patched =
+            apply_patch(patch, content).expect("Failed to apply patch");
+        assert_eq!(patched, test_patched, "Patched content does not match");
+    }
+
+    #[test]
+    fn apply_patch_no_context_override() {
+        let patch = r#"--- test        2020-06-06 10:06:44.375560000 +0200
++++ test2       2020-06-06 10:06:49.245635957 +0200
+@@ -1,3 +1,3 @@
+ test5
+-test2
++test4
+ test3
+"#;
+        let content = r#"test1
+test2
+test3
+"#;
+        let patch = Patch::from_single(patch).expect("Unable to parse patch");
+        // first line context doesn't match
+        assert_eq!(
+            apply_patch(patch, content),
+            Err(HunkMismatch {
+                hunk: 0,
+                old_line: 0,
+                new_line: 0
+            })
+        );
+    }
+
+    fn apply(patch: &str, content: &str) -> Result<String, HunkMismatch> {
+        let patch = mark_missing_newlines(patch);
+        let patch = Patch::from_single(&patch).expect("Unable to parse patch");
+        apply_patch(patch, content)
+    }
+
+    #[test]
+    fn apply_patch_append() {
+        let patch = "--- test\n+++ test\n@@ -2,0 +3,2 @@\n+third\n+fourth\n";
+        assert_eq!(
+            apply(patch, "first\nsecond\n"),
+            Ok("first\nsecond\nthird\nfourth\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn apply_patch_insert_after_line() {
+        let patch = "--- test\n+++ test\n@@ -1,0 +2 @@\n+inserted\n";
+        assert_eq!(
+            apply(patch, "first\nsecond\n"),
+            Ok("first\ninserted\nsecond\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn apply_patch_prepend_to_empty_file() {
+        let patch = "--- test\n+++ test\n@@ -0,0 +1,2 @@\n+first\n+second\n";
+        assert_eq!(apply(patch, ""), Ok("first\nsecond\n".to_owned()));
+    }
+
+    #[test]
+    fn apply_patch_beyond_end_of_file() {
+        let patch = "--- test\n+++ test\n@@ -5,0 +6 @@\n+sixth\n";
+        assert_eq!(
+            apply(patch, "first\nsecond\n"),
+            Err(HunkMismatch {
+                hunk: 0,
+                old_line: 0,
+                new_line: 5
+            })
+        );
+    }
+
+    #[test]
+    fn apply_patch_add_missing_newline() {
+        let patch = "--- test\n+++ test\n@@ -1,2 +1,2 @@\n first\n-second\n\\ No newline at end of file\n+second\n";
+        assert_eq!(
+            apply(patch, "first\nsecond"),
+            Ok("first\nsecond\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn apply_patch_remove_newline() {
+        let patch = "--- test\n+++ test\n@@ -1,2 +1,2 @@\n first\n-second\n+second\n\\ No newline at end of file\n";
+        assert_eq!(
+            apply(patch, "first\nsecond\n"),
+            Ok("first\nsecond".to_owned())
+        );
+    }
+
+    #[test]
+    fn apply_patch_keep_missing_newline() {
+        let patch = "--- test\n+++ test\n@@ -1,2 +1,2 @@\n-first\n+patched\n second\n\\ No newline at end of file\n";
+        assert_eq!(
+            apply(patch, "first\nsecond"),
+            Ok("patched\nsecond".to_owned())
+        );
+    }
+
+    #[test]
+    fn apply_patch_untouched_end_of_file() {
+        let patch = "--- test\n+++ test\n@@ -1 +1 @@\n-first\n+patched\n";
+        assert_eq!(
+            apply(patch, "first\nsecond"),
+            Ok("patched\nsecond".to_owned())
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Error type returned by the public functions of this crate.
+
+use std::{
+    fmt::{Display, Formatter},
+    io,
+    path::PathBuf,
+};
+
+/// Failure while patching dependencies.
+///
+/// Library users like build scripts can match on the variants to tell
+/// failure classes apart. Errors from cargo are kept as [`anyhow::Error`]
+/// and displayed unchanged.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The dependencies of the workspace could not be resolved.
+    ResolveFailed(anyhow::Error),
+    /// A patch file is not a valid diff. `file` is `-` for diffs passed to
+    /// [`apply`](crate::apply).
+    PatchParse { file: PathBuf },
+    /// A hunk does not match the file it is applied to.
+    HunkFailed {
+        /// The file the hunk was applied to.
+        file: PathBuf,
+        /// The line of the file the hunk failed on, starting at 1.
+        line: u64,
+        /// The same line in the patched file according to the hunk header,
+        /// starting at 1.
+        new_line: u64,
+        /// The number of the failed hunk within the file diff, starting at 1.
+        hunk: usize,
+        /// The header of the failed hunk including git's function context,
+        /// e.g. `@@ -8,9 +8,7 @@ fn serialize`.
+        header: String,
+        /// The patch file containing the hunk, `-` for diffs passed to
+        /// [`apply`](crate::apply).
+        patch: PathBuf,
+        /// An earlier patch of the same entry which changed the lines of the
+        /// hunk.
+        changed_by: Option<PathBuf>,
+    },
+    /// A file diff names a path outside of the patched directory, e.g. with
+    /// `..` components or through a symlink.
+    PathEscape {
+        /// The path as given in the diff.
+        path: PathBuf,
+        /// The patched package or directory and the old and new path of the
+        /// file diff.
+        location: String,
+    },
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// The manifest of the workspace is invalid.
+    ManifestInvalid(anyhow::Error),
+    /// The dependency is not part of the dependency graph.
+    PackageNotFound { name: String },
+    /// Patching was stopped by Ctrl-C, see
+    /// [`handle_interrupts`](crate::handle_interrupts).
+    Interrupted,
+    /// Any other failure.
+    Other(anyhow::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ResolveFailed(err)
+            | Self::ManifestInvalid(err)
+            | Self::Other(err) => Display::fmt(err, f),
+            Self::PatchParse { file } => {
+                write!(f, "Unable to parse patch file {}", file.display())
+            }
+            Self::HunkFailed {
+                file,
+                line,
+                new_line,
+                hunk,
+                header,
+                patch,
+                changed_by,
+            } => {
+                write!(
+                    f,
+                    "failed to apply patch to {} on line {line} (line {new_line} \
+                     after patching) in hunk {hunk} `{header}` of {}",
+                    file.display(),
+                    patch.display()
+                )?;
+                if let Some(changed_by) = changed_by {
+                    write!(
+                        f,
+                        ". The lines of the hunk were changed by {} before",
+                        changed_by.display()
+                    )?;
+                }
+                Ok(())
+            }
+            Self::PathEscape { location, .. } => {
+                write!(
+                    f,
+                    "Patch file tried to escape dependency folder ({location})"
+                )
+            }
+            Self::Io(err) => Display::fmt(err, f),
+            Self::PackageNotFound { name } => {
+                write!(f, "Unable to find package {name} in dependencies")
+            }
+            Self::Interrupted => write!(f, "Interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // the wrapped error is displayed in place of this one
+            Self::ResolveFailed(err)
+            | Self::ManifestInvalid(err)
+            | Self::Other(err) => err.source(),
+            Self::Io(err) => err.source(),
+            Self::PatchParse { .. }
+            | Self::HunkFailed { .. }
+            | Self::PathEscape { .. }
+            | Self::PackageNotFound { .. }
+            | Self::Interrupted => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    /// Recovers an [`Error`] or [`io::Error`] raised inside of the crate.
+    fn from(err: anyhow::Error) -> Self {
+        // errors with added context are kept as is to not lose the context
+        let outermost = err.chain().next();
+        if outermost.is_some_and(|outer| outer.is::<Self>()) {
+            return err.downcast::<Self>().unwrap_or_else(Self::Other);
+        }
+        if outermost.is_some_and(|outer| outer.is::<io::Error>()) {
+            return err
+                .downcast::<io::Error>()
+                .map_or_else(Self::Other, Self::Io);
+        }
+        Self::Other(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use std::io;
+
+    #[test]
+    fn from_anyhow_recovers_variants() {
+        let err = anyhow::Error::from(Error::PackageNotFound {
+            name: "serde".to_owned(),
+        });
+        assert!(matches!(
+            Error::from(err),
+            Error::PackageNotFound { name } if name == "serde"
+        ));
+
+        let err = anyhow::Error::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(Error::from(err), Error::Io(_)));
+
+        let err = anyhow::anyhow!("something else");
+        assert!(matches!(Error::from(err), Error::Other(_)));
+    }
+}
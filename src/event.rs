@@ -0,0 +1,132 @@
+//! Typed progress events for embedders of the library.
+
+use crate::{
+    orchestrator::{patch_with_options, Options},
+    Error,
+};
+use semver::Version;
+use std::{cell::RefCell, fmt, path::PathBuf};
+
+/// Progress of a [`Patcher`] run, delivered to its [`Patcher::on_event`]
+/// handler in addition to the regular output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// An entry resolved to a package of the dependency graph, which is
+    /// patched next unless its copy is [fresh](Event::PackageFresh).
+    PackageResolved { name: String, version: Version },
+    /// The persisted copy of a package is up to date and was kept.
+    PackageFresh { name: String, version: Version },
+    /// The sources of a package were copied to `path`.
+    PackageCopied { name: String, path: PathBuf },
+    /// A hunk was applied to `file`, relative to the package, starting at
+    /// `line`. The internal engine only applies hunks exactly; for external
+    /// backends only hunks applied with an offset or fuzz are reported, as
+    /// the tools are silent about the others.
+    HunkApplied {
+        name: String,
+        file: PathBuf,
+        line: u64,
+        offset: i64,
+        fuzz: u64,
+    },
+    /// A patch file was applied completely.
+    PatchApplied { name: String, patch: PathBuf },
+    /// A patch file failed to apply, patching stops with the error.
+    PatchFailed {
+        name: String,
+        patch: PathBuf,
+        error: String,
+    },
+    /// All patches of a package were applied to the copy at `path`.
+    PackagePatched {
+        name: String,
+        version: Version,
+        path: PathBuf,
+    },
+}
+
+type Handler = Box<dyn FnMut(Event)>;
+
+thread_local! {
+    /// Handler of the running [`Patcher`], if any.
+    static HANDLER: RefCell<Option<Handler>> = const { RefCell::new(None) };
+}
+
+/// Delivers an event to the handler of the running [`Patcher`]. The event is
+/// only built if there is one.
+pub fn emit(event: impl FnOnce() -> Event) {
+    // taken out while running, so a handler calling into the library
+    // doesn't see itself
+    let Some(mut handler) = HANDLER.take() else {
+        return;
+    };
+    handler(event());
+    HANDLER.with_borrow_mut(|current| {
+        if current.is_none() {
+            *current = Some(handler);
+        }
+    });
+}
+
+/// Removes the handler again when patching returns or panics.
+struct Installed;
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        drop(HANDLER.take());
+    }
+}
+
+/// Patches dependencies like [`patch_with_options`] while reporting
+/// progress as [`Event`]s, e.g. to render it in an xtask or GUI.
+///
+/// ```no_run
+/// use cargo_patch::{Event, Patcher};
+///
+/// Patcher::default()
+///     .on_event(|event| {
+///         if let Event::PackagePatched { name, version, .. } = event {
+///             println!("cargo:warning=patched {name} {version}");
+///         }
+///     })
+///     .patch()
+///     .expect("Failed while patching");
+/// ```
+#[derive(Default)]
+pub struct Patcher {
+    options: Options,
+    handler: Option<Handler>,
+}
+
+impl Patcher {
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            handler: None,
+        }
+    }
+
+    /// Calls `handler` with every event of the run, on the calling thread.
+    #[must_use]
+    pub fn on_event(mut self, handler: impl FnMut(Event) + 'static) -> Self {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Patches all dependencies configured in the workspace.
+    pub fn patch(self) -> Result<(), Error> {
+        HANDLER.set(self.handler);
+        let _installed = Installed;
+        patch_with_options(&self.options)
+    }
+}
+
+impl fmt::Debug for Patcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Patcher")
+            .field("options", &self.options)
+            .field("handler", &self.handler.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,111 @@
+//! Exporting the patches of a dependency as `git format-patch` files.
+
+use crate::{
+    git_repo,
+    long_path::long_path,
+    orchestrator::{
+        get_config, load_workspace, lock_package_cache, patch_base,
+        patch_package_unlogged, resolve_patches, resolve_ws, setup_gctx, Options,
+    },
+    status, Error,
+};
+use anyhow::Result;
+use semver::VersionReq;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Directory below `target/patch` copies are patched in if they don't
+/// have a git repository yet.
+const SCRATCH_DIR: &str = "target/patch/.export";
+
+/// Writes the patch stack of the dependency `name` to `out` as numbered
+/// `NNNN-<subject>.patch` files.
+///
+/// Copies created with `git-init` are exported as they are, including
+/// commits made in them after patching. Other dependencies are patched again
+/// in a scratch directory with a repository, so every configured patch file
+/// becomes one exported patch.
+pub fn export(
+    name: &str,
+    version: Option<&str>,
+    out: &Path,
+    options: &Options,
+) -> Result<(), Error> {
+    let version = version
+        .map(VersionReq::parse)
+        .transpose()
+        .map_err(anyhow::Error::from)?;
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+    let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+    let ids = resolve_patches(&workspace, &pkg_set, &resolve, &config)?
+        .into_iter()
+        .filter(|(patch, id)| {
+            patch.name == name
+                && version
+                    .as_ref()
+                    .map_or(true, |req| req.matches(id.version()))
+        })
+        .collect::<Vec<_>>();
+    if ids.is_empty() {
+        return Err(Error::PackageNotFound {
+            name: name.to_owned(),
+        });
+    }
+
+    fs::create_dir_all(out)?;
+    let dir = env::current_dir()?.join(out);
+    let mut number = 1;
+    for (patch, id) in ids {
+        let package = pkg_set.get_one(id)?;
+        let base = patch_base(&patch, package, &config).ok();
+        let exported = match base.filter(|base| base.join(".git").is_dir()) {
+            Some(base) => {
+                if git_repo::has_uncommitted_changes(&base)? {
+                    status::warning(format!(
+                        "uncommitted changes in {} are not exported",
+                        base.display()
+                    ));
+                }
+                git_repo::format_patch(&base, &dir, number)?
+            }
+            None => {
+                let mut scratch_config = config.clone();
+                scratch_config.persist = Some(PathBuf::from(SCRATCH_DIR));
+                let mut scratch_patch = patch.clone();
+                scratch_patch.git_init = true;
+                let scratch = long_path(Path::new(SCRATCH_DIR))?;
+                if scratch.exists() {
+                    fs::remove_dir_all(&scratch)?;
+                }
+                let result = patch_package_unlogged(
+                    &scratch_patch,
+                    package,
+                    &scratch_config,
+                    options,
+                )
+                .and_then(|()| {
+                    let base = patch_base(&scratch_patch, package, &scratch_config)?;
+                    git_repo::format_patch(&base, &dir, number)
+                });
+                fs::remove_dir_all(&scratch)?;
+                result?
+            }
+        };
+        status::status(
+            "Exported",
+            format!(
+                "{} patch(es) of {name} v{} to {}",
+                exported.len(),
+                id.version(),
+                out.display()
+            ),
+        );
+        number += exported.len();
+    }
+    Ok(())
+}
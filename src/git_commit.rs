@@ -0,0 +1,97 @@
+//! Patches taken from commits of upstream git repositories, e.g. a fix
+//! which isn't released yet.
+
+use anyhow::{anyhow, Result};
+use cargo::util::hex::short_hash;
+use std::{fs, path::Path, process::Command};
+
+/// Bare repositories commits are fetched into, one per URL.
+const REPO_DIR: &str = "target/patch-cache/git";
+
+fn git(dir: &Path) -> Command {
+    let mut command = Command::new("git");
+    let _ = command
+        .current_dir(dir)
+        // keep git from treating a surrounding repository as the root
+        .env("GIT_CEILING_DIRECTORIES", dir.parent().unwrap_or(dir))
+        // fail instead of waiting for credentials nobody enters
+        .env("GIT_TERMINAL_PROMPT", "0");
+    command
+}
+
+fn run(command: &mut Command) -> Result<Vec<u8>> {
+    let output = command.output().map_err(|err| {
+        anyhow!("Unable to run git, which patches from git commits require: {err}")
+    })?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Returns whether the commit `rev` and its parent are in the repository.
+fn has_commit(dir: &Path, rev: &str) -> bool {
+    git(dir)
+        .args(["rev-parse", "--verify", "--quiet", &format!("{rev}^")])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Returns the changes of the commit `rev` of the repository at `url` as
+/// `git diff` output.
+///
+/// With `shallow` only the commit and its parent are fetched, which needs a
+/// full commit hash and a server allowing to fetch single commits, as
+/// GitHub, GitLab and git's protocol v2 do. Otherwise all branches and tags
+/// are fetched. Unless `follow_redirects` is set, git doesn't follow HTTP
+/// redirects, which could lead outside of the allowed patch URLs.
+pub fn diff(
+    url: &str,
+    rev: &str,
+    shallow: bool,
+    follow_redirects: bool,
+) -> Result<Vec<u8>> {
+    let dir = Path::new(REPO_DIR).join(short_hash(&url));
+    if !dir.join("HEAD").exists() {
+        fs::create_dir_all(&dir)?;
+        let _ = run(git(&dir).args(["init", "--bare", "--quiet"]))?;
+    }
+    if !has_commit(&dir, rev) {
+        let mut fetch = git(&dir);
+        if !follow_redirects {
+            let _ = fetch.args(["-c", "http.followRedirects=false"]);
+        }
+        let fetched = if shallow {
+            run(fetch.args(["fetch", "--quiet", "--depth=2", "--no-tags", url, rev]))
+        } else {
+            run(fetch.args([
+                "fetch",
+                "--quiet",
+                "--tags",
+                url,
+                "+refs/heads/*:refs/heads/*",
+            ]))
+        };
+        fetched.map_err(|err| {
+            let help = if shallow {
+                "\nhelp: if the server doesn't allow fetching single commits, set \
+                 `git-shallow = false` in `[workspace.metadata.patch-config]` or \
+                 `cargo-patch.git-shallow = false` in the cargo config"
+            } else {
+                ""
+            };
+            anyhow!("Unable to fetch commit {rev} from {url}: {err}{help}")
+        })?;
+    }
+    run(git(&dir).args([
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        &format!("{rev}^"),
+        rev,
+    ]))
+    .map_err(|err| anyhow!("Unable to read commit {rev} of {url}: {err}"))
+}
@@ -0,0 +1,429 @@
+//! Git extended header lines like `new file mode` or `rename from`.
+//!
+//! The patch parser skips these lines in front of a file diff, but git omits
+//! the `---`/`+++` lines entirely for empty files, pure renames and mode
+//! changes. Such diffs are turned into [`HeaderOperation`]s instead.
+
+use crate::{engine::check_path, noise, permissions, PatchSource};
+#[cfg(feature = "orchestrator")]
+use crate::{porcelain, status};
+use anyhow::Result;
+use std::{fs, path::Path};
+
+/// File operation described by the extended header lines of a git diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderOperation<'a> {
+    /// Create an empty file.
+    Create { path: &'a str },
+    /// Delete a file.
+    Delete { path: &'a str },
+    /// Move a file.
+    Rename { from: &'a str, to: &'a str },
+    /// Copy a file.
+    Copy { from: &'a str, to: &'a str },
+    /// Change the permissions of a file.
+    Mode { path: &'a str, mode: u32 },
+}
+
+/// Returns the old and new path of a `diff --git <old> <new>` line. Both
+/// are expected to have the same length, which holds unless the file is
+/// renamed or copied. Those carry their paths in extra header lines.
+fn diff_paths<'a>(
+    line: &'a str,
+    source: &PatchSource,
+) -> Option<(&'a str, &'a str)> {
+    let paths = line.strip_prefix("diff --git ")?;
+    let mid = paths.len() / 2;
+    if paths.len() % 2 == 0 || paths.as_bytes().get(mid) != Some(&b' ') {
+        return None;
+    }
+    let (old, new) = (paths.get(..mid)?, paths.get(mid + 1..)?);
+    Some(match source {
+        PatchSource::Default => (old, new),
+        PatchSource::GithubPrDiff | PatchSource::GitDiff => (
+            old.strip_prefix("a/").unwrap_or(old),
+            new.strip_prefix("b/").unwrap_or(new),
+        ),
+    })
+}
+
+fn mode(value: &str) -> Option<u32> {
+    u32::from_str_radix(value.trim(), 8).ok()
+}
+
+/// Collects the operations of the header lines of a single file diff.
+fn block_operations<'a>(
+    block: &'a str,
+    source: &PatchSource,
+    has_content: bool,
+    operations: &mut Vec<HeaderOperation<'a>>,
+) {
+    let mut lines = block.lines();
+    let (old, new) = lines
+        .next()
+        .and_then(|line| diff_paths(line, source))
+        .map_or((None, None), |(old, new)| (Some(old), Some(new)));
+    let mut rename = (None, None);
+    let mut copy = (None, None);
+    let mut new_mode = None;
+    let mut created = false;
+    let mut deleted = false;
+    for line in lines.take_while(|line| !line.starts_with("--- ")) {
+        if let Some(path) = line.strip_prefix("rename from ") {
+            rename.0 = Some(path);
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            rename.1 = Some(path);
+        } else if let Some(path) = line.strip_prefix("copy from ") {
+            copy.0 = Some(path);
+        } else if let Some(path) = line.strip_prefix("copy to ") {
+            copy.1 = Some(path);
+        } else if let Some(value) = line.strip_prefix("new mode ") {
+            new_mode = mode(value);
+        } else if let Some(value) = line.strip_prefix("new file mode ") {
+            created = true;
+            // regular files are created with the default permissions
+            new_mode = mode(value).filter(|mode| *mode != 0o100_644);
+        } else if line.starts_with("deleted file mode ") {
+            deleted = true;
+        }
+    }
+
+    let target = match (rename, copy) {
+        ((Some(from), Some(to)), _) => {
+            if has_content {
+                // the file diff writes the new file from the old one
+                operations.push(HeaderOperation::Delete { path: from });
+            } else {
+                operations.push(HeaderOperation::Rename { from, to });
+            }
+            Some(to)
+        }
+        (_, (Some(from), Some(to))) => {
+            if !has_content {
+                operations.push(HeaderOperation::Copy { from, to });
+            }
+            Some(to)
+        }
+        _ => {
+            if !has_content {
+                if let (true, Some(path)) = (created, new) {
+                    operations.push(HeaderOperation::Create { path });
+                } else if let (true, Some(path)) = (deleted, old) {
+                    operations.push(HeaderOperation::Delete { path });
+                }
+            }
+            new
+        }
+    };
+    if let (Some(path), Some(mode), false) = (target, new_mode, deleted) {
+        operations.push(HeaderOperation::Mode { path, mode });
+    }
+}
+
+/// Whether a line ends the diff of a mail created by `git format-patch`,
+/// either as its signature or as the start of the next mail.
+fn ends_mail(line: &str) -> bool {
+    line.trim_end_matches(['\r', '\n']) == "-- "
+        || (line.starts_with("From ") && line.contains("Mon Sep 17 00:00:00 2001"))
+}
+
+/// Returns the number of old and new lines of a `@@ -a,b +c,d @@` hunk
+/// header.
+fn hunk_counts(line: &str) -> Option<(usize, usize)> {
+    let mut ranges = line.strip_prefix("@@ -")?.split(' ');
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, count)) => count.parse().ok(),
+        None => Some(1),
+    };
+    let old = count(ranges.next()?)?;
+    let new = count(ranges.next()?.strip_prefix('+')?)?;
+    Some((old, new))
+}
+
+/// Counts `line` against the old and new lines left in the current `hunk`,
+/// or starts a new hunk at a hunk header. Returns whether the line is part
+/// of a hunk.
+fn track_hunk(line: &str, hunk: &mut (usize, usize)) -> bool {
+    let (old, new) = *hunk;
+    if (old, new) != (0, 0) {
+        let left = match line.as_bytes().first() {
+            Some(b' ') => Some((old.saturating_sub(1), new.saturating_sub(1))),
+            Some(b'-') => Some((old.saturating_sub(1), new)),
+            Some(b'+') => Some((old, new.saturating_sub(1))),
+            Some(b'\\') => Some((old, new)),
+            _ => None,
+        };
+        if let Some(left) = left {
+            *hunk = left;
+            return true;
+        }
+    }
+    *hunk = hunk_counts(line).unwrap_or_default();
+    false
+}
+
+/// Splits a patch into the part understood by the patch parser and the
+/// operations only described by git extended header lines. File diffs
+/// without `---`/`+++` lines are removed from the returned text, as are the
+/// mail headers, message and signature of `git format-patch` output, and
+/// any other text between the file diffs.
+pub fn extract<'a>(
+    data: &'a str,
+    source: &PatchSource,
+) -> (String, Vec<HeaderOperation<'a>>) {
+    let mut text = String::with_capacity(data.len());
+    let mut operations = Vec::new();
+    let mut block_start = None;
+    let mut offset = 0;
+    let mut finish_block = |start: usize, end: usize, text: &mut String| {
+        let block = &data[start..end];
+        let has_content = block.lines().any(|line| line.starts_with("--- "));
+        block_operations(block, source, has_content, &mut operations);
+        if has_content {
+            text.push_str(block);
+        }
+    };
+    let mbox = data.starts_with("From ");
+    // a removed `- ` line inside a hunk looks like a mail signature
+    let mut hunk = (0, 0);
+    for line in data.split_inclusive('\n') {
+        let in_hunk = track_hunk(line, &mut hunk);
+        if line.starts_with("diff --git ") {
+            if let Some(start) = block_start.replace(offset) {
+                finish_block(start, offset, &mut text);
+            }
+        } else if mbox && !in_hunk && ends_mail(line) {
+            if let Some(start) = block_start.take() {
+                finish_block(start, offset, &mut text);
+            }
+        } else if block_start.is_none() && !mbox {
+            text.push_str(line);
+        }
+        offset += line.len();
+    }
+    if let Some(start) = block_start {
+        finish_block(start, data.len(), &mut text);
+    }
+    (noise::strip(&text), operations)
+}
+
+impl HeaderOperation<'_> {
+    /// Returns the file changed by the operation.
+    pub const fn file(&self) -> &str {
+        match self {
+            Self::Create { path }
+            | Self::Delete { path }
+            | Self::Mode { path, .. } => path,
+            Self::Rename { to, .. } | Self::Copy { to, .. } => to,
+        }
+    }
+
+    /// Returns the existing file the operation expects, if any.
+    #[cfg(feature = "orchestrator")]
+    pub const fn source(&self) -> Option<&str> {
+        match self {
+            Self::Create { .. } => None,
+            Self::Delete { path } | Self::Mode { path, .. } => Some(path),
+            Self::Rename { from, .. } | Self::Copy { from, .. } => Some(from),
+        }
+    }
+
+    /// Applies the operation to the dependency `name` at `base` and returns
+    /// its location for the log.
+    pub fn perform(&self, name: &str, base: &Path) -> Result<String> {
+        Ok(match self {
+            Self::Create { path } => {
+                let loc = format!("{name}: /dev/null -> {path}");
+                let path = check_path(base, path, &loc)?;
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                permissions::write(&path, "")?;
+                loc
+            }
+            Self::Delete { path } => {
+                let loc = format!("{name}: {path} -> /dev/null");
+                fs::remove_file(check_path(base, path, &loc)?)?;
+                loc
+            }
+            Self::Rename { from, to } => {
+                let loc = format!("{name}: {from} -> {to}");
+                let to_path = check_path(base, to, &loc)?;
+                if let Some(parent) = to_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(check_path(base, from, &loc)?, to_path)?;
+                loc
+            }
+            Self::Copy { from, to } => {
+                let loc = format!("{name}: {from} -> {to}");
+                let to_path = check_path(base, to, &loc)?;
+                if let Some(parent) = to_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let _ = fs::copy(check_path(base, from, &loc)?, to_path)?;
+                loc
+            }
+            Self::Mode { path, mode } => {
+                let loc = format!("{name}: {path}");
+                set_mode(&check_path(base, path, &loc)?, *mode)?;
+                loc
+            }
+        })
+    }
+
+    /// Applies the operation to the dependency at `base`.
+    #[cfg(feature = "orchestrator")]
+    pub fn apply(&self, name: &str, base: &Path) -> Result<()> {
+        let loc = self.perform(name, base)?;
+        status::status("Patched", loc);
+        porcelain::patched(name, self.file(), 0);
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract, HeaderOperation};
+    use crate::PatchSource;
+
+    #[test]
+    fn extract_header_only_diffs() {
+        let diff = "diff --git a/empty.txt b/empty.txt
+new file mode 100644
+index 0000000..e69de29
+diff --git a/old.rs b/new.rs
+similarity index 100%
+rename from old.rs
+rename to new.rs
+diff --git a/build.sh b/build.sh
+old mode 100644
+new mode 100755
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+index e69de29..0000000
+";
+        let (text, operations) = extract(diff, &PatchSource::GitDiff);
+        assert_eq!(text, "");
+        assert_eq!(
+            operations,
+            [
+                HeaderOperation::Create { path: "empty.txt" },
+                HeaderOperation::Rename {
+                    from: "old.rs",
+                    to: "new.rs"
+                },
+                HeaderOperation::Mode {
+                    path: "build.sh",
+                    mode: 0o100_755
+                },
+                HeaderOperation::Delete { path: "gone.txt" },
+            ]
+        );
+    }
+
+    #[test]
+    fn extract_keeps_file_diffs() {
+        let diff = "diff --git a/lib.rs b/lib.rs
+index 3b18e51..a3c2f3e 100644
+--- a/lib.rs
++++ b/lib.rs
+@@ -1 +1 @@
+-old
++new
+diff --git a/run.sh b/run.sh
+new file mode 100755
+index 0000000..3b18e51
+--- /dev/null
++++ b/run.sh
+@@ -0,0 +1 @@
++echo
+";
+        let (text, operations) = extract(diff, &PatchSource::GitDiff);
+        assert_eq!(
+            text,
+            "--- a/lib.rs\n+++ b/lib.rs\n@@ -1 +1 @@\n-old\n+new\n\
+             --- /dev/null\n+++ b/run.sh\n@@ -0,0 +1 @@\n+echo\n"
+        );
+        assert_eq!(
+            operations,
+            [HeaderOperation::Mode {
+                path: "run.sh",
+                mode: 0o100_755
+            }]
+        );
+    }
+
+    #[test]
+    fn extract_format_patch_mails() {
+        let diff = "From 0123456789abcdef Mon Sep 17 00:00:00 2001
+From: Jane Doe <jane@example.com>
+Subject: [PATCH 1/2] First
+---
+ lib.rs | 2 +-
+
+diff --git a/lib.rs b/lib.rs
+--- a/lib.rs
++++ b/lib.rs
+@@ -1 +1 @@
+-old
++new
+-- 
+2.44.0
+
+From fedcba9876543210 Mon Sep 17 00:00:00 2001
+Subject: [PATCH 2/2] Second
+---
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+";
+        let (text, operations) = extract(diff, &PatchSource::GitDiff);
+        assert_eq!(
+            text,
+            "--- a/lib.rs\n+++ b/lib.rs\n@@ -1 +1 @@\n-old\n+new\n"
+        );
+        assert_eq!(
+            operations,
+            [HeaderOperation::Mode {
+                path: "run.sh",
+                mode: 0o100_755
+            }]
+        );
+    }
+
+    #[test]
+    fn extract_format_patch_removing_list_items() {
+        let diff = "From 0123456789abcdef Mon Sep 17 00:00:00 2001
+From: Jane Doe <jane@example.com>
+Subject: [PATCH] Drop empty item
+---
+diff --git a/list.md b/list.md
+--- a/list.md
++++ b/list.md
+@@ -1,3 +1,2 @@
+ - first
+-- 
+ - last
+-- 
+2.44.0
+";
+        let (text, _) = extract(diff, &PatchSource::GitDiff);
+        assert_eq!(
+            text,
+            "--- a/list.md\n+++ b/list.md\n@@ -1,3 +1,2 @@\n - first\n-- \n - last\n"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Git repositories in patched copies with one commit per patch file.
+
+use anyhow::{anyhow, Result};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Identity used for commits without an author from the patch file.
+const NAME: &str = "cargo-patch";
+const EMAIL: &str = "cargo-patch@localhost";
+/// Message of the commit created by [`commit_adjustments`].
+const ADJUSTMENTS: &str = "Adjust manifest for cargo-patch";
+
+/// Author and message of a patch in `git format-patch` (mbox) format.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Mail {
+    author: Option<String>,
+    date: Option<String>,
+    subject: Option<String>,
+    body: String,
+}
+
+impl Mail {
+    /// Parses the mail headers and the message body in front of the diff.
+    /// Patches which don't start with an mbox `From ` line have neither.
+    fn parse(data: &str) -> Self {
+        let mut mail = Self::default();
+        let mut lines = data.lines();
+        if !lines.next().is_some_and(|line| line.starts_with("From ")) {
+            return mail;
+        }
+        let mut header: Option<(&str, String)> = None;
+        let mut headers = vec![];
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, value)) = &mut header {
+                    value.push_str(line);
+                }
+                continue;
+            }
+            headers.extend(header.take());
+            header = line
+                .split_once(':')
+                .map(|(key, value)| (key, value.trim().to_owned()));
+        }
+        headers.extend(header);
+        for (key, value) in headers {
+            match key {
+                "From" => mail.author = Some(value),
+                "Date" => mail.date = Some(value),
+                "Subject" => {
+                    mail.subject = Some(strip_patch_prefix(&value).to_owned())
+                }
+                _ => {}
+            }
+        }
+        mail.body = lines
+            .take_while(|line| *line != "---" && !line.starts_with("diff --git "))
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_owned();
+        mail
+    }
+}
+
+/// Strips the `[PATCH 1/2]` prefix `git format-patch` adds to subjects.
+fn strip_patch_prefix(subject: &str) -> &str {
+    subject
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+        .filter(|(prefix, _)| prefix.starts_with("PATCH"))
+        .map_or(subject, |(_, subject)| subject)
+}
+
+fn git(path: &Path) -> Command {
+    let mut command = Command::new("git");
+    let _ = command
+        .current_dir(path)
+        // keep git from treating a surrounding repository as the root
+        .env("GIT_CEILING_DIRECTORIES", path.parent().unwrap_or(path))
+        .env("GIT_COMMITTER_NAME", NAME)
+        .env("GIT_COMMITTER_EMAIL", EMAIL);
+    command
+}
+
+fn run(command: &mut Command) -> Result<String> {
+    let output = command.output().map_err(|err| {
+        anyhow!("Unable to run git, which `git-init` requires: {err}")
+    })?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Commits everything in the repository at `path`.
+fn commit_all(
+    path: &Path,
+    message: &str,
+    author: Option<&str>,
+    date: Option<&str>,
+) -> Result<()> {
+    let _ = run(git(path).args(["add", "--all"]))?;
+    let mut commit = git(path);
+    let _ = commit.args([
+        "commit",
+        "--quiet",
+        "--allow-empty",
+        "--no-verify",
+        "-m",
+        message,
+    ]);
+    let author = author.map_or_else(|| format!("{NAME} <{EMAIL}>"), str::to_owned);
+    let _ = commit.arg(format!("--author={author}"));
+    if let Some(date) = date {
+        let _ = commit.arg(format!("--date={date}"));
+    }
+    let _ = run(&mut commit)?;
+    Ok(())
+}
+
+/// Initializes a repository in the copy at `path` and commits the pristine
+/// sources.
+pub fn init(path: &Path, id: &str) -> Result<()> {
+    let _ = run(git(path).args(["init", "--quiet"]))?;
+    commit_all(path, &format!("Pristine sources of {id}"), None, None)
+}
+
+/// Commits the changes of the patch file `file` with the author, date and
+/// message of its mbox headers if present.
+pub fn commit_patch(path: &Path, file: &str, data: &str) -> Result<()> {
+    let mail = Mail::parse(data);
+    let subject = mail.subject.unwrap_or_else(|| format!("Apply {file}"));
+    let message = if mail.body.is_empty() {
+        subject
+    } else {
+        format!("{subject}\n\n{}", mail.body)
+    };
+    commit_all(path, &message, mail.author.as_deref(), mail.date.as_deref())
+}
+
+/// Commits all changes with `message` if there are any.
+pub fn commit_changes(path: &Path, message: &str) -> Result<()> {
+    if run(git(path).args(["status", "--porcelain"]))?
+        .trim()
+        .is_empty()
+    {
+        return Ok(());
+    }
+    commit_all(path, message, None, None)
+}
+
+/// Commits changes cargo-patch made after applying the patches, e.g. to the
+/// manifest, if there are any.
+pub fn commit_adjustments(path: &Path) -> Result<()> {
+    commit_changes(path, ADJUSTMENTS)
+}
+
+/// Whether tracked files in the repository at `path` have uncommitted
+/// changes.
+pub fn has_uncommitted_changes(path: &Path) -> Result<bool> {
+    let status =
+        run(git(path).args(["status", "--porcelain", "--untracked-files=no"]))?;
+    Ok(!status.trim().is_empty())
+}
+
+/// Writes every commit after the pristine sources, except the manifest
+/// adjustments, with `git format-patch` to `out`, numbered from `start`.
+/// Returns the written files.
+pub fn format_patch(path: &Path, out: &Path, start: usize) -> Result<Vec<PathBuf>> {
+    let log = run(git(path).args(["log", "--reverse", "--format=%H %s"]))?;
+    let commits = log
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(_, subject)| *subject != ADJUSTMENTS)
+        .map(|(commit, _)| commit);
+    let mut files = vec![];
+    for (number, commit) in (start..).zip(commits) {
+        let file = run(git(path)
+            .args([
+                "format-patch",
+                "-1",
+                "--zero-commit",
+                "--no-signature",
+                "--start-number",
+                &number.to_string(),
+                "-o",
+            ])
+            .arg(out)
+            .arg(commit))?;
+        files.push(PathBuf::from(file.trim()));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mail;
+
+    #[test]
+    fn parse_mbox() {
+        let data = "From 0123456789abcdef Mon Sep 17 00:00:00 2001\n\
+                    From: Jane Doe <jane@example.com>\n\
+                    Date: Fri, 1 Mar 2024 12:00:00 +0100\n\
+                    Subject: [PATCH 1/2] Fix the parser for\n \
+                    long inputs\n\
+                    \n\
+                    The parser overflowed.\n\
+                    ---\n \
+                    src/lib.rs | 2 +-\n\
+                    \n\
+                    diff --git a/src/lib.rs b/src/lib.rs\n";
+        assert_eq!(
+            Mail::parse(data),
+            Mail {
+                author: Some("Jane Doe <jane@example.com>".to_owned()),
+                date: Some("Fri, 1 Mar 2024 12:00:00 +0100".to_owned()),
+                subject: Some("Fix the parser for long inputs".to_owned()),
+                body: "The parser overflowed.".to_owned(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_plain_diff() {
+        let data = "--- src/lib.rs\n+++ src/lib.rs\n";
+        assert_eq!(Mail::parse(data), Mail::default());
+    }
+}
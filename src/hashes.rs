@@ -0,0 +1,148 @@
+//! Content hashes of the patched copies, for build systems like Bazel, Buck
+//! or Nix using them as cache keys.
+//!
+//! The hash of a copy only depends on the relative paths, contents, kinds
+//! and executable bits of its files, so it is the same on every machine.
+//! Timestamps, the provenance file and the `.git` directory of `git-init`
+//! are left out. It is the SHA-256 of one line per file, sorted by path:
+//!
+//! ```text
+//! <file|symlink> <0644|0755> <SHA-256 of the contents or link target> <path>
+//! ```
+
+use crate::{
+    orchestrator::{copy_root, PatchConfig},
+    permissions,
+    provenance::PROVENANCE_FILE,
+};
+use anyhow::Result;
+use cargo::core::Package;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+/// Version of the format of the hashes file.
+const FORMAT_VERSION: u32 = 1;
+
+fn sha256(reader: &mut impl io::Read) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let _ = io::copy(reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+const fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Adds the line of every file below `dir` to `lines`.
+fn collect(
+    root: &Path,
+    dir: &Path,
+    lines: &mut Vec<(String, String)>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let relative = path
+            .strip_prefix(root)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if relative == PROVENANCE_FILE || relative == ".git" {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        let (kind, mode, sum) = if file_type.is_symlink() {
+            let target = fs::read_link(&path)?;
+            let target = target.to_string_lossy();
+            ("symlink", "0644", sha256(&mut target.as_bytes())?)
+        } else if file_type.is_dir() {
+            collect(root, &path, lines)?;
+            continue;
+        } else {
+            let mode = if is_executable(&entry.metadata()?) {
+                "0755"
+            } else {
+                "0644"
+            };
+            ("file", mode, sha256(&mut File::open(&path)?)?)
+        };
+        lines.push((
+            relative.clone(),
+            format!("{kind} {mode} {sum} {relative}\n"),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the content hash of the copy at `root`.
+pub fn hash_dir(root: &Path) -> Result<String> {
+    let mut lines = vec![];
+    collect(root, root, &mut lines)?;
+    lines.sort();
+    let mut hasher = Sha256::new();
+    for (_, line) in lines {
+        hasher.update(line);
+    }
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
+
+/// Writes the content hash of the copy of every package to `out` as JSON.
+pub fn emit(out: &Path, packages: &[&Package], config: &PatchConfig) -> Result<()> {
+    let packages = packages
+        .iter()
+        .map(|package| {
+            let root = copy_root(package, config);
+            Ok(json!({
+                "id": package.package_id().to_spec().to_string(),
+                "name": package.name().as_str(),
+                "version": package.version().to_string(),
+                "path": root,
+                "hash": hash_dir(&root)?,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let hashes = json!({
+        "version": FORMAT_VERSION,
+        "packages": packages,
+    });
+    permissions::write(out, format!("{hashes:#}\n"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash_dir;
+    use std::fs;
+
+    #[test]
+    fn hashes_contents_not_metadata() {
+        let dir = tempfile::tempdir().expect("Unable to create dir");
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("src")).expect("Unable to create dir");
+        fs::write(dir.join("src/lib.rs"), "pub fn a() {}\n")
+            .expect("Unable to write");
+        let first = hash_dir(dir).expect("Unable to hash");
+
+        fs::write(dir.join(".cargo-patch.json"), "{}").expect("Unable to write");
+        fs::write(dir.join("src/lib.rs"), "pub fn a() {}\n")
+            .expect("Unable to write");
+        assert_eq!(hash_dir(dir).expect("Unable to hash"), first);
+
+        fs::write(dir.join("src/lib.rs"), "pub fn b() {}\n")
+            .expect("Unable to write");
+        assert_ne!(hash_dir(dir).expect("Unable to hash"), first);
+    }
+}
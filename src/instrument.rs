@@ -0,0 +1,327 @@
+//! Generating entry and exit tracing for functions of a dependency.
+
+use crate::{
+    diff::unified_diff,
+    orchestrator::{
+        apply_patch_data, existing_or_copy_package, get_config, get_id,
+        load_workspace, lock_package_cache, resolve_ws, setup_gctx, Options,
+        PatchTarget, VersionFilter,
+    },
+    overlap::History,
+    Error, FinalNewline, PatchSource,
+};
+use anyhow::{anyhow, Result};
+use semver::VersionReq;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+use syn::{
+    visit::{self, Visit},
+    Block, ImplItemFn, ItemFn, ItemImpl, ItemMod, ItemTrait, Signature, TraitItemFn,
+    Type,
+};
+
+/// How the inserted instrumentation reports entering and leaving.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstrumentStyle {
+    /// `println!`, which needs no dependencies.
+    #[default]
+    Println,
+    /// `tracing::trace!`, for dependencies already using `tracing`.
+    Tracing,
+}
+
+impl InstrumentStyle {
+    /// Returns the statement inserted at the start of the function body. A
+    /// guard reports leaving the function when it is dropped, so early
+    /// returns, `?` and panics are covered as well.
+    fn statement(self, function: &str) -> String {
+        let log = match self {
+            Self::Println => "println!",
+            Self::Tracing => "tracing::trace!",
+        };
+        format!(
+            "let _cargo_patch_guard = {{ struct Guard; impl Drop for Guard {{ \
+             fn drop(&mut self) {{ {log}(\"[cargo-patch] exit {function}\"); }} }} \
+             {log}(\"[cargo-patch] enter {function}\"); Guard }};"
+        )
+    }
+}
+
+/// A function found in the sources of a dependency.
+#[derive(Debug)]
+struct Function {
+    /// Path of the function within the crate, e.g. `de::Deserializer::new`.
+    path: Vec<String>,
+    /// Line and column, in characters, of the opening brace of the body.
+    brace: (usize, usize),
+    is_const: bool,
+}
+
+/// Collects every function with a body in a file.
+struct Collector {
+    /// Modules and the impl or trait the visitor currently is in.
+    scope: Vec<String>,
+    functions: Vec<Function>,
+}
+
+impl Collector {
+    fn add(&mut self, sig: &Signature, block: &Block) {
+        let mut path = self.scope.clone();
+        path.push(sig.ident.to_string());
+        let start = block.brace_token.span.open().start();
+        self.functions.push(Function {
+            path,
+            brace: (start.line, start.column),
+            is_const: sig.constness.is_some(),
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for Collector {
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_mod(self, item);
+        let _ = self.scope.pop();
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        let name = match &*item.self_ty {
+            Type::Path(ty) => ty.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        };
+        self.scope.push(name.unwrap_or_else(|| "_".to_owned()));
+        visit::visit_item_impl(self, item);
+        let _ = self.scope.pop();
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast ItemTrait) {
+        self.scope.push(item.ident.to_string());
+        visit::visit_item_trait(self, item);
+        let _ = self.scope.pop();
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+        self.add(&item.sig, &item.block);
+        visit::visit_item_fn(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+        self.add(&item.sig, &item.block);
+        visit::visit_impl_item_fn(self, item);
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.add(&item.sig, block);
+        }
+        visit::visit_trait_item_fn(self, item);
+    }
+}
+
+/// Returns the module path of a file below `src`, e.g. `de/mod.rs` is `de`.
+fn module_path(file: &Path) -> Vec<String> {
+    let mut path = file
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    match path.pop().as_deref() {
+        Some("lib.rs" | "main.rs" | "mod.rs") | None => {}
+        Some(file) => path.push(file.trim_end_matches(".rs").to_owned()),
+    }
+    path
+}
+
+/// Returns the Rust files below `dir` relative to it.
+fn rust_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir.join(relative))? {
+        let entry = entry?;
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            rust_files(dir, &path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the byte offset of a line and character column, both as
+/// reported by `proc-macro2` with lines starting at 1.
+fn offset(source: &str, (line, column): (usize, usize)) -> Option<usize> {
+    let start = source
+        .split_inclusive('\n')
+        .take(line.checked_sub(1)?)
+        .map(str::len)
+        .sum::<usize>();
+    let line = source.get(start..)?;
+    let (index, _) = line.char_indices().nth(column)?;
+    Some(start + index)
+}
+
+/// Finds the only function matching `function` in the sources of the copy
+/// at `path` and returns its file relative to `path`, its source and the
+/// instrumented source.
+fn instrument_source(
+    path: &Path,
+    crate_name: &str,
+    function: &str,
+    style: InstrumentStyle,
+) -> Result<(PathBuf, String, String)> {
+    let crate_name = crate_name.replace('-', "_");
+    let wanted = function
+        .split("::")
+        .filter(|segment| *segment != "crate" && *segment != crate_name)
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    let src = if path.join("src").is_dir() {
+        PathBuf::from("src")
+    } else {
+        PathBuf::new()
+    };
+    let mut files = vec![];
+    rust_files(path, &src, &mut files)?;
+    files.sort();
+
+    let mut found = vec![];
+    for file in files {
+        let source = fs::read_to_string(path.join(&file))?;
+        // skip files syn can't parse, e.g. because of unstable syntax
+        let Ok(ast) = syn::parse_file(&source) else {
+            continue;
+        };
+        let mut collector = Collector {
+            scope: module_path(file.strip_prefix(&src).unwrap_or(&file)),
+            functions: vec![],
+        };
+        collector.visit_file(&ast);
+        for candidate in collector.functions {
+            if candidate.path.ends_with(&wanted) {
+                found.push((file.clone(), source.clone(), candidate));
+            }
+        }
+    }
+
+    let (file, source, candidate) = match found.len() {
+        0 => {
+            return Err(anyhow!(
+                "Unable to find function {function} in {crate_name}"
+            ))
+        }
+        1 => found.remove(0),
+        _ => {
+            let paths = found
+                .iter()
+                .map(|(file, _, candidate)| {
+                    format!("{} in {}", candidate.path.join("::"), file.display())
+                })
+                .collect::<Vec<_>>();
+            return Err(anyhow!(
+                "Function {function} is ambiguous, use a longer path:\n    {}",
+                paths.join("\n    ")
+            ));
+        }
+    };
+    let full_path = candidate.path.join("::");
+    if candidate.is_const {
+        return Err(anyhow!("Unable to instrument const fn {full_path}"));
+    }
+    let brace = offset(&source, candidate.brace)
+        .ok_or_else(|| anyhow!("Unable to locate the body of {full_path}"))?;
+    let indent = source
+        .get(..brace)
+        .and_then(|before| before.rsplit('\n').next())
+        .map(|line| line.len() - line.trim_start().len())
+        .unwrap_or_default();
+    let mut instrumented = source.clone();
+    instrumented.insert_str(
+        brace + 1,
+        &format!(
+            "\n{:indent$}    {}",
+            "",
+            style.statement(&format!("{crate_name}::{full_path}"))
+        ),
+    );
+    Ok((file, source, instrumented))
+}
+
+/// Inserts entry and exit tracing into `function` of the patched copy of
+/// the dependency `name`, e.g. `de::Deserializer::new`.
+///
+/// A diff is generated and applied like with [`apply`](crate::apply), so the
+/// copy is created if it does not exist yet and running `cargo patch` again
+/// removes the instrumentation.
+pub fn instrument(
+    name: &str,
+    version: Option<&str>,
+    function: &str,
+    style: InstrumentStyle,
+    options: &Options,
+) -> Result<(), Error> {
+    let version = version
+        .map(VersionReq::parse)
+        .transpose()
+        .map_err(anyhow::Error::from)?
+        .map(VersionFilter::Req);
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+
+    let id = get_id(name, &version, None, &resolve).ok_or_else(|| {
+        Error::PackageNotFound {
+            name: name.to_owned(),
+        }
+    })?;
+    let package = pkg_set.get_one(id)?;
+    let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+    let path = existing_or_copy_package(package, &config)?;
+    let (file, old, new) = instrument_source(&path, name, function, style)?;
+    let label = file
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let diff = unified_diff(&label, &label, &old, &new);
+    let target = PatchTarget {
+        name,
+        path: &path,
+        use_orig_manifest: false,
+        final_newline: FinalNewline::default(),
+    };
+    let mut history = History::default();
+    let _ = apply_patch_data(
+        &target,
+        Path::new("-"),
+        &diff,
+        &PatchSource::Default,
+        &mut history,
+        options,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{module_path, offset};
+    use std::path::Path;
+
+    #[test]
+    fn module_path_of_files() {
+        assert!(module_path(Path::new("lib.rs")).is_empty());
+        assert_eq!(module_path(Path::new("de/mod.rs")), ["de"]);
+        assert_eq!(module_path(Path::new("de/value.rs")), ["de", "value"]);
+    }
+
+    #[test]
+    fn offset_counts_characters() {
+        let source = "fn a() {}\n// ä\nfn b() {}\n";
+        assert_eq!(offset(source, (1, 7)), Some(7));
+        assert_eq!(offset(source, (3, 7)), Some(23));
+        assert_eq!(offset(source, (4, 0)), None);
+    }
+}
@@ -0,0 +1,53 @@
+//! Stopping cleanly on Ctrl-C.
+//!
+//! Killing cargo-patch while it patches a package would leave a half patched
+//! copy behind, which cargo then builds. With [`handle_interrupts`] the
+//! first SIGINT or SIGTERM only sets a flag, which patching checks between
+//! its steps to remove the incomplete copy and stop with
+//! [`Error::Interrupted`]. A second signal terminates right away.
+
+use crate::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    // SAFETY: resetting the disposition is async-signal-safe
+    unsafe {
+        let _ = libc::signal(signal, libc::SIG_DFL);
+    }
+}
+
+/// Lets Ctrl-C stop patching at the next safe point instead of right away.
+/// Meant for binaries, build scripts should leave signals to cargo.
+#[cfg(unix)]
+pub fn handle_interrupts() {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only touches an atomic and is async-signal-safe
+        unsafe {
+            let _ = libc::signal(signal, handler);
+        }
+    }
+}
+
+/// Lets Ctrl-C stop patching at the next safe point instead of right away.
+/// Not supported on this platform, where Ctrl-C terminates right away.
+#[cfg(not(unix))]
+pub const fn handle_interrupts() {}
+
+/// Returns whether an interrupt was received.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Fails with [`Error::Interrupted`] if an interrupt was received.
+pub fn check() -> Result<(), Error> {
+    if interrupted() {
+        Err(Error::Interrupted)
+    } else {
+        Ok(())
+    }
+}
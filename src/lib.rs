@@ -54,578 +54,155 @@
 
 #![deny(clippy::all, clippy::nursery)]
 #![deny(nonstandard_style, rust_2018_idioms)]
-
-use anyhow::{anyhow, Result};
-use cargo::{
-    core::{
-        package::{Package, PackageSet},
-        registry::PackageRegistry,
-        resolver::{features::CliFeatures, HasDevUnits},
-        shell::Verbosity,
-        PackageId, Resolve, Workspace,
-    },
-    ops::{get_resolved_packages, load_pkg_lockfile, resolve_with_previous},
-    util::important_paths::find_root_manifest_for_wd,
-    GlobalContext,
+// `Option::is_none_or` requires Rust 1.82
+#![allow(clippy::unnecessary_map_or)]
+
+#[cfg(feature = "orchestrator")]
+mod apply;
+#[cfg(feature = "orchestrator")]
+mod backend;
+#[cfg(feature = "orchestrator")]
+mod build_surface;
+#[cfg(feature = "orchestrator")]
+mod checkout;
+#[cfg(feature = "orchestrator")]
+mod ci;
+#[cfg(feature = "orchestrator")]
+mod copy_ignore;
+mod diff;
+mod engine;
+mod error;
+#[cfg(feature = "orchestrator")]
+mod event;
+#[cfg(feature = "orchestrator")]
+mod export;
+#[cfg(feature = "orchestrator")]
+mod git_commit;
+mod git_header;
+#[cfg(feature = "orchestrator")]
+mod git_repo;
+#[cfg(feature = "orchestrator")]
+mod hashes;
+#[cfg(feature = "orchestrator")]
+mod instrument;
+#[cfg(feature = "orchestrator")]
+mod interrupt;
+#[cfg(feature = "orchestrator")]
+mod lockfile;
+#[cfg(any(feature = "orchestrator", feature = "metadata-backend"))]
+mod long_path;
+#[cfg(feature = "metadata-backend")]
+mod metadata;
+#[cfg(feature = "orchestrator")]
+mod missing;
+#[cfg(feature = "orchestrator")]
+mod mtime;
+mod noise;
+#[cfg(feature = "orchestrator")]
+mod notes;
+#[cfg(feature = "orchestrator")]
+mod open;
+// The items of the orchestrator are shared with its sibling modules but are
+// not part of the API, `pub(crate)` says so explicitly.
+#[allow(clippy::redundant_pub_crate)]
+#[cfg(feature = "orchestrator")]
+mod orchestrator;
+#[cfg(feature = "orchestrator")]
+mod order;
+#[cfg(feature = "orchestrator")]
+mod overlap;
+#[cfg(feature = "orchestrator")]
+mod packaging;
+mod patched_file;
+#[cfg(feature = "orchestrator")]
+mod paths;
+mod permissions;
+#[cfg(feature = "orchestrator")]
+mod porcelain;
+#[cfg(feature = "orchestrator")]
+mod pristine;
+#[cfg(feature = "orchestrator")]
+mod provenance;
+#[cfg(feature = "orchestrator")]
+mod rebase;
+#[cfg(feature = "orchestrator")]
+mod recovery;
+#[cfg(feature = "orchestrator")]
+mod remote;
+#[cfg(feature = "orchestrator")]
+mod report;
+#[cfg(feature = "orchestrator")]
+mod sbom;
+#[cfg(feature = "orchestrator")]
+mod sed;
+#[cfg(feature = "orchestrator")]
+mod selftest;
+#[cfg(feature = "orchestrator")]
+mod signature;
+#[cfg(feature = "orchestrator")]
+mod snippet;
+#[cfg(feature = "orchestrator")]
+mod status;
+#[cfg(feature = "orchestrator")]
+mod targets;
+#[cfg(feature = "orchestrator")]
+mod test_build;
+#[cfg(feature = "orchestrator")]
+mod timings;
+#[cfg(feature = "orchestrator")]
+mod transform;
+#[cfg(feature = "orchestrator")]
+mod unused;
+#[cfg(feature = "orchestrator")]
+mod upstream;
+#[cfg(feature = "orchestrator")]
+mod verify;
+#[cfg(feature = "orchestrator")]
+mod watch;
+
+#[cfg(feature = "orchestrator")]
+pub use apply::apply;
+pub use diff::diff_dirs;
+pub use engine::{
+    apply_diff, apply_to_dir, FinalNewline, PatchSource, PatchSpec, Report,
 };
-
-use cargo::sources::SourceConfigMap;
-use cargo::util::cache_lock::CacheLockMode::DownloadExclusive;
-use fs_extra::dir::{copy, CopyOptions};
-use patch::{Line, Patch};
-use semver::VersionReq;
-use std::fmt::{Display, Formatter};
-use std::{
-    fs,
-    io::ErrorKind,
-    path::{Path, PathBuf},
+pub use error::Error;
+#[cfg(feature = "orchestrator")]
+pub use event::{Event, Patcher};
+#[cfg(feature = "orchestrator")]
+pub use export::export;
+#[cfg(feature = "orchestrator")]
+pub use instrument::{instrument, InstrumentStyle};
+#[cfg(feature = "orchestrator")]
+pub use interrupt::handle_interrupts;
+#[cfg(feature = "metadata-backend")]
+pub use metadata::{patch_from_metadata, MetadataOptions, MetadataSource};
+#[cfg(feature = "orchestrator")]
+pub use open::open;
+#[cfg(feature = "orchestrator")]
+pub use orchestrator::{
+    patch, patch_with_config, patch_with_options, patch_workspace, Options,
 };
-use toml::Value;
-
-#[derive(Debug, Clone, Default)]
-enum PatchSource {
-    #[default]
-    Default,
-    GithubPrDiff,
-}
-
-#[derive(Debug, Clone)]
-struct PatchItem<'a> {
-    path: &'a Path,
-    source: PatchSource,
-}
-
-#[derive(Debug, Clone)]
-struct PatchEntry<'a> {
-    name: &'a str,
-    version: Option<VersionReq>,
-    patches: Vec<PatchItem<'a>>,
-}
-
-#[derive(Debug)]
-struct PatchFailed {
-    line: u64,
-    file: PathBuf,
-}
-
-#[derive(Debug, Eq, PartialEq)]
-enum PatchType {
-    Modify,
-    Create,
-    Delete,
-}
-
-impl PatchSource {
-    fn from_str(s: &str) -> Self {
-        match s {
-            "Default" => Self::Default,
-            "GithubPrDiff" => Self::GithubPrDiff,
-            &_ => {
-                eprintln!("Unknown patch source: {s}");
-                Self::Default
-            }
-        }
-    }
-}
-
-impl std::error::Error for PatchFailed {}
-
-impl Display for PatchFailed {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "failed to apply patch to {} on line {}",
-            self.file.display(),
-            self.line + 1
-        )
-    }
-}
-
-#[allow(clippy::wildcard_enum_match_arm)]
-fn clear_patch_folder() -> Result<()> {
-    match fs::remove_dir_all("target/patch") {
-        Ok(_) => Ok(()),
-        Err(err) => match err.kind() {
-            ErrorKind::NotFound => Ok(()),
-            _ => Err(err.into()),
-        },
-    }
-}
-
-fn setup_gctx() -> Result<GlobalContext> {
-    let gctx = GlobalContext::default()?;
-    gctx.shell().set_verbosity(Verbosity::Quiet);
-    Ok(gctx)
-}
-
-fn find_cargo_toml(path: &Path) -> Result<PathBuf> {
-    let path = fs::canonicalize(path)?;
-    find_root_manifest_for_wd(&path)
-}
-
-fn fetch_workspace<'gctx>(
-    gctx: &'gctx GlobalContext,
-    path: &Path,
-) -> Result<Workspace<'gctx>> {
-    Workspace::new(path, gctx)
-}
-
-fn resolve_ws<'a>(ws: &Workspace<'a>) -> Result<(PackageSet<'a>, Resolve)> {
-    let scm = SourceConfigMap::new(ws.gctx())?;
-    let mut registry = PackageRegistry::new_with_source_config(ws.gctx(), scm)?;
-
-    registry.lock_patches();
-    let resolve = {
-        let prev = load_pkg_lockfile(ws)?;
-        let resolve: Resolve = resolve_with_previous(
-            &mut registry,
-            ws,
-            &CliFeatures::new_all(true),
-            HasDevUnits::No,
-            prev.as_ref(),
-            None,
-            &[],
-            false,
-        )?;
-        resolve
-    };
-    let packages = get_resolved_packages(&resolve, registry)?;
-    Ok((packages, resolve))
-}
-
-fn get_patches(
-    custom_metadata: &Value,
-) -> impl Iterator<Item = PatchEntry<'_>> + '_ {
-    custom_metadata
-        .as_table()
-        .and_then(|table| table.get("patch"))
-        .into_iter()
-        .flat_map(|patch| patch.as_table().into_iter())
-        .flat_map(|table| {
-            table
-                .into_iter()
-                .filter_map(|(k, v)| parse_patch_entry(k, v))
-        })
-}
-
-fn parse_patch_entry<'a>(name: &'a str, entry: &'a Value) -> Option<PatchEntry<'a>> {
-    let entry = entry.as_table().or_else(|| {
-        eprintln!("Entry {name} must contain a table.");
-        None
-    })?;
-
-    let version = entry.get("version").and_then(|version| {
-        let value = version.as_str().and_then(|s| VersionReq::parse(s).ok());
-        if value.is_none() {
-            eprintln!("Version must be a value semver string: {version}");
-        }
-        value
-    });
-
-    let patches = entry
-        .get("patches")
-        .and_then(Value::as_array)
-        .into_iter()
-        .flat_map(|patches| {
-            patches.iter().flat_map(|patch| {
-                let item = if patch.is_str() {
-                    Some((patch.as_str(), Default::default()))
-                } else {
-                    patch.as_table().map(
-                        |it| (
-                            it.get("path").and_then(Value::as_str),
-                            it.get("source").and_then(Value::as_str)
-                              .map_or_else(Default::default, PatchSource::from_str)
-                        ))
-                };
-
-                let (path, source) = if let Some(item) = item {item } else {
-                    eprintln!("Patch Entry must be a string or a table with path and source: {patch}");
-                    return None;
-                };
-
-                let path = path.map(Path::new);
-                let path = if let Some(path) = path {
-                    path
-                } else {
-                    eprintln!("Patch Entry must be a string or a table with path and source: {patch}");
-                    return None;
-                };
-
-                Some(PatchItem {
-                    path,
-                    source,
-                })
-            })
-        })
-        .collect();
-
-    Some(PatchEntry {
-        name,
-        version,
-        patches,
-    })
-}
-
-fn get_id(
-    name: &str,
-    version: &Option<VersionReq>,
-    resolve: &Resolve,
-) -> Option<PackageId> {
-    let mut matched_dep = None;
-    for dep in resolve.iter() {
-        if dep.name().as_str() == name
-            && version
-                .as_ref()
-                .map_or(true, |ver| ver.matches(dep.version()))
-        {
-            if matched_dep.is_none() {
-                matched_dep = Some(dep);
-            } else {
-                eprintln!("There are multiple versions of {name} available. Try specifying a version.");
-            }
-        }
-    }
-    if matched_dep.is_none() {
-        eprintln!("Unable to find package {name} in dependencies");
-    }
-    matched_dep
-}
-
-fn copy_package(pkg: &Package) -> Result<PathBuf> {
-    fs::create_dir_all("target/patch/")?;
-    let options = CopyOptions::new();
-    let _ = copy(pkg.root(), "target/patch/", &options)?;
-    if let Some(name) = pkg.root().file_name() {
-        let buf = PathBuf::from("target/patch/");
-        let buf = buf.join(name).canonicalize()?;
-        Ok(buf)
-    } else {
-        Err(anyhow!("Dependency Folder does not have a name"))
-    }
-}
-
-fn do_patch(
-    diff: Patch<'_>,
-    old_path: Option<PathBuf>,
-    new_path: Option<PathBuf>,
-) -> Result<PatchType> {
-    // delete
-    if new_path.is_none() {
-        if let Some(old) = old_path {
-            fs::remove_file(old)?;
-            return Ok(PatchType::Delete);
-        }
-        return Err(anyhow!("Both old and new file are all empty."));
-    }
-    let new_path = new_path.unwrap();
-
-    let (old_data, patch_type) = if let Some(old) = old_path {
-        // modify
-        (fs::read_to_string(old)?, PatchType::Modify)
-    } else {
-        // create
-        ("".to_string(), PatchType::Create)
-    };
-
-    let data =
-        apply_patch(diff, &old_data).map_err(|line| PatchFailed {
-            file: PathBuf::from(new_path.to_owned().file_name().map_or_else(
-                || "".to_string(),
-                |it| it.to_string_lossy().to_string(),
-            )),
-            line,
-        })?;
-
-    if patch_type == PatchType::Create {
-        if let Some(parent) = new_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-    }
-    fs::write(&new_path, data)?;
-
-    Ok(patch_type)
-}
-
-fn apply_patches<'a>(
-    name: &str,
-    patches: impl Iterator<Item = PatchItem<'a>> + 'a,
-    path: &Path,
-) -> Result<()> {
-    for PatchItem {
-        path: patch,
-        source,
-    } in patches
-    {
-        let data = read_to_string(patch)?;
-        let patches = Patch::from_multiple(&data)
-            .map_err(|_| anyhow!("Unable to parse patch file"))?;
-        for patch in patches {
-            fn check_path<P: AsRef<Path>>(
-                base: &Path,
-                path: P,
-                loc: &str,
-            ) -> Result<PathBuf> {
-                let path = base.join(path);
-                let canonicalize_result = path.canonicalize();
-
-                if canonicalize_result.is_err()
-                    && path.to_string_lossy().contains("..")
-                {
-                    return Err(anyhow!(
-                        "Failed to canonicalize path and the path has .. in it. ({loc})",
-                    ));
-                } else if canonicalize_result.is_err() {
-                    return Ok(path);
-                }
-
-                if canonicalize_result?.strip_prefix(base).is_err() {
-                    return Err(anyhow!(
-                        "Patch file tried to escape dependency folder ({loc})",
-                    ));
-                }
-
-                Ok(path)
-            }
-
-            let (old_path, new_path) = match source {
-                PatchSource::Default => {
-                    (patch.old.path.as_ref(), patch.new.path.as_ref())
-                }
-                PatchSource::GithubPrDiff => (
-                    patch
-                        .old
-                        .path
-                        .strip_prefix("a/")
-                        .unwrap_or_else(|| patch.old.path.as_ref()),
-                    patch
-                        .new
-                        .path
-                        .strip_prefix("b/")
-                        .unwrap_or_else(|| patch.new.path.as_ref()),
-                ),
-            };
-
-            let loc = format!("{name}: {old_path} -> {new_path}");
-            let loc_simple = format!("{name}: {old_path}");
-
-            let new_file_path = check_path(path, new_path, &loc);
-            let old_file_path = check_path(path, old_path, &loc);
-
-            let new_file_path = if patch.new.path == "/dev/null" {
-                None
-            } else {
-                Some(new_file_path?)
-            };
-            let old_file_path = if patch.old.path == "/dev/null" {
-                None
-            } else {
-                Some(old_file_path?)
-            };
-
-            let patch_type = do_patch(patch, old_file_path, new_file_path)?;
-
-            let loc = match patch_type {
-                PatchType::Modify => loc_simple,
-                PatchType::Create | PatchType::Delete => loc,
-            };
-            println!("Patched {loc}");
-        }
-    }
-    Ok(())
-}
-
-/// Apply a patch to the given text.
-/// If the apply fails (i.e. due to mismatch in context lines), returns an Err with the line number
-/// it failed on (0-based).
-#[allow(
-    clippy::as_conversions,
-    clippy::indexing_slicing,
-    clippy::cast_possible_truncation
-)]
-fn apply_patch(diff: Patch<'_>, old: &str) -> Result<String, u64> {
-    let old_lines = old.lines().collect::<Vec<&str>>();
-    let mut out: Vec<&str> = vec![];
-    let mut old_line = 0;
-    for hunk in diff.hunks {
-        while hunk.old_range.start != 0 && old_line < hunk.old_range.start - 1 {
-            out.push(old_lines[old_line as usize]);
-            old_line += 1;
-        }
-        for line in hunk.lines {
-            match line {
-                Line::Context(line) => {
-                    let old = old_lines.get(old_line as usize);
-                    if old != Some(&line) {
-                        return Err(old_line);
-                    }
-                    if (old_line as usize) < old_lines.len() {
-                        out.push(line);
-                    }
-                    old_line += 1;
-                }
-                Line::Add(s) => out.push(s),
-                Line::Remove(line) => {
-                    if old_lines[old_line as usize] != line {
-                        return Err(old_line);
-                    }
-                    old_line += 1;
-                }
-            }
-        }
-    }
-    for line in old_lines.get((old_line as usize)..).unwrap_or(&[]) {
-        out.push(line);
-    }
-    if old.ends_with('\n') {
-        out.push("");
-    }
-    Ok(out.join("\n"))
-}
-
-#[allow(clippy::wildcard_enum_match_arm)]
-fn read_to_string(path: &Path) -> Result<String> {
-    match fs::read_to_string(path) {
-        Ok(data) => Ok(data),
-        Err(err) => match err.kind() {
-            ErrorKind::NotFound => {
-                Err(anyhow!("Unable to find patch file with path: {:?}", path))
-            }
-            _ => Err(err.into()),
-        },
-    }
-}
-
-pub fn patch() -> Result<()> {
-    clear_patch_folder()?;
-    let gctx = setup_gctx()?;
-    let _lock = gctx.acquire_package_cache_lock(DownloadExclusive)?;
-    let workspace_path = find_cargo_toml(&PathBuf::from("."))?;
-    let workspace = fetch_workspace(&gctx, &workspace_path)?;
-    let (pkg_set, resolve) = resolve_ws(&workspace)?;
-
-    let custom_metadata = workspace.custom_metadata().into_iter().chain(
-        workspace
-            .members()
-            .flat_map(|member| member.manifest().custom_metadata()),
-    );
-
-    let patches = custom_metadata.flat_map(get_patches);
-    let ids = patches.flat_map(|patch| {
-        get_id(patch.name, &patch.version, &resolve).map(|id| (patch, id))
-    });
-
-    let mut patched = false;
-
-    for (patch, id) in ids {
-        let package = pkg_set.get_one(id)?;
-        let path = copy_package(package)?;
-        patched = true;
-        apply_patches(patch.name, patch.patches.into_iter(), &path)?;
-    }
-
-    if !patched {
-        println!("No patches found");
-    }
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::apply_patch;
-    use patch::Patch;
-
-    #[test]
-    fn apply_patch_simply() {
-        let patch = r#"--- test	2020-05-21 08:50:06.629765310 +0200
-+++ test	2020-05-21 08:50:19.689878523 +0200
-@@ -1,6 +1,6 @@
- This is the first line
- 
--This is the second line
-+This is the patched line
- 
- This is the third line
-"#;
-        let content = r#"This is the first line
-
-This is the second line
-
-This is the third line
-"#;
-        let patched = r#"This is the first line
-
-This is the patched line
-
-This is the third line
-"#;
-        let patch = Patch::from_single(patch).expect("Unable to parse patch");
-        let test_patched =
-            apply_patch(patch, content).expect("Failed to apply patch");
-        assert_eq!(patched, test_patched, "Patched content does not match");
-    }
-
-    #[test]
-    fn apply_patch_middle() {
-        let patch = r#"--- test1	2020-05-22 17:30:38.119170176 +0200
-+++ test2	2020-05-22 17:30:48.905935473 +0200
-@@ -2,8 +2,7 @@
- adipiscing elit, sed do eiusmod tempor 
- incididunt ut labore et dolore magna 
- aliqua. Ut enim ad minim veniam, quis 
--nostrud exercitation ullamco laboris 
--nisi ut aliquip ex ea commodo consequat. 
-+PATCHED
- Duis aute irure dolor in reprehenderit 
- in voluptate velit esse cillum dolore 
- eu fugiat nulla pariatur. Excepteur sint 
-"#;
-        let content = r#"Lorem ipsum dolor sit amet, consectetur 
-adipiscing elit, sed do eiusmod tempor 
-incididunt ut labore et dolore magna 
-aliqua. Ut enim ad minim veniam, quis 
-nostrud exercitation ullamco laboris 
-nisi ut aliquip ex ea commodo consequat. 
-Duis aute irure dolor in reprehenderit 
-in voluptate velit esse cillum dolore 
-eu fugiat nulla pariatur. Excepteur sint 
-occaecat cupidatat non proident, sunt in 
-culpa qui officia deserunt mollit anim 
-id est laborum.
-"#;
-        let patched = r#"Lorem ipsum dolor sit amet, consectetur 
-adipiscing elit, sed do eiusmod tempor 
-incididunt ut labore et dolore magna 
-aliqua. Ut enim ad minim veniam, quis 
-PATCHED
-Duis aute irure dolor in reprehenderit 
-in voluptate velit esse cillum dolore 
-eu fugiat nulla pariatur. Excepteur sint 
-occaecat cupidatat non proident, sunt in 
-culpa qui officia deserunt mollit anim 
-id est laborum.
-"#;
-        let patch = Patch::from_single(patch).expect("Unable to parse patch");
-        let test_patched =
-            apply_patch(patch, content).expect("Failed to apply patch");
-        assert_eq!(patched, test_patched, "Patched content does not match");
-    }
-
-    #[test]
-    fn apply_patch_no_context_override() {
-        let patch = r#"--- test        2020-06-06 10:06:44.375560000 +0200
-+++ test2       2020-06-06 10:06:49.245635957 +0200
-@@ -1,3 +1,3 @@
- test5
--test2
-+test4
- test3
-"#;
-        let content = r#"test1
-test2
-test3
-"#;
-        let patch = Patch::from_single(patch).expect("Unable to parse patch");
-        assert_eq!(apply_patch(patch, content), Err(0)); // first line context doesn't match
-    }
-}
+#[cfg(feature = "orchestrator")]
+pub use paths::{paths, OverridePathStyle, PathsFormat};
+#[cfg(feature = "orchestrator")]
+pub use rebase::rebase;
+#[cfg(feature = "orchestrator")]
+pub use report::{report, ReportFormat};
+#[cfg(feature = "orchestrator")]
+pub use sbom::{sbom, SbomFormat};
+#[cfg(feature = "orchestrator")]
+pub use selftest::selftest;
+#[cfg(feature = "orchestrator")]
+pub use status::OutputStyle;
+#[cfg(feature = "orchestrator")]
+pub use timings::TimingsFormat;
+#[cfg(feature = "orchestrator")]
+pub use verify::verify;
+
+/// Environment variable selecting the profile if `--profile` isn't given.
+#[cfg(any(feature = "orchestrator", feature = "metadata-backend"))]
+const PROFILE_ENV: &str = "CARGO_PATCH_PROFILE";
+/// Profile used if none is selected, matching cargo's default.
+#[cfg(any(feature = "orchestrator", feature = "metadata-backend"))]
+const DEFAULT_PROFILE: &str = "dev";
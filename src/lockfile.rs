@@ -0,0 +1,223 @@
+//! `cargo-patch.lock`, recording which packages are patched with which
+//! steps and patch files.
+//!
+//! Committed next to `Cargo.lock`, changes of the patch configuration or the
+//! patch files show up in reviews, and `--locked-metadata` rejects them in
+//! CI until the lock is written again with `--write-lock`.
+
+use crate::{
+    orchestrator::{read_patch_bytes, PatchConfig, PatchEntry},
+    permissions, status,
+};
+use anyhow::{anyhow, Context, Result};
+use cargo::core::PackageId;
+use sha2::{Digest, Sha256};
+use std::fs;
+use toml::{Table, Value};
+
+/// Name of the lock in the workspace root.
+pub const LOCK_FILE: &str = "cargo-patch.lock";
+
+/// Version of the format of the lock.
+const FORMAT_VERSION: i64 = 1;
+
+const HEADER: &str = "\
+# This file is written by `cargo patch --write-lock` and checked by
+# `cargo patch --locked-metadata`. It is not meant to be edited by hand.
+";
+
+/// Returns the lock of the packages about to be patched. Remote patch files
+/// are fetched, or taken from the cache, to hash them.
+pub fn generate(
+    ids: &[(PatchEntry<'_>, PackageId)],
+    config: &PatchConfig,
+) -> Result<Table> {
+    let mut packages = ids
+        .iter()
+        .map(|(patch, id)| {
+            let patches = patch
+                .patches
+                .iter()
+                .map(|item| {
+                    let (_, data) = read_patch_bytes(item, config)?;
+                    let file = item.label(&config.root);
+                    let mut table = Table::new();
+                    let _ = table.insert("file".to_owned(), Value::String(file));
+                    let _ = table.insert(
+                        "sha256".to_owned(),
+                        Value::String(format!("{:x}", Sha256::digest(&data))),
+                    );
+                    Ok(Value::Table(table))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let steps = patch
+                .steps(&config.root)
+                .into_iter()
+                .map(Value::String)
+                .collect();
+            let mut table = Table::new();
+            let _ = table
+                .insert("name".to_owned(), Value::String(id.name().to_string()));
+            let _ = table.insert(
+                "version".to_owned(),
+                Value::String(id.version().to_string()),
+            );
+            let _ = table.insert(
+                "source".to_owned(),
+                Value::String(id.source_id().as_url().to_string()),
+            );
+            let _ = table.insert("steps".to_owned(), Value::Array(steps));
+            let _ = table.insert("patches".to_owned(), Value::Array(patches));
+            Ok(table)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    packages.sort_by_key(label);
+    let mut lock = Table::new();
+    let _ = lock.insert("version".to_owned(), Value::Integer(FORMAT_VERSION));
+    let _ = lock.insert(
+        "package".to_owned(),
+        Value::Array(packages.into_iter().map(Value::Table).collect()),
+    );
+    Ok(lock)
+}
+
+/// Writes `lock` to [`LOCK_FILE`] in the workspace root.
+pub fn write(lock: &Table, config: &PatchConfig) -> Result<()> {
+    let path = config.root.join(LOCK_FILE);
+    permissions::write(&path, format!("{HEADER}{}", toml::to_string(lock)?))?;
+    let count = lock
+        .get("package")
+        .and_then(Value::as_array)
+        .map_or(0, Vec::len);
+    status::status("Locked", format!("{count} package(s) in {LOCK_FILE}"));
+    Ok(())
+}
+
+/// Returns `name vversion` of a package table of the lock.
+fn label(package: &Table) -> String {
+    let field = |key| package.get(key).and_then(Value::as_str).unwrap_or_default();
+    format!("{} v{}", field("name"), field("version"))
+}
+
+fn packages(lock: &Table) -> Vec<&Table> {
+    lock.get("package")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_table)
+        .collect()
+}
+
+/// Returns the files and hashes of the patches of a package table.
+fn hashes(package: &Table) -> Vec<(&str, &str)> {
+    package
+        .get("patches")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|patch| {
+            Some((patch.get("file")?.as_str()?, patch.get("sha256")?.as_str()?))
+        })
+        .collect()
+}
+
+/// Returns the differences of the package tables `new` and `old` of the
+/// same package.
+fn package_changes(new: &Table, old: &Table) -> Vec<String> {
+    let label = label(new);
+    let mut changes = vec![];
+    if new.get("source") != old.get("source") {
+        changes.push(format!("{label} comes from another source"));
+    }
+    if new.get("steps") != old.get("steps") {
+        changes.push(format!("{label} is patched with other steps"));
+    }
+    let old_hashes = hashes(old);
+    for (file, hash) in hashes(new) {
+        if old_hashes
+            .iter()
+            .any(|(old_file, old_hash)| *old_file == file && *old_hash != hash)
+        {
+            changes.push(format!("{file} of {label} changed"));
+        }
+    }
+    changes
+}
+
+/// Fails if `lock` differs from [`LOCK_FILE`] in the workspace root, listing
+/// every difference.
+pub fn check(lock: &Table, config: &PatchConfig) -> Result<()> {
+    let path = config.root.join(LOCK_FILE);
+    let data = fs::read_to_string(&path).with_context(|| {
+        format!("Unable to read {LOCK_FILE}, run `cargo patch --write-lock` to create it")
+    })?;
+    let locked = data
+        .parse::<Table>()
+        .with_context(|| format!("Invalid {}", path.display()))?;
+    if locked.get("version").and_then(Value::as_integer) != Some(FORMAT_VERSION) {
+        return Err(anyhow!(
+            "Unsupported version of {LOCK_FILE}, run `cargo patch --write-lock` to update it"
+        ));
+    }
+    let (new, old) = (packages(lock), packages(&locked));
+    let mut changes = vec![];
+    for package in &new {
+        match old.iter().find(|old| label(old) == label(package)) {
+            Some(old) => changes.extend(package_changes(package, old)),
+            None => changes.push(format!("{} isn't locked", label(package))),
+        }
+    }
+    for package in &old {
+        if !new.iter().any(|new| label(new) == label(package)) {
+            changes.push(format!("{} is locked but not patched", label(package)));
+        }
+    }
+    if changes.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "The patches differ from {LOCK_FILE}:\n    {}\nRun `cargo patch --write-lock` to update it",
+        changes.join("\n    ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{package_changes, packages};
+    use toml::Table;
+
+    #[test]
+    fn lists_package_changes() {
+        let lock = |hash: &str, step: &str| {
+            format!(
+                r#"
+version = 1
+
+[[package]]
+name = "serde"
+version = "1.0.110"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+steps = ["{step}"]
+
+[[package.patches]]
+file = "patches/serde.patch"
+sha256 = "{hash}"
+"#
+            )
+            .parse::<Table>()
+            .expect("Invalid lock")
+        };
+        let old = lock("aa", "patches/serde.patch");
+        let new = lock("bb", "patches/serde.patch");
+        assert_eq!(
+            package_changes(packages(&new)[0], packages(&old)[0]),
+            ["patches/serde.patch of serde v1.0.110 changed"]
+        );
+        let moved = lock("aa", "sed `a` in *.rs");
+        assert_eq!(
+            package_changes(packages(&moved)[0], packages(&old)[0]),
+            ["serde v1.0.110 is patched with other steps"]
+        );
+        assert!(package_changes(packages(&old)[0], packages(&old)[0]).is_empty());
+    }
+}
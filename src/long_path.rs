@@ -0,0 +1,43 @@
+//! Extended-length paths on Windows.
+//!
+//! Copies of deeply nested crates easily exceed `MAX_PATH` (260 characters)
+//! below `target/patch`. Windows only lifts this limit for absolute paths
+//! with the `\\?\` prefix, which are passed to every file operation on the
+//! copies. Paths shown to the user stay in their short form.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Returns the absolute, extended-length form of `path`. Paths which
+/// already have a verbatim prefix or aren't valid unicode are returned as
+/// they are.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> io::Result<PathBuf> {
+    use std::path::{Component, Prefix};
+
+    let path = std::path::absolute(path)?;
+    let Some(text) = path.to_str() else {
+        return Ok(path);
+    };
+    #[allow(clippy::wildcard_enum_match_arm)]
+    let long = match path.components().next() {
+        Some(Component::Prefix(prefix)) => match prefix.kind() {
+            Prefix::Disk(_) => format!(r"\\?\{text}"),
+            Prefix::UNC(..) => match text.strip_prefix(r"\\") {
+                Some(rest) => format!(r"\\?\UNC\{rest}"),
+                None => return Ok(path),
+            },
+            _ => return Ok(path),
+        },
+        _ => return Ok(path),
+    };
+    Ok(PathBuf::from(long))
+}
+
+/// Returns `path` unchanged as only Windows limits the length of paths.
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> io::Result<PathBuf> {
+    Ok(path.to_path_buf())
+}
@@ -1,3 +1,706 @@
+use anyhow::Context;
+use cargo_patch::{
+    Error, InstrumentStyle, Options, OutputStyle, OverridePathStyle, PatchSource,
+    PathsFormat, ReportFormat, SbomFormat, TimingsFormat,
+};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+/// Exit code after Ctrl-C, like shells report a process killed by SIGINT.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+fn cli() -> Command {
+    metadata_arg(Command::new("cargo-patch")
+        .bin_name("cargo patch")
+        .about("Patch dependencies using patch files")
+        .arg(
+            Arg::new("restrict")
+                .long("restrict")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Only read patch files from the allowed patch roots and never access the network"),
+        )
+        .arg(
+            Arg::new("strict")
+                .long("strict")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Turn warnings about risky patches and unused configuration into errors"),
+        )
+        .arg(
+            Arg::new("show-diff")
+                .long("show-diff")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Print the changes of every patched file before writing it"),
+        )
+        .arg(
+            Arg::new("no-network")
+                .long("no-network")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Never access the network"),
+        )
+        .arg(
+            Arg::new("refresh-patches")
+                .long("refresh-patches")
+                .action(ArgAction::SetTrue)
+                .global(true)
+                .help("Download remote patch files again regardless of their cache policy"),
+        )
+        .arg(
+            Arg::new("unstable")
+                .short('Z')
+                .value_name("FLAG")
+                .action(ArgAction::Append)
+                .global(true)
+                .help("Unstable cargo flags, requires the invoking cargo to be nightly"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("KEY=VALUE")
+                .action(ArgAction::Append)
+                .global(true)
+                .help("Override a cargo configuration value"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .value_parser(["auto", "cargo", "plain", "stderr"])
+                .default_value("auto")
+                .global(true)
+                .help("Print cargo-style status lines, plain lines or plain lines on stderr"),
+        )
+        .arg(
+            Arg::new("porcelain")
+                .long("porcelain")
+                .value_name("VERSION")
+                .value_parser(["v1"])
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("v1")
+                .global(true)
+                .help("Print records of a stable format for scripts on stdout and everything else on stderr"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_parser(["auto", "always", "never"])
+                .global(true)
+                .help("Coloring of cargo-style status lines"),
+        )
+        .arg(
+            Arg::new("override-path-style")
+                .long("override-path-style")
+                .value_parser(["relative", "absolute"])
+                .default_value("relative")
+                .global(true)
+                .help("Write override paths relative to the workspace root or absolute"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .global(true)
+                .help("Only apply entries without a profile or for this profile"),
+        )
+        .arg(
+            Arg::new("set")
+                .long("set")
+                .value_name("NAME")
+                .action(ArgAction::Append)
+                .global(true)
+                .help("Also apply the entries of this patch set from `patch-profiles`"),
+        )
+        .arg(
+            Arg::new("lock-timeout")
+                .long("lock-timeout")
+                .value_name("SECS")
+                .value_parser(value_parser!(u64))
+                .global(true)
+                .help("Give up waiting for the package cache lock of another cargo process after SECS seconds"),
+        )
+        .arg(
+            Arg::new("keep-going")
+                .long("keep-going")
+                .action(ArgAction::SetTrue)
+                .help("Patch the remaining dependencies if one fails"),
+        )
+        .arg(
+            Arg::new("frozen-copy")
+                .long("frozen-copy")
+                .action(ArgAction::SetTrue)
+                .help("Fail if a git dependency moved to another commit since it was patched"),
+        )
+        .arg(
+            Arg::new("no-fuzz-tolerance")
+                .long("no-fuzz-tolerance")
+                .action(ArgAction::SetTrue)
+                .help("Fail if a patch only applies with offsets or fuzz"),
+        )
+        .arg(
+            Arg::new("logs")
+                .long("logs")
+                .action(ArgAction::SetTrue)
+                .help("Write a detailed log of every package to target/patch/.logs"),
+        )
+        .arg(
+            Arg::new("timings")
+                .long("timings")
+                .value_name("FMT")
+                .value_parser(["html", "json"])
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("html")
+                .help("Write a report of the time spent per phase and package to target/cargo-patch-timings"),
+        )
+        .arg(
+            Arg::new("crate")
+                .long("crate")
+                .value_name("NAME")
+                .help("Patch only this dependency with the --patch files instead of the configured entries"),
+        )
+        .arg(
+            Arg::new("crate-version")
+                .long("version")
+                .value_name("REQ")
+                .requires("crate")
+                .help("Version requirement of the --crate dependency"),
+        )
+        .arg(
+            Arg::new("patch")
+                .long("patch")
+                .value_name("FILE")
+                .action(ArgAction::Append)
+                .requires("crate")
+                .help("Patch file applied to the --crate dependency"),
+        )
+        .arg(
+            Arg::new("explain")
+                .long("explain")
+                .action(ArgAction::SetTrue)
+                .help("Print the packages and the patches applied to each in order without patching"),
+        )
+        .arg(
+            Arg::new("test-build")
+                .long("test-build")
+                .action(ArgAction::SetTrue)
+                .help("Run cargo check for the patched dependencies and the workspace after patching"),
+        )
+        .arg(
+            Arg::new("ci")
+                .long("ci")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("watch")
+                .help("Check strictly, report all failures as file:line and write target/cargo-patch-summary.json"),
+        )
+        .arg(
+            Arg::new("check-upstream")
+                .long("check-upstream")
+                .action(ArgAction::SetTrue)
+                .help("Warn if the newest release of a dependency contains the fix of a patch"),
+        )
+        .arg(
+            Arg::new("allow-lock-update")
+                .long("allow-lock-update")
+                .action(ArgAction::SetTrue)
+                .help("Generate Cargo.lock before resolving if it is missing"),
+        )
+        .arg(
+            Arg::new("pure")
+                .long("pure")
+                .value_name("DIR")
+                .conflicts_with_all(["allow-lock-update", "check-upstream", "watch"])
+                .help("Patch into DIR without network access, only from local sources and with deterministic timestamps and permissions"),
+        )
+        .arg(
+            Arg::new("emit-hashes")
+                .long("emit-hashes")
+                .value_name("FILE")
+                .help("Write a content hash of every patched copy to FILE as JSON, e.g. as cache key"),
+        )
+        .arg(
+            Arg::new("write-lock")
+                .long("write-lock")
+                .action(ArgAction::SetTrue)
+                .help("Record the patched packages and the hashes of their patch files in cargo-patch.lock"),
+        )
+        .arg(
+            Arg::new("locked-metadata")
+                .long("locked-metadata")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("write-lock")
+                .help("Fail if the patched packages or patch files differ from cargo-patch.lock"),
+        )
+        .arg(
+            Arg::new("disable-failed-overrides")
+                .long("disable-failed-overrides")
+                .action(ArgAction::SetTrue)
+                .help("Comment out the [patch] override of a dependency in Cargo.toml if it fails to patch"),
+        )
+        .arg(
+            Arg::new("target")
+                .long("target")
+                .value_name("TRIPLE")
+                .action(ArgAction::Append)
+                .help("Patch only dependencies built for this target, may be given several times"),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .action(ArgAction::SetTrue)
+                .help("Re-apply patches whenever a patch file changes"),
+        )
+        .subcommand(
+            Command::new("apply")
+                .about("Apply a diff to a dependency without any patch metadata")
+                .arg(Arg::new("crate").required(true).help("Dependency to patch"))
+                .arg(
+                    Arg::new("patch")
+                        .required(true)
+                        .help("Patch file to apply or `-` to read it from stdin"),
+                )
+                .arg(
+                    Arg::new("version")
+                        .long("version")
+                        .help("Version requirement of the dependency"),
+                )
+                .arg(
+                    Arg::new("source")
+                        .long("source")
+                        .value_parser(["Default", "GithubPrDiff", "GitDiff"])
+                        .default_value("Default")
+                        .help("Source of the patch"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write the patches of a dependency as numbered `git format-patch` files")
+                .arg(Arg::new("crate").required(true).help("Dependency to export"))
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .value_name("DIR")
+                        .default_value("patches")
+                        .help("Directory the patch files are written to"),
+                )
+                .arg(
+                    Arg::new("version")
+                        .long("version")
+                        .help("Version requirement of the dependency"),
+                ),
+        )
+        .subcommand(
+            Command::new("instrument")
+                .about("Insert entry and exit tracing into a function of a dependency")
+                .arg(Arg::new("crate").required(true).help("Dependency to instrument"))
+                .arg(
+                    Arg::new("fn")
+                        .long("fn")
+                        .value_name("PATH")
+                        .required(true)
+                        .help("Path of the function, e.g. `de::Deserializer::new`"),
+                )
+                .arg(
+                    Arg::new("version")
+                        .long("version")
+                        .help("Version requirement of the dependency"),
+                )
+                .arg(
+                    Arg::new("with")
+                        .long("with")
+                        .value_parser(["println", "tracing"])
+                        .default_value("println")
+                        .help("Macro used to report entering and leaving the function"),
+                ),
+        )
+        .subcommand(
+            Command::new("open")
+                .about("Open the patched copy of a dependency in $EDITOR or the file manager")
+                .arg(Arg::new("crate").required(true).help("Dependency to open"))
+                .arg(
+                    Arg::new("version")
+                        .long("version")
+                        .help("Version requirement of the dependency"),
+                )
+                .arg(
+                    Arg::new("pristine")
+                        .long("pristine")
+                        .action(ArgAction::SetTrue)
+                        .help("Open the unmodified sources instead"),
+                )
+                .arg(
+                    Arg::new("print")
+                        .long("print")
+                        .action(ArgAction::SetTrue)
+                        .help("Only print the absolute path"),
+                ),
+        )
+        .subcommand(
+            Command::new("paths")
+                .about("Print where the configured dependencies are patched to")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the paths as JSON"),
+                )
+                .arg(
+                    Arg::new("toml")
+                        .long("toml")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("json")
+                        .help("Print [patch] sections for Cargo.toml"),
+                ),
+        )
+        .subcommand(
+            Command::new("rebase")
+                .about("Re-apply the patches of a dependency after its version changed")
+                .arg(Arg::new("crate").required(true).help("Dependency to rebase")),
+        )
+        .subcommand(
+            Command::new("report")
+                .about(
+                    "List the changes the configured patches make to dependencies",
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["text", "markdown"])
+                        .default_value("text")
+                        .help("Output format of the report"),
+                ),
+        )
+        .subcommand(
+            Command::new("sbom")
+                .about("Print an SBOM fragment describing the patched dependencies")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["cyclonedx", "spdx"])
+                        .default_value("cyclonedx")
+                        .help("SBOM format of the fragment"),
+                ),
+        )
+        .subcommand(
+            Command::new("selftest")
+                .about("Run a corpus of original, patch and expected files through the patch engine")
+                .arg(
+                    Arg::new("dir")
+                        .required(true)
+                        .help("Directory containing `<name>.orig`, `<name>.patch` and `<name>.expected` files"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check that the patched dependencies match their patches"),
+        ))
+}
+
+/// Arguments the metadata backend doesn't support, rejected together with
+/// `--metadata`.
+#[cfg(feature = "metadata-backend")]
+const NOT_WITH_METADATA: [&str; 29] = [
+    "restrict",
+    "strict",
+    "show-diff",
+    "refresh-patches",
+    "unstable",
+    "porcelain",
+    "override-path-style",
+    "set",
+    "lock-timeout",
+    "keep-going",
+    "frozen-copy",
+    "no-fuzz-tolerance",
+    "logs",
+    "timings",
+    "crate",
+    "crate-version",
+    "patch",
+    "explain",
+    "test-build",
+    "ci",
+    "check-upstream",
+    "allow-lock-update",
+    "pure",
+    "emit-hashes",
+    "write-lock",
+    "locked-metadata",
+    "disable-failed-overrides",
+    "target",
+    "watch",
+];
+
+/// Adds `--metadata` if the `metadata-backend` feature is enabled.
+#[cfg(feature = "metadata-backend")]
+fn metadata_arg(command: Command) -> Command {
+    command.arg(
+        Arg::new("metadata")
+            .long("metadata")
+            .value_name("FILE")
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("")
+            .conflicts_with_all(NOT_WITH_METADATA)
+            .help("Resolve the dependencies from `cargo metadata --format-version 1` output in FILE, or by running it, instead of cargo's library"),
+    )
+}
+
+#[cfg(not(feature = "metadata-backend"))]
+const fn metadata_arg(command: Command) -> Command {
+    command
+}
+
+/// Returns where `--metadata` reads the metadata from, if given.
+#[cfg(feature = "metadata-backend")]
+fn metadata_source(matches: &ArgMatches) -> Option<cargo_patch::MetadataSource> {
+    matches
+        .get_one::<String>("metadata")
+        .map(|file| match file.as_str() {
+            "" => cargo_patch::MetadataSource::Run,
+            file => cargo_patch::MetadataSource::File(PathBuf::from(file)),
+        })
+}
+
+fn report_format(matches: &ArgMatches) -> ReportFormat {
+    match matches.get_one::<String>("format").map(String::as_str) {
+        Some("markdown") => ReportFormat::Markdown,
+        _ => ReportFormat::Text,
+    }
+}
+
+fn values(matches: &ArgMatches, id: &str) -> Vec<String> {
+    matches
+        .get_many::<String>(id)
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+fn paths_format(matches: &ArgMatches) -> PathsFormat {
+    if matches.get_flag("json") {
+        PathsFormat::Json
+    } else if matches.get_flag("toml") {
+        PathsFormat::Toml
+    } else {
+        PathsFormat::Text
+    }
+}
+
+fn output_style(matches: &ArgMatches) -> OutputStyle {
+    if matches.contains_id("porcelain") {
+        return OutputStyle::Porcelain;
+    }
+    match matches.get_one::<String>("output").map(String::as_str) {
+        Some("cargo") => OutputStyle::Cargo,
+        Some("plain") => OutputStyle::Plain,
+        Some("stderr") => OutputStyle::Stderr,
+        _ => OutputStyle::Auto,
+    }
+}
+
+fn override_path_style(matches: &ArgMatches) -> OverridePathStyle {
+    match matches
+        .get_one::<String>("override-path-style")
+        .map(String::as_str)
+    {
+        Some("absolute") => OverridePathStyle::Absolute,
+        _ => OverridePathStyle::Relative,
+    }
+}
+
+fn timings_format(matches: &ArgMatches) -> Option<TimingsFormat> {
+    match matches.get_one::<String>("timings").map(String::as_str) {
+        Some("json") => Some(TimingsFormat::Json),
+        Some(_) => Some(TimingsFormat::Html),
+        None => None,
+    }
+}
+
+fn instrument_style(matches: &ArgMatches) -> InstrumentStyle {
+    match matches.get_one::<String>("with").map(String::as_str) {
+        Some("tracing") => InstrumentStyle::Tracing,
+        _ => InstrumentStyle::Println,
+    }
+}
+
+fn sbom_format(matches: &ArgMatches) -> SbomFormat {
+    match matches.get_one::<String>("format").map(String::as_str) {
+        Some("spdx") => SbomFormat::Spdx,
+        _ => SbomFormat::CycloneDx,
+    }
+}
+
+fn patch_source(matches: &ArgMatches) -> PatchSource {
+    match matches.get_one::<String>("source").map(String::as_str) {
+        Some("GithubPrDiff") => PatchSource::GithubPrDiff,
+        Some("GitDiff") => PatchSource::GitDiff,
+        _ => PatchSource::Default,
+    }
+}
+
+fn apply(matches: &ArgMatches) -> Result<(), Error> {
+    let name = matches
+        .get_one::<String>("crate")
+        .context("Missing crate")?;
+    let patch = matches
+        .get_one::<String>("patch")
+        .context("Missing patch")?;
+    let diff = if patch == "-" {
+        let mut diff = String::new();
+        let _ = std::io::stdin().read_to_string(&mut diff)?;
+        diff
+    } else {
+        fs::read_to_string(patch)
+            .with_context(|| format!("Unable to read patch file {patch}"))?
+    };
+    cargo_patch::apply(
+        name,
+        matches.get_one::<String>("version").map(String::as_str),
+        &patch_source(matches),
+        &diff,
+        &options(matches),
+    )
+}
+
+fn options(matches: &ArgMatches) -> Options {
+    let mut options = Options::default();
+    options.restrict = matches.get_flag("restrict");
+    options.offline = matches.get_flag("no-network");
+    options.strict = matches.get_flag("strict");
+    options.show_diff = matches.get_flag("show-diff");
+    options.refresh_patches = matches.get_flag("refresh-patches");
+    options.profile = matches.get_one::<String>("profile").cloned();
+    options.sets = values(matches, "set");
+    options.unstable_flags = values(matches, "unstable");
+    options.config = values(matches, "config");
+    options.output = output_style(matches);
+    options.override_path_style = override_path_style(matches);
+    options.color = matches.get_one::<String>("color").cloned();
+    options.lock_timeout = matches
+        .get_one::<u64>("lock-timeout")
+        .map(|secs| Duration::from_secs(*secs));
+    options
+}
+
 pub fn main() -> anyhow::Result<()> {
-    cargo_patch::patch()
+    let mut args = std::env::args_os().collect::<Vec<_>>();
+    // `cargo patch` invokes the binary as `cargo-patch patch`
+    if args.get(1).is_some_and(|arg| arg == "patch") {
+        let _ = args.remove(1);
+    }
+
+    let matches = cli().get_matches_from(args);
+    let result = match matches.subcommand() {
+        Some(("apply", matches)) => {
+            cargo_patch::handle_interrupts();
+            apply(matches)
+        }
+        Some(("export", matches)) => cargo_patch::export(
+            matches
+                .get_one::<String>("crate")
+                .context("Missing crate")?,
+            matches.get_one::<String>("version").map(String::as_str),
+            Path::new(
+                matches
+                    .get_one::<String>("out")
+                    .context("Missing output directory")?,
+            ),
+            &options(matches),
+        ),
+        Some(("instrument", matches)) => cargo_patch::instrument(
+            matches
+                .get_one::<String>("crate")
+                .context("Missing crate")?,
+            matches.get_one::<String>("version").map(String::as_str),
+            matches
+                .get_one::<String>("fn")
+                .context("Missing function")?,
+            instrument_style(matches),
+            &options(matches),
+        ),
+        Some(("open", matches)) => cargo_patch::open(
+            matches
+                .get_one::<String>("crate")
+                .context("Missing crate")?,
+            matches.get_one::<String>("version").map(String::as_str),
+            matches.get_flag("pristine"),
+            matches.get_flag("print"),
+            &options(matches),
+        ),
+        Some(("paths", matches)) => {
+            cargo_patch::paths(paths_format(matches), &options(matches))
+        }
+        Some(("rebase", matches)) => cargo_patch::rebase(
+            matches
+                .get_one::<String>("crate")
+                .context("Missing crate")?,
+            &options(matches),
+        ),
+        Some(("report", matches)) => {
+            cargo_patch::report(report_format(matches), &options(matches))
+        }
+        Some(("sbom", matches)) => {
+            cargo_patch::sbom(sbom_format(matches), &options(matches))
+        }
+        Some(("selftest", matches)) => cargo_patch::selftest(Path::new(
+            matches
+                .get_one::<String>("dir")
+                .context("Missing directory")?,
+        )),
+        Some(("verify", matches)) => cargo_patch::verify(&options(matches)),
+        _ => {
+            // only patching has to stop at a safe point, other commands end
+            // on the first Ctrl-C
+            cargo_patch::handle_interrupts();
+            let mut options = options(&matches);
+            options.watch = matches.get_flag("watch");
+            options.keep_going = matches.get_flag("keep-going");
+            options.explain = matches.get_flag("explain");
+            options.allow_lock_update = matches.get_flag("allow-lock-update");
+            options.test_build = matches.get_flag("test-build");
+            options.targets = values(&matches, "target");
+            options.disable_failed_overrides =
+                matches.get_flag("disable-failed-overrides");
+            options.pure = matches.get_one::<String>("pure").map(PathBuf::from);
+            options.emit_hashes =
+                matches.get_one::<String>("emit-hashes").map(PathBuf::from);
+            options.write_lock = matches.get_flag("write-lock");
+            options.locked_metadata = matches.get_flag("locked-metadata");
+            options.check_upstream = matches.get_flag("check-upstream");
+            options.ci = matches.get_flag("ci");
+            options.frozen_copy = matches.get_flag("frozen-copy");
+            options.strict_apply = matches.get_flag("no-fuzz-tolerance");
+            options.logs = matches.get_flag("logs");
+            options.timings = timings_format(&matches);
+            options.crate_name = matches.get_one::<String>("crate").cloned();
+            options.crate_version =
+                matches.get_one::<String>("crate-version").cloned();
+            options.patches = values(&matches, "patch")
+                .into_iter()
+                .map(PathBuf::from)
+                .collect();
+            #[cfg(feature = "metadata-backend")]
+            if let Some(source) = metadata_source(&matches) {
+                return Ok(cargo_patch::patch_from_metadata(
+                    &source,
+                    &(&options).into(),
+                )?);
+            }
+            cargo_patch::patch_with_options(&options)
+        }
+    };
+    if let Err(err @ Error::Interrupted) = result {
+        eprintln!("Error: {err}");
+        process::exit(INTERRUPTED_EXIT_CODE);
+    }
+    Ok(result?)
 }
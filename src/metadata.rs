@@ -0,0 +1,560 @@
+//! Resolution of the patched packages from the output of `cargo metadata`
+//! instead of cargo's library, enabled with the `metadata-backend` feature.
+//!
+//! The backend only needs the [`crate::engine`], so it doesn't pull in cargo
+//! unless the default `orchestrator` feature is enabled as well. Only the
+//! entries of the workspace and its members are read, and the packages are
+//! looked up in the resolved dependency graph of the metadata.
+//!
+//! Entries may only use `version`, `registry = "crates-io"`, `source-git`,
+//! `source`, `all-versions`, `profile`, `final-newline` and `patches` with
+//! local patch files, any other key is rejected. `patch-config`, `dependency-metadata`,
+//! recipes and patch sets aren't read, no provenance files are written and
+//! changes of build scripts aren't checked.
+
+use crate::{
+    engine::{apply_to_base, FinalNewline, PatchSource, PatchSpec},
+    long_path::long_path,
+    permissions, Error, DEFAULT_PROFILE, PROFILE_ENV,
+};
+#[cfg(feature = "orchestrator")]
+use crate::{orchestrator::Options, status, OutputStyle};
+use anyhow::{anyhow, Context, Result};
+use fs_extra::dir::{copy, CopyOptions};
+use semver::{Version, VersionReq};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashSet,
+    env,
+    ffi::OsString,
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Where the output of `cargo metadata --format-version 1` is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataSource {
+    /// Run `cargo metadata` in the current directory.
+    Run,
+    /// Read it from a file, e.g. one written by a build system.
+    File(PathBuf),
+}
+
+/// Options of [`patch_from_metadata`].
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct MetadataOptions {
+    /// Run `cargo metadata` with `--offline`.
+    pub offline: bool,
+    /// `--config` values passed on to `cargo metadata`.
+    pub config: Vec<String>,
+    /// Profile selecting the entries, `CARGO_PATCH_PROFILE` or `dev` if not
+    /// set.
+    pub profile: Option<String>,
+    /// How progress is printed.
+    #[cfg(feature = "orchestrator")]
+    pub output: OutputStyle,
+    /// Whether cargo-style output is colored, like cargo's `--color`.
+    #[cfg(feature = "orchestrator")]
+    pub color: Option<String>,
+}
+
+#[cfg(feature = "orchestrator")]
+impl From<&Options> for MetadataOptions {
+    fn from(options: &Options) -> Self {
+        Self {
+            offline: options.offline,
+            config: options.config.clone(),
+            profile: options.profile.clone(),
+            output: options.output,
+            color: options.color.clone(),
+        }
+    }
+}
+
+/// Keys of an entry supported by this backend.
+const ENTRY_KEYS: [&str; 8] = [
+    "version",
+    "registry",
+    "source",
+    "source-git",
+    "all-versions",
+    "profile",
+    "final-newline",
+    "patches",
+];
+
+/// Keys of a patch item table supported by this backend.
+const ITEM_KEYS: [&str; 3] = ["path", "source", "final-newline"];
+
+/// Where an entry selects its package from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PackageSource<'a> {
+    CratesIo,
+    Git(&'a str),
+}
+
+/// An entry of `[package.metadata.patch]`.
+#[derive(Debug, Clone)]
+struct Entry<'a> {
+    name: &'a str,
+    version: Option<VersionReq>,
+    package_source: Option<PackageSource<'a>>,
+    all_versions: bool,
+    profiles: Option<Vec<&'a str>>,
+    patches: Vec<PatchSpec>,
+}
+
+/// A package of the dependency graph.
+#[derive(Debug)]
+struct MetadataPackage {
+    name: String,
+    version: Version,
+    /// Source like `registry+https://...`, `None` for path dependencies.
+    source: Option<String>,
+    /// Directory containing the manifest.
+    root: PathBuf,
+}
+
+/// Sources of crates.io as written by `cargo metadata`.
+const CRATES_IO: [&str; 2] = [
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+impl PackageSource<'_> {
+    /// Returns whether a package with the `source` written by
+    /// `cargo metadata` comes from this source.
+    fn matches(&self, source: Option<&str>) -> bool {
+        let normalize = |url: &str| {
+            url.trim_end_matches('/')
+                .trim_end_matches(".git")
+                .to_ascii_lowercase()
+        };
+        match (self, source) {
+            (Self::CratesIo, Some(source)) => CRATES_IO.contains(&source),
+            (Self::Git(url), Some(source)) => source
+                .strip_prefix("git+")
+                .and_then(|source| source.split(['?', '#']).next())
+                .is_some_and(|source| normalize(source) == normalize(url)),
+            (_, None) => false,
+        }
+    }
+}
+
+/// Prints a status line, in the output style of the command line tool if
+/// the orchestrator is built and on stderr otherwise.
+fn status(verb: &str, message: impl Display) {
+    #[cfg(feature = "orchestrator")]
+    status::status(verb, message);
+    #[cfg(not(feature = "orchestrator"))]
+    eprintln!("{verb} {message}");
+}
+
+/// Prints a line without a verb, see [`status`].
+fn line(message: impl Display) {
+    #[cfg(feature = "orchestrator")]
+    status::line(message);
+    #[cfg(not(feature = "orchestrator"))]
+    eprintln!("{message}");
+}
+
+/// Prints a warning, see [`status`].
+fn warning(message: impl Display) {
+    #[cfg(feature = "orchestrator")]
+    status::warning(message);
+    #[cfg(not(feature = "orchestrator"))]
+    eprintln!("warning: {message}");
+}
+
+fn load(source: &MetadataSource, options: &MetadataOptions) -> Result<Value> {
+    let data = match source {
+        MetadataSource::File(path) => fs::read(path)
+            .with_context(|| format!("Unable to read {}", path.display()))?,
+        MetadataSource::Run => {
+            let cargo =
+                env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo"));
+            let mut command = Command::new(cargo);
+            let _ = command.args(["metadata", "--format-version", "1"]);
+            if options.offline {
+                let _ = command.arg("--offline");
+            }
+            for config in &options.config {
+                let _ = command.args(["--config", config]);
+            }
+            let output = command
+                .output()
+                .map_err(|err| anyhow!("Unable to run cargo metadata: {err}"))?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "cargo metadata failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            output.stdout
+        }
+    };
+    serde_json::from_slice(&data).context("Invalid cargo metadata output")
+}
+
+fn str_field<'v>(value: &'v Value, key: &str) -> Result<&'v str> {
+    value[key]
+        .as_str()
+        .ok_or_else(|| anyhow!("cargo metadata output lacks `{key}`"))
+}
+
+/// Returns the packages of the resolved dependency graph.
+fn packages(metadata: &Value) -> Result<Vec<MetadataPackage>> {
+    let resolved = metadata["resolve"]["nodes"].as_array().map(|nodes| {
+        nodes
+            .iter()
+            .filter_map(|node| node["id"].as_str())
+            .collect::<HashSet<_>>()
+    });
+    let mut packages = vec![];
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        let id = str_field(package, "id")?;
+        if resolved
+            .as_ref()
+            .is_some_and(|resolved| !resolved.contains(id))
+        {
+            continue;
+        }
+        let manifest = Path::new(str_field(package, "manifest_path")?);
+        packages.push(MetadataPackage {
+            name: str_field(package, "name")?.to_owned(),
+            version: str_field(package, "version")?.parse()?,
+            source: package["source"].as_str().map(str::to_owned),
+            root: manifest
+                .parent()
+                .ok_or_else(|| {
+                    anyhow!("Invalid manifest path {}", manifest.display())
+                })?
+                .to_path_buf(),
+        });
+    }
+    packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    Ok(packages)
+}
+
+/// Returns the metadata tables of the workspace and then its members in
+/// alphabetical order, like the orchestrator reads them.
+fn custom_metadata(metadata: &Value) -> Vec<&Value> {
+    let members = metadata["workspace_members"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect::<HashSet<_>>();
+    let mut member_metadata = metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|package| {
+            package["id"]
+                .as_str()
+                .is_some_and(|id| members.contains(id))
+        })
+        .map(|package| (package["name"].as_str(), &package["metadata"]))
+        .collect::<Vec<_>>();
+    member_metadata.sort_by_key(|(name, _)| *name);
+    std::iter::once(&metadata["workspace_metadata"])
+        .chain(member_metadata.into_iter().map(|(_, metadata)| metadata))
+        .filter(|metadata| metadata.is_object())
+        .collect()
+}
+
+/// Fails on the first key of `table` which isn't in `supported`.
+fn check_keys(
+    table: &Map<String, Value>,
+    supported: &[&str],
+    what: &str,
+) -> Result<()> {
+    if let Some(key) = table.keys().find(|key| !supported.contains(&key.as_str())) {
+        return Err(anyhow!(
+            "`{key}` of {what} isn't supported by the metadata backend"
+        ));
+    }
+    Ok(())
+}
+
+fn parse_patch_source(source: &str) -> Result<PatchSource> {
+    match source {
+        "Default" => Ok(PatchSource::Default),
+        "GithubPrDiff" => Ok(PatchSource::GithubPrDiff),
+        "GitDiff" => Ok(PatchSource::GitDiff),
+        _ => Err(anyhow!("Unknown patch source: {source}")),
+    }
+}
+
+fn parse_final_newline(value: &Value) -> Result<FinalNewline> {
+    match value.as_str() {
+        Some("preserve") => Ok(FinalNewline::Preserve),
+        Some("always") => Ok(FinalNewline::Always),
+        Some("never") => Ok(FinalNewline::Never),
+        _ => Err(anyhow!("Unknown final newline policy: {value}")),
+    }
+}
+
+/// Parses an entry, resolving the paths of its patch files against the
+/// workspace `root`.
+fn parse_entry<'a>(
+    name: &'a str,
+    value: &'a Value,
+    root: &Path,
+) -> Result<Entry<'a>> {
+    let what = format!("the patch entry {name}");
+    let entry = value
+        .as_object()
+        .ok_or_else(|| anyhow!("Entry {name} must contain a table."))?;
+    check_keys(entry, &ENTRY_KEYS, &what)?;
+    let version = entry
+        .get("version")
+        .map(|version| {
+            version
+                .as_str()
+                .and_then(|version| VersionReq::parse(version).ok())
+                .ok_or_else(|| {
+                    anyhow!("Version must be a value semver string: {version}")
+                })
+        })
+        .transpose()?;
+    let package_source = match (entry.get("registry"), entry.get("source-git")) {
+        (Some(registry), _) if registry.as_str() == Some("crates-io") => {
+            Some(PackageSource::CratesIo)
+        }
+        (Some(registry), _) => {
+            return Err(anyhow!("Registry must be \"crates-io\": {registry}"));
+        }
+        (None, Some(url)) => {
+            Some(PackageSource::Git(url.as_str().ok_or_else(|| {
+                anyhow!("Source git must be a url string: {url}")
+            })?))
+        }
+        (None, None) => None,
+    };
+    let source = entry
+        .get("source")
+        .and_then(Value::as_str)
+        .map_or(Ok(PatchSource::Default), parse_patch_source)?;
+    let final_newline = entry
+        .get("final-newline")
+        .map_or(Ok(FinalNewline::Preserve), parse_final_newline)?;
+    let patches = entry
+        .get("patches")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|item| {
+            let mut spec = PatchSpec {
+                path: PathBuf::new(),
+                source: source.clone(),
+                final_newline,
+            };
+            let path = match item {
+                Value::String(path) => path,
+                Value::Object(item) => {
+                    check_keys(item, &ITEM_KEYS, &format!("a patch of {what}"))?;
+                    if let Some(source) = item.get("source").and_then(Value::as_str)
+                    {
+                        spec.source = parse_patch_source(source)?;
+                    }
+                    if let Some(value) = item.get("final-newline") {
+                        spec.final_newline = parse_final_newline(value)?;
+                    }
+                    item.get("path")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| anyhow!("A patch of {what} lacks a `path`"))?
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Patch Entry must be a string or a table with path: {item}"
+                    ))
+                }
+            };
+            spec.path = root.join(path);
+            Ok(spec)
+        })
+        .collect::<Result<_>>()?;
+    let profiles = entry
+        .get("profile")
+        .map(|profile| match profile {
+            Value::String(profile) => Ok(vec![profile.as_str()]),
+            Value::Array(profiles) => profiles
+                .iter()
+                .map(Value::as_str)
+                .collect::<Option<_>>()
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Profile must be a string or an array of strings: {profile}"
+                    )
+                }),
+            _ => Err(anyhow!(
+                "Profile must be a string or an array of strings: {profile}"
+            )),
+        })
+        .transpose()?;
+    Ok(Entry {
+        name,
+        version,
+        package_source,
+        all_versions: entry
+            .get("all-versions")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        profiles,
+        patches,
+    })
+}
+
+/// Returns the packages an entry applies to, like the orchestrator looks
+/// them up in cargo's resolve.
+fn matching<'m>(
+    entry: &Entry<'_>,
+    packages: &'m [MetadataPackage],
+) -> Vec<&'m MetadataPackage> {
+    let matching = packages
+        .iter()
+        .filter(|package| {
+            package.name == entry.name
+                && entry
+                    .version
+                    .as_ref()
+                    .map_or(true, |version| version.matches(&package.version))
+                && entry
+                    .package_source
+                    .as_ref()
+                    .map_or(true, |source| source.matches(package.source.as_deref()))
+        })
+        .collect::<Vec<_>>();
+    if entry.all_versions {
+        return matching;
+    }
+    if let Some(first) = matching.first() {
+        if matching
+            .iter()
+            .any(|package| package.source != first.source)
+        {
+            warning(format!(
+                "{} is available from multiple sources. Try setting `registry = \"crates-io\"` or `source-git = \"<url>\"`.",
+                entry.name
+            ));
+        } else if matching.len() > 1 {
+            warning(format!(
+                "There are multiple versions of {} available. Try specifying a version or set `all-versions = true`.",
+                entry.name
+            ));
+        }
+    }
+    matching.into_iter().take(1).collect()
+}
+
+/// Copies `package` into `output` and applies the patch files of its entry.
+fn patch_package(
+    entry: &Entry<'_>,
+    package: &MetadataPackage,
+    output: &Path,
+) -> Result<()> {
+    let label = format!("{}-{}", package.name, package.version);
+    #[cfg(feature = "orchestrator")]
+    status::status_or("Patching", label.replace('-', " v"), None);
+    let root = long_path(&output.join(label))?;
+    fs::create_dir_all(&root)?;
+    let copy_options = CopyOptions::new().content_only(true);
+    let _ = copy(long_path(&package.root)?, &root, &copy_options)?;
+    permissions::make_writable(&root)?;
+    let base = root.canonicalize()?;
+    for spec in &entry.patches {
+        let data = fs::read_to_string(&spec.path).with_context(|| {
+            format!("Unable to read patch file {}", spec.path.display())
+        })?;
+        for patched in apply_to_base(&base, entry.name, spec, &data)? {
+            status("Patched", patched.location);
+        }
+    }
+    Ok(())
+}
+
+/// Patches the dependencies configured in the workspace like
+/// `patch_with_options`, but resolves them from the output of
+/// `cargo metadata` instead of cargo's library.
+///
+/// The previous copies in `target/patch` are removed first. Entry keys this
+/// backend doesn't support are rejected, see the
+/// [README](https://github.com/itmettkeDE/cargo-patch#resolving-with-cargo-metadata).
+pub fn patch_from_metadata(
+    source: &MetadataSource,
+    options: &MetadataOptions,
+) -> Result<(), Error> {
+    #[cfg(feature = "orchestrator")]
+    status::init(&Options {
+        output: options.output,
+        color: options.color.clone(),
+        ..Options::default()
+    })?;
+    let metadata = load(source, options)?;
+    let packages = packages(&metadata)?;
+    let root = PathBuf::from(str_field(&metadata, "workspace_root")?);
+    let output =
+        PathBuf::from(str_field(&metadata, "target_directory")?).join("patch");
+    let profile = options
+        .profile
+        .clone()
+        .or_else(|| env::var(PROFILE_ENV).ok())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_owned());
+
+    let mut merged = Vec::<(Entry<'_>, &MetadataPackage)>::new();
+    for table in custom_metadata(&metadata) {
+        let Some(entries) = table.get("patch") else {
+            continue;
+        };
+        let entries = entries
+            .as_object()
+            .ok_or_else(|| anyhow!("`patch` metadata must be a table"))?;
+        for (name, value) in entries {
+            let entry = parse_entry(name, value, &root)?;
+            if entry
+                .profiles
+                .as_ref()
+                .is_some_and(|profiles| !profiles.contains(&profile.as_str()))
+            {
+                continue;
+            }
+            for package in matching(&entry, &packages) {
+                match merged
+                    .iter_mut()
+                    .find(|(_, other)| std::ptr::eq(*other, package))
+                {
+                    Some((other, _)) => {
+                        for spec in &entry.patches {
+                            if !other
+                                .patches
+                                .iter()
+                                .any(|other| other.path == spec.path)
+                            {
+                                other.patches.push(spec.clone());
+                            }
+                        }
+                    }
+                    None => merged.push((entry.clone(), package)),
+                }
+            }
+        }
+    }
+
+    match fs::remove_dir_all(long_path(&output)?) {
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+        _ => {}
+    }
+    if merged.is_empty() {
+        line("No patches found");
+        return Ok(());
+    }
+    for (entry, package) in &merged {
+        patch_package(entry, package, &output)?;
+    }
+    Ok(())
+}
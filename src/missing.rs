@@ -0,0 +1,51 @@
+//! Explaining why a configured package isn't part of the dependency graph.
+
+use cargo::{
+    core::{dependency::DepKind, Resolve},
+    util::edit_distance,
+};
+
+/// Returns a hint to append to "Unable to find package {name} in
+/// dependencies". The graph is resolved with all features of the workspace
+/// members and for all targets, so the package is either misspelled, an
+/// optional dependency nothing enables, or doesn't match the configured
+/// version or source.
+pub fn hint(name: &str, resolve: &Resolve) -> String {
+    let mut found = resolve
+        .iter()
+        .filter(|id| id.name().as_str() == name)
+        .collect::<Vec<_>>();
+    if !found.is_empty() {
+        found.sort();
+        let versions = found
+            .iter()
+            .map(|id| id.version().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return format!(
+            ", found version(s) {versions} which don't match the configured version or source"
+        );
+    }
+
+    let mut optional_of = resolve
+        .iter()
+        .filter(|id| {
+            resolve.summary(*id).dependencies().iter().any(|dep| {
+                dep.package_name() == name
+                    && dep.is_optional()
+                    && dep.kind() != DepKind::Development
+            })
+        })
+        .collect::<Vec<_>>();
+    optional_of.sort();
+    if let Some(id) = optional_of.first() {
+        return format!(
+            ", it is an optional dependency of {} {} which none of its enabled features activate",
+            id.name(),
+            id.version()
+        );
+    }
+
+    edit_distance::closest(name, resolve.iter(), |id| id.name().as_str())
+        .map_or_else(String::new, |id| format!(", did you mean `{}`?", id.name()))
+}
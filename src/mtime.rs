@@ -0,0 +1,123 @@
+//! Deterministic modification times of patched copies, for caches keyed on
+//! file metadata like sccache or remote execution.
+
+use anyhow::{anyhow, Result};
+use patch::{FileMetadata, Patch};
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use toml::Value;
+
+/// Reads the `mtime` setting of `patch-config`, either seconds since the
+/// epoch or `"lockfile"` for the modification time of `Cargo.lock` in `root`.
+pub fn parse(value: &Value, root: &Path) -> Result<SystemTime> {
+    match value {
+        Value::Integer(secs) => u64::try_from(*secs)
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+            .map_err(|_| anyhow!("Mtime must not be negative: {secs}")),
+        Value::String(s) if s == "lockfile" => {
+            let lockfile = root.join("Cargo.lock");
+            fs::metadata(&lockfile)
+                .and_then(|metadata| metadata.modified())
+                .map_err(|err| {
+                    anyhow!(
+                        "Unable to read the mtime of {}: {err}",
+                        lockfile.display()
+                    )
+                })
+        }
+        _ => Err(anyhow!(
+            "Mtime must be seconds since the epoch or \"lockfile\": {value}"
+        )),
+    }
+}
+
+/// Returns the time of the `SOURCE_DATE_EPOCH` environment variable used by
+/// reproducible builds, or one second after the epoch like the Nix store.
+pub fn source_date_epoch() -> Result<SystemTime> {
+    let secs = match env::var("SOURCE_DATE_EPOCH") {
+        Ok(secs) => secs.parse::<u64>().map_err(|_| {
+            anyhow!("SOURCE_DATE_EPOCH must be seconds since the epoch: {secs}")
+        })?,
+        Err(_) => 1,
+    };
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Returns the timestamp of the `+++` header of a file diff, like
+/// `2023-07-30 15:39:01.284727222 +0200`, if it has one.
+pub fn header_time(patch: &Patch<'_>) -> Option<SystemTime> {
+    let Some(FileMetadata::DateTime(time)) = &patch.new.meta else {
+        return None;
+    };
+    let secs = u64::try_from(time.timestamp()).ok()?;
+    Some(UNIX_EPOCH + Duration::new(secs, time.timestamp_subsec_nanos()))
+}
+
+/// Sets the modification time of everything below `root` to `mtime`, except
+/// for the files in `headers` which get the timestamp of their patch.
+/// Symlinks are skipped, as opening them would change their targets, which
+/// may lie outside of `root`.
+pub fn set(
+    root: &Path,
+    mtime: SystemTime,
+    headers: &HashMap<PathBuf, SystemTime>,
+) -> Result<()> {
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        let metadata = fs::symlink_metadata(&path)?;
+        if metadata.is_symlink() {
+            continue;
+        }
+        if metadata.is_dir() {
+            set(&path, mtime, headers)?;
+        }
+        // directories can only be opened on windows with special flags
+        if cfg!(unix) || !metadata.is_dir() {
+            let time = headers.get(&path).copied().unwrap_or(mtime);
+            File::open(&path)?.set_modified(time)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::set;
+    use std::{
+        collections::HashMap,
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    #[test]
+    fn set_skips_symlinks() {
+        let outside = tempfile::tempdir().expect("Unable to create dir");
+        let root = tempfile::tempdir().expect("Unable to create dir");
+        let target = outside.path().join("target");
+        fs::write(&target, "").expect("Unable to write");
+        fs::write(root.path().join("file"), "").expect("Unable to write");
+        std::os::unix::fs::symlink(&target, root.path().join("link"))
+            .expect("Unable to create symlink");
+        std::os::unix::fs::symlink(outside.path(), root.path().join("dir"))
+            .expect("Unable to create symlink");
+        let before = fs::metadata(&target)
+            .and_then(|metadata| metadata.modified())
+            .expect("Unable to read mtime");
+
+        let mtime = UNIX_EPOCH + Duration::from_secs(1);
+        set(root.path(), mtime, &HashMap::new()).expect("Unable to set mtimes");
+
+        let modified = |path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .expect("Unable to read mtime")
+        };
+        assert_eq!(modified(root.path().join("file")), mtime);
+        assert_eq!(modified(target), before);
+    }
+}
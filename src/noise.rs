@@ -0,0 +1,139 @@
+//! Dropping comments and prose between the file diffs of a patch, as found
+//! in patches copied from mailing lists or tickets.
+
+/// Returns the old and new line counts of a hunk header like
+/// `@@ -8,9 +8,7 @@`, where an omitted count is 1.
+fn hunk_counts(line: &str) -> Option<(usize, usize)> {
+    let (ranges, _) = line.strip_prefix("@@ -")?.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, count)) => count.parse().ok(),
+        None => range.parse::<usize>().ok().map(|_| 1),
+    };
+    Some((count(old)?, count(new)?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Outside of a file diff, lines are dropped.
+    Outside,
+    /// After the `---`/`+++` lines or a complete hunk.
+    Between,
+    /// Inside of a hunk with the remaining old and new lines.
+    Hunk(usize, usize),
+}
+
+/// Keeps only the file diffs of `text`: their `---`/`+++` lines and hunks.
+/// Lines in front of a file diff, like `# comments`, svn's `Index:` headers
+/// or prose, are dropped. Lines looking like hunk lines after a complete
+/// hunk are kept, so a hunk with wrong line counts still fails to parse
+/// instead of being applied partially. Without any file diff the text is
+/// returned unchanged.
+pub fn strip(text: &str) -> String {
+    let lines = text.split_inclusive('\n').collect::<Vec<_>>();
+    let mut kept = String::with_capacity(text.len());
+    let mut state = State::Outside;
+    let mut found = false;
+    for (index, line) in lines.iter().enumerate() {
+        if let State::Hunk(old, new) = state {
+            let (old, new) = match line.as_bytes().first() {
+                Some(b'-') => (old.saturating_sub(1), new),
+                Some(b'+') => (old, new.saturating_sub(1)),
+                Some(b'\\') => (old, new),
+                // mailers strip the space of empty context lines
+                _ => (old.saturating_sub(1), new.saturating_sub(1)),
+            };
+            kept.push_str(line);
+            state = if old == 0 && new == 0 {
+                State::Between
+            } else {
+                State::Hunk(old, new)
+            };
+            continue;
+        }
+        let starts_file = line.starts_with("--- ")
+            && lines
+                .get(index + 1)
+                .is_some_and(|next| next.starts_with("+++ "));
+        if starts_file {
+            found = true;
+            kept.push_str(line);
+            state = State::Between;
+        } else if line.starts_with("+++ ") && state == State::Between {
+            kept.push_str(line);
+        } else if state == State::Between {
+            if let Some((old, new)) = hunk_counts(line) {
+                kept.push_str(line);
+                state = State::Hunk(old, new);
+            } else if line.starts_with(['+', '-', ' ', '\\']) {
+                kept.push_str(line);
+            } else {
+                state = State::Outside;
+            }
+        }
+    }
+    if found {
+        kept
+    } else {
+        text.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hunk_counts, strip};
+
+    #[test]
+    fn counts() {
+        assert_eq!(hunk_counts("@@ -8,9 +8,7 @@ fn serialize\n"), Some((9, 7)));
+        assert_eq!(hunk_counts("@@ -1 +1 @@\n"), Some((1, 1)));
+        assert_eq!(hunk_counts("@@ -0,0 +1 @@\n"), Some((0, 1)));
+        assert_eq!(hunk_counts("@@ invalid @@\n"), None);
+    }
+
+    #[test]
+    fn strips_noise() {
+        let text = "# Fixes the parser, see the ticket\n\
+                    Index: src/lib.rs\n\
+                    ===================================================================\n\
+                    --- src/lib.rs\n\
+                    +++ src/lib.rs\n\
+                    @@ -1,2 +1,2 @@\n\
+                    -old\n\
+                    +new\n\
+                    \n\
+                    And another change:\n\
+                    \n\
+                    --- README.md\n\
+                    +++ README.md\n\
+                    @@ -1 +1 @@\n\
+                    --- a list item\n\
+                    +++ a list item\n\
+                    Thanks!\n";
+        assert_eq!(
+            strip(text),
+            "--- src/lib.rs\n\
+             +++ src/lib.rs\n\
+             @@ -1,2 +1,2 @@\n\
+             -old\n\
+             +new\n\
+             \n\
+             --- README.md\n\
+             +++ README.md\n\
+             @@ -1 +1 @@\n\
+             --- a list item\n\
+             +++ a list item\n"
+        );
+    }
+
+    #[test]
+    fn keeps_overlong_hunks() {
+        let text = "--- a\n+++ a\n@@ -1 +1 @@\n-old\n+new\n+extra\n";
+        assert_eq!(strip(text), text);
+    }
+
+    #[test]
+    fn keeps_text_without_diff() {
+        assert_eq!(strip("not a patch\n"), "not a patch\n");
+    }
+}
@@ -0,0 +1,164 @@
+//! Why a patch exists and when it should be revisited.
+
+use crate::{orchestrator::PatchEntry, status};
+use cargo::core::PackageId;
+use semver::{Version, VersionReq};
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+use toml::Table;
+
+/// The `description`, `issue`, `expires` and `fixed-in` keys of a patch item.
+/// They don't change how it is applied, but are shown by `cargo patch
+/// report`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Notes<'a> {
+    pub description: Option<&'a str>,
+    /// Link to the issue or pull request the patch belongs to.
+    pub issue: Option<&'a str>,
+    /// Date like `2025-12-31` after which the patch is reported as expired.
+    pub expires: Option<&'a str>,
+    /// Versions which contain the fix upstream, so the patch can be dropped
+    /// once the dependency is updated to one of them.
+    pub fixed_in: Option<VersionReq>,
+}
+
+/// Returns the end of the day `date`, formatted like `2025-12-31`, in UTC.
+fn end_of_day(date: &str) -> Option<SystemTime> {
+    let start = humantime::parse_rfc3339(&format!("{date}T00:00:00Z")).ok()?;
+    Some(start + Duration::from_secs(24 * 60 * 60))
+}
+
+impl<'a> Notes<'a> {
+    /// Reads the notes of a patch item table, ignoring an invalid date.
+    pub fn parse(item: &'a Table) -> Self {
+        let text = |key| item.get(key).and_then(toml::Value::as_str);
+        let expires = text("expires");
+        if let Some(date) = expires.filter(|date| end_of_day(date).is_none()) {
+            eprintln!("Expires must be a date like 2025-12-31: {date}");
+        }
+        let fixed_in = text("fixed-in").and_then(|req| {
+            let parsed = VersionReq::parse(req).ok();
+            if parsed.is_none() {
+                eprintln!("Fixed-in must be a version requirement: {req}");
+            }
+            parsed
+        });
+        Self {
+            description: text("description"),
+            issue: text("issue"),
+            expires: expires.filter(|date| end_of_day(date).is_some()),
+            fixed_in,
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.description.is_none()
+            && self.issue.is_none()
+            && self.expires.is_none()
+            && self.fixed_in.is_none()
+    }
+
+    /// Returns whether the expiry date has passed at `now`.
+    pub fn expired(&self, now: SystemTime) -> bool {
+        self.expires
+            .and_then(end_of_day)
+            .is_some_and(|end| end <= now)
+    }
+
+    /// Joins the notes into one line like `Backport of serde#1234,
+    /// https://…, expires 2025-12-31 (expired), fixed in >=1.0.200`.
+    pub fn summary(&self, now: SystemTime) -> String {
+        let expires = self.expires.map(|date| {
+            if self.expired(now) {
+                format!("expires {date} (expired)")
+            } else {
+                format!("expires {date}")
+            }
+        });
+        self.description
+            .map(str::to_owned)
+            .into_iter()
+            .chain(self.issue.map(str::to_owned))
+            .chain(expires)
+            .chain(self.fixed_in.as_ref().map(|req| format!("fixed in {req}")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Warns about every patch item whose expiry date has passed or whose fix
+/// is in the resolved version, or in the newest release from `latest`, so
+/// obsolete patches get dropped.
+pub fn warn(
+    ids: &[(PatchEntry<'_>, PackageId)],
+    latest: &HashMap<PackageId, Version>,
+) {
+    let now = SystemTime::now();
+    for (entry, id) in ids {
+        for item in &entry.patches {
+            let notes = &item.notes;
+            let patch = item.path.display();
+            let context = Notes {
+                expires: None,
+                fixed_in: None,
+                ..notes.clone()
+            }
+            .summary(now);
+            let context = if context.is_empty() {
+                context
+            } else {
+                format!(" ({context})")
+            };
+            if notes.expired(now) {
+                status::warning(format!(
+                    "Patch {patch} of {} expired on {}, check whether it is still needed{context}",
+                    entry.name,
+                    notes.expires.unwrap_or_default()
+                ));
+            }
+            let Some(fixed_in) = &notes.fixed_in else {
+                continue;
+            };
+            if fixed_in.matches(id.version()) {
+                status::warning(format!(
+                    "Patch {patch} of {} is fixed in {fixed_in}, which v{} matches, so it is no longer needed{context}",
+                    entry.name,
+                    id.version()
+                ));
+            } else if let Some(version) =
+                latest.get(id).filter(|version| fixed_in.matches(version))
+            {
+                status::warning(format!(
+                    "Patch {patch} of {} is fixed in {fixed_in}, which the release v{version} matches. Update {} and drop the patch{context}",
+                    entry.name, entry.name
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{end_of_day, Notes};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn expired_after_the_day() {
+        let notes = Notes {
+            expires: Some("1970-01-02"),
+            ..Notes::default()
+        };
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert!(!notes.expired(UNIX_EPOCH + day));
+        assert!(!notes.expired(UNIX_EPOCH + day * 2 - Duration::from_secs(1)));
+        assert!(notes.expired(UNIX_EPOCH + day * 2));
+    }
+
+    #[test]
+    fn invalid_date() {
+        assert_eq!(end_of_day("31.12.2025"), None);
+        assert_eq!(end_of_day("2025-13-01"), None);
+    }
+}
@@ -0,0 +1,91 @@
+//! Opening the patched or pristine sources of a dependency.
+
+use crate::{
+    orchestrator::{
+        get_config, get_id, load_workspace, lock_package_cache, package_copy_path,
+        resolve_ws, setup_gctx, Options, VersionFilter,
+    },
+    Error,
+};
+use anyhow::{anyhow, Context, Result};
+use semver::VersionReq;
+use std::{env, path::Path, process::Command};
+
+/// Program opening directories if neither `VISUAL` nor `EDITOR` is set.
+#[cfg(target_os = "macos")]
+const FILE_MANAGER: &str = "open";
+#[cfg(windows)]
+const FILE_MANAGER: &str = "explorer";
+#[cfg(not(any(target_os = "macos", windows)))]
+const FILE_MANAGER: &str = "xdg-open";
+
+/// Opens `path` in the editor configured by `VISUAL` or `EDITOR`, falling
+/// back to the file manager. Editors may be given with arguments like
+/// `code --wait`.
+fn open_dir(path: &Path) -> Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| FILE_MANAGER.to_owned());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or(FILE_MANAGER);
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Unable to run `{program}`"))?;
+    // explorer exits with 1 even if it opened the directory
+    if !status.success() && program != "explorer" {
+        return Err(anyhow!("`{program}` failed with {status}"));
+    }
+    Ok(())
+}
+
+/// Opens the patched copy of the dependency `name` in `$VISUAL`, `$EDITOR`
+/// or the file manager. With `pristine` the unmodified sources are opened
+/// instead, with `print` only the absolute path is printed.
+pub fn open(
+    name: &str,
+    version: Option<&str>,
+    pristine: bool,
+    print: bool,
+    options: &Options,
+) -> Result<(), Error> {
+    let version = version
+        .map(VersionReq::parse)
+        .transpose()
+        .map_err(anyhow::Error::from)?
+        .map(VersionFilter::Req);
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+
+    let id = get_id(name, &version, None, &resolve).ok_or_else(|| {
+        Error::PackageNotFound {
+            name: name.to_owned(),
+        }
+    })?;
+    let package = pkg_set.get_one(id)?;
+    let path = if pristine {
+        package.root().to_path_buf()
+    } else {
+        let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+        let path = env::current_dir()?.join(package_copy_path(package, &config)?);
+        if !path.exists() {
+            return Err(anyhow!(
+                "{name} v{} is not patched yet, run `cargo patch` or pass --pristine",
+                id.version()
+            )
+            .into());
+        }
+        path
+    };
+
+    if print {
+        println!("{}", path.display());
+        return Ok(());
+    }
+    Ok(open_dir(&path)?)
+}
@@ -0,0 +1,2925 @@
+//! Resolution of the patched dependencies with cargo, copying them and
+//! applying their entries with the [`crate::engine`]. Enabled with the
+//! default `orchestrator` feature.
+
+use crate::{
+    backend, ci, copy_ignore,
+    engine::{
+        applies_to, do_patch, file_paths, hunk_header, mark_missing_newlines,
+        parse_patches, patch_paths, FinalNewline, PatchSource, PatchType,
+    },
+    event, git_header, git_repo, hashes, interrupt, lockfile, missing, mtime, notes,
+    order, permissions, porcelain, pristine, provenance, recovery, remote, status,
+    targets, test_build, timings, unused, upstream, watch, Error, Event,
+    OutputStyle, OverridePathStyle, TimingsFormat, DEFAULT_PROFILE, PROFILE_ENV,
+};
+
+use anyhow::{anyhow, Result};
+use cargo::{
+    core::{
+        package::{Package, PackageSet},
+        registry::PackageRegistry,
+        resolver::{features::CliFeatures, HasDevUnits},
+        shell::Verbosity,
+        GitReference, PackageId, Resolve, SourceId, Workspace,
+    },
+    ops::{
+        generate_lockfile, get_resolved_packages, load_pkg_lockfile,
+        resolve_with_previous,
+    },
+    util::{hex::short_hash, important_paths::find_root_manifest_for_wd},
+    GlobalContext,
+};
+
+use crate::backend::Backend;
+use crate::build_surface::BuildSurface;
+use crate::checkout::Member;
+use crate::long_path::long_path;
+use crate::notes::Notes;
+use crate::order::LinkMode;
+use crate::overlap::{History, HunkLines};
+use crate::packaging::Packaging;
+use crate::provenance::{AppliedPatch, GitCommit};
+use crate::remote::{CachePolicy, HttpConfig, Remote};
+use crate::sed::SedEdit;
+use crate::signature::verify_signature;
+use crate::snippet::Snippet;
+use crate::timings::Phase;
+use crate::transform::{Encoding, Transform};
+use cargo::sources::SourceConfigMap;
+use cargo::util::cache_lock::{CacheLock, CacheLockMode::DownloadExclusive};
+use fs_extra::dir::{copy, CopyOptions};
+use patch::{Hunk, Line, Patch};
+use semver::{BuildMetadata, Version, VersionReq};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env,
+    fmt::{Display, Formatter},
+    fs,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    process::Command,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+use toml::Value;
+use toml_edit::DocumentMut;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PatchItem<'a> {
+    pub(crate) path: Cow<'a, Path>,
+    pub(crate) source: PatchSource,
+    pub(crate) final_newline: FinalNewline,
+    pub(crate) signature: Option<Cow<'a, Path>>,
+    pub(crate) remote: Option<Remote<'a>>,
+    pub(crate) notes: Notes<'a>,
+    pub(crate) encoding: Encoding,
+    /// Conversions applied in order after decoding the patch file.
+    pub(crate) transforms: Vec<Transform>,
+    /// Package the item comes from unless it is from the workspace.
+    pub(crate) origin: Option<Origin>,
+}
+
+/// Package providing patch items, via its metadata or a recipe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Origin {
+    /// `name-version` of the package.
+    pub(crate) name: String,
+    /// Root of the package source.
+    pub(crate) root: PathBuf,
+}
+
+impl Origin {
+    pub(crate) fn new(package: &Package) -> Self {
+        Self {
+            name: format!("{}-{}", package.name(), package.version()),
+            root: package.root().to_path_buf(),
+        }
+    }
+}
+
+/// Source an entry is restricted to, for dependencies which are in the
+/// dependency graph from several sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PackageSource<'a> {
+    /// crates.io, selected with `registry = "crates-io"`.
+    CratesIo,
+    /// A git repository, selected with `source-git = "<url>"`.
+    Git(&'a str),
+}
+
+impl PackageSource<'_> {
+    pub(crate) fn matches(&self, source: SourceId) -> bool {
+        // `https://github.com/serde-rs/serde.git/` and `…/serde` are the same
+        let normalize = |url: &str| {
+            url.trim_end_matches('/')
+                .trim_end_matches(".git")
+                .to_ascii_lowercase()
+        };
+        match self {
+            Self::CratesIo => source.is_crates_io(),
+            Self::Git(url) => {
+                source.is_git() && normalize(source.url().as_str()) == normalize(url)
+            }
+        }
+    }
+}
+
+/// Versions an entry is restricted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum VersionFilter {
+    /// A semver requirement, which like in cargo only matches pre-releases
+    /// of the same `major.minor.patch`, e.g. `=1.0.0-alpha.1`.
+    Req(VersionReq),
+    /// The version as written in the lockfile, selected with
+    /// `exact-version = true`. Pre-release and build metadata have to match
+    /// as well.
+    Exact(Version),
+}
+
+impl VersionFilter {
+    pub(crate) fn matches(&self, version: &Version) -> bool {
+        match self {
+            Self::Req(req) => req.matches(version),
+            Self::Exact(exact) => exact == version,
+        }
+    }
+}
+
+impl Display for VersionFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Req(req) => Display::fmt(req, f),
+            Self::Exact(version) => write!(f, "={version}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PatchEntry<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) version: Option<VersionFilter>,
+    /// Source the entry is restricted to, any source if `None`.
+    pub(crate) package_source: Option<PackageSource<'a>>,
+    pub(crate) patches: Vec<PatchItem<'a>>,
+    pub(crate) version_suffix: Option<String>,
+    pub(crate) allow_build_script_changes: bool,
+    pub(crate) all_versions: bool,
+    pub(crate) backend: Backend,
+    pub(crate) use_orig_manifest: bool,
+    pub(crate) copy_repo_root: bool,
+    /// Profiles the entry is restricted to, all profiles if `None`.
+    pub(crate) profiles: Option<Vec<String>>,
+    pub(crate) strip_dev_targets: bool,
+    /// Replacement for the `links` key of the patched manifest.
+    pub(crate) links: Option<String>,
+    /// Initialize a git repository in the copy with one commit per patch.
+    pub(crate) git_init: bool,
+    /// Gitignore-style file listing paths removed from the copy.
+    pub(crate) copy_ignore: Option<&'a Path>,
+    /// `replace-snippet` items, applied after the patch files.
+    pub(crate) snippets: Vec<Snippet<'a>>,
+    /// Regex substitutions across the files of the package.
+    pub(crate) sed: Vec<SedEdit<'a>>,
+    /// Name of the copy's directory instead of `<name>-<version>`.
+    pub(crate) folder_name: Option<&'a str>,
+    /// Dependencies of the package whose patched copy or pristine crate its
+    /// copy is built with, overriding `link-patched`.
+    pub(crate) link_deps: BTreeMap<&'a str, LinkMode>,
+    /// Patched packages the copy depends on with the paths of their copies,
+    /// which its manifest is pointed to, see [`order::link`].
+    pub(crate) linked: Vec<(PackageId, PathBuf)>,
+}
+
+/// Options controlling a [`patch_with_options`] run.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Options {
+    /// Only read patch files from the allowed patch roots (the workspace
+    /// root unless configured otherwise) and never access the network.
+    pub restrict: bool,
+    /// Never access the network.
+    pub offline: bool,
+    /// Access the network when called from a build script. This is denied
+    /// by default as cargo already downloaded all dependencies and some
+    /// sandboxed builders forbid it.
+    pub build_script_network: bool,
+    /// Turn warnings about risky patches and unused configuration into errors.
+    pub strict: bool,
+    /// Print the changes of every patched file before writing it.
+    pub show_diff: bool,
+    /// Keep running and re-apply the patches of a package whenever one of
+    /// its patch files changes.
+    pub watch: bool,
+    /// Download remote patch files again regardless of their cache policy.
+    pub refresh_patches: bool,
+    /// Fail instead of warning if a git dependency referenced by a branch
+    /// or tag resolves to another commit than its patched copy was created
+    /// from. The existing copy is left untouched.
+    pub frozen_copy: bool,
+    /// Fail if a patch only applies with offsets or fuzz, so patch files
+    /// are regenerated whenever upstream moves.
+    pub strict_apply: bool,
+    /// Write everything printed for a package and the details of every
+    /// applied hunk to `target/patch/.logs/<name>-<version>.log`.
+    pub logs: bool,
+    /// Continue with the remaining packages if patching one fails and
+    /// report all failures at the end.
+    pub keep_going: bool,
+    /// Print the packages which would be patched and the items applied to
+    /// each of them in order instead of patching.
+    pub explain: bool,
+    /// Run `cargo check` for every patched package and then the workspace
+    /// after patching, naming the patches of a package which fails to build.
+    pub test_build: bool,
+    /// Look up the newest release of every patched package from a registry
+    /// and warn if it contains the fix of a patch with `fixed-in`.
+    pub check_upstream: bool,
+    /// Check strictly, continue after failures, print failed hunks as
+    /// `file:line: error: ...` and write a summary to
+    /// `target/cargo-patch-summary.json`, for CI jobs.
+    pub ci: bool,
+    /// Generate `Cargo.lock` before resolving if the workspace has none, so
+    /// the patched versions are the ones the build uses.
+    pub allow_lock_update: bool,
+    /// Target triples like cargo's `--target`. If set, only dependencies
+    /// built for one of them are patched, and `--test-build` checks for
+    /// them. Otherwise dependencies of all targets are patched.
+    pub targets: Vec<String>,
+    /// Comment out the `[patch]` override of a package in `Cargo.toml` if
+    /// it fails to patch, so builds use the pristine crate instead of a
+    /// broken copy.
+    pub disable_failed_overrides: bool,
+    /// Unstable `-Z` flags, only accepted if the invoking cargo is nightly.
+    pub unstable_flags: Vec<String>,
+    /// Config overrides like cargo's `--config KEY=VALUE`.
+    pub config: Vec<String>,
+    /// How progress is printed.
+    pub output: OutputStyle,
+    /// Write a report of the time spent in each phase in this format.
+    pub timings: Option<TimingsFormat>,
+    /// How override paths are written into generated `[patch]` sections and
+    /// provenance files.
+    pub override_path_style: OverridePathStyle,
+    /// Whether cargo-style output is colored: `auto`, `always` or `never`.
+    pub color: Option<String>,
+    /// Profile selecting the entries restricted to profiles. Falls back to
+    /// the `CARGO_PATCH_PROFILE` environment variable and then `dev`.
+    pub profile: Option<String>,
+    /// Named patch sets from `patch-profiles` whose entries are applied in
+    /// addition to the regular ones.
+    pub sets: Vec<String>,
+    /// Patch only this dependency with [`Options::patches`] and ignore the
+    /// configured entries, e.g. to try a patch before configuring it.
+    pub crate_name: Option<String>,
+    /// Version requirement of [`Options::crate_name`].
+    pub crate_version: Option<String>,
+    /// Patch files applied to [`Options::crate_name`].
+    pub patches: Vec<PathBuf>,
+    /// Give up waiting for the package cache lock held by another cargo
+    /// process after this long instead of waiting forever.
+    pub lock_timeout: Option<Duration>,
+    /// Write the content hash of every patched copy to this JSON file, see
+    /// [`hashes`].
+    pub emit_hashes: Option<PathBuf>,
+    /// Write the patched packages and the hashes of their patch files to
+    /// [`lockfile::LOCK_FILE`].
+    pub write_lock: bool,
+    /// Fail before patching if the patched packages or patch files differ
+    /// from [`lockfile::LOCK_FILE`].
+    pub locked_metadata: bool,
+    /// Patch into this directory without network access, only from locally
+    /// available sources and with deterministic timestamps and permissions,
+    /// e.g. inside of a Nix derivation.
+    pub pure: Option<PathBuf>,
+}
+
+/// Workspace wide settings from `[workspace.metadata.patch-config]` and
+/// the `[cargo-patch]` table of the cargo config.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PatchConfig {
+    pub(crate) trusted_keys: Vec<String>,
+    pub(crate) require_signatures: bool,
+    pub(crate) allowed_roots: Option<Vec<PathBuf>>,
+    /// Restricted mode, see [`Options::restrict`]. Remote patches are
+    /// rejected, even when they are cached.
+    pub(crate) restrict: bool,
+    /// URL prefixes remote patches may be downloaded from when
+    /// `allowed_roots` is set.
+    pub(crate) allowed_urls: Vec<String>,
+    pub(crate) dependency_metadata: Vec<String>,
+    pub(crate) offline: bool,
+    pub(crate) refresh_patches: bool,
+    pub(crate) lockfile_hash: String,
+    pub(crate) http: HttpConfig,
+    /// Directory patched copies are kept in across `cargo clean`.
+    pub(crate) persist: Option<PathBuf>,
+    /// Leave directories in `target/patch` not created by cargo-patch alone.
+    pub(crate) keep_unrelated: bool,
+    /// Fail if an external backend applies a patch with offsets or fuzz.
+    pub(crate) strict_apply: bool,
+    /// Keep the permissions of the sources in the copies instead of making
+    /// every file writable.
+    pub(crate) preserve_permissions: bool,
+    /// Sync written files and their directories to disk.
+    pub(crate) fsync: bool,
+    /// Fetch all branches and tags for patches from git commits instead of
+    /// only the commits, for servers which don't support shallow fetches.
+    pub(crate) git_full_fetch: bool,
+    /// Point the dependencies of copies on other patched packages to their
+    /// copies.
+    pub(crate) link_patched: bool,
+    /// Give the files of copies deterministic permissions, see
+    /// [`Options::pure`].
+    pub(crate) pure: bool,
+    /// Modification time of all files of the copies, see [`mtime`].
+    pub(crate) mtime: Option<SystemTime>,
+    /// Active profile, see [`Options::profile`].
+    pub(crate) profile: String,
+    /// Active patch sets, see [`Options::sets`].
+    pub(crate) sets: Vec<String>,
+    /// Entry given on the command line, see [`Options::crate_name`].
+    pub(crate) cli_entry: Option<CliEntry>,
+    /// Patch recipes included via `extends` with the directory containing
+    /// them.
+    pub(crate) recipes: Vec<(PathBuf, Origin, Value)>,
+    /// Dependencies in the graph from more than one source, whose copies
+    /// get the source in their directory name.
+    pub(crate) multi_source: BTreeSet<String>,
+    /// Directory names of copies set with `folder-name`.
+    pub(crate) folder_names: BTreeMap<PackageId, String>,
+    /// Root of the workspace the overrides are written for.
+    pub(crate) root: PathBuf,
+    pub(crate) override_path_style: OverridePathStyle,
+}
+
+/// Patch entry built from command line flags instead of metadata.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CliEntry {
+    pub(crate) name: String,
+    pub(crate) version: Option<VersionFilter>,
+    pub(crate) patches: Vec<PathBuf>,
+}
+
+impl CliEntry {
+    pub(crate) fn from_options(options: &Options) -> Result<Option<Self>> {
+        let Some(name) = &options.crate_name else {
+            return Ok(None);
+        };
+        let version = options
+            .crate_version
+            .as_deref()
+            .map(VersionReq::parse)
+            .transpose()?
+            .map(VersionFilter::Req);
+        Ok(Some(Self {
+            name: name.clone(),
+            version,
+            patches: options.patches.clone(),
+        }))
+    }
+
+    pub(crate) fn entry(&self) -> PatchEntry<'_> {
+        PatchEntry {
+            name: &self.name,
+            version: self.version.clone(),
+            patches: self
+                .patches
+                .iter()
+                .map(|path| PatchItem {
+                    path: Cow::Borrowed(path),
+                    source: PatchSource::default(),
+                    final_newline: FinalNewline::default(),
+                    signature: None,
+                    remote: None,
+                    notes: Notes::default(),
+                    encoding: Encoding::default(),
+                    transforms: vec![],
+                    origin: None,
+                })
+                .collect(),
+            ..PatchEntry::default()
+        }
+    }
+}
+
+impl PatchConfig {
+    /// Returns the directory the patched copies are written to.
+    pub(crate) fn output_dir(&self) -> &Path {
+        self.persist
+            .as_deref()
+            .unwrap_or_else(|| Path::new("target/patch"))
+    }
+
+    /// Fails if the file lies outside of the allowed patch roots.
+    pub(crate) fn check_allowed(&self, path: &Path) -> Result<()> {
+        let Some(roots) = &self.allowed_roots else {
+            return Ok(());
+        };
+        // missing files are reported when they are read, until then the
+        // nearest existing directory decides where they would be read from
+        let (dir, rest) = path
+            .ancestors()
+            .find_map(|dir| {
+                let existing = if dir.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    dir
+                };
+                let rest = path.strip_prefix(dir).ok()?;
+                Some((existing.canonicalize().ok()?, rest))
+            })
+            .ok_or_else(|| {
+                anyhow!("Unable to resolve patch file {}", path.display())
+            })?;
+        let path = if rest.as_os_str().is_empty() {
+            dir
+        } else {
+            dir.join(rest)
+        };
+        let resolved = rest.components().all(|component| {
+            matches!(component, Component::Normal(_) | Component::CurDir)
+        });
+        if resolved && roots.iter().any(|root| path.starts_with(root)) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Patch file {} is outside of the allowed patch roots",
+                path.display()
+            ))
+        }
+    }
+
+    /// Returns the URLs remote patches may be downloaded from, if they are
+    /// restricted, which they are together with the patch roots.
+    pub(crate) fn allowed_urls(&self) -> Option<&[String]> {
+        self.allowed_roots
+            .as_ref()
+            .map(|_| self.allowed_urls.as_slice())
+    }
+
+    /// Fails if the item is a remote patch and remote patches are disabled
+    /// or its URL lies outside of the allowed patch URLs.
+    pub(crate) fn check_remote(&self, item: &PatchItem<'_>) -> Result<()> {
+        match &item.remote {
+            Some(remote) if self.restrict => Err(anyhow!(
+                "Remote patch {} is not allowed in restricted mode",
+                remote.label()
+            )),
+            Some(remote)
+                if self.allowed_urls().is_some_and(|allowed| {
+                    !remote::is_allowed(remote.url, allowed)
+                }) =>
+            {
+                Err(anyhow!(
+                    "Remote patch {} is outside of the allowed patch URLs",
+                    remote.label()
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl PatchItem<'_> {
+    /// Returns how the item is named in reports: the URL of remote patches,
+    /// otherwise the path relative to the workspace `root`, or prefixed with
+    /// `name-version` relative to the package providing it, with `/`
+    /// separators.
+    pub(crate) fn label(&self, root: &Path) -> String {
+        if let Some(remote) = &self.remote {
+            return remote.label();
+        }
+        let (prefix, root) = self
+            .origin
+            .as_ref()
+            .map_or((None, root), |origin| (Some(&origin.name), &*origin.root));
+        let relative = self.path.strip_prefix(root).unwrap_or(&self.path);
+        prefix
+            .map(|name| Cow::Owned(name.clone()))
+            .into_iter()
+            .chain(
+                relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy()),
+            )
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Resolves the paths of the item against `base` instead of the current
+    /// directory, for items provided by the package `origin`.
+    pub(crate) fn relative_to(self, base: &Path, origin: &Origin) -> Self {
+        if self.remote.is_some() {
+            return self;
+        }
+        Self {
+            path: Cow::Owned(base.join(&self.path)),
+            signature: self
+                .signature
+                .map(|signature| Cow::Owned(base.join(signature))),
+            origin: Some(origin.clone()),
+            ..self
+        }
+    }
+}
+
+impl<'a> PatchEntry<'a> {
+    /// Whether the entry applies when patching for `profile`.
+    pub(crate) fn in_profile(&self, profile: &str) -> bool {
+        self.profiles
+            .as_ref()
+            .map_or(true, |profiles| profiles.iter().any(|p| p == profile))
+    }
+
+    /// Appends the items of another entry for the same package after the
+    /// own ones, skipping items which are already there. The settings of
+    /// `self` win.
+    pub(crate) fn merge(&mut self, other: Self) {
+        push_unique(&mut self.patches, other.patches);
+        push_unique(&mut self.snippets, other.snippets);
+        push_unique(&mut self.sed, other.sed);
+        for (name, mode) in other.link_deps {
+            let _ = self.link_deps.entry(name).or_insert(mode);
+        }
+    }
+
+    /// Describes the items in the order they are applied: sed edits
+    /// running before the patch files, the patch files, the remaining sed
+    /// edits and the snippets.
+    pub(crate) fn steps(&self, root: &Path) -> Vec<String> {
+        let sed = |before| {
+            self.sed
+                .iter()
+                .filter(move |edit| edit.before == before)
+                .map(|edit| format!("sed `{}` in {}", edit.pattern, edit.glob))
+        };
+        let patches = self.patches.iter().map(|item| item.label(root));
+        let snippets = self
+            .snippets
+            .iter()
+            .map(|snippet| format!("replace-snippet in {}", snippet.file));
+        sed(true)
+            .chain(patches)
+            .chain(sed(false))
+            .chain(snippets)
+            .collect()
+    }
+
+    /// Removes repeated items, keeping the first one.
+    pub(crate) fn dedup(&mut self) {
+        let entry = Self {
+            patches: vec![],
+            snippets: vec![],
+            sed: vec![],
+            ..self.clone()
+        };
+        let other = std::mem::replace(self, entry);
+        self.merge(other);
+    }
+}
+
+/// Appends the `new` items which aren't in `items` yet.
+pub(crate) fn push_unique<T: PartialEq>(items: &mut Vec<T>, new: Vec<T>) {
+    for item in new {
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+}
+
+impl FinalNewline {
+    fn from_str(s: &str) -> Self {
+        match s {
+            "preserve" => Self::Preserve,
+            "always" => Self::Always,
+            "never" => Self::Never,
+            &_ => {
+                eprintln!("Unknown final newline policy: {s}");
+                Self::Preserve
+            }
+        }
+    }
+}
+
+impl PatchSource {
+    fn from_str(s: &str) -> Self {
+        match s {
+            "Default" => Self::Default,
+            "GithubPrDiff" => Self::GithubPrDiff,
+            "GitDiff" => Self::GitDiff,
+            &_ => {
+                eprintln!("Unknown patch source: {s}");
+                Self::Default
+            }
+        }
+    }
+}
+
+/// Removes the previous patched copies. With `keep-unrelated` only copies of
+/// the given `packages` and directories created by cargo-patch are removed.
+#[allow(clippy::wildcard_enum_match_arm)]
+pub(crate) fn clear_patch_folder(
+    config: &PatchConfig,
+    packages: &[&Package],
+) -> Result<()> {
+    if !config.keep_unrelated {
+        return match fs::remove_dir_all(long_path(config.output_dir())?) {
+            Ok(_) => Ok(()),
+            Err(err) => match err.kind() {
+                ErrorKind::NotFound => Ok(()),
+                _ => Err(err.into()),
+            },
+        };
+    }
+    let roots = packages
+        .iter()
+        .map(|package| Ok(long_path(&copy_root(package, config))?))
+        .collect::<Result<Vec<_>>>()?;
+    let entries = match fs::read_dir(long_path(config.output_dir())?) {
+        Ok(entries) => entries,
+        Err(err) => match err.kind() {
+            ErrorKind::NotFound => return Ok(()),
+            _ => return Err(err.into()),
+        },
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if roots.contains(&path) || provenance::is_copy(&path) {
+            fs::remove_dir_all(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes the copies in the persisted output directory which don't belong
+/// to any of the given `packages` anymore, e.g. after a version bump. Only
+/// directories created by cargo-patch are touched.
+#[allow(clippy::wildcard_enum_match_arm)]
+pub(crate) fn prune_patch_folder(
+    config: &PatchConfig,
+    packages: &[&Package],
+) -> Result<()> {
+    let roots = packages
+        .iter()
+        .map(|package| Ok(long_path(&copy_root(package, config))?))
+        .collect::<Result<Vec<_>>>()?;
+    let entries = match fs::read_dir(long_path(config.output_dir())?) {
+        Ok(entries) => entries,
+        Err(err) => match err.kind() {
+            ErrorKind::NotFound => return Ok(()),
+            _ => return Err(err.into()),
+        },
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if !roots.contains(&path) && provenance::is_copy(&path) {
+            fs::remove_dir_all(&path)?;
+            let name = entry.file_name();
+            status::status(
+                "Removed",
+                format!("unused copy {}", name.to_string_lossy()),
+            );
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn is_restricted(
+    gctx: &GlobalContext,
+    options: &Options,
+) -> Result<bool> {
+    Ok(options.restrict
+        || gctx
+            .get::<Option<bool>>("cargo-patch.restrict")?
+            .unwrap_or_default())
+}
+
+/// Returns whether the cargo this subcommand was invoked by, e.g. with
+/// `cargo +nightly patch`, allows unstable features.
+pub(crate) fn invoked_by_nightly() -> bool {
+    env::var_os("CARGO")
+        .and_then(|cargo| Command::new(cargo).arg("--version").output().ok())
+        .is_some_and(|output| {
+            let version = String::from_utf8_lossy(&output.stdout);
+            version.contains("-nightly") || version.contains("-dev")
+        })
+}
+
+/// Returns whether cargo-patch is called from a build script.
+pub(crate) fn in_build_script() -> bool {
+    env::var_os("OUT_DIR").is_some() && env::var_os("CARGO_MANIFEST_DIR").is_some()
+}
+
+/// Creates the cargo context. Like cargo itself it uses `CARGO_HOME` and the
+/// cargo config of the current directory, so the same registries and
+/// credentials as the invoking cargo are used.
+pub(crate) fn setup_gctx(options: &Options) -> Result<GlobalContext> {
+    let mut gctx = GlobalContext::default()?;
+    gctx.nightly_features_allowed |= invoked_by_nightly();
+    let restrict = is_restricted(&gctx, options)?;
+    status::init(options)?;
+    gctx.configure(
+        0,
+        true,
+        options.color.as_deref(),
+        options.pure.is_some(),
+        false,
+        options.offline
+            || options.pure.is_some()
+            || restrict
+            || (in_build_script() && !options.build_script_network),
+        &None,
+        &options.unstable_flags,
+        &options.config,
+    )?;
+    gctx.shell().set_verbosity(Verbosity::Quiet);
+    Ok(gctx)
+}
+
+/// Acquires the package cache lock like cargo. If another cargo process
+/// holds it, a `Blocking` line is printed as the shell of the context is
+/// quiet, and the lock is polled until [`Options::lock_timeout`] expires.
+pub(crate) fn lock_package_cache<'a>(
+    gctx: &'a GlobalContext,
+    options: &Options,
+) -> Result<CacheLock<'a>> {
+    if let Some(lock) = gctx.try_acquire_package_cache_lock(DownloadExclusive)? {
+        return Ok(lock);
+    }
+    status::status("Blocking", "waiting for file lock on package cache");
+    let start = Instant::now();
+    loop {
+        thread::sleep(Duration::from_millis(100));
+        interrupt::check()?;
+        if let Some(lock) = gctx.try_acquire_package_cache_lock(DownloadExclusive)? {
+            return Ok(lock);
+        }
+        if let Some(timeout) = options.lock_timeout {
+            if start.elapsed() >= timeout {
+                return Err(anyhow!(
+                    "Timed out after {} waiting for the package cache lock held by another cargo process",
+                    humantime::format_duration(timeout)
+                ));
+            }
+        }
+    }
+}
+
+pub(crate) fn find_cargo_toml(path: &Path) -> Result<PathBuf> {
+    let path = fs::canonicalize(path)?;
+    find_root_manifest_for_wd(&path)
+}
+
+pub(crate) fn fetch_workspace<'gctx>(
+    gctx: &'gctx GlobalContext,
+    path: &Path,
+) -> Result<Workspace<'gctx>> {
+    Workspace::new(path, gctx)
+}
+
+/// Without `Cargo.lock` dependencies resolve to the newest matching
+/// versions, which the next build may not pick, e.g. once it writes its own
+/// lockfile after a registry update. With [`Options::allow_lock_update`]
+/// the lockfile is generated first and both use the same versions.
+pub(crate) fn ensure_lockfile(
+    workspace: &Workspace<'_>,
+    options: &Options,
+) -> Result<()> {
+    if !options.allow_lock_update || workspace.root().join("Cargo.lock").exists() {
+        return Ok(());
+    }
+    generate_lockfile(workspace).map_err(Error::ResolveFailed)?;
+    status::status("Generated", "Cargo.lock");
+    Ok(())
+}
+
+pub(crate) fn resolve_ws<'a>(
+    ws: &Workspace<'a>,
+) -> Result<(PackageSet<'a>, Resolve)> {
+    resolve_packages(ws).map_err(|err| Error::ResolveFailed(err).into())
+}
+
+pub(crate) fn resolve_packages<'a>(
+    ws: &Workspace<'a>,
+) -> Result<(PackageSet<'a>, Resolve)> {
+    let scm = SourceConfigMap::new(ws.gctx())?;
+    let mut registry = PackageRegistry::new_with_source_config(ws.gctx(), scm)?;
+
+    registry.lock_patches();
+    let resolve = {
+        let prev = load_pkg_lockfile(ws)?;
+        let resolve: Resolve = resolve_with_previous(
+            &mut registry,
+            ws,
+            &CliFeatures::new_all(true),
+            HasDevUnits::No,
+            prev.as_ref(),
+            None,
+            &[],
+            false,
+        )?;
+        resolve
+    };
+    let packages = get_resolved_packages(&resolve, registry)?;
+    Ok((packages, resolve))
+}
+
+pub(crate) fn get_patches(
+    custom_metadata: &Value,
+) -> impl Iterator<Item = PatchEntry<'_>> + '_ {
+    custom_metadata
+        .as_table()
+        .and_then(|table| table.get("patch"))
+        .into_iter()
+        .flat_map(get_entries)
+}
+
+/// Returns the named patch sets of `patch-profiles`, whose entries are only
+/// applied if the set is activated with `--set`.
+pub(crate) fn get_patch_sets(
+    custom_metadata: &Value,
+) -> impl Iterator<Item = (&str, &Value)> + '_ {
+    custom_metadata
+        .as_table()
+        .and_then(|table| table.get("patch-profiles"))
+        .and_then(Value::as_table)
+        .into_iter()
+        .flatten()
+        .map(|(set, entries)| (set.as_str(), entries))
+}
+
+/// Parses a table of patch entries keyed by the name of the dependency.
+pub(crate) fn get_entries(
+    entries: &Value,
+) -> impl Iterator<Item = PatchEntry<'_>> + '_ {
+    entries.as_table().into_iter().flat_map(|table| {
+        table
+            .into_iter()
+            .flat_map(|(k, v)| parse_patch_entries(k, v))
+    })
+}
+
+/// Parses an entry, which applies to every dependency listed in `packages`
+/// instead of the one it is named after if that is set.
+pub(crate) fn parse_patch_entries<'a>(
+    name: &'a str,
+    value: &'a Value,
+) -> Vec<PatchEntry<'a>> {
+    let Some(entry) = parse_patch_entry(name, value) else {
+        return vec![];
+    };
+    let Some(packages) = value.get("packages") else {
+        return vec![entry];
+    };
+    let Some(packages) = packages.as_array().and_then(|packages| {
+        packages
+            .iter()
+            .map(Value::as_str)
+            .collect::<Option<Vec<_>>>()
+    }) else {
+        eprintln!("Packages must be an array of strings: {packages}");
+        return vec![];
+    };
+    packages
+        .into_iter()
+        .map(|package| PatchEntry {
+            name: package,
+            ..entry.clone()
+        })
+        .collect()
+}
+
+/// Returns the entries of the patch sets activated with `--set`. Fails if
+/// one of them isn't defined.
+pub(crate) fn active_set_patches<'a>(
+    workspace: &'a Workspace<'_>,
+    config: &PatchConfig,
+) -> Result<Vec<PatchEntry<'a>>> {
+    let mut patches = vec![];
+    for set in &config.sets {
+        let entries = custom_metadata(workspace)
+            .flat_map(get_patch_sets)
+            .filter(|(name, _)| name == set)
+            .map(|(_, entries)| entries)
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Err(anyhow!(
+                "Patch set {set} is not defined in any `patch-profiles` table"
+            ));
+        }
+        patches.extend(entries.into_iter().flat_map(get_entries));
+    }
+    Ok(patches)
+}
+
+pub(crate) fn parse_patch_entry<'a>(
+    name: &'a str,
+    entry: &'a Value,
+) -> Option<PatchEntry<'a>> {
+    let entry = entry.as_table().or_else(|| {
+        eprintln!("Entry {name} must contain a table.");
+        None
+    })?;
+
+    let exact_version = entry
+        .get("exact-version")
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
+    let version = entry.get("version").and_then(|version| {
+        if exact_version {
+            let value = version
+                .as_str()
+                .and_then(|s| Version::parse(s.trim_start_matches('=')).ok())
+                .map(VersionFilter::Exact);
+            if value.is_none() {
+                eprintln!("Version must be a semver version with `exact-version = true`: {version}");
+            }
+            return value;
+        }
+        let value = version.as_str().and_then(|s| VersionReq::parse(s).ok());
+        if value.is_none() {
+            eprintln!("Version must be a value semver string: {version}");
+        }
+        value.map(VersionFilter::Req)
+    });
+    if exact_version && !entry.contains_key("version") {
+        eprintln!("Entry {name} sets `exact-version = true` without a version");
+    }
+
+    let package_source = match entry.get("registry") {
+        Some(registry) if registry.as_str() == Some("crates-io") => {
+            Some(PackageSource::CratesIo)
+        }
+        Some(registry) => {
+            eprintln!("Registry must be \"crates-io\": {registry}");
+            None
+        }
+        None => entry.get("source-git").and_then(|url| {
+            let value = url.as_str().map(PackageSource::Git);
+            if value.is_none() {
+                eprintln!("Source git must be a url string: {url}");
+            }
+            value
+        }),
+    };
+    let source = entry
+        .get("source")
+        .and_then(Value::as_str)
+        .map_or_else(Default::default, PatchSource::from_str);
+    let final_newline = entry
+        .get("final-newline")
+        .and_then(Value::as_str)
+        .map_or_else(Default::default, FinalNewline::from_str);
+
+    let (snippets, patches): (Vec<_>, Vec<_>) = entry
+        .get("patches")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .partition(|patch| patch.get("replace-snippet").is_some());
+    let patches = patches
+        .into_iter()
+        .filter_map(|patch| parse_patch_item(patch, &source, final_newline))
+        .collect();
+    let snippets = snippets
+        .into_iter()
+        .filter_map(|patch| {
+            let value = patch.get("replace-snippet").and_then(Snippet::parse);
+            if value.is_none() {
+                eprintln!("Replace snippet must be a table with file, find and replace: {patch}");
+            }
+            value
+        })
+        .collect();
+    let sed = entry
+        .get("sed")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let value = SedEdit::parse(item);
+            if value.is_none() {
+                eprintln!("Sed edit must be a table with glob, pattern and replace, an optional count and when = \"before\" or \"after\": {item}");
+            }
+            value
+        })
+        .collect();
+
+    let version_suffix = entry.get("version-suffix").and_then(|suffix| {
+        let value = match suffix {
+            Value::Boolean(true) => Some("patched"),
+            Value::Boolean(false) => return None,
+            _ => suffix.as_str(),
+        };
+        let value = value.filter(|s| BuildMetadata::new(s).is_ok());
+        if value.is_none() {
+            eprintln!("Version suffix must be a boolean or a valid semver build metadata string: {suffix}");
+        }
+        value.map(str::to_owned)
+    });
+
+    Some(PatchEntry {
+        name,
+        version,
+        package_source,
+        patches,
+        version_suffix,
+        allow_build_script_changes: entry
+            .get("allow-build-script-changes")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        all_versions: entry
+            .get("all-versions")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        backend: entry
+            .get("backend")
+            .and_then(Value::as_str)
+            .map_or_else(Backend::default, Backend::from_str),
+        use_orig_manifest: entry
+            .get("use-orig-manifest")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        copy_repo_root: entry
+            .get("copy-repo-root")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        profiles: entry.get("profile").and_then(|profile| {
+            let value = match profile {
+                Value::String(profile) => Some(vec![profile.clone()]),
+                Value::Array(profiles) => profiles
+                    .iter()
+                    .map(|profile| profile.as_str().map(str::to_owned))
+                    .collect(),
+                _ => None,
+            };
+            if value.is_none() {
+                eprintln!(
+                    "Profile must be a string or an array of strings: {profile}"
+                );
+            }
+            value
+        }),
+        strip_dev_targets: entry
+            .get("strip-dev-targets")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        links: entry.get("links").and_then(|links| {
+            let value = links.as_str().map(str::to_owned);
+            if value.is_none() {
+                eprintln!("Links must be a string: {links}");
+            }
+            value
+        }),
+        git_init: entry
+            .get("git-init")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        copy_ignore: entry.get("copy-ignore").and_then(|file| {
+            let value = file.as_str().map(Path::new);
+            if value.is_none() {
+                eprintln!("Copy ignore must be a path string: {file}");
+            }
+            value
+        }),
+        snippets,
+        sed,
+        folder_name: entry.get("folder-name").and_then(|folder| {
+            let value = folder.as_str().filter(|folder| {
+                !folder.is_empty()
+                    && !folder.starts_with('.')
+                    && !folder.contains(['/', '\\'])
+            });
+            if value.is_none() {
+                eprintln!("Folder name must be a directory name not starting with a dot: {folder}");
+            }
+            value
+        }),
+        link_deps: entry
+            .get("link-deps")
+            .and_then(order::parse_link_deps)
+            .unwrap_or_default(),
+        linked: vec![],
+    })
+}
+
+pub(crate) fn parse_patch_item<'a>(
+    patch: &'a Value,
+    default_source: &PatchSource,
+    default_final_newline: FinalNewline,
+) -> Option<PatchItem<'a>> {
+    let item = match patch {
+        Value::String(path) => Some(PatchItem {
+            path: Cow::Borrowed(Path::new(path)),
+            source: default_source.clone(),
+            final_newline: default_final_newline,
+            signature: None,
+            remote: None,
+            notes: Notes::default(),
+            encoding: Encoding::default(),
+            transforms: vec![],
+            origin: None,
+        }),
+        Value::Table(item) => {
+            let cache = item
+                .get("cache")
+                .and_then(Value::as_str)
+                .map_or_else(CachePolicy::default, CachePolicy::from_str);
+            let commit = item.get("git").and_then(Value::as_str).and_then(|url| {
+                let rev = item.get("rev").and_then(Value::as_str);
+                if rev.is_none() {
+                    eprintln!("Patch from git repository {url} needs a `rev`");
+                }
+                Some((url, rev?))
+            });
+            let remote = item
+                .get("url")
+                .and_then(Value::as_str)
+                .map(|url| Remote {
+                    url,
+                    cache,
+                    rev: None,
+                })
+                .or_else(|| {
+                    commit.map(|(url, rev)| Remote {
+                        url,
+                        cache,
+                        rev: Some(rev),
+                    })
+                });
+            item.get("path")
+                .and_then(Value::as_str)
+                .or_else(|| remote.as_ref().map(|remote| remote.url))
+                .map(|path| PatchItem {
+                    path: Cow::Borrowed(Path::new(path)),
+                    // `git diff` prefixes paths with `a/` and `b/`
+                    source: item.get("source").and_then(Value::as_str).map_or_else(
+                        || {
+                            if commit.is_some() {
+                                PatchSource::GitDiff
+                            } else {
+                                default_source.clone()
+                            }
+                        },
+                        PatchSource::from_str,
+                    ),
+                    final_newline: item
+                        .get("final-newline")
+                        .and_then(Value::as_str)
+                        .map_or(default_final_newline, FinalNewline::from_str),
+                    signature: item
+                        .get("signature")
+                        .and_then(Value::as_str)
+                        .map(|path| Cow::Borrowed(Path::new(path))),
+                    remote,
+                    notes: Notes::parse(item),
+                    encoding: item
+                        .get("encoding")
+                        .and_then(Value::as_str)
+                        .map_or_else(Encoding::default, Encoding::from_str),
+                    transforms: item
+                        .get("transform")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Value::as_str)
+                        .filter_map(Transform::from_str)
+                        .collect(),
+                    origin: None,
+                })
+        }
+        _ => None,
+    };
+    if item.is_none() {
+        eprintln!(
+            "Patch Entry must be a string or a table with path, url or git: {patch}"
+        );
+    }
+    item
+}
+
+/// Returns the directories patch files may be read from. These come from
+/// `cargo-patch.allowed-roots` in the cargo config (never from the possibly
+/// untrusted workspace) or default to the workspace root in restricted mode.
+pub(crate) fn allowed_roots(
+    gctx: &GlobalContext,
+    workspace: &Workspace<'_>,
+    options: &Options,
+) -> Result<Option<Vec<PathBuf>>> {
+    if let Some(roots) = gctx.get_list("cargo-patch.allowed-roots")? {
+        let roots = roots
+            .val
+            .iter()
+            .map(|(root, def)| def.root(gctx).join(root).canonicalize())
+            .collect::<Result<_, _>>()?;
+        return Ok(Some(roots));
+    }
+    let restrict = is_restricted(gctx, options)?;
+    Ok(restrict
+        .then(|| workspace.root().to_path_buf())
+        .map(|root| vec![root]))
+}
+
+/// Reads a patch recipe given as `crate-name/path/in/crate.toml` out of the
+/// source directory of the resolved package.
+pub(crate) fn load_recipe(
+    recipe: &str,
+    pkg_set: &PackageSet<'_>,
+    resolve: &Resolve,
+) -> Result<(PathBuf, Origin, Value)> {
+    let (name, path) = recipe.split_once('/').ok_or_else(|| {
+        anyhow!("Recipe {recipe} must be of the form `crate-name/patches.toml`")
+    })?;
+    let id = get_id(name, &None, None, resolve).ok_or_else(|| {
+        Error::PackageNotFound {
+            name: name.to_owned(),
+        }
+    })?;
+    let package = pkg_set.get_one(id)?;
+    let path = package.root().join(path);
+    let recipe = fs::read_to_string(&path)
+        .map_err(|err| anyhow!("Unable to read recipe {}: {err}", path.display()))?
+        .parse::<toml::Table>()
+        .map_err(|err| {
+            anyhow!("Unable to parse recipe {}: {err}", path.display())
+        })?;
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    Ok((dir, Origin::new(package), Value::Table(recipe)))
+}
+
+/// Checks the `required-version` of the manifest at `path` and of the
+/// manifests above it up to the workspace root. The files are read directly,
+/// before cargo loads path dependencies, resolves or downloads anything.
+pub(crate) fn check_manifest_versions(path: &Path) -> Result<()> {
+    let manifests = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::ancestors)
+        .map(|dir| dir.join("Cargo.toml"))
+        .filter(|manifest| manifest.is_file());
+    for manifest in manifests {
+        // invalid manifests are reported by cargo when loading the workspace
+        let Ok(table) = fs::read_to_string(&manifest)?.parse::<toml::Table>() else {
+            return Ok(());
+        };
+        for key in ["package", "workspace"] {
+            if let Some(required) = table
+                .get(key)
+                .and_then(|table| table.get("metadata"))
+                .and_then(|metadata| metadata.get("patch-config"))
+                .and_then(|config| config.get("required-version"))
+            {
+                check_required_version(required)?;
+            }
+        }
+        if table.contains_key("workspace") {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Fails if the running cargo-patch doesn't satisfy the `required-version`
+/// of the workspace or one of its members.
+pub(crate) fn check_required_versions(workspace: &Workspace<'_>) -> Result<()> {
+    custom_metadata(workspace)
+        .filter_map(Value::as_table)
+        .filter_map(|table| table.get("patch-config"))
+        .filter_map(Value::as_table)
+        .filter_map(|table| table.get("required-version"))
+        .try_for_each(check_required_version)
+}
+
+/// Fails if the running cargo-patch doesn't satisfy `required`, so teammates
+/// with a stale install don't silently ignore newer settings.
+pub(crate) fn check_required_version(required: &Value) -> Result<()> {
+    let required = required
+        .as_str()
+        .and_then(|req| VersionReq::parse(req).ok())
+        .ok_or_else(|| {
+            anyhow!("Required version must be a semver requirement: {required}")
+        })?;
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    if !required.matches(&current) {
+        return Err(anyhow!(
+            "This workspace requires cargo-patch {required}, but {current} is \
+             running. Upgrade with `cargo install cargo-patch`"
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn get_config(
+    gctx: &GlobalContext,
+    workspace: &Workspace<'_>,
+    pkg_set: &PackageSet<'_>,
+    resolve: &Resolve,
+    options: &Options,
+) -> Result<PatchConfig> {
+    let lockfile = fs::read(workspace.root().join("Cargo.lock")).unwrap_or_default();
+    let mut config = PatchConfig {
+        allowed_roots: allowed_roots(gctx, workspace, options)?,
+        restrict: is_restricted(gctx, options)?,
+        allowed_urls: gctx
+            .get::<Option<Vec<String>>>("cargo-patch.allowed-urls")?
+            .unwrap_or_default(),
+        offline: gctx.offline(),
+        refresh_patches: options.refresh_patches,
+        lockfile_hash: short_hash(&lockfile),
+        root: workspace.root().to_path_buf(),
+        override_path_style: options.override_path_style,
+        strict_apply: options.strict_apply,
+        http: HttpConfig::new(gctx)?,
+        profile: options
+            .profile
+            .clone()
+            .or_else(|| env::var(PROFILE_ENV).ok())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_owned()),
+        sets: options.sets.clone(),
+        cli_entry: CliEntry::from_options(options)?,
+        multi_source: multi_source(resolve),
+        ..PatchConfig::default()
+    };
+    for table in custom_metadata(workspace)
+        .filter_map(Value::as_table)
+        .filter_map(|table| table.get("patch-config"))
+        .filter_map(Value::as_table)
+    {
+        if let Some(keys) = table.get("trusted-keys").and_then(Value::as_array) {
+            config
+                .trusted_keys
+                .extend(keys.iter().filter_map(Value::as_str).map(str::to_owned));
+        }
+        if let Some(require) =
+            table.get("require-signatures").and_then(Value::as_bool)
+        {
+            config.require_signatures |= require;
+        }
+        if table.get("persist").and_then(Value::as_bool) == Some(true) {
+            let dir = table
+                .get("dir")
+                .and_then(Value::as_str)
+                .unwrap_or("patched");
+            config.persist = Some(persist_dir(
+                workspace.root(),
+                dir,
+                config.allowed_roots.as_deref(),
+            )?);
+        }
+        if let Some(keep) = table.get("keep-unrelated").and_then(Value::as_bool) {
+            config.keep_unrelated |= keep;
+        }
+        if let Some(strict) = table.get("strict-apply").and_then(Value::as_bool) {
+            config.strict_apply |= strict;
+        }
+        if let Some(preserve) =
+            table.get("preserve-permissions").and_then(Value::as_bool)
+        {
+            config.preserve_permissions |= preserve;
+        }
+        if let Some(fsync) = table.get("fsync").and_then(Value::as_bool) {
+            config.fsync |= fsync;
+        }
+        if let Some(shallow) = table.get("git-shallow").and_then(Value::as_bool) {
+            config.git_full_fetch |= !shallow;
+        }
+        if let Some(link) = table.get("link-patched").and_then(Value::as_bool) {
+            config.link_patched |= link;
+        }
+        if let Some(mtime) = table.get("mtime") {
+            config.mtime = Some(mtime::parse(mtime, workspace.root())?);
+        }
+        if let Some(names) =
+            table.get("dependency-metadata").and_then(Value::as_array)
+        {
+            config
+                .dependency_metadata
+                .extend(names.iter().filter_map(Value::as_str).map(str::to_owned));
+        }
+        let extends = match table.get("extends") {
+            Some(Value::String(recipe)) => vec![recipe.as_str()],
+            Some(Value::Array(recipes)) => {
+                recipes.iter().filter_map(Value::as_str).collect()
+            }
+            Some(extends) => {
+                eprintln!(
+                    "Extends must be a string or an array of strings: {extends}"
+                );
+                vec![]
+            }
+            None => vec![],
+        };
+        for recipe in extends {
+            config.recipes.push(load_recipe(recipe, pkg_set, resolve)?);
+        }
+    }
+    config.fsync |= gctx
+        .get::<Option<bool>>("cargo-patch.fsync")?
+        .unwrap_or_default();
+    permissions::set_sync(config.fsync);
+    config.git_full_fetch |= !gctx
+        .get::<Option<bool>>("cargo-patch.git-shallow")?
+        .unwrap_or(true);
+    if let Some(dir) = &options.pure {
+        config.persist = Some(env::current_dir()?.join(dir));
+        config.mtime = Some(match config.mtime {
+            Some(mtime) => mtime,
+            None => mtime::source_date_epoch()?,
+        });
+        config.preserve_permissions = false;
+        config.pure = true;
+    }
+    config.folder_names = folder_names(workspace, resolve)?;
+    Ok(config)
+}
+
+/// Returns the `dir` of the `persist` setting below the workspace `root`.
+/// Fails if it is absolute, leaves the root with `..` or through a symlink,
+/// or lies outside of the allowed patch roots in restricted mode.
+fn persist_dir(
+    root: &Path,
+    dir: &str,
+    allowed_roots: Option<&[PathBuf]>,
+) -> Result<PathBuf> {
+    let relative = Path::new(dir);
+    let components = relative.components().collect::<Vec<_>>();
+    if !components.iter().all(|component| {
+        matches!(component, Component::Normal(_) | Component::CurDir)
+    }) || !components
+        .iter()
+        .any(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(anyhow!(
+            "Persist directory must be a relative path below the workspace root: {dir}"
+        ));
+    }
+    let path = root.join(relative);
+    let existing = path.ancestors().find(|path| path.exists()).unwrap_or(root);
+    let resolved = existing
+        .canonicalize()?
+        .join(path.strip_prefix(existing).unwrap_or(relative));
+    if !resolved.starts_with(root.canonicalize()?) {
+        return Err(anyhow!(
+            "Persist directory {dir} is outside of the workspace root"
+        ));
+    }
+    if let Some(roots) = allowed_roots {
+        if !roots.iter().any(|allowed| {
+            resolved.starts_with(allowed) || path.starts_with(allowed)
+        }) {
+            return Err(anyhow!(
+                "Persist directory {} is outside of the allowed patch roots",
+                path.display()
+            ));
+        }
+    }
+    Ok(path)
+}
+
+/// Returns the packages whose entry in the workspace sets `folder-name`
+/// together with it. Fails if an entry matches several packages or two
+/// packages would share a directory.
+pub(crate) fn folder_names(
+    workspace: &Workspace<'_>,
+    resolve: &Resolve,
+) -> Result<BTreeMap<PackageId, String>> {
+    // only entries setting it are parsed, others report their errors later
+    let entries = custom_metadata(workspace)
+        .filter_map(|metadata| metadata.get("patch")?.as_table())
+        .flatten()
+        .filter(|(_, entry)| entry.get("folder-name").is_some())
+        .flat_map(|(name, entry)| parse_patch_entries(name, entry));
+    let mut names = BTreeMap::<PackageId, String>::new();
+    for entry in entries {
+        let Some(folder) = entry.folder_name else {
+            continue;
+        };
+        let ids = matching_ids(
+            entry.name,
+            &entry.version,
+            entry.package_source.as_ref(),
+            resolve,
+        );
+        if ids.len() > 1 {
+            return Err(anyhow!(
+                "Folder name {folder} of {} matches several packages, restrict the entry with `version` or `source`",
+                entry.name
+            ));
+        }
+        for id in ids {
+            if let Some((other, _)) =
+                names.iter().find(|(_, name)| name.as_str() == folder)
+            {
+                return Err(anyhow!(
+                    "Folder name {folder} is used by both {other} and {id}"
+                ));
+            }
+            names.insert(id, folder.to_owned());
+        }
+    }
+    Ok(names)
+}
+
+/// Returns the names of the packages which are in the resolve graph from
+/// more than one source.
+pub(crate) fn multi_source(resolve: &Resolve) -> BTreeSet<String> {
+    let mut sources = BTreeMap::<&str, Vec<SourceId>>::new();
+    for id in resolve.iter() {
+        let sources = sources.entry(id.name().as_str()).or_default();
+        if !sources.contains(&id.source_id()) {
+            sources.push(id.source_id());
+        }
+    }
+    sources
+        .into_iter()
+        .filter(|(_, sources)| sources.len() > 1)
+        .map(|(name, _)| name.to_owned())
+        .collect()
+}
+
+/// Returns all packages in the resolve graph matching name, version and
+/// source, sorted by version.
+pub(crate) fn matching_ids(
+    name: &str,
+    version: &Option<VersionFilter>,
+    source: Option<&PackageSource<'_>>,
+    resolve: &Resolve,
+) -> Vec<PackageId> {
+    let mut ids = resolve
+        .iter()
+        .filter(|dep| {
+            dep.name().as_str() == name
+                && version
+                    .as_ref()
+                    .map_or(true, |ver| ver.matches(dep.version()))
+                && source.map_or(true, |source| source.matches(dep.source_id()))
+        })
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+/// Like [`matching_ids`], but reports missing packages.
+pub(crate) fn get_ids(
+    name: &str,
+    version: &Option<VersionFilter>,
+    source: Option<&PackageSource<'_>>,
+    resolve: &Resolve,
+) -> Vec<PackageId> {
+    let ids = matching_ids(name, version, source, resolve);
+    if ids.is_empty() {
+        eprintln!(
+            "Unable to find package {name} in dependencies{}",
+            missing::hint(name, resolve)
+        );
+    }
+    ids
+}
+
+pub(crate) fn get_id(
+    name: &str,
+    version: &Option<VersionFilter>,
+    source: Option<&PackageSource<'_>>,
+    resolve: &Resolve,
+) -> Option<PackageId> {
+    let ids = get_ids(name, version, source, resolve);
+    if let Some(first) = ids.first() {
+        warn_ambiguous(
+            name,
+            ids.iter().any(|id| id.source_id() != first.source_id()),
+            ids.len(),
+        );
+    }
+    ids.first().copied()
+}
+
+/// Warns that an entry without `all-versions` matches `count` packages, of
+/// which only the first one is patched.
+pub(crate) fn warn_ambiguous(name: &str, multiple_sources: bool, count: usize) {
+    if multiple_sources {
+        eprintln!(
+            "{name} is available from multiple sources. Try setting `registry = \"crates-io\"` or `source-git = \"<url>\"`."
+        );
+    } else if count > 1 {
+        eprintln!(
+            "There are multiple versions of {name} available. Try specifying a version or set `all-versions = true`."
+        );
+    }
+}
+
+/// Returns the directory in the output directory everything belonging to the
+/// package's copy is placed in. For members of a git repository this contains
+/// the repository layout.
+pub(crate) fn copy_root(pkg: &Package, config: &PatchConfig) -> PathBuf {
+    config
+        .output_dir()
+        .join(copy_dir_name(pkg.package_id(), config))
+}
+
+/// Returns the name of the directory a package is copied to, the
+/// `folder-name` of its entry or `<name>-<version>`. Packages in the
+/// dependency graph from several sources get a hash of their source
+/// appended, unless they come from crates.io, e.g.
+/// `serde-1.0.110-8f3a2b1c9d0e4f56`.
+pub(crate) fn copy_dir_name(id: PackageId, config: &PatchConfig) -> String {
+    if let Some(folder) = config.folder_names.get(&id) {
+        return folder.clone();
+    }
+    let source = id.source_id();
+    if config.multi_source.contains(id.name().as_str()) && !source.is_crates_io() {
+        let url = source.without_precise().as_url().to_string();
+        format!("{}-{}-{}", id.name(), id.version(), short_hash(&url))
+    } else {
+        format!("{}-{}", id.name(), id.version())
+    }
+}
+
+/// Returns the location of the package's copy in the output directory.
+pub(crate) fn package_copy_path(
+    pkg: &Package,
+    config: &PatchConfig,
+) -> Result<PathBuf> {
+    let root = copy_root(pkg, config);
+    Ok(match Member::of(pkg) {
+        Some(member) => root.join(member.path),
+        None => root,
+    })
+}
+
+/// Returns the directory the patches of an entry are relative to.
+pub(crate) fn patch_base(
+    patch: &PatchEntry<'_>,
+    pkg: &Package,
+    config: &PatchConfig,
+) -> Result<PathBuf> {
+    if patch.copy_repo_root {
+        Ok(copy_root(pkg, config).canonicalize()?)
+    } else {
+        Ok(package_copy_path(pkg, config)?.canonicalize()?)
+    }
+}
+
+/// Returns the copy of the package in the output directory, copying it
+/// there first if it does not exist yet.
+pub(crate) fn existing_or_copy_package(
+    pkg: &Package,
+    config: &PatchConfig,
+) -> Result<PathBuf> {
+    let path = package_copy_path(pkg, config)?;
+    if path.exists() {
+        Ok(path.canonicalize()?)
+    } else {
+        copy_package(pkg, config, false)
+    }
+}
+
+/// Copies the package to the output directory and returns the location of
+/// the copy. Members of a git repository keep their path inside of it, with
+/// `copy_repo_root` the whole repository is copied.
+pub(crate) fn copy_package(
+    pkg: &Package,
+    config: &PatchConfig,
+    copy_repo_root: bool,
+) -> Result<PathBuf> {
+    let output_dir = long_path(config.output_dir())?;
+    fs::create_dir_all(&output_dir)?;
+    let root = long_path(&copy_root(pkg, config))?;
+    if let Some(member) = Member::of(pkg) {
+        member.copy(&root, copy_repo_root)?;
+    } else {
+        // copy into `root`, which isn't named like the source directory
+        fs::create_dir_all(&root)?;
+        let options = CopyOptions::new().content_only(true);
+        let _ = copy(long_path(pkg.root())?, &root, &options)?;
+    }
+    if !config.preserve_permissions {
+        permissions::make_writable(&root)?;
+    }
+    Ok(package_copy_path(pkg, config)?.canonicalize()?)
+}
+
+/// Returns a hash over everything that influences the patched copy of a
+/// package, so persisted copies can be checked for staleness. Remote patch
+/// files are fetched, or taken from the cache, to hash their contents.
+pub(crate) fn patch_stamp(
+    patch: &PatchEntry<'_>,
+    package: &Package,
+    config: &PatchConfig,
+) -> Result<String> {
+    let items = patch
+        .patches
+        .iter()
+        .map(|item| {
+            let (_, data) = read_patch_bytes(item, config)?;
+            Ok((
+                (&item.source, item.final_newline),
+                (item.encoding, &item.transforms),
+                data,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let copy_ignore = patch
+        .copy_ignore
+        .map(|path| fs::read(path).unwrap_or_default());
+    let entry = (
+        (&patch.version_suffix, patch.backend, &patch.links),
+        (
+            patch.use_orig_manifest,
+            patch.copy_repo_root,
+            patch.strip_dev_targets,
+            patch.git_init,
+        ),
+        (&patch.snippets, &patch.sed, patch.folder_name),
+        (&patch.link_deps, &patch.linked, copy_ignore),
+    );
+    // workspace settings changing the contents or metadata of the copy
+    let settings = (
+        (config.pure, config.mtime, config.preserve_permissions),
+        config.override_path_style,
+    );
+    Ok(short_hash(&(
+        env!("CARGO_PKG_VERSION"),
+        package.package_id().to_string(),
+        entry,
+        settings,
+        items,
+    )))
+}
+
+/// Directory in the output directory the per-package logs are written to.
+pub(crate) const LOG_DIR: &str = ".logs";
+
+/// Copies the pristine package to the output directory and applies its
+/// patches. With `logs` enabled, everything printed for the package and the
+/// hunk-by-hunk details are written to its log file as well.
+pub(crate) fn patch_package(
+    patch: &PatchEntry<'_>,
+    package: &Package,
+    config: &PatchConfig,
+    options: &Options,
+) -> Result<()> {
+    if !options.logs {
+        return patch_package_checked(patch, package, config, options);
+    }
+    let log = config.output_dir().join(LOG_DIR).join(format!(
+        "{}.log",
+        copy_dir_name(package.package_id(), config)
+    ));
+    status::start_log(&log)?;
+    let result = patch_package_checked(patch, package, config, options);
+    if let Err(err) = &result {
+        status::log(format!("error: {err:#}"));
+    }
+    let finished = status::finish_log();
+    result.and(finished)
+}
+
+/// Patches a package and removes its copy again if patching was interrupted,
+/// so cargo never builds a half patched copy.
+pub(crate) fn patch_package_checked(
+    patch: &PatchEntry<'_>,
+    package: &Package,
+    config: &PatchConfig,
+    options: &Options,
+) -> Result<()> {
+    let result = patch_package_unlogged(patch, package, config, options);
+    if result.is_err() && interrupt::interrupted() {
+        let root = long_path(&copy_root(package, config))?;
+        if root.exists() {
+            fs::remove_dir_all(root)?;
+            status::status("Removed", format!("incomplete copy of {}", patch.name));
+        }
+        return Err(Error::Interrupted.into());
+    }
+    result
+}
+
+pub(crate) fn patch_package_unlogged(
+    patch: &PatchEntry<'_>,
+    package: &Package,
+    config: &PatchConfig,
+    options: &Options,
+) -> Result<()> {
+    timings::package(patch.name, package.version());
+    event::emit(|| Event::PackageResolved {
+        name: patch.name.to_owned(),
+        version: package.version().clone(),
+    });
+    let stamp = patch_stamp(patch, package, config)?;
+    let id = format!("{} v{}", patch.name, package.version());
+    if config.persist.is_some() {
+        let copy = package_copy_path(package, config)?;
+        if provenance::read_stamp(&copy).as_ref() == Some(&stamp) {
+            let plain = format!("{} is up to date", patch.name);
+            status::status_or("Fresh", id, Some(plain));
+            event::emit(|| Event::PackageFresh {
+                name: patch.name.to_owned(),
+                version: package.version().clone(),
+            });
+            return Ok(());
+        }
+        let root = long_path(&copy_root(package, config))?;
+        if root.exists() {
+            timings::time(Phase::Cleanup, || fs::remove_dir_all(root))?;
+        }
+    }
+    status::status_or("Patching", id, None);
+    pristine::check(package, options)?;
+    let path = timings::time(Phase::Copy, || {
+        copy_package(package, config, patch.copy_repo_root)
+    })?;
+    timings::copy_size(&path);
+    event::emit(|| Event::PackageCopied {
+        name: patch.name.to_owned(),
+        path: path.clone(),
+    });
+    interrupt::check()?;
+    let base = patch_base(patch, package, config)?;
+    if let Some(ignore_file) = patch.copy_ignore {
+        let removed = copy_ignore::prune(&base, ignore_file)?;
+        status::status(
+            "Removed",
+            format!("{removed} ignored path(s) from {}", patch.name),
+        );
+    }
+    let pristine = BuildSurface::read(&path)?;
+    if patch.git_init {
+        git_repo::init(&base, &format!("{} v{}", patch.name, package.version()))?;
+    }
+    apply_sed(patch, &base, true)?;
+    let packaging = Packaging::of(package)?;
+    let mut history = History::default();
+    let applied = timings::time(Phase::Apply, || {
+        apply_patches(
+            patch,
+            &base,
+            packaging.as_ref(),
+            config,
+            &mut history,
+            options,
+        )
+    })?;
+    apply_sed(patch, &base, false)?;
+    timings::time(Phase::Apply, || {
+        patch
+            .snippets
+            .iter()
+            .try_for_each(|snippet| snippet.apply(patch.name, &base))
+    })?;
+    if patch.git_init && !patch.snippets.is_empty() {
+        git_repo::commit_changes(&base, "Replace snippets")?;
+    }
+    if !patch.allow_build_script_changes {
+        if let Err(err) = check_build_surface(patch.name, &pristine, &path, options)
+        {
+            // the rejected copy must not be built
+            fs::remove_dir_all(long_path(&copy_root(package, config))?)?;
+            return Err(err);
+        }
+    }
+    if patch.strip_dev_targets {
+        strip_dev_targets(&path)?;
+        status::status("Stripped", format!("dev targets of {}", patch.name));
+    }
+    if let Some(links) = &patch.links {
+        set_links(&path, links)?;
+        status::status("Set", format!("{} links to `{links}`", patch.name));
+    }
+    if let Some(suffix) = &patch.version_suffix {
+        let version = set_version_suffix(&path, package.version(), suffix)?;
+        status::status("Set", format!("{} version to {version}", patch.name));
+    }
+    if !patch.linked.is_empty() {
+        let names = order::link_manifest(&path, &patch.linked)?;
+        if !names.is_empty() {
+            status::status(
+                "Linked",
+                format!("{} to the patched {}", patch.name, names.join(", ")),
+            );
+        }
+    }
+    if patch.git_init {
+        git_repo::commit_adjustments(&base)?;
+    }
+    let override_path = config.override_path_style.format(&path, &config.root);
+    let patched_at = config.mtime.unwrap_or_else(SystemTime::now);
+    provenance::write(&path, package, &applied, &stamp, &override_path, patched_at)?;
+    if config.pure {
+        permissions::normalize(&copy_root(package, config))?;
+    }
+    if let Some(time) = config.mtime {
+        let headers = history
+            .times()
+            .map(|(file, time)| (base.join(file), time))
+            .collect();
+        mtime::set(&copy_root(package, config).canonicalize()?, time, &headers)?;
+    }
+    event::emit(|| Event::PackagePatched {
+        name: patch.name.to_owned(),
+        version: package.version().clone(),
+        path,
+    });
+    Ok(())
+}
+
+/// Runs the `sed` edits of an entry which run before or after the patch
+/// files.
+pub(crate) fn apply_sed(
+    patch: &PatchEntry<'_>,
+    base: &Path,
+    before: bool,
+) -> Result<()> {
+    let edits = patch
+        .sed
+        .iter()
+        .filter(|edit| edit.before == before)
+        .collect::<Vec<_>>();
+    if edits.is_empty() {
+        return Ok(());
+    }
+    timings::time(Phase::Apply, || {
+        edits
+            .iter()
+            .try_for_each(|edit| edit.apply(patch.name, base))
+    })?;
+    if patch.git_init {
+        git_repo::commit_changes(base, "Apply sed edits")?;
+    }
+    Ok(())
+}
+
+/// Describes every hunk of a file diff for the log.
+pub(crate) fn hunk_details(patch: &Patch<'_>) -> Vec<String> {
+    patch
+        .hunks
+        .iter()
+        .enumerate()
+        .map(|(index, hunk)| {
+            let count = |kind: fn(&Line<'_>) -> bool| {
+                hunk.lines.iter().filter(|line| kind(line)).count()
+            };
+            format!(
+                "    hunk {} `{}`: {} added, {} removed",
+                index + 1,
+                hunk_header(hunk),
+                count(|line| matches!(line, Line::Add(_))),
+                count(|line| matches!(line, Line::Remove(_)))
+            )
+        })
+        .collect()
+}
+
+/// Returns the distinct function contexts of the hunks of a file diff.
+pub(crate) fn hunk_contexts<'p>(patch: &'p Patch<'_>) -> Vec<&'p str> {
+    let mut contexts = vec![];
+    for hint in patch.hunks.iter().filter_map(Hunk::hint).map(str::trim_end) {
+        if !hint.is_empty() && !contexts.contains(&hint) {
+            contexts.push(hint);
+        }
+    }
+    contexts
+}
+
+/// The unnormalized manifest included in published packages.
+pub(crate) const ORIG_MANIFEST: &str = "Cargo.toml.orig";
+
+/// The dependency copy a patch file is applied to.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PatchTarget<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) path: &'a Path,
+    /// Apply patches for `Cargo.toml` to [`ORIG_MANIFEST`] instead.
+    pub(crate) use_orig_manifest: bool,
+    pub(crate) final_newline: FinalNewline,
+}
+
+/// Replaces the normalized manifest of a package with its patched
+/// [`ORIG_MANIFEST`]. Fails if the latter relies on its workspace.
+pub(crate) fn replace_manifest(path: &Path, orig_manifest: &Path) -> Result<()> {
+    let manifest = fs::read_to_string(orig_manifest)?;
+    let uses_workspace = manifest
+        .parse::<DocumentMut>()?
+        .iter()
+        .filter_map(|(_, item)| item.as_table_like())
+        .flat_map(|table| table.iter())
+        .any(|(_, item)| {
+            item.as_table_like()
+                .and_then(|table| table.get("workspace"))
+                .and_then(|item| item.as_bool())
+                == Some(true)
+        });
+    if uses_workspace {
+        return Err(anyhow!(
+            "{} inherits from its workspace and can't be used as Cargo.toml",
+            orig_manifest.display()
+        ));
+    }
+    permissions::write(&path.join("Cargo.toml"), manifest)?;
+    Ok(())
+}
+
+/// Applies every file diff contained in `data` to the dependency at `path`.
+pub(crate) fn apply_patch_data(
+    target: &PatchTarget<'_>,
+    file: &Path,
+    data: &str,
+    source: &PatchSource,
+    history: &mut History,
+    options: &Options,
+) -> Result<Vec<PathBuf>> {
+    let PatchTarget { name, path, .. } = *target;
+    let (data, operations) =
+        timings::time(Phase::Parse, || git_header::extract(data, source));
+    let data = mark_missing_newlines(&data);
+    let patches = timings::time(Phase::Parse, || {
+        if data.trim().is_empty() && !operations.is_empty() {
+            Ok(vec![])
+        } else {
+            parse_patches(&data).ok_or_else(|| Error::PatchParse {
+                file: file.to_owned(),
+            })
+        }
+    })?;
+    let mut changed = vec![];
+    for patch in patches {
+        interrupt::check()?;
+        let (old_path, new_path) = patch_paths(&patch, source);
+
+        let loc = format!("{name}: {old_path} -> {new_path}");
+        let loc_simple = format!("{name}: {old_path}");
+
+        let (old_file_path, new_file_path) =
+            file_paths(path, &patch, (old_path, new_path), &loc)?;
+
+        let labels = options
+            .show_diff
+            .then(|| (old_path.to_owned(), new_path.to_owned()));
+        let preview = labels
+            .as_ref()
+            .map(|(old, new)| (old.as_str(), new.as_str()));
+
+        let orig_manifest = path.join(ORIG_MANIFEST);
+        let targets_manifest = old_path == "Cargo.toml"
+            && new_path == "Cargo.toml"
+            && orig_manifest.exists();
+        if targets_manifest && target.use_orig_manifest {
+            let hunks = patch.hunks.len();
+            do_patch(
+                file,
+                patch,
+                Some(orig_manifest.clone()),
+                Some(orig_manifest.clone()),
+                preview,
+                target.final_newline,
+            )?;
+            replace_manifest(path, &orig_manifest)?;
+            status::status(
+                "Patched",
+                format!("{name}: {ORIG_MANIFEST} -> Cargo.toml"),
+            );
+            porcelain::patched(name, "Cargo.toml", hunks);
+            changed.push(PathBuf::from("Cargo.toml"));
+            continue;
+        }
+        let hunks = patch.hunks.len();
+        let starts = patch
+            .hunks
+            .iter()
+            .map(|hunk| hunk.new_range.start)
+            .collect::<Vec<_>>();
+        let header_time = mtime::header_time(&patch);
+        let contexts = hunk_contexts(&patch).join(", ");
+        let details = hunk_details(&patch);
+        let lines = HunkLines::of(&patch);
+        let (old_file, new_file) = (old_path.to_owned(), new_path.to_owned());
+        let fallback = targets_manifest.then(|| patch.clone());
+        let patch_type = match do_patch(
+            file,
+            patch,
+            old_file_path,
+            new_file_path,
+            preview,
+            target.final_newline,
+        ) {
+            Ok(patch_type) => patch_type,
+            Err(err) => {
+                let err = history.explain(&new_file, &lines, err);
+                return Err(match &fallback {
+                    Some(fallback) if applies_to(fallback, &orig_manifest) => err
+                        .context(format!(
+                            "The patch for Cargo.toml of {name} matches {ORIG_MANIFEST}. \
+                             Published manifests are normalized, set \
+                             `use-orig-manifest = true` on the entry to patch \
+                             {ORIG_MANIFEST} and use it as Cargo.toml"
+                        )),
+                    _ => err,
+                });
+            }
+        };
+
+        let (verb, message, loc) = match patch_type {
+            PatchType::Modify if !contexts.is_empty() => (
+                "Applied",
+                format!("{hunks} hunk(s) to {new_file} in {contexts}"),
+                format!("{loc_simple} ({contexts})"),
+            ),
+            PatchType::Modify => (
+                "Applied",
+                format!("{hunks} hunk(s) to {new_file}"),
+                loc_simple,
+            ),
+            PatchType::Create => ("Created", new_file.clone(), loc),
+            PatchType::Delete => ("Deleted", old_file.clone(), loc),
+        };
+        status::status_or(verb, message, Some(format!("Patched {loc}")));
+        porcelain::patched(
+            name,
+            match patch_type {
+                PatchType::Modify | PatchType::Create => &new_file,
+                PatchType::Delete => &old_file,
+            },
+            hunks,
+        );
+        for detail in details {
+            status::log(detail);
+        }
+        match patch_type {
+            PatchType::Modify | PatchType::Create => {
+                history.record(&new_file, file, &lines);
+                if let Some(time) = header_time {
+                    history.record_time(&new_file, time);
+                }
+            }
+            PatchType::Delete => history.remove(&old_file),
+        }
+        let changed_file = PathBuf::from(match patch_type {
+            PatchType::Modify | PatchType::Create => new_file,
+            PatchType::Delete => old_file,
+        });
+        // the internal engine only applies hunks at their exact position
+        for line in starts {
+            event::emit(|| Event::HunkApplied {
+                name: name.to_owned(),
+                file: changed_file.clone(),
+                line,
+                offset: 0,
+                fuzz: 0,
+            });
+        }
+        changed.push(changed_file);
+    }
+    for operation in operations {
+        operation.apply(name, path)?;
+        changed.push(PathBuf::from(operation.file()));
+    }
+    Ok(changed)
+}
+
+/// Reads the patch file of an item as it is, downloading it first if it is
+/// remote. Returns the item pointing to the file which was read.
+pub(crate) fn read_patch_bytes<'a>(
+    item: &PatchItem<'a>,
+    config: &PatchConfig,
+) -> Result<(PatchItem<'a>, Vec<u8>)> {
+    // before the cache is looked at, which may hold an earlier download
+    config.check_remote(item)?;
+    let item = remote::fetch(item, config)?;
+    if item.remote.is_none() {
+        config.check_allowed(&item.path)?;
+    }
+    let data = fs::read(&item.path).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => {
+            anyhow!("Unable to find patch file with path: {:?}", item.path)
+        }
+        _ => err.into(),
+    })?;
+    Ok((item, data))
+}
+
+/// Decodes a patch file with the encoding of its item and applies the
+/// item's transforms.
+pub(crate) fn decode_patch(item: &PatchItem<'_>, data: Vec<u8>) -> Result<String> {
+    let data = item.encoding.decode(data, &item.path)?;
+    Ok(item
+        .transforms
+        .iter()
+        .fold(data, |data, transform| transform.apply(&data)))
+}
+
+/// Reads and decodes the patch file of an item, downloading it first if it
+/// is remote. Returns the item pointing to the file which was read.
+pub(crate) fn read_patch<'a>(
+    item: &PatchItem<'a>,
+    config: &PatchConfig,
+) -> Result<(PatchItem<'a>, String)> {
+    let (item, data) = read_patch_bytes(item, config)?;
+    let data = decode_patch(&item, data)?;
+    Ok((item, data))
+}
+
+pub(crate) fn apply_patches(
+    patch: &PatchEntry<'_>,
+    path: &Path,
+    packaging: Option<&Packaging>,
+    config: &PatchConfig,
+    history: &mut History,
+    options: &Options,
+) -> Result<Vec<AppliedPatch>> {
+    let mut applied = vec![];
+    for item in &patch.patches {
+        interrupt::check()?;
+        status::log(format!("Applying {}", item.path.display()));
+        let result =
+            apply_patch_item(patch, item, path, packaging, config, history, options);
+        if let Err(err) = &result {
+            event::emit(|| Event::PatchFailed {
+                name: patch.name.to_owned(),
+                patch: item.path.to_path_buf(),
+                error: format!("{err:#}"),
+            });
+        }
+        applied.push(result?);
+        event::emit(|| Event::PatchApplied {
+            name: patch.name.to_owned(),
+            patch: item.path.to_path_buf(),
+        });
+    }
+    Ok(applied)
+}
+
+/// Applies a single patch file of an entry. With the packaging rules of a
+/// registry package, warns first about files which were left out of it.
+pub(crate) fn apply_patch_item(
+    patch: &PatchEntry<'_>,
+    item: &PatchItem<'_>,
+    path: &Path,
+    packaging: Option<&Packaging>,
+    config: &PatchConfig,
+    history: &mut History,
+    options: &Options,
+) -> Result<AppliedPatch> {
+    let (item, data) =
+        timings::time(Phase::Parse, || read_patch_bytes(item, config))?;
+    // signatures cover the file as it is
+    verify_signature(&item, &data, config)?;
+    let data = decode_patch(&item, data)?;
+    if let Some(packaging) = packaging {
+        packaging.check(patch.name, path, &data, &item.source);
+    }
+    match patch.backend.external() {
+        None => {
+            let target = PatchTarget {
+                name: patch.name,
+                path,
+                use_orig_manifest: patch.use_orig_manifest,
+                final_newline: item.final_newline,
+            };
+            apply_patch_data(
+                &target,
+                &item.path,
+                &data,
+                &item.source,
+                history,
+                options,
+            )?;
+        }
+        Some(tool) => {
+            if item.final_newline != FinalNewline::default() {
+                return Err(anyhow!(
+                    "final-newline is not supported by the {tool} backend of {}",
+                    patch.name
+                ));
+            }
+            if options.show_diff {
+                return Err(anyhow!(
+                    "--show-diff is not supported by the {tool} backend of {}",
+                    patch.name
+                ));
+            }
+            backend::apply_external(
+                tool,
+                patch.name,
+                &item.path,
+                &data,
+                &item.source,
+                path,
+                config.strict_apply,
+            )?;
+        }
+    }
+    let file = item
+        .remote
+        .as_ref()
+        .map_or_else(|| item.path.display().to_string(), Remote::label);
+    if patch.git_init {
+        git_repo::commit_patch(path, &file, &data)?;
+    }
+    Ok(AppliedPatch::new(file, data.as_bytes()))
+}
+
+/// Warns, or fails in strict mode, if the patches changed the build script,
+/// `links` key or proc-macro crate type of a package, as these escalate what
+/// the patched code can do at build time.
+pub(crate) fn check_build_surface(
+    name: &str,
+    pristine: &BuildSurface,
+    path: &Path,
+    options: &Options,
+) -> Result<()> {
+    let changes = pristine.changes(&BuildSurface::read(path)?);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "patches change the {} of {name}. Set `allow-build-script-changes = true` to allow this",
+        changes.join(", ")
+    );
+    if options.strict {
+        return Err(anyhow!(message));
+    }
+    status::warning(message);
+    Ok(())
+}
+
+/// Warns, or fails in strict mode, if a patched package now links to the
+/// same native library as another package of the dependency graph. cargo
+/// only allows one package per `links` value.
+pub(crate) fn check_links_conflicts(
+    packages: &[(PatchEntry<'_>, &Package)],
+    resolve: &Resolve,
+    config: &PatchConfig,
+    options: &Options,
+) -> Result<()> {
+    let mut patched = HashMap::new();
+    for (_, package) in packages {
+        let path = package_copy_path(package, config)?;
+        let links = BuildSurface::read(&path)?.links().map(str::to_owned);
+        let _ = patched.insert(package.package_id(), links);
+    }
+    let links_of = |id: PackageId| {
+        patched.get(&id).cloned().unwrap_or_else(|| {
+            resolve.summary(id).links().map(|links| links.to_string())
+        })
+    };
+    for (_, package) in packages {
+        let id = package.package_id();
+        let Some(links) = links_of(id) else {
+            continue;
+        };
+        if package.manifest().links() == Some(links.as_str()) {
+            continue;
+        }
+        for other in resolve.iter().filter(|other| *other != id) {
+            if links_of(other).as_deref() != Some(links.as_str()) {
+                continue;
+            }
+            let message = format!(
+                "{} v{} now links to `{links}` like {} v{}, but only one package may link to a native library",
+                id.name(),
+                id.version(),
+                other.name(),
+                other.version()
+            );
+            if options.strict {
+                return Err(anyhow!(message));
+            }
+            status::warning(message);
+        }
+    }
+    Ok(())
+}
+
+/// Warns, or fails with `frozen_copy`, if a git dependency referenced by a
+/// branch or tag now resolves to another commit than its patched copy was
+/// created from, as the patches might not match the new code.
+pub(crate) fn check_git_drift(
+    packages: &[&Package],
+    config: &PatchConfig,
+    options: &Options,
+) -> Result<()> {
+    let recorded = provenance::git_commits(config.output_dir())?;
+    for package in packages {
+        let id = package.package_id();
+        if matches!(
+            id.source_id().git_reference(),
+            None | Some(GitReference::Rev(_))
+        ) {
+            continue;
+        }
+        let Some(current) = GitCommit::of(package) else {
+            continue;
+        };
+        let mut previous = recorded
+            .iter()
+            .filter(|git| git.name == current.name && git.source == current.source);
+        if previous.clone().any(|git| git.commit == current.commit) {
+            continue;
+        }
+        let Some(previous) = previous.next() else {
+            continue;
+        };
+        let message = format!(
+            "{} v{} now resolves to commit {}, but the patched copy was created from commit {}. Check that the patches still match",
+            id.name(),
+            id.version(),
+            current.commit,
+            previous.commit
+        );
+        if options.frozen_copy {
+            return Err(anyhow!(message));
+        }
+        status::warning(message);
+    }
+    Ok(())
+}
+
+/// Sets the `links` key of the patched copy.
+pub(crate) fn set_links(path: &Path, links: &str) -> Result<()> {
+    let manifest_path = path.join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path)?.parse::<DocumentMut>()?;
+    let package = manifest
+        .get_mut("package")
+        .and_then(|package| package.as_table_like_mut())
+        .ok_or_else(|| anyhow!("Dependency manifest has no package section"))?;
+    package.insert("links", toml_edit::value(links));
+    permissions::write(&manifest_path, manifest.to_string())?;
+    Ok(())
+}
+
+pub(crate) fn suffixed_version(version: &Version, suffix: &str) -> Result<Version> {
+    let mut version = version.clone();
+    version.build = if version.build.is_empty() {
+        BuildMetadata::new(suffix)?
+    } else {
+        BuildMetadata::new(&format!("{}.{suffix}", version.build))?
+    };
+    Ok(version)
+}
+
+/// Rewrites the version of the patched copy to carry the given suffix as
+/// build metadata (e.g. `1.0.110+patched`). Build metadata is ignored when
+/// matching version requirements, so `[patch]` overrides stay satisfied.
+pub(crate) fn set_version_suffix(
+    path: &Path,
+    version: &Version,
+    suffix: &str,
+) -> Result<Version> {
+    let manifest_path = path.join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path)?.parse::<DocumentMut>()?;
+    let version = suffixed_version(version, suffix)?;
+
+    let package = manifest
+        .get_mut("package")
+        .and_then(|package| package.as_table_like_mut())
+        .ok_or_else(|| anyhow!("Dependency manifest has no package section"))?;
+    package.insert("version", toml_edit::value(version.to_string()));
+    permissions::write(&manifest_path, manifest.to_string())?;
+    Ok(version)
+}
+
+/// Targets only needed to develop a package: their manifest table, default
+/// directory and the `package` key enabling their discovery.
+pub(crate) const DEV_TARGETS: [(&str, &str, &str); 3] = [
+    ("bench", "benches", "autobenches"),
+    ("example", "examples", "autoexamples"),
+    ("test", "tests", "autotests"),
+];
+
+/// Removes benches, examples and tests from the patched copy. Registry
+/// packages never build them, but as a path dependency cargo reads their
+/// targets, which commonly fails as their files aren't published.
+pub(crate) fn strip_dev_targets(path: &Path) -> Result<()> {
+    let manifest_path = path.join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path)?.parse::<DocumentMut>()?;
+    for (table, dir, auto) in DEV_TARGETS {
+        let _ = manifest.remove(table);
+        let package = manifest
+            .get_mut("package")
+            .and_then(|package| package.as_table_like_mut())
+            .ok_or_else(|| anyhow!("Dependency manifest has no package section"))?;
+        package.insert(auto, toml_edit::value(false));
+        let dir = path.join(dir);
+        if dir.is_dir() {
+            fs::remove_dir_all(dir)?;
+        }
+    }
+    permissions::write(&manifest_path, manifest.to_string())?;
+    Ok(())
+}
+
+#[allow(clippy::wildcard_enum_match_arm)]
+pub(crate) fn read_to_string(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(data) => Ok(data),
+        Err(err) => match err.kind() {
+            ErrorKind::NotFound => {
+                Err(anyhow!("Unable to find patch file with path: {:?}", path))
+            }
+            _ => Err(err.into()),
+        },
+    }
+}
+
+pub(crate) fn load_workspace(gctx: &GlobalContext) -> Result<Workspace<'_>> {
+    let path =
+        find_cargo_toml(&PathBuf::from(".")).map_err(Error::ManifestInvalid)?;
+    check_manifest_versions(&path)?;
+    let workspace = fetch_workspace(gctx, &path).map_err(Error::ManifestInvalid)?;
+    check_required_versions(&workspace)?;
+    Ok(workspace)
+}
+
+/// Returns the custom metadata of the workspace and all its members, the
+/// workspace first and then the members in alphabetical order.
+pub(crate) fn custom_metadata<'a>(
+    workspace: &'a Workspace<'_>,
+) -> impl Iterator<Item = &'a Value> + 'a {
+    // members in alphabetical order, so entries don't depend on the order of
+    // `workspace.members`
+    let mut members = workspace.members().collect::<Vec<_>>();
+    members.sort_by_key(|member| member.name());
+    workspace.custom_metadata().into_iter().chain(
+        members
+            .into_iter()
+            .flat_map(|member| member.manifest().custom_metadata()),
+    )
+}
+
+/// Collects the patch entries of the workspace, all its members and the
+/// dependencies listed in `dependency-metadata` together with the id of the
+/// package they apply to. Entries for the same package are merged in this
+/// order, without repeating identical items.
+pub(crate) fn resolve_patches<'a>(
+    workspace: &'a Workspace<'_>,
+    pkg_set: &'a PackageSet<'_>,
+    resolve: &Resolve,
+    config: &'a PatchConfig,
+) -> Result<Vec<(PatchEntry<'a>, PackageId)>> {
+    if let Some(cli_entry) = &config.cli_entry {
+        let entry = cli_entry.entry();
+        let id =
+            get_id(entry.name, &entry.version, None, resolve).ok_or_else(|| {
+                Error::PackageNotFound {
+                    name: cli_entry.name.clone(),
+                }
+            })?;
+        return Ok(vec![(entry, id)]);
+    }
+    let mut dependency_patches = Vec::new();
+    for name in &config.dependency_metadata {
+        let Some(id) = get_id(name, &None, None, resolve) else {
+            continue;
+        };
+        let package = pkg_set.get_one(id)?;
+        let root = package.root();
+        let origin = Origin::new(package);
+        let entries = package
+            .manifest()
+            .custom_metadata()
+            .into_iter()
+            .flat_map(get_patches)
+            .map(|entry| PatchEntry {
+                patches: entry
+                    .patches
+                    .into_iter()
+                    .map(|item| item.relative_to(root, &origin))
+                    .collect(),
+                ..entry
+            });
+        dependency_patches.extend(entries);
+    }
+    let recipe_patches = config.recipes.iter().flat_map(|(dir, origin, recipe)| {
+        get_patches(recipe).map(|entry| PatchEntry {
+            patches: entry
+                .patches
+                .into_iter()
+                .map(|item| item.relative_to(dir, origin))
+                .collect(),
+            ..entry
+        })
+    });
+
+    // patch sets add their items to the regular entry of a dependency
+    let mut workspace_patches = custom_metadata(workspace)
+        .flat_map(get_patches)
+        .collect::<Vec<_>>();
+    for entry in active_set_patches(workspace, config)? {
+        match workspace_patches.iter_mut().find(|patch| {
+            patch.name == entry.name
+                && patch.version == entry.version
+                && patch.package_source == entry.package_source
+        }) {
+            Some(patch) => patch.merge(entry),
+            None => workspace_patches.push(entry),
+        }
+    }
+
+    let resolved = workspace_patches
+        .into_iter()
+        .chain(dependency_patches)
+        .chain(recipe_patches)
+        .filter(|patch| patch.in_profile(&config.profile))
+        .flat_map(|patch| {
+            let ids = if patch.all_versions {
+                get_ids(
+                    patch.name,
+                    &patch.version,
+                    patch.package_source.as_ref(),
+                    resolve,
+                )
+            } else {
+                get_id(
+                    patch.name,
+                    &patch.version,
+                    patch.package_source.as_ref(),
+                    resolve,
+                )
+                .into_iter()
+                .collect()
+            };
+            ids.into_iter().map(move |id| (patch.clone(), id))
+        });
+    let mut merged = Vec::<(PatchEntry<'a>, PackageId)>::new();
+    for (mut patch, id) in resolved {
+        match merged.iter_mut().find(|(_, other)| *other == id) {
+            Some((entry, _)) => entry.merge(patch),
+            None => {
+                patch.dedup();
+                merged.push((patch, id));
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Prints the packages which would be patched with the items applied to
+/// them in order, see [`Options::explain`].
+pub(crate) fn print_plan(ids: &[(PatchEntry<'_>, PackageId)], root: &Path) {
+    for (patch, id) in ids {
+        println!("{} v{} ({})", id.name(), id.version(), id.source_id());
+        for (number, step) in patch.steps(root).iter().enumerate() {
+            println!("    {}. {step}", number + 1);
+        }
+    }
+}
+
+/// Patches all dependencies configured in the workspace using default
+/// [`Options`].
+pub fn patch() -> Result<(), Error> {
+    patch_with_options(&Options::default())
+}
+
+/// Patches all dependencies configured in the workspace.
+pub fn patch_with_options(options: &Options) -> Result<(), Error> {
+    run(options, |options| {
+        let gctx = timings::time(Phase::Resolution, || setup_gctx(options))?;
+        patch_gctx(&gctx, options)
+    })
+}
+
+/// Patches all dependencies configured in the workspace of the current
+/// directory using the cargo context `gctx` of an embedding tool, e.g. an
+/// xtask built on the cargo API.
+///
+/// The settings of `gctx` are used as they are, so [`Options::offline`],
+/// [`Options::config`], [`Options::unstable_flags`] and
+/// [`Options::restrict`] have no effect on it. The package cache lock is
+/// shared with the caller if it already holds it.
+pub fn patch_with_config(
+    gctx: &GlobalContext,
+    options: &Options,
+) -> Result<(), Error> {
+    run(options, |options| {
+        status::init(options)?;
+        patch_gctx(gctx, options)
+    })
+}
+
+/// Patches all dependencies configured in an already loaded `workspace`,
+/// see [`patch_with_config`].
+pub fn patch_workspace(
+    workspace: &Workspace<'_>,
+    options: &Options,
+) -> Result<(), Error> {
+    run(options, |options| {
+        status::init(options)?;
+        check_required_versions(workspace)?;
+        let lock = lock_package_cache(workspace.gctx(), options)?;
+        patch_loaded(workspace, lock, options)
+    })
+}
+
+/// Runs `patch` with the timings and the CI summary of the options.
+pub(crate) fn run(
+    options: &Options,
+    patch: impl FnOnce(&Options) -> Result<(), Error>,
+) -> Result<(), Error> {
+    if options.timings.is_some() {
+        timings::start();
+    }
+    let result = if options.ci {
+        ci::start();
+        let options = Options {
+            strict: true,
+            keep_going: true,
+            ..options.clone()
+        };
+        let result = patch(&options);
+        ci::finish(&result).map(|_| result)?
+    } else {
+        patch(options)
+    };
+    // successful runs write the report before building or watching, failed
+    // ones here
+    let finished = options.timings.map(timings::finish).transpose();
+    result?;
+    let _ = finished?;
+    Ok(())
+}
+
+pub(crate) fn patch_gctx(
+    gctx: &GlobalContext,
+    options: &Options,
+) -> Result<(), Error> {
+    let workspace = timings::time(Phase::Resolution, || load_workspace(gctx))?;
+    interrupt::check()?;
+    let lock = lock_package_cache(gctx, options)?;
+    patch_loaded(&workspace, lock, options)
+}
+
+pub(crate) fn patch_loaded(
+    workspace: &Workspace<'_>,
+    lock: CacheLock<'_>,
+    options: &Options,
+) -> Result<(), Error> {
+    let gctx = workspace.gctx();
+    ci::set_target_dir(workspace.target_dir().as_path_unlocked());
+    timings::time(Phase::Resolution, || ensure_lockfile(workspace, options))?;
+    let (pkg_set, resolve) =
+        timings::time(Phase::Resolution, || resolve_ws(workspace))?;
+    interrupt::check()?;
+    let config = timings::time(Phase::Resolution, || {
+        get_config(gctx, workspace, &pkg_set, &resolve, options)
+    })?;
+    interrupt::check()?;
+    let ids = timings::time(Phase::Resolution, || {
+        resolve_patches(workspace, &pkg_set, &resolve, &config).and_then(|ids| {
+            targets::retain(ids, workspace, &resolve, &options.targets)
+        })
+    })
+    .map(|ids| order::sort(ids, &resolve))
+    .and_then(|ids| order::link(ids, &pkg_set, &resolve, &config))?;
+    let latest = if options.check_upstream {
+        timings::time(Phase::Resolution, || {
+            upstream::latest_versions(gctx, ids.iter().map(|(_, id)| *id))
+        })?
+    } else {
+        HashMap::new()
+    };
+    notes::warn(&ids, &latest);
+    if options.explain {
+        print_plan(&ids, &config.root);
+        return Ok(());
+    }
+    let locked = (options.write_lock || options.locked_metadata)
+        .then(|| lockfile::generate(&ids, &config))
+        .transpose()?;
+    if let Some(locked) = locked.as_ref().filter(|_| options.locked_metadata) {
+        lockfile::check(locked, &config)?;
+    }
+    let packages = ids
+        .iter()
+        .map(|(_, id)| pkg_set.get_one(*id))
+        .collect::<Result<Vec<_>>>()?;
+    interrupt::check()?;
+    check_git_drift(&packages, &config, options)?;
+    timings::time(Phase::Cleanup, || {
+        if config.persist.is_none() {
+            clear_patch_folder(&config, &packages)
+        } else {
+            prune_patch_folder(&config, &packages)
+        }
+    })?;
+
+    let mut packages = vec![];
+    let mut failures = vec![];
+    for (patch, id) in ids {
+        interrupt::check()?;
+        let package = pkg_set.get_one(id)?;
+        match patch_package(&patch, package, &config, options) {
+            Ok(()) => {
+                ci::patched(
+                    patch.name,
+                    id.version(),
+                    &package_copy_path(package, &config)?,
+                );
+            }
+            Err(_) if interrupt::interrupted() => return Err(Error::Interrupted),
+            Err(err) if options.watch => eprintln!("Error: {err:#}"),
+            Err(err) => {
+                recovery::recover(
+                    workspace.root(),
+                    patch.name,
+                    id.version(),
+                    &package_copy_path(package, &config)?,
+                    options.disable_failed_overrides,
+                )?;
+                if !options.keep_going {
+                    return Err(err.into());
+                }
+                ci::failed(patch.name, id.version(), &err);
+                failures.push(format!("{} v{}: {err:#}", id.name(), id.version()));
+            }
+        }
+        packages.push((patch, package));
+    }
+    if let Some(format) = options.timings {
+        let _ = timings::finish(format)?;
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "Failed to patch {} package(s):\n    {}",
+            failures.len(),
+            failures.join("\n    ")
+        )
+        .into());
+    }
+    check_links_conflicts(&packages, &resolve, &config, options)?;
+    if let Some(out) = &options.emit_hashes {
+        let copies = packages
+            .iter()
+            .map(|(_, package)| *package)
+            .collect::<Vec<_>>();
+        hashes::emit(out, &copies, &config)?;
+    }
+    if config.cli_entry.is_none() {
+        unused::check(workspace, &resolve, &config, options)?;
+    }
+    if let Some(locked) = locked.filter(|_| options.write_lock) {
+        lockfile::write(&locked, &config)?;
+    }
+
+    if packages.is_empty() {
+        status::line("No patches found");
+        return Ok(());
+    }
+    // other cargo invocations must not be blocked while checking or watching
+    drop(lock);
+    if options.test_build {
+        test_build::run(&packages, &config.root, options)?;
+    }
+    if options.watch {
+        let manifests = workspace
+            .members()
+            .map(|member| member.manifest_path().to_path_buf())
+            .chain(Some(workspace.root_manifest().to_path_buf()))
+            .collect::<Vec<_>>();
+        if watch::watch(&packages, &manifests, &config, options)? {
+            status::status("Reloading", "the patch configuration");
+            check_manifest_versions(workspace.root_manifest())?;
+            let workspace = Workspace::new(workspace.root_manifest(), gctx)?;
+            check_required_versions(&workspace)?;
+            let lock = lock_package_cache(gctx, options)?;
+            return patch_loaded(&workspace, lock, options);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_patch_entry, PatchSource};
+    use toml::Value;
+
+    #[test]
+    fn parse_patch_entry_default_source() {
+        let entry = r#"
+            source = "GitDiff"
+            patches = [
+                "first.patch",
+                { path = "second.patch" },
+                { path = "third.patch", source = "Default" },
+            ]
+        "#
+        .parse::<toml::Table>()
+        .expect("Unable to parse entry");
+        let entry = Value::Table(entry);
+        let entry = parse_patch_entry("serde", &entry).expect("Invalid entry");
+        let sources = entry
+            .patches
+            .iter()
+            .map(|item| item.source.clone())
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            sources.as_slice(),
+            [
+                PatchSource::GitDiff,
+                PatchSource::GitDiff,
+                PatchSource::Default
+            ]
+        ));
+    }
+}
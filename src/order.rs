@@ -0,0 +1,298 @@
+//! Patching packages which depend on each other in order, so dependencies
+//! are patched before their dependents, and pointing the copies of
+//! dependents to the copies of their patched dependencies.
+
+use crate::{
+    orchestrator::{package_copy_path, PatchConfig, PatchEntry},
+    permissions, status,
+};
+use anyhow::Result;
+use cargo::core::{PackageId, PackageSet, Resolve};
+use semver::VersionReq;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, InlineTable, Item, TableLike, Value};
+
+/// Dependency tables of a manifest, also used below `target.<cfg>`.
+const DEPENDENCY_TABLES: [&str; 5] = [
+    "dependencies",
+    "dev-dependencies",
+    "dev_dependencies",
+    "build-dependencies",
+    "build_dependencies",
+];
+
+/// Which copy a dependency of a patched package is built from, set with
+/// `link-deps` on its entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkMode {
+    /// The patched copy of the dependency.
+    Patched,
+    /// The pristine dependency, even with `link-patched` enabled.
+    Pristine,
+}
+
+impl LinkMode {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "patched" => Some(Self::Patched),
+            "pristine" => Some(Self::Pristine),
+            &_ => {
+                eprintln!("Unknown link mode: {s}");
+                None
+            }
+        }
+    }
+}
+
+/// Returns the patched packages `id` depends on, directly or through
+/// packages which aren't patched.
+fn patched_deps(
+    id: PackageId,
+    patched: &HashSet<PackageId>,
+    resolve: &Resolve,
+) -> HashSet<PackageId> {
+    let mut found = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut queue = vec![id];
+    while let Some(current) = queue.pop() {
+        for (dep, _) in resolve.deps(current) {
+            if dep == id || !visited.insert(dep) {
+                continue;
+            }
+            if patched.contains(&dep) {
+                let _ = found.insert(dep);
+            }
+            queue.push(dep);
+        }
+    }
+    found
+}
+
+/// Sorts the packages so each one comes after the patched packages it
+/// depends on. Otherwise the configured order is kept.
+pub fn sort<'a>(
+    ids: Vec<(PatchEntry<'a>, PackageId)>,
+    resolve: &Resolve,
+) -> Vec<(PatchEntry<'a>, PackageId)> {
+    let patched = ids.iter().map(|(_, id)| *id).collect::<HashSet<_>>();
+    let mut pending = ids
+        .into_iter()
+        .map(|(patch, id)| {
+            let deps = patched_deps(id, &patched, resolve);
+            (patch, id, deps)
+        })
+        .collect::<Vec<_>>();
+    let mut done = HashSet::new();
+    let mut sorted = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        // cycles through dev-dependencies of members keep the configured order
+        let next = pending
+            .iter()
+            .position(|(_, _, deps)| deps.is_subset(&done))
+            .unwrap_or(0);
+        let (patch, id, _) = pending.remove(next);
+        let _ = done.insert(id);
+        sorted.push((patch, id));
+    }
+    sorted
+}
+
+/// Sets the copies of the patched packages each package directly depends on
+/// as its [`PatchEntry::linked`] dependencies, if `link-deps` of its entry
+/// or else `link-patched` selects them. Warns about `link-deps` naming no
+/// patched dependency of the package.
+pub fn link<'a>(
+    ids: Vec<(PatchEntry<'a>, PackageId)>,
+    pkg_set: &PackageSet<'_>,
+    resolve: &Resolve,
+    config: &PatchConfig,
+) -> Result<Vec<(PatchEntry<'a>, PackageId)>> {
+    if !config.link_patched
+        && ids.iter().all(|(patch, _)| patch.link_deps.is_empty())
+    {
+        return Ok(ids);
+    }
+    let cwd = env::current_dir()?;
+    let copies = ids
+        .iter()
+        .map(|(_, id)| {
+            let package = pkg_set.get_one(*id)?;
+            Ok((*id, cwd.join(package_copy_path(package, config)?)))
+        })
+        .collect::<Result<HashMap<_, _>>>()?;
+    Ok(ids
+        .into_iter()
+        .map(|(mut patch, id)| {
+            let deps = resolve
+                .deps(id)
+                .filter(|(dep, _)| *dep != id)
+                .filter_map(|(dep, _)| Some((dep, copies.get(&dep)?.clone())))
+                .collect::<Vec<_>>();
+            for name in patch.link_deps.keys() {
+                if !deps.iter().any(|(dep, _)| dep.name() == *name) {
+                    status::warning(format!(
+                        "`link-deps` of {} names {name}, which isn't a patched dependency of it",
+                        patch.name
+                    ));
+                }
+            }
+            patch.linked = deps
+                .into_iter()
+                .filter(|(dep, _)| {
+                    patch.link_deps.get(dep.name().as_str()).map_or(
+                        config.link_patched,
+                        |mode| *mode == LinkMode::Patched,
+                    )
+                })
+                .collect();
+            (patch, id)
+        })
+        .collect())
+}
+
+/// Parses `link-deps` of an entry, a table of package names and
+/// [`LinkMode`]s.
+pub fn parse_link_deps(deps: &toml::Value) -> Option<BTreeMap<&str, LinkMode>> {
+    let value = deps.as_table().and_then(|deps| {
+        deps.iter()
+            .map(|(name, mode)| {
+                Some((name.as_str(), LinkMode::from_str(mode.as_str()?)?))
+            })
+            .collect()
+    });
+    if value.is_none() {
+        eprintln!(
+            "Link deps must be a table of package names and `patched` or `pristine`: {deps}"
+        );
+    }
+    value
+}
+
+/// Returns the copy a dependency of a manifest is linked to, if it is one of
+/// the `linked` packages and its version requirement matches.
+fn linked_copy<'l>(
+    key: &str,
+    item: &Item,
+    linked: &'l [(PackageId, PathBuf)],
+) -> Option<&'l (PackageId, PathBuf)> {
+    let name = item.get("package").and_then(Item::as_str).unwrap_or(key);
+    let req = match item {
+        Item::Value(Value::String(version)) => Some(version.value().as_str()),
+        _ => item.get("version").and_then(Item::as_str),
+    };
+    linked.iter().find(|(id, _)| {
+        id.name() == name
+            && req.map_or(true, |req| {
+                VersionReq::parse(req).is_ok_and(|req| req.matches(id.version()))
+            })
+    })
+}
+
+/// Adds the path of the linked copy to the matching dependencies of a table
+/// and returns the names of the linked packages.
+fn link_table(
+    table: &mut dyn TableLike,
+    linked: &[(PackageId, PathBuf)],
+) -> Vec<String> {
+    let mut names = vec![];
+    for (key, item) in table.iter_mut() {
+        let Some((id, copy)) = linked_copy(key.get(), item, linked) else {
+            continue;
+        };
+        let path = copy.display().to_string();
+        if let Item::Value(Value::String(version)) = item {
+            let mut dependency = InlineTable::new();
+            dependency.insert("version", version.value().as_str().into());
+            dependency.insert("path", path.into());
+            *item = toml_edit::value(dependency);
+        } else if let Some(dependency) = item.as_table_like_mut() {
+            let _ = dependency.insert("path", toml_edit::value(path));
+        } else {
+            continue;
+        }
+        names.push(id.name().to_string());
+    }
+    names
+}
+
+/// Points the dependencies in the manifest of the copy at `path` on the
+/// `linked` packages to their copies. Returns the names of the linked
+/// packages.
+pub fn link_manifest(
+    path: &Path,
+    linked: &[(PackageId, PathBuf)],
+) -> Result<Vec<String>> {
+    let manifest_path = path.join("Cargo.toml");
+    let mut manifest = fs::read_to_string(&manifest_path)?.parse::<DocumentMut>()?;
+    let mut names = vec![];
+    for table in DEPENDENCY_TABLES {
+        if let Some(table) =
+            manifest.get_mut(table).and_then(Item::as_table_like_mut)
+        {
+            names.extend(link_table(table, linked));
+        }
+    }
+    if let Some(targets) =
+        manifest.get_mut("target").and_then(Item::as_table_like_mut)
+    {
+        for (_, target) in targets.iter_mut() {
+            for table in DEPENDENCY_TABLES {
+                if let Some(table) =
+                    target.get_mut(table).and_then(Item::as_table_like_mut)
+                {
+                    names.extend(link_table(table, linked));
+                }
+            }
+        }
+    }
+    permissions::write(&manifest_path, manifest.to_string())?;
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::link_table;
+    use cargo::{
+        core::{PackageId, SourceId},
+        util::IntoUrl,
+    };
+    use std::path::PathBuf;
+    use toml_edit::DocumentMut;
+
+    #[test]
+    fn links_matching_dependencies() {
+        let mut manifest = r#"
+[dependencies]
+tokio = "1.38"
+io = { package = "tokio", version = "1.38", features = ["io-util"] }
+old = { package = "tokio", version = "0.2" }
+bytes = "1"
+"#
+        .parse::<DocumentMut>()
+        .expect("Invalid manifest");
+        let url = "https://example.com/index".into_url().expect("Invalid URL");
+        let source = SourceId::for_registry(&url).expect("Invalid source");
+        let id = PackageId::try_new("tokio", "1.38.0", source).expect("Invalid id");
+        let linked = [(id, PathBuf::from("/ws/target/patch/tokio-1.38.0"))];
+        let table = manifest["dependencies"]
+            .as_table_like_mut()
+            .expect("No dependencies");
+        assert_eq!(link_table(table, &linked), ["tokio", "tokio"]);
+        assert_eq!(
+            manifest.to_string(),
+            r#"
+[dependencies]
+tokio = { version = "1.38", path = "/ws/target/patch/tokio-1.38.0" }
+io = { package = "tokio", version = "1.38", features = ["io-util"] , path = "/ws/target/patch/tokio-1.38.0" }
+old = { package = "tokio", version = "0.2" }
+bytes = "1"
+"#
+        );
+    }
+}
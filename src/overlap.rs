@@ -0,0 +1,238 @@
+//! Tracking of the lines changed by stacked patches, to explain which
+//! earlier patch of an entry got in the way of a later one, and of the
+//! timestamps of the files they changed.
+
+use crate::{status, Error};
+use patch::{Line, Patch};
+use std::{
+    collections::HashMap,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The lines a hunk covers, starting at 1.
+#[derive(Debug, Clone)]
+pub struct HunkLines {
+    /// All lines of the file before patching, including context.
+    old: RangeInclusive<u64>,
+    /// Lines removed from the file before patching, or the line an
+    /// addition is inserted after.
+    removed: RangeInclusive<u64>,
+    /// Lines added to the patched file, or the line a removal happened
+    /// after.
+    added: RangeInclusive<u64>,
+    /// How many lines the hunk adds in total.
+    delta: i64,
+}
+
+impl HunkLines {
+    /// Returns the lines of every hunk of a file diff.
+    pub fn of(patch: &Patch<'_>) -> Vec<Self> {
+        patch
+            .hunks
+            .iter()
+            .map(|hunk| {
+                let (mut old, mut new) =
+                    (hunk.old_range.start, hunk.new_range.start);
+                let (mut removed, mut added) = (None, None);
+                let (mut insert_at, mut remove_at) = (None, None);
+                let extend = |range: &mut Option<(u64, u64)>, line: u64| {
+                    let (start, _) = range.get_or_insert((line, line));
+                    *range = Some((*start, line));
+                };
+                for line in &hunk.lines {
+                    match line {
+                        Line::Context(_) => {
+                            old += 1;
+                            new += 1;
+                        }
+                        Line::Remove(_) => {
+                            extend(&mut removed, old);
+                            let _ = remove_at.get_or_insert(new);
+                            old += 1;
+                        }
+                        Line::Add(_) => {
+                            extend(&mut added, new);
+                            let _ = insert_at.get_or_insert(old);
+                            new += 1;
+                        }
+                    }
+                }
+                let point = |line: Option<u64>| line.map(|line| (line, line));
+                let removed =
+                    removed.or_else(|| point(insert_at)).unwrap_or((old, old));
+                let added = added.or_else(|| point(remove_at)).unwrap_or((new, new));
+                let old_end = hunk.old_range.start + hunk.old_range.count;
+                Self {
+                    old: hunk.old_range.start..=old_end.saturating_sub(1),
+                    removed: removed.0..=removed.1,
+                    added: added.0..=added.1,
+                    delta: i64::try_from(hunk.new_range.count).unwrap_or_default()
+                        - i64::try_from(hunk.old_range.count).unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Lines of a file changed by an earlier patch.
+#[derive(Debug, Clone)]
+struct Region {
+    patch: PathBuf,
+    lines: RangeInclusive<u64>,
+}
+
+const fn overlaps(a: &RangeInclusive<u64>, b: &RangeInclusive<u64>) -> bool {
+    *a.start() <= *b.end() && *b.start() <= *a.end()
+}
+
+fn describe(lines: &RangeInclusive<u64>) -> String {
+    if lines.start() == lines.end() {
+        format!("line {}", lines.start())
+    } else {
+        format!("lines {}-{}", lines.start(), lines.end())
+    }
+}
+
+/// Lines changed by the patches applied to a package so far, by file.
+#[derive(Debug, Default)]
+pub struct History {
+    files: HashMap<String, Vec<Region>>,
+    /// Timestamps of the `+++` headers of the last patch changing a file.
+    times: HashMap<String, SystemTime>,
+}
+
+impl History {
+    /// Returns the earlier patch which changed any of `lines` of `file`.
+    fn changed_by(
+        &self,
+        file: &str,
+        lines: &RangeInclusive<u64>,
+    ) -> Option<&Region> {
+        self.files
+            .get(file)?
+            .iter()
+            .find(|region| overlaps(&region.lines, lines))
+    }
+
+    /// Adds the earlier patch which changed the lines of a failed hunk to
+    /// the error.
+    pub fn explain(
+        &self,
+        file: &str,
+        hunks: &[HunkLines],
+        err: anyhow::Error,
+    ) -> anyhow::Error {
+        match err.downcast::<Error>() {
+            Ok(Error::HunkFailed {
+                file: name,
+                line,
+                new_line,
+                hunk,
+                header,
+                patch,
+                changed_by: None,
+            }) => {
+                let changed_by = hunks
+                    .get(hunk - 1)
+                    .and_then(|lines| self.changed_by(file, &lines.old))
+                    .map(|region| region.patch.clone());
+                Error::HunkFailed {
+                    file: name,
+                    line,
+                    new_line,
+                    hunk,
+                    header,
+                    patch,
+                    changed_by,
+                }
+                .into()
+            }
+            Ok(err) => err.into(),
+            Err(err) => err,
+        }
+    }
+
+    /// Records the lines `patch` changed in `file`, warning about lines an
+    /// earlier patch changed as well.
+    pub fn record(&mut self, file: &str, patch: &Path, hunks: &[HunkLines]) {
+        for (number, lines) in hunks.iter().enumerate() {
+            if let Some(region) = self.changed_by(file, &lines.removed) {
+                status::warning(format!(
+                    "hunk {} of {} changes {} of {file} which {} changed before",
+                    number + 1,
+                    patch.display(),
+                    describe(&lines.removed),
+                    region.patch.display()
+                ));
+            }
+        }
+
+        let regions = self.files.entry(file.to_owned()).or_default();
+        for region in regions.iter_mut() {
+            let delta = hunks
+                .iter()
+                .filter(|lines| lines.old.end() < region.lines.start())
+                .map(|lines| lines.delta)
+                .sum::<i64>();
+            let shift = |line: u64| line.saturating_add_signed(delta);
+            region.lines = shift(*region.lines.start())..=shift(*region.lines.end());
+        }
+        regions.extend(hunks.iter().map(|lines| Region {
+            patch: patch.to_owned(),
+            lines: lines.added.clone(),
+        }));
+    }
+
+    /// Forgets the changes to a deleted file.
+    pub fn remove(&mut self, file: &str) {
+        let _ = self.files.remove(file);
+        let _ = self.times.remove(file);
+    }
+
+    /// Records the timestamp of the header of the patch which changed
+    /// `file` last.
+    pub fn record_time(&mut self, file: &str, time: SystemTime) {
+        let _ = self.times.insert(file.to_owned(), time);
+    }
+
+    /// Returns the recorded header timestamps by file.
+    pub fn times(&self) -> impl Iterator<Item = (&str, SystemTime)> + '_ {
+        self.times.iter().map(|(file, time)| (file.as_str(), *time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, HunkLines};
+    use patch::Patch;
+    use std::path::Path;
+
+    fn lines(diff: &str) -> Vec<HunkLines> {
+        HunkLines::of(&Patch::from_single(diff).expect("Unable to parse patch"))
+    }
+
+    #[test]
+    fn history_shifts_regions() {
+        let mut history = History::default();
+        history.record(
+            "lines.txt",
+            Path::new("first.patch"),
+            &lines("--- a\n+++ a\n@@ -8,3 +8,3 @@\n 7\n-8\n+EIGHT\n 9\n"),
+        );
+        history.record(
+            "lines.txt",
+            Path::new("second.patch"),
+            &lines("--- a\n+++ a\n@@ -1,2 +1,4 @@\n 1\n+1.25\n+1.5\n 2\n"),
+        );
+
+        let moved = lines("--- a\n+++ a\n@@ -10,3 +10,3 @@\n 7\n-EIGHT\n+8\n 9\n");
+        let region = history
+            .changed_by("lines.txt", &moved[0].removed)
+            .expect("Missing region");
+        assert_eq!(region.patch, Path::new("first.patch"));
+        assert_eq!(region.lines, 11..=11);
+        assert!(history.changed_by("lines.txt", &(4..=10)).is_none());
+    }
+}
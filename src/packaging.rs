@@ -0,0 +1,94 @@
+//! Files which the `include` and `exclude` keys leave out of published
+//! packages.
+
+use crate::{
+    engine::{parse_patches, patch_paths},
+    git_header::{self, HeaderOperation},
+    status, PatchSource,
+};
+use anyhow::Result;
+use cargo::core::Package;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// The packaging rules of a registry package. Files they leave out were
+/// never uploaded, so patches written against the git repository can't
+/// change them.
+pub struct Packaging {
+    include: Option<Gitignore>,
+    exclude: Gitignore,
+}
+
+/// Builds a matcher for the gitignore-style `patterns` relative to `root`.
+fn matcher(root: &Path, patterns: &[String]) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        let _ = builder.add_line(None, pattern)?;
+    }
+    Ok(builder.build()?)
+}
+
+impl Packaging {
+    /// Reads the rules of `pkg`. Returns `None` unless it comes from a
+    /// registry and sets `include` or `exclude`.
+    pub fn of(pkg: &Package) -> Result<Option<Self>> {
+        let manifest = pkg.manifest();
+        let (include, exclude) = (manifest.include(), manifest.exclude());
+        if !pkg.package_id().source_id().is_registry()
+            || (include.is_empty() && exclude.is_empty())
+        {
+            return Ok(None);
+        }
+        let root = pkg.root();
+        Ok(Some(Self {
+            include: if include.is_empty() {
+                None
+            } else {
+                Some(matcher(root, include)?)
+            },
+            exclude: matcher(root, exclude)?,
+        }))
+    }
+
+    /// Returns whether cargo leaves `file`, relative to the package, out of
+    /// the published package. The manifest is kept regardless of the rules.
+    fn excludes(&self, file: &str) -> bool {
+        if file == "Cargo.toml" {
+            return false;
+        }
+        let path = Path::new(file);
+        self.include.as_ref().map_or_else(
+            || {
+                self.exclude
+                    .matched_path_or_any_parents(path, false)
+                    .is_ignore()
+            },
+            |include| !include.matched_path_or_any_parents(path, false).is_ignore(),
+        )
+    }
+
+    /// Warns about every file the patch `data` expects which is missing from
+    /// the package `name` at `path` because it is excluded from packaging.
+    /// Files the patch creates are fine, as the copy is built as it is.
+    pub fn check(&self, name: &str, path: &Path, data: &str, source: &PatchSource) {
+        let (text, operations) = git_header::extract(data, source);
+        let patches = parse_patches(&text).unwrap_or_default();
+        let mut files = patches
+            .iter()
+            .filter(|patch| patch.hunks.iter().any(|hunk| hunk.old_range.count > 0))
+            .map(|patch| patch_paths(patch, source).0)
+            .filter(|file| *file != "/dev/null")
+            .chain(operations.iter().filter_map(HeaderOperation::source))
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        files.sort();
+        files.dedup();
+        for file in files {
+            if self.excludes(&file) && !path.join(&file).exists() {
+                status::warning(format!(
+                    "{name}: {file} is excluded from the published package by `include`/`exclude`, so patching it has no effect on the registry source. Depend on the git repository of {name} instead to patch it"
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Writing patched files through a temporary file.
+
+use crate::{permissions, FinalNewline};
+use anyhow::{anyhow, Result};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// A patched file written line by line into a temporary file next to it,
+/// which replaces the file once complete. Huge generated files are never
+/// kept in memory as a whole, and a crash never leaves a half written file
+/// behind.
+pub struct PatchedFile<'a> {
+    path: PathBuf,
+    tmp: PathBuf,
+    out: Option<BufWriter<File>>,
+    /// The line added last, written once it is known whether a newline
+    /// follows it.
+    last: Option<&'a str>,
+    /// Whether any line before [`PatchedFile::last`] was written.
+    written: bool,
+    /// The first failed write, reported by [`PatchedFile::finish`].
+    error: Option<io::Error>,
+}
+
+impl<'a> PatchedFile<'a> {
+    /// Starts writing the new content of `path`.
+    pub fn create(path: &Path) -> Result<Self> {
+        let path = permissions::resolve_link(path)?;
+        let tmp = permissions::tmp_path(&path)?;
+        let out = BufWriter::new(File::create(&tmp)?);
+        Ok(Self {
+            path,
+            tmp,
+            out: Some(out),
+            last: None,
+            written: false,
+            error: None,
+        })
+    }
+
+    /// Adds the next line without its newline.
+    pub fn line(&mut self, line: &'a str) {
+        if let Some(previous) = self.last.replace(line) {
+            self.write(previous, true);
+            self.written = true;
+        }
+    }
+
+    fn write(&mut self, line: &str, newline: bool) {
+        let (Some(out), None) = (&mut self.out, &self.error) else {
+            return;
+        };
+        let result = out.write_all(line.as_bytes()).and_then(|()| {
+            if newline {
+                out.write_all(b"\n")
+            } else {
+                Ok(())
+            }
+        });
+        self.error = result.err();
+    }
+
+    /// Writes the last line, followed by a newline if `newline` is set and
+    /// the policy agrees, and replaces the file.
+    pub fn finish(
+        mut self,
+        newline: bool,
+        final_newline: FinalNewline,
+    ) -> Result<()> {
+        if let Some(last) = self.last {
+            let (last, newline) = match final_newline {
+                FinalNewline::Preserve => (last, newline),
+                FinalNewline::Always => {
+                    (last, newline || self.written || !last.is_empty())
+                }
+                FinalNewline::Never if newline => {
+                    (last.strip_suffix('\r').unwrap_or(last), false)
+                }
+                FinalNewline::Never => (last, false),
+            };
+            self.write(last, newline);
+        }
+        if let Some(error) = self.error.take() {
+            return Err(error.into());
+        }
+        let out = self
+            .out
+            .take()
+            .ok_or_else(|| anyhow!("{} is already closed", self.tmp.display()))?;
+        let file = out.into_inner().map_err(io::IntoInnerError::into_error)?;
+        permissions::persist(file, &self.tmp, &self.path)
+    }
+}
+
+impl Drop for PatchedFile<'_> {
+    fn drop(&mut self) {
+        // unless finished, e.g. because a hunk failed, the file stays as is
+        drop(self.out.take());
+        if self.tmp.exists() {
+            let _ = fs::remove_file(&self.tmp);
+        }
+    }
+}
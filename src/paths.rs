@@ -0,0 +1,182 @@
+//! Lookup of the locations patched dependencies are written to.
+
+use crate::{
+    orchestrator::{
+        get_config, load_workspace, lock_package_cache, package_copy_path,
+        resolve_patches, resolve_ws, setup_gctx, Options,
+    },
+    Error,
+};
+use anyhow::Result;
+use cargo::{
+    core::{PackageId, SourceId},
+    util::CanonicalUrl,
+    GlobalContext,
+};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, InlineTable, Item, Table};
+
+/// Output format of [`paths`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathsFormat {
+    #[default]
+    Text,
+    Json,
+    /// `[patch]` sections to paste into `Cargo.toml`.
+    Toml,
+}
+
+/// How the paths of patched copies are written into `[patch]` sections and
+/// provenance files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OverridePathStyle {
+    /// Relative to the workspace root, like `./target/patch/serde-1.0.110`.
+    #[default]
+    Relative,
+    /// Absolute, for manifests in nested directories or other workspaces.
+    Absolute,
+}
+
+impl OverridePathStyle {
+    /// Formats the path of a copy for the workspace at `root`. Copies outside
+    /// of the workspace always get an absolute path.
+    pub fn format(self, path: &Path, root: &Path) -> String {
+        match (self, path.strip_prefix(root)) {
+            (Self::Relative, Ok(relative)) => format!("./{}", relative.display()),
+            _ => path.display().to_string(),
+        }
+    }
+}
+
+/// Returns the name of the registry in `[registries]` of the cargo config
+/// whose index is the one of `source`. Sources read from `Cargo.lock` only
+/// know their URL.
+fn registry_name(gctx: &GlobalContext, source: SourceId) -> Option<String> {
+    let (registries, _) = gctx
+        .values()
+        .ok()?
+        .get("registries")?
+        .table("registries")
+        .ok()?;
+    let mut names = registries.keys().collect::<Vec<_>>();
+    names.sort();
+    names
+        .into_iter()
+        .find(|name| {
+            gctx.get_registry_index(name)
+                .ok()
+                .and_then(|index| CanonicalUrl::new(&index).ok())
+                .as_ref()
+                == Some(source.canonical_url())
+        })
+        .cloned()
+}
+
+/// Returns the key of the `[patch]` section overriding packages of the
+/// source, `None` for path dependencies which can't be overridden.
+///
+/// Git URLs are normalized the way cargo matches them, so dependencies on
+/// `https://github.com/Foo/bar.git` and `https://github.com/foo/bar` share
+/// one section.
+fn patch_section(gctx: &GlobalContext, source: SourceId) -> Option<String> {
+    if source.is_crates_io() {
+        Some("crates-io".to_owned())
+    } else if source.is_path() {
+        None
+    } else if let Some(registry) = source.alt_registry_key() {
+        Some(registry.to_owned())
+    } else if let Some(registry) = source
+        .is_registry()
+        .then(|| registry_name(gctx, source))
+        .flatten()
+    {
+        Some(registry)
+    } else {
+        Some(source.canonical_url().raw_canonicalized_url().to_string())
+    }
+}
+
+/// Prints `[patch]` sections overriding every package with its copy.
+fn print_toml(
+    gctx: &GlobalContext,
+    entries: &[(PackageId, PathBuf)],
+    root: &Path,
+    style: OverridePathStyle,
+) {
+    let mut patch = Table::new();
+    patch.set_implicit(true);
+    for (id, path) in entries {
+        let Some(section) = patch_section(gctx, id.source_id()) else {
+            continue;
+        };
+        let table = patch
+            .entry(&section)
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_mut()
+            .expect("patch sections are tables");
+        let mut dependency = InlineTable::new();
+        dependency.insert("path", style.format(path, root).into());
+        let mut key = id.name().to_string();
+        if table.contains_key(&key) {
+            key = format!("{}-{}", id.name(), id.version());
+            dependency.insert("package", id.name().as_str().into());
+        }
+        table.insert(&key, toml_edit::value(dependency));
+    }
+    let mut document = DocumentMut::new();
+    document.insert("patch", Item::Table(patch));
+    print!("{document}");
+}
+
+/// Prints name, version and the location in `target/patch` of each
+/// configured package and whether it exists yet. Nothing is copied.
+pub fn paths(format: PathsFormat, options: &Options) -> Result<(), Error> {
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+    let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+    let ids = resolve_patches(&workspace, &pkg_set, &resolve, &config)?;
+    let cwd = std::env::current_dir()?;
+
+    let mut entries = vec![];
+    for (_, id) in ids {
+        let package = pkg_set.get_one(id)?;
+        let path = cwd.join(package_copy_path(package, &config)?);
+        entries.push((id, path.exists(), path));
+    }
+
+    if format == PathsFormat::Toml {
+        let entries = entries
+            .into_iter()
+            .map(|(id, _, path)| (id, path))
+            .collect::<Vec<_>>();
+        print_toml(
+            &gctx,
+            &entries,
+            workspace.root(),
+            options.override_path_style,
+        );
+    } else if format == PathsFormat::Json {
+        let entries = entries
+            .iter()
+            .map(|(id, exists, path)| {
+                json!({
+                    "id": id.to_spec().to_string(),
+                    "name": id.name().as_str(),
+                    "version": id.version().to_string(),
+                    "path": path,
+                    "exists": exists,
+                })
+            })
+            .collect::<Vec<_>>();
+        println!("{:#}", json!(entries));
+    } else {
+        for (id, exists, path) in entries {
+            let state = if exists { "" } else { " (missing)" };
+            println!("{} {} {}{state}", id.name(), id.version(), path.display());
+        }
+    }
+    Ok(())
+}
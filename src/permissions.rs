@@ -0,0 +1,177 @@
+//! Handling of read-only files in copied packages and writing files
+//! atomically.
+//!
+//! Registry sources are often extracted read-only, and copies keep the
+//! permissions of their sources, so writing patched files would fail.
+
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Returns the permissions with write access for the owner added.
+#[cfg(unix)]
+fn with_write(mut permissions: fs::Permissions) -> fs::Permissions {
+    use std::os::unix::fs::PermissionsExt;
+
+    permissions.set_mode(permissions.mode() | 0o200);
+    permissions
+}
+
+/// Returns the permissions with write access for the owner added.
+#[cfg(not(unix))]
+#[allow(clippy::permissions_set_readonly_false)]
+fn with_write(mut permissions: fs::Permissions) -> fs::Permissions {
+    permissions.set_readonly(false);
+    permissions
+}
+
+/// Makes `path` and, if it is a directory, everything below it writable.
+#[cfg(any(feature = "orchestrator", feature = "metadata-backend"))]
+pub fn make_writable(path: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+    if metadata.permissions().readonly() {
+        fs::set_permissions(path, with_write(metadata.permissions()))?;
+    }
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            make_writable(&entry?.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Gives everything below `path` the permissions `0755` if it is a directory
+/// or an executable file and `0644` otherwise, so they don't depend on the
+/// umask or the permissions of the sources.
+#[cfg(all(unix, feature = "orchestrator"))]
+pub fn normalize(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+    let mode = if metadata.is_dir() || metadata.permissions().mode() & 0o111 != 0 {
+        0o755
+    } else {
+        0o644
+    };
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    if metadata.is_dir() {
+        for entry in fs::read_dir(path)? {
+            normalize(&entry?.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Permissions only consist of the read-only flag on this platform, which
+/// copies never have.
+#[cfg(all(not(unix), feature = "orchestrator"))]
+#[allow(clippy::unnecessary_wraps)]
+pub const fn normalize(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Whether written files and their directories are synced to disk, see
+/// [`set_sync`].
+static SYNC: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`write`] and [`persist`] sync the written file and its directory
+/// to disk before returning, so not even a power loss can truncate them.
+#[cfg(feature = "orchestrator")]
+pub fn set_sync(sync: bool) {
+    SYNC.store(sync, Ordering::Relaxed);
+}
+
+/// Returns the temporary sibling `path` is written to before replacing it.
+pub fn tmp_path(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file name {}", path.display()))?;
+    let mut tmp_name = OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(".cargo-patch.tmp");
+    Ok(path.with_file_name(tmp_name))
+}
+
+/// Writes `data` to `path` through a temporary file, so `path` is either
+/// left as it was or completely written, even if the process is killed.
+/// An existing file keeps its permissions, even if it is read-only.
+pub fn write(path: &Path, data: impl AsRef<[u8]>) -> Result<()> {
+    let path = resolve_link(path)?;
+    let tmp = tmp_path(&path)?;
+    let result = File::create(&tmp)
+        .and_then(|mut file| file.write_all(data.as_ref()).map(|()| file))
+        .map_err(anyhow::Error::from)
+        .and_then(|file| persist(file, &tmp, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Replaces `path` with the completely written temporary file `tmp`, which
+/// is open as `file`.
+pub fn persist(file: File, tmp: &Path, path: &Path) -> Result<()> {
+    let sync = SYNC.load(Ordering::Relaxed);
+    if sync {
+        file.sync_all()?;
+    }
+    drop(file);
+    replace(tmp, path)?;
+    if sync {
+        sync_dir(path)?;
+    }
+    Ok(())
+}
+
+/// Returns the file a symlink at `path` points to, as replacing the link
+/// itself would break it.
+pub fn resolve_link(path: &Path) -> Result<PathBuf> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            Ok(fs::canonicalize(path)?)
+        }
+        _ => Ok(path.to_owned()),
+    }
+}
+
+/// Syncs the directory containing `path`, which makes a rename durable.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Directories can't be synced on this platform.
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+const fn sync_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Moves the file `tmp` over `path`. The file gets the permissions of the
+/// file it replaces, which is made writable first as a read-only file can't
+/// be replaced on every platform.
+fn replace(tmp: &Path, path: &Path) -> Result<()> {
+    if let Ok(metadata) = fs::metadata(path) {
+        let permissions = metadata.permissions();
+        if permissions.readonly() {
+            fs::set_permissions(path, with_write(permissions.clone()))?;
+        }
+        fs::set_permissions(tmp, permissions)?;
+    }
+    fs::rename(tmp, path)?;
+    Ok(())
+}
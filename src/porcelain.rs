@@ -0,0 +1,100 @@
+//! Line-oriented output for scripts, which stays stable across releases
+//! unlike the human readable status lines.
+//!
+//! Version 1 prints one record per line on stdout. Fields are separated by
+//! tabs, and tabs, newlines and backslashes within fields are escaped as
+//! `\t`, `\n` and `\\`. Records:
+//!
+//! - `patched <package> <file> <hunks>`: a file of a package was changed by a
+//!   patch file with `hunks` hunks. `file` is relative to the package root.
+//!
+//! Later releases may add record types and trailing fields to version 1, so
+//! consumers have to ignore unknown records and fields.
+
+use std::cell::Cell;
+
+thread_local! {
+    /// Whether porcelain records are printed.
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Enables or disables printing records.
+pub fn init(enabled: bool) {
+    ENABLED.set(enabled);
+}
+
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn record(fields: &[&str]) {
+    if ENABLED.get() {
+        let fields = fields.iter().map(|field| escape(field)).collect::<Vec<_>>();
+        println!("{}", fields.join("\t"));
+    }
+}
+
+/// Records that `hunks` hunks changed `file` of the package `name`.
+pub fn patched(name: &str, file: &str, hunks: usize) {
+    record(&["patched", name, file, &hunks.to_string()]);
+}
+
+/// Returns the files a diff changes with the number of hunks of each, for
+/// patches applied by external tools. `strip` removes the `a/` and `b/`
+/// prefixes of git diffs.
+pub fn diff_files(data: &str, strip: bool) -> Vec<(String, usize)> {
+    let path = |line: &str, prefix: &str| {
+        let path = line
+            .strip_prefix(prefix)
+            .unwrap_or_default()
+            .split('\t')
+            .next()
+            .unwrap_or_default()
+            .trim_end();
+        if strip && path != "/dev/null" {
+            path.split_once('/')
+                .map_or(path, |(_, path)| path)
+                .to_owned()
+        } else {
+            path.to_owned()
+        }
+    };
+    let lines = data.lines().collect::<Vec<_>>();
+    let mut files = Vec::<(String, usize)>::new();
+    for (index, line) in lines.iter().enumerate() {
+        let next = lines.get(index + 1).copied().unwrap_or_default();
+        // a removed line starting with `-- ` looks like a header, too
+        if line.starts_with("--- ") && next.starts_with("+++ ") {
+            let (old, new) = (path(line, "--- "), path(next, "+++ "));
+            files.push((if new == "/dev/null" { old } else { new }, 0));
+        } else if line.starts_with("@@ ") {
+            if let Some((_, hunks)) = files.last_mut() {
+                *hunks += 1;
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_files, escape};
+
+    #[test]
+    fn escapes_fields() {
+        assert_eq!(escape("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+    }
+
+    #[test]
+    fn counts_hunks_per_file() {
+        let data = "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n@@ -5 +5 @@\n-c\n+d\n\
+                    --- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-gone\n";
+        assert_eq!(
+            diff_files(data, true),
+            [("src/lib.rs".to_owned(), 2), ("old.rs".to_owned(), 1)]
+        );
+    }
+}
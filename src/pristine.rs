@@ -0,0 +1,194 @@
+//! Detection of local edits to the cached sources of a package, which would
+//! end up in its patched copy unnoticed.
+
+use crate::{orchestrator::Options, status};
+use anyhow::{anyhow, Result};
+use cargo::core::Package;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+/// Checksums of vendored sources, written by `cargo vendor`.
+const CHECKSUM_FILE: &str = ".cargo-checksum.json";
+/// Marker cargo writes after extracting a registry package.
+const EXTRACTED_MARKER: &str = ".cargo-ok";
+
+fn sha256(reader: &mut impl io::Read) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let _ = io::copy(reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Adds the checksums of all files below `dir`, keyed by their path
+/// relative to `root` with `/` as separator.
+fn collect(
+    root: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect(root, &path, files)?;
+            continue;
+        }
+        let relative = path
+            .strip_prefix(root)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if relative != CHECKSUM_FILE && relative != EXTRACTED_MARKER {
+            let _ = files.insert(relative, sha256(&mut File::open(&path)?)?);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the checksums of the files listed in `.cargo-checksum.json`.
+fn vendored(path: &Path) -> Result<BTreeMap<String, String>> {
+    let checksums = serde_json::from_str::<Value>(&fs::read_to_string(path)?)?;
+    Ok(checksums
+        .get("files")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter_map(|(file, sum)| Some((file.clone(), sum.as_str()?.to_owned())))
+        .collect())
+}
+
+/// Returns the checksums of the files in the `.crate` archive cargo
+/// downloaded the registry package from. Returns `None` if it isn't cached.
+fn archived(package: &Package) -> Result<Option<BTreeMap<String, String>>> {
+    // sources are extracted to `registry/src/<index>/<name>-<version>` and
+    // archives cached as `registry/cache/<index>/<name>-<version>.crate`
+    let root = package.root();
+    let (Some(index), Some(registry)) = (
+        root.parent().and_then(Path::file_name),
+        root.parent().and_then(Path::parent).and_then(Path::parent),
+    ) else {
+        return Ok(None);
+    };
+    let dir_name = format!("{}-{}", package.name(), package.version());
+    let archive = registry
+        .join("cache")
+        .join(index)
+        .join(format!("{dir_name}.crate"));
+    let Ok(file) = File::open(archive) else {
+        return Ok(None);
+    };
+    let mut files = BTreeMap::new();
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?;
+        let Ok(relative) = path.strip_prefix(&dir_name) else {
+            continue;
+        };
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let _ = files.insert(relative, sha256(&mut entry)?);
+    }
+    Ok(Some(files))
+}
+
+/// Returns the changed, removed and added files of `actual` compared to
+/// `expected`.
+fn changes(
+    expected: &BTreeMap<String, String>,
+    actual: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut changes = vec![];
+    for (file, sum) in expected {
+        match actual.get(file) {
+            Some(actual) if actual == sum => {}
+            Some(_) => changes.push(format!("{file} changed")),
+            None => changes.push(format!("{file} removed")),
+        }
+    }
+    changes.extend(
+        actual
+            .keys()
+            .filter(|file| !expected.contains_key(*file))
+            .map(|file| format!("{file} added")),
+    );
+    changes
+}
+
+/// Warns, or fails in strict mode, if the sources of `package` differ from
+/// their `.cargo-checksum.json` for vendored packages or from the cached
+/// `.crate` archive for registry packages. Patches would otherwise be applied
+/// on top of unknown local edits.
+pub fn check(package: &Package, options: &Options) -> Result<()> {
+    let root = package.root();
+    let checksums = root.join(CHECKSUM_FILE);
+    let (expected, fix) = if checksums.is_file() {
+        (vendored(&checksums)?, "Vendor it again")
+    } else if package.package_id().source_id().is_registry() {
+        let Some(expected) = archived(package)? else {
+            return Ok(());
+        };
+        (expected, "Delete it to have cargo extract it again")
+    } else {
+        return Ok(());
+    };
+    let mut actual = BTreeMap::new();
+    collect(root, root, &mut actual)?;
+    let changes = changes(&expected, &actual);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "The pristine source of {} v{} at {} was modified locally ({}), so the patched copy contains these edits. {fix}",
+        package.name(),
+        package.version(),
+        root.display(),
+        changes.join(", ")
+    );
+    if options.strict {
+        return Err(anyhow!(message));
+    }
+    status::warning(message);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::changes;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn lists_changes() {
+        let expected = BTreeMap::from([
+            ("Cargo.toml".to_owned(), "a".to_owned()),
+            ("src/lib.rs".to_owned(), "b".to_owned()),
+            ("README.md".to_owned(), "c".to_owned()),
+        ]);
+        let actual = BTreeMap::from([
+            ("Cargo.toml".to_owned(), "a".to_owned()),
+            ("src/lib.rs".to_owned(), "x".to_owned()),
+            ("src/extra.rs".to_owned(), "d".to_owned()),
+        ]);
+        assert_eq!(
+            changes(&expected, &actual),
+            [
+                "README.md removed",
+                "src/lib.rs changed",
+                "src/extra.rs added"
+            ]
+        );
+    }
+}
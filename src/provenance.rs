@@ -0,0 +1,141 @@
+//! Provenance file recording how a patched copy was created.
+
+use crate::permissions;
+use anyhow::Result;
+use cargo::core::package::Package;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{fs, path::Path, time::SystemTime};
+
+/// Name of the provenance file in each patched copy.
+pub const PROVENANCE_FILE: &str = ".cargo-patch.json";
+
+/// A patch file applied to a package.
+#[derive(Debug, Clone)]
+pub struct AppliedPatch {
+    /// Path or URL of the patch file.
+    pub file: String,
+    /// SHA-256 hash of the applied content.
+    pub sha256: String,
+}
+
+impl AppliedPatch {
+    pub fn new(file: String, data: &[u8]) -> Self {
+        Self {
+            file,
+            sha256: format!("{:x}", Sha256::digest(data)),
+        }
+    }
+}
+
+/// Writes the provenance file into the patched copy at `path`, together with
+/// the path a `[patch]` section overrides the package with.
+pub fn write(
+    path: &Path,
+    package: &Package,
+    patches: &[AppliedPatch],
+    stamp: &str,
+    override_path: &str,
+    patched_at: SystemTime,
+) -> Result<()> {
+    let patches = patches
+        .iter()
+        .map(|patch| json!({ "file": patch.file, "sha256": patch.sha256 }))
+        .collect::<Vec<_>>();
+    let mut info = json!({
+        "tool": format!("cargo-patch {}", env!("CARGO_PKG_VERSION")),
+        "package": package.package_id().to_string(),
+        "pkgid": package.package_id().to_spec().to_string(),
+        "override-path": override_path,
+        "patched-at": humantime::format_rfc3339_seconds(patched_at).to_string(),
+        "stamp": stamp,
+        "patches": patches,
+    });
+    if let Some(git) = GitCommit::of(package) {
+        info["git-source"] = json!(git.source);
+        info["git-commit"] = json!(git.commit);
+    }
+    permissions::write(&path.join(PROVENANCE_FILE), format!("{info:#}\n"))?;
+    Ok(())
+}
+
+/// Returns the stamp recorded in the provenance file of the copy at `path`.
+pub fn read_stamp(path: &Path) -> Option<String> {
+    let data = fs::read_to_string(path.join(PROVENANCE_FILE)).ok()?;
+    let info = serde_json::from_str::<Value>(&data).ok()?;
+    info.get("stamp")?.as_str().map(str::to_owned)
+}
+
+/// The commit a git dependency was resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitCommit {
+    pub name: String,
+    /// Source of the package without the resolved commit.
+    pub source: String,
+    pub commit: String,
+}
+
+impl GitCommit {
+    /// Returns the commit `package` was resolved to, `None` if it isn't a
+    /// git dependency.
+    pub fn of(package: &Package) -> Option<Self> {
+        let source = package.package_id().source_id();
+        if !source.is_git() {
+            return None;
+        }
+        Some(Self {
+            name: package.name().to_string(),
+            source: source.with_git_precise(None).as_url().to_string(),
+            commit: source.precise_git_fragment()?.to_owned(),
+        })
+    }
+
+    /// Reads the commit recorded in the provenance file of the copy at
+    /// `path`.
+    fn read(path: &Path) -> Option<Self> {
+        let data = fs::read_to_string(path.join(PROVENANCE_FILE)).ok()?;
+        let info = serde_json::from_str::<Value>(&data).ok()?;
+        let field = |key| info.get(key)?.as_str().map(str::to_owned);
+        Some(Self {
+            name: field("package")?.split(' ').next()?.to_owned(),
+            source: field("git-source")?,
+            commit: field("git-commit")?,
+        })
+    }
+}
+
+/// Returns whether `dir` holds a copy patched by cargo-patch, either
+/// directly or below it for copies of whole repositories.
+pub fn is_copy(dir: &Path) -> bool {
+    if dir.join(PROVENANCE_FILE).exists() {
+        return true;
+    }
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|entry| {
+            entry.file_type().is_ok_and(|kind| kind.is_dir())
+                && entry.file_name() != ".git"
+                && is_copy(&entry.path())
+        })
+}
+
+/// Returns the git commits recorded by the patched copies below `dir`.
+pub fn git_commits(dir: &Path) -> Result<Vec<GitCommit>> {
+    let mut commits = vec![];
+    if !dir.is_dir() {
+        return Ok(commits);
+    }
+    if dir.join(PROVENANCE_FILE).exists() {
+        commits.extend(GitCommit::read(dir));
+        return Ok(commits);
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && entry.file_name() != ".git" {
+            commits.extend(git_commits(&entry.path())?);
+        }
+    }
+    Ok(commits)
+}
@@ -0,0 +1,348 @@
+//! Re-applying patches onto a new version of a dependency.
+
+use crate::{
+    copy_ignore,
+    diff::unified_diff,
+    engine::{
+        check_path, mark_missing_newlines, parse_patches, patch_paths,
+        split_no_newline,
+    },
+    git_header,
+    long_path::long_path,
+    orchestrator::{
+        copy_package, copy_root, get_config, load_workspace, lock_package_cache,
+        patch_base, read_patch, resolve_patches, resolve_ws, setup_gctx, Options,
+        PatchConfig, PatchItem,
+    },
+    permissions, status,
+    transform::Encoding,
+    Error,
+};
+use anyhow::{anyhow, Result};
+use patch::{Hunk, Line};
+use std::{fs, path::Path};
+
+/// Number of context lines which may be ignored at either end of a hunk.
+const MAX_FUZZ: usize = 2;
+
+/// How a hunk could be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HunkOutcome {
+    /// At the position given in the hunk header.
+    Exact,
+    /// At another position or ignoring some context lines.
+    Moved,
+    /// Not at all.
+    Failed,
+}
+
+/// Old and new lines of a hunk with up to `fuzz` context lines removed at
+/// both ends.
+fn hunk_lines<'a>(
+    hunk: &Hunk<'a>,
+    fuzz: usize,
+) -> (usize, Vec<&'a str>, Vec<&'a str>) {
+    let lines = &hunk.lines;
+    let leading = lines
+        .iter()
+        .take_while(|line| matches!(line, Line::Context(_)))
+        .count()
+        .min(fuzz);
+    let trailing = lines
+        .iter()
+        .rev()
+        .take_while(|line| matches!(line, Line::Context(_)))
+        .count()
+        .min(fuzz)
+        .min(lines.len() - leading);
+    let mut old = vec![];
+    let mut new = vec![];
+    for line in lines
+        .iter()
+        .skip(leading)
+        .take(lines.len() - leading - trailing)
+    {
+        match line {
+            Line::Context(line) => {
+                old.push(split_no_newline(line).0);
+                new.push(split_no_newline(line).0);
+            }
+            Line::Add(line) => new.push(split_no_newline(line).0),
+            Line::Remove(line) => old.push(split_no_newline(line).0),
+        }
+    }
+    (leading, old, new)
+}
+
+/// Returns the position closest to `expected` at or after `min` where
+/// `needle` matches `lines`.
+fn find_position(
+    lines: &[&str],
+    needle: &[&str],
+    expected: usize,
+    min: usize,
+) -> Option<usize> {
+    let last = lines.len().checked_sub(needle.len())?;
+    let matches = |pos: usize| {
+        pos >= min && pos <= last && lines[pos..pos + needle.len()] == *needle
+    };
+    (0..=lines.len()).find_map(|distance| {
+        [
+            expected.checked_add(distance),
+            expected.checked_sub(distance),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|pos| matches(*pos))
+    })
+}
+
+/// Applies the hunks to `old`, looking for each hunk around its expected
+/// position and ignoring up to [`MAX_FUZZ`] context lines if necessary.
+/// Hunks which can't be found are skipped.
+#[allow(clippy::as_conversions, clippy::cast_possible_truncation)]
+fn apply_fuzzy(hunks: &[Hunk<'_>], old: &str) -> (String, Vec<HunkOutcome>) {
+    let mut lines = old.lines().collect::<Vec<_>>();
+    let mut outcomes = vec![];
+    // lines added by the previous hunks
+    let mut delta = 0_isize;
+    let mut min = 0;
+    for hunk in hunks {
+        let start = if hunk.old_range.count == 0 {
+            hunk.old_range.start
+        } else {
+            hunk.old_range.start.saturating_sub(1)
+        } as usize;
+        let expected = start.saturating_add_signed(delta);
+        let found = (0..=MAX_FUZZ).find_map(|fuzz| {
+            let (leading, old_lines, new_lines) = hunk_lines(hunk, fuzz);
+            find_position(&lines, &old_lines, expected + leading, min)
+                .map(|pos| (fuzz, pos, old_lines, new_lines, leading))
+        });
+        let Some((fuzz, pos, old_lines, new_lines, leading)) = found else {
+            outcomes.push(HunkOutcome::Failed);
+            continue;
+        };
+        outcomes.push(if fuzz == 0 && pos == expected + leading {
+            HunkOutcome::Exact
+        } else {
+            HunkOutcome::Moved
+        });
+        let added = new_lines.len();
+        let removed = old_lines.len();
+        let _ = lines.splice(pos..pos + removed, new_lines);
+        delta += added as isize - removed as isize;
+        min = pos + added;
+    }
+    let mut new = lines.join("\n");
+    if !lines.is_empty() && (old.ends_with('\n') || old.is_empty()) {
+        new.push('\n');
+    }
+    (new, outcomes)
+}
+
+/// Rebases a single patch file onto the package at `path`. Returns the
+/// number of hunks which need manual attention.
+fn rebase_item(
+    name: &str,
+    item: &PatchItem<'_>,
+    path: &Path,
+    config: &PatchConfig,
+) -> Result<usize> {
+    let file = item.path.display();
+    if item.remote.is_some() {
+        status::status("Skipping", format!("remote patch {file}"));
+        return Ok(0);
+    }
+    if let Some(origin) = &item.origin {
+        status::status("Skipping", format!("patch {file} of {}", origin.name));
+        return Ok(0);
+    }
+    // decoded like when applying, the file itself may differ
+    let (_, original) = read_patch(item, config)?;
+    let (diffs, operations) = git_header::extract(&original, &item.source);
+    let data = mark_missing_newlines(&diffs);
+    let patches = parse_patches(&data).ok_or_else(|| Error::PatchParse {
+        file: item.path.to_path_buf(),
+    })?;
+
+    let mut failed = 0;
+    let mut moved = false;
+    let mut regenerated = String::new();
+    for patch in &patches {
+        let (old_path, new_path) = patch_paths(patch, &item.source);
+        let loc = item.path.display().to_string();
+        let old_file = (patch.old.path != "/dev/null")
+            .then(|| check_path(path, old_path, &loc))
+            .transpose()?;
+        let new_file = (patch.new.path != "/dev/null")
+            .then(|| check_path(path, new_path, &loc))
+            .transpose()?;
+        let before = match &old_file {
+            Some(file) if !file.exists() => {
+                status::line(format!("{old_path} no longer exists"));
+                failed += patch.hunks.len();
+                continue;
+            }
+            Some(file) => fs::read_to_string(file)?,
+            None => String::new(),
+        };
+        let (after, outcomes) = apply_fuzzy(&patch.hunks, &before);
+        for (index, outcome) in outcomes.iter().enumerate() {
+            if *outcome == HunkOutcome::Failed {
+                status::line(format!(
+                    "Hunk {} of {new_path} in {file} needs manual attention",
+                    index + 1
+                ));
+                failed += 1;
+            }
+        }
+        moved |= outcomes.contains(&HunkOutcome::Moved);
+        match new_file {
+            Some(new_file) => {
+                if let Some(parent) = new_file.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                permissions::write(&new_file, &after)?;
+                if let Some(old_file) = old_file.filter(|_| old_path != new_path) {
+                    fs::remove_file(old_file)?;
+                }
+            }
+            None => {
+                if let Some(old_file) = old_file {
+                    fs::remove_file(old_file)?;
+                }
+            }
+        }
+        let old_label = patch.old.path.as_ref();
+        let new_label = patch.new.path.as_ref();
+        regenerated.push_str(&unified_diff(old_label, new_label, &before, &after));
+    }
+    for operation in &operations {
+        operation.apply(name, path)?;
+    }
+
+    if failed > 0 {
+        status::line(format!("Unable to rebase {file}"));
+    } else if moved && !operations.is_empty() {
+        status::line(format!(
+            "{file} applies with offsets, update it manually to keep its git headers"
+        ));
+    } else if moved
+        && (item.encoding != Encoding::Utf8 || !item.transforms.is_empty())
+    {
+        status::line(format!(
+            "{file} applies with offsets, update it manually to keep its encoding"
+        ));
+    } else if moved && diffs != original {
+        status::line(format!(
+            "{file} applies with offsets, update it manually to keep the text around its diffs"
+        ));
+    } else if moved && item.signature.is_some() {
+        status::warning(format!(
+            "{file} applies with offsets, update and sign it again manually"
+        ));
+    } else if moved {
+        permissions::write(&item.path, regenerated)?;
+        status::status("Updated", file);
+    } else {
+        status::line(format!("{file} applies cleanly"));
+    }
+    Ok(failed)
+}
+
+/// Re-applies the patches of the dependency `name` to its current version.
+///
+/// Moved hunks and changed context lines are tolerated. Patch files which
+/// only apply that way are rewritten, hunks which can't be applied are
+/// reported. Patch files of other packages, signed ones and those with text
+/// besides their diffs are left alone.
+pub fn rebase(name: &str, options: &Options) -> Result<(), Error> {
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+    let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+    let ids = resolve_patches(&workspace, &pkg_set, &resolve, &config)?
+        .into_iter()
+        .filter(|(patch, _)| patch.name == name)
+        .collect::<Vec<_>>();
+    if ids.is_empty() {
+        return Err(Error::PackageNotFound {
+            name: name.to_owned(),
+        });
+    }
+
+    let mut failed = 0;
+    for (patch, id) in ids {
+        status::status(
+            "Rebasing",
+            format!("patches of {} v{}", id.name(), id.version()),
+        );
+        let package = pkg_set.get_one(id)?;
+        let root = long_path(&copy_root(package, &config))?;
+        if root.exists() {
+            fs::remove_dir_all(root)?;
+        }
+        let _ = copy_package(package, &config, patch.copy_repo_root)?;
+        let base = patch_base(&patch, package, &config)?;
+        if let Some(ignore_file) = patch.copy_ignore {
+            let _ = copy_ignore::prune(&base, ignore_file)?;
+        }
+        for item in &patch.patches {
+            failed += rebase_item(name, item, &base, &config)?;
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} hunk(s) need manual attention").into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_fuzzy, HunkOutcome};
+    use patch::Patch;
+
+    const OLD: &str = "one\ntwo\nthree\nfour\nfive\nsix\n";
+
+    #[test]
+    fn apply_fuzzy_offset() {
+        let patch =
+            "--- test\n+++ test\n@@ -1,3 +1,3 @@\n four\n-five\n+FIVE\n six\n";
+        let patch = Patch::from_single(patch).expect("Unable to parse patch");
+        let (new, outcomes) = apply_fuzzy(&patch.hunks, OLD);
+        assert_eq!(new, "one\ntwo\nthree\nfour\nFIVE\nsix\n");
+        assert_eq!(outcomes, [HunkOutcome::Moved]);
+    }
+
+    #[test]
+    fn apply_fuzzy_changed_context() {
+        let patch =
+            "--- test\n+++ test\n@@ -2,3 +2,3 @@\n 2\n-three\n+THREE\n four\n";
+        let patch = Patch::from_single(patch).expect("Unable to parse patch");
+        let (new, outcomes) = apply_fuzzy(&patch.hunks, OLD);
+        assert_eq!(new, "one\ntwo\nTHREE\nfour\nfive\nsix\n");
+        assert_eq!(outcomes, [HunkOutcome::Moved]);
+    }
+
+    #[test]
+    fn apply_fuzzy_failed() {
+        let patch = "--- test\n+++ test\n@@ -2 +2 @@\n-seven\n+SEVEN\n";
+        let patch = Patch::from_single(patch).expect("Unable to parse patch");
+        let (new, outcomes) = apply_fuzzy(&patch.hunks, OLD);
+        assert_eq!(new, OLD);
+        assert_eq!(outcomes, [HunkOutcome::Failed]);
+    }
+
+    #[test]
+    fn apply_fuzzy_shifted_hunks() {
+        let patch = "--- test\n+++ test\n\
+            @@ -1,2 +1,4 @@\n one\n+ONE\n+UNO\n two\n\
+            @@ -5,2 +7,2 @@\n five\n-six\n+SIX\n";
+        let patch = Patch::from_single(patch).expect("Unable to parse patch");
+        let (new, outcomes) = apply_fuzzy(&patch.hunks, OLD);
+        assert_eq!(new, "one\nONE\nUNO\ntwo\nthree\nfour\nfive\nSIX\n");
+        assert_eq!(outcomes, [HunkOutcome::Exact, HunkOutcome::Exact]);
+    }
+}
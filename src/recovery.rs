@@ -0,0 +1,174 @@
+//! Keeping the workspace buildable after a package failed to patch.
+
+use crate::{permissions, status};
+use anyhow::Result;
+use semver::Version;
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use toml_edit::{ImDocument, Item, Key};
+
+/// First line of an override commented out by [`recover`].
+const DISABLED_NOTE: &str = "# Disabled by cargo-patch as patching failed:";
+
+/// Returns the path of a `[patch]` entry, resolved against the workspace
+/// root, with symlinks resolved if it exists.
+fn override_path(item: &Item, root: &Path) -> Option<PathBuf> {
+    let path = root.join(item.get("path")?.as_str()?);
+    Some(path.canonicalize().unwrap_or(path))
+}
+
+/// Returns the range of the lines of a `[patch]` entry, either an entry like
+/// `serde = { path = "..." }` or a table like `[patch.crates-io.serde]`.
+fn entry_lines(text: &str, key: &Key, item: &Item) -> Option<Range<usize>> {
+    let (start, end) = match item {
+        Item::Value(value) => (key.span()?.start, value.span()?.end),
+        Item::Table(table) => {
+            let end = table
+                .iter()
+                .filter_map(|(_, item)| item.span())
+                .map(|span| span.end)
+                .fold(table.span()?.end, usize::max);
+            (table.span()?.start, end)
+        }
+        _ => return None,
+    };
+    let start = text[..start].rfind('\n').map_or(0, |index| index + 1);
+    let end = text[end..]
+        .find('\n')
+        .map_or(text.len(), |index| end + index + 1);
+    Some(start..end)
+}
+
+/// Returns the lines of the `[patch]` entries in `text` pointing to `copy`,
+/// which are the overrides cargo-patch manages.
+fn managed_overrides(text: &str, root: &Path, copy: &Path) -> Vec<Range<usize>> {
+    let Ok(document) = ImDocument::parse(text) else {
+        return vec![];
+    };
+    let Some(sections) = document.get("patch").and_then(Item::as_table_like) else {
+        return vec![];
+    };
+    let mut lines = vec![];
+    for (_, section) in sections.iter() {
+        let Some(section) = section.as_table_like() else {
+            continue;
+        };
+        for (name, item) in section.iter() {
+            if override_path(item, root).as_deref() != Some(copy) {
+                continue;
+            }
+            if let Some(range) = section
+                .get_key_value(name)
+                .and_then(|(key, item)| entry_lines(text, key, item))
+            {
+                lines.push(range);
+            }
+        }
+    }
+    lines.sort_by_key(|range| range.start);
+    lines
+}
+
+/// Comments out the lines `ranges` of `text` with a note naming the package.
+fn comment_out(text: &str, ranges: &[Range<usize>], package: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for range in ranges {
+        result.push_str(&text[last..range.start]);
+        result.push_str(&format!("{DISABLED_NOTE} {package}\n"));
+        for line in text[range.clone()].split_inclusive('\n') {
+            result.push_str("# ");
+            result.push_str(line);
+        }
+        last = range.end;
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+/// Prints how to recover from `name` failing to patch if the manifest of
+/// the workspace at `root` overrides it with its `copy`, as builds would use
+/// the broken copy. With `disable` the override is commented out, so builds
+/// use the pristine crate until the patches apply again.
+pub fn recover(
+    root: &Path,
+    name: &str,
+    version: &Version,
+    copy: &Path,
+    disable: bool,
+) -> Result<()> {
+    let manifest = root.join("Cargo.toml");
+    let text = fs::read_to_string(&manifest)?;
+    let copy = copy.canonicalize().unwrap_or_else(|_| copy.to_path_buf());
+    let overrides = managed_overrides(&text, root, &copy);
+    if overrides.is_empty() {
+        return Ok(());
+    }
+    let package = format!("{name} v{version}");
+    if disable {
+        permissions::write(&manifest, comment_out(&text, &overrides, &package))?;
+        status::status(
+            "Disabled",
+            format!("the override of {name} in Cargo.toml, builds use the pristine crate"),
+        );
+        eprintln!(
+            "help: {package} failed to patch. Next steps:\n    \
+             1. Fix its patches, or run `cargo patch rebase {name}` if it was updated\n    \
+             2. Run `cargo patch` again\n    \
+             3. Uncomment the override of {name} in Cargo.toml below `{DISABLED_NOTE}`"
+        );
+    } else {
+        eprintln!(
+            "help: {package} failed to patch, but `[patch]` in Cargo.toml still points to its copy, so builds use the broken copy. Next steps:\n    \
+             1. Fix its patches, or run `cargo patch rebase {name}` if it was updated, and run `cargo patch` again\n    \
+             2. Or comment out the override of {name} in Cargo.toml to build with the pristine crate, which `--disable-failed-overrides` does automatically"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{comment_out, managed_overrides};
+    use std::path::Path;
+
+    #[test]
+    fn comments_out_managed_overrides() {
+        let text = r#"[package]
+name = "example"
+
+[patch.crates-io]
+serde = { path = "./target/patch/serde-1.0.0" }
+log = { path = "../log" }
+
+[patch.my-registry.serde]
+path = "./target/patch/serde-1.0.0"
+
+[dependencies]
+"#;
+        let root = Path::new("/ws");
+        let copy = Path::new("/ws/target/patch/serde-1.0.0");
+        let overrides = managed_overrides(text, root, copy);
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(
+            comment_out(text, &overrides, "serde v1.0.0"),
+            r#"[package]
+name = "example"
+
+[patch.crates-io]
+# Disabled by cargo-patch as patching failed: serde v1.0.0
+# serde = { path = "./target/patch/serde-1.0.0" }
+log = { path = "../log" }
+
+# Disabled by cargo-patch as patching failed: serde v1.0.0
+# [patch.my-registry.serde]
+# path = "./target/patch/serde-1.0.0"
+
+[dependencies]
+"#
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Patch files downloaded from a URL, e.g. a GitHub gist or raw file, or
+//! taken from a commit of a git repository.
+
+use crate::{
+    git_commit,
+    orchestrator::{PatchConfig, PatchItem},
+    permissions,
+};
+use anyhow::{anyhow, Result};
+use cargo::{
+    util::{hex::short_hash, network::proxy::http_proxy},
+    GlobalContext,
+};
+use curl::easy::{Easy, SslOpt};
+use std::{
+    borrow::Cow,
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use url::Url;
+
+const CACHE_DIR: &str = "target/patch-cache";
+
+/// Largest patch file which is downloaded, in bytes.
+const MAX_SIZE: usize = 16 * 1024 * 1024;
+
+/// Most redirects followed for one download.
+const MAX_REDIRECTS: usize = 10;
+
+/// Proxy environment variables picked up by libcurl.
+const PROXY_ENVS: [&str; 4] =
+    ["https_proxy", "HTTPS_PROXY", "http_proxy", "HTTP_PROXY"];
+
+/// When a downloaded patch file is fetched again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Download again whenever `Cargo.lock` changes.
+    #[default]
+    PerLockfile,
+    /// Download on every run.
+    Never,
+    /// Download once and keep it until the cache is refreshed.
+    Forever,
+}
+
+impl CachePolicy {
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "per-lockfile" => Self::PerLockfile,
+            "never" => Self::Never,
+            "forever" => Self::Forever,
+            &_ => {
+                eprintln!("Unknown cache policy: {s}");
+                Self::default()
+            }
+        }
+    }
+}
+
+/// URL and cache policy of a remote patch file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote<'a> {
+    pub url: &'a str,
+    pub cache: CachePolicy,
+    /// Commit of the git repository at `url` whose changes are the patch.
+    pub rev: Option<&'a str>,
+}
+
+impl Remote<'_> {
+    /// Returns the URL, followed by the commit for git repositories.
+    pub fn label(&self) -> String {
+        self.rev
+            .map_or_else(|| self.url.to_owned(), |rev| format!("{}@{rev}", self.url))
+    }
+}
+
+/// Network settings from the `[http]` table of the cargo config.
+#[derive(Debug, Clone, Default)]
+pub struct HttpConfig {
+    proxy: Option<String>,
+    cainfo: Option<PathBuf>,
+    check_revoke: Option<bool>,
+    timeout: Option<u64>,
+}
+
+impl HttpConfig {
+    pub fn new(gctx: &GlobalContext) -> Result<Self> {
+        let http = gctx.http_config()?;
+        Ok(Self {
+            proxy: http_proxy(http),
+            cainfo: http.cainfo.as_ref().map(|cainfo| cainfo.resolve_path(gctx)),
+            check_revoke: http.check_revoke,
+            timeout: http.timeout,
+        })
+    }
+
+    fn configure(&self, handle: &mut Easy) -> Result<()> {
+        // proxies from the environment are used by libcurl itself
+        if let Some(proxy) = &self.proxy {
+            handle.proxy(proxy)?;
+        }
+        if let Some(cainfo) = &self.cainfo {
+            handle.cainfo(cainfo)?;
+        }
+        if let Some(check) = self.check_revoke {
+            handle.ssl_options(SslOpt::new().no_revoke(!check))?;
+        }
+        if let Some(timeout) = self.timeout {
+            handle.connect_timeout(Duration::from_secs(timeout))?;
+        }
+        handle.useragent(&format!("cargo-patch/{}", env!("CARGO_PKG_VERSION")))?;
+        Ok(())
+    }
+
+    /// Returns the proxy used for downloads, if any.
+    fn used_proxy(&self) -> Option<String> {
+        self.proxy
+            .clone()
+            .or_else(|| PROXY_ENVS.iter().find_map(|name| env::var(name).ok()))
+    }
+}
+
+fn cache_path(remote: &Remote<'_>, config: &PatchConfig) -> PathBuf {
+    // commits never change
+    let key = match remote.cache {
+        _ if remote.rev.is_some() => short_hash(&(remote.url, remote.rev)),
+        CachePolicy::PerLockfile => short_hash(&(remote.url, &config.lockfile_hash)),
+        CachePolicy::Never | CachePolicy::Forever => short_hash(&remote.url),
+    };
+    Path::new(CACHE_DIR).join(format!("{key}.patch"))
+}
+
+/// Turns a curl error into one telling the user which setting to look at.
+fn download_error(url: &str, err: &curl::Error, http: &HttpConfig) -> anyhow::Error {
+    let proxy = http.used_proxy();
+    let help = match proxy {
+        Some(proxy)
+            if err.is_couldnt_resolve_proxy() || err.is_couldnt_connect() =>
+        {
+            format!(
+                "unable to use the proxy {proxy}, check `http.proxy` in the \
+                 cargo config and the HTTPS_PROXY environment variable"
+            )
+        }
+        None if err.is_couldnt_resolve_host() || err.is_couldnt_connect() => {
+            "if your network requires a proxy, set `http.proxy` in the cargo \
+             config or the HTTPS_PROXY environment variable"
+                .to_owned()
+        }
+        _ if err.is_ssl_cacert()
+            || err.is_ssl_cacert_badfile()
+            || err.is_peer_failed_verification() =>
+        {
+            "the server certificate could not be verified, set `http.cainfo` \
+             in the cargo config to the CA bundle of your network"
+                .to_owned()
+        }
+        _ if err.is_operation_timedout() => {
+            "increase `http.timeout` in the cargo config".to_owned()
+        }
+        _ => return anyhow!("Unable to download patch {url}: {err}"),
+    };
+    anyhow!("Unable to download patch {url}: {err}\nhelp: {help}")
+}
+
+fn too_large(url: &str) -> anyhow::Error {
+    anyhow!(
+        "Unable to download patch {url}: larger than {} MiB",
+        MAX_SIZE / 1024 / 1024
+    )
+}
+
+/// Returns whether `url` lies below one of the `allowed` URLs: same scheme,
+/// host and port, and a path starting with all segments of the allowed path.
+pub fn is_allowed(url: &str, allowed: &[String]) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let segments = |url: &Url| {
+        url.path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    let path = segments(&url);
+    allowed
+        .iter()
+        .filter_map(|allowed| Url::parse(allowed).ok())
+        .any(|allowed| {
+            allowed.scheme() == url.scheme()
+                && allowed.host_str() == url.host_str()
+                && allowed.port_or_known_default() == url.port_or_known_default()
+                && path.starts_with(&segments(&allowed))
+        })
+}
+
+/// Performs a single request without following redirects. Returns the
+/// response code, the body and the URL a redirect points to.
+fn request(url: &str, http: &HttpConfig) -> Result<(u32, Vec<u8>, Option<String>)> {
+    let mut data = Vec::new();
+    let mut handle = Easy::new();
+    handle.url(url)?;
+    http.configure(&mut handle)?;
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|chunk| {
+            if data.len() + chunk.len() > MAX_SIZE {
+                // a short count makes curl abort the transfer
+                return Ok(0);
+            }
+            data.extend_from_slice(chunk);
+            Ok(chunk.len())
+        })?;
+        transfer.perform().map_err(|err| {
+            if err.is_write_error() {
+                too_large(url)
+            } else {
+                download_error(url, &err, http)
+            }
+        })?;
+    }
+    let redirect = handle.redirect_url()?.map(str::to_owned);
+    Ok((handle.response_code()?, data, redirect))
+}
+
+/// Downloads `url`, following redirects itself so every target is checked
+/// against the `allowed` URLs, if given.
+fn download(
+    url: &str,
+    http: &HttpConfig,
+    allowed: Option<&[String]>,
+) -> Result<Vec<u8>> {
+    let mut current = url.to_owned();
+    for _ in 0..=MAX_REDIRECTS {
+        let (code, data, redirect) = request(&current, http)?;
+        match redirect {
+            Some(next) if (300..400).contains(&code) => {
+                if allowed.is_some_and(|allowed| !is_allowed(&next, allowed)) {
+                    return Err(anyhow!(
+                        "Unable to download patch {url}: redirected to {next}, \
+                         which is outside of the allowed patch URLs"
+                    ));
+                }
+                current = next;
+            }
+            _ if code >= 400 => {
+                return Err(anyhow!("Unable to download patch {url}: HTTP {code}"));
+            }
+            _ => return Ok(data),
+        }
+    }
+    Err(anyhow!(
+        "Unable to download patch {url}: too many redirects"
+    ))
+}
+
+/// Downloads the patch file of a remote item into the cache unless a valid
+/// copy exists already, and returns the item pointing to the cached file.
+/// Local items are returned unchanged.
+pub fn fetch<'a>(
+    item: &PatchItem<'a>,
+    config: &PatchConfig,
+) -> Result<PatchItem<'a>> {
+    let Some(remote) = &item.remote else {
+        return Ok(item.clone());
+    };
+    let path = cache_path(remote, config);
+    let cached = path.exists()
+        && !config.refresh_patches
+        && remote.cache != CachePolicy::Never;
+    if !cached {
+        if config.offline {
+            return Err(anyhow!(
+                "Unable to download patch {} without network access",
+                remote.label()
+            ));
+        }
+        let data = match remote.rev {
+            Some(rev) => git_commit::diff(
+                remote.url,
+                rev,
+                !config.git_full_fetch,
+                config.allowed_urls().is_none(),
+            )?,
+            None => download(remote.url, &config.http, config.allowed_urls())?,
+        };
+        if data.len() > MAX_SIZE {
+            return Err(too_large(&remote.label()));
+        }
+        fs::create_dir_all(CACHE_DIR)?;
+        permissions::write(&path, data)?;
+    }
+    Ok(PatchItem {
+        path: Cow::Owned(path),
+        ..item.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_allowed;
+
+    #[test]
+    fn allowed_urls_compare_path_segments() {
+        let allowed = ["https://github.com/my-org".to_owned()];
+        assert!(is_allowed(
+            "https://github.com/my-org/repo/fix.patch",
+            &allowed
+        ));
+        assert!(!is_allowed(
+            "https://github.com/my-org-evil/fix.patch",
+            &allowed
+        ));
+        assert!(!is_allowed("http://github.com/my-org/fix.patch", &allowed));
+        assert!(!is_allowed(
+            "https://github.com.evil/my-org/fix.patch",
+            &allowed
+        ));
+        assert!(!is_allowed(
+            "https://github.com:8443/my-org/fix.patch",
+            &allowed
+        ));
+        assert!(!is_allowed("not a url", &allowed));
+    }
+}
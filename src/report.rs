@@ -0,0 +1,203 @@
+//! Report listing how patched dependencies diverge from upstream.
+
+use crate::{
+    engine::{mark_missing_newlines, parse_patches, patch_paths},
+    git_header,
+    orchestrator::{
+        get_config, load_workspace, lock_package_cache, read_patch, resolve_patches,
+        resolve_ws, setup_gctx, Options, PatchConfig, PatchEntry, PatchItem,
+    },
+    Error,
+};
+use anyhow::Result;
+use patch::Line;
+use std::{path::Path, time::SystemTime};
+
+/// Output format of [`report`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Markdown,
+}
+
+#[derive(Debug)]
+struct FileChange {
+    file: String,
+    /// Added and removed lines, unknown for sed edits and snippets until
+    /// they run.
+    lines: Option<(usize, usize)>,
+    /// Patch file, sed edit or snippet making the change.
+    source: String,
+}
+
+fn file_changes(
+    item: &PatchItem<'_>,
+    config: &PatchConfig,
+) -> Result<Vec<FileChange>> {
+    let (_, data) = read_patch(item, config)?;
+    let (data, operations) = git_header::extract(&data, &item.source);
+    let data = mark_missing_newlines(&data);
+    let patches = if data.trim().is_empty() && !operations.is_empty() {
+        vec![]
+    } else {
+        parse_patches(&data).ok_or_else(|| Error::PatchParse {
+            file: item.path.to_path_buf(),
+        })?
+    };
+    let label = item.label(&config.root);
+    let header_changes = operations
+        .iter()
+        .map(|operation| FileChange {
+            file: operation.file().to_owned(),
+            lines: Some((0, 0)),
+            source: label.clone(),
+        })
+        .collect::<Vec<_>>();
+    Ok(patches
+        .iter()
+        .map(|patch| {
+            let (old_path, new_path) = patch_paths(patch, &item.source);
+            let file = if patch.new.path == "/dev/null" {
+                old_path
+            } else {
+                new_path
+            };
+            let lines = patch.hunks.iter().flat_map(|hunk| hunk.lines.iter());
+            let (added, removed) =
+                lines.fold((0, 0), |(added, removed), line| match line {
+                    Line::Add(_) => (added + 1, removed),
+                    Line::Remove(_) => (added, removed + 1),
+                    Line::Context(_) => (added, removed),
+                });
+            FileChange {
+                file: file.to_owned(),
+                lines: Some((added, removed)),
+                source: label.clone(),
+            }
+        })
+        .chain(header_changes)
+        .collect())
+}
+
+/// Returns the changes of all items of `patch` in the order they are
+/// applied, see [`PatchEntry::steps`].
+fn entry_changes(
+    patch: &PatchEntry<'_>,
+    config: &PatchConfig,
+) -> Result<Vec<FileChange>> {
+    let sed = |before| {
+        patch
+            .sed
+            .iter()
+            .filter(move |edit| edit.before == before)
+            .map(|edit| FileChange {
+                file: edit.glob.to_owned(),
+                lines: None,
+                source: format!("sed `{}`", edit.pattern),
+            })
+    };
+    let mut changes = sed(true).collect::<Vec<_>>();
+    for item in &patch.patches {
+        changes.extend(file_changes(item, config)?);
+    }
+    changes.extend(sed(false));
+    changes.extend(patch.snippets.iter().map(|snippet| FileChange {
+        file: snippet.file.to_owned(),
+        lines: None,
+        source: format!("replace-snippet `{}`", snippet.find),
+    }));
+    Ok(changes)
+}
+
+fn print_package(
+    format: ReportFormat,
+    package: &str,
+    changes: &[FileChange],
+    items: &[PatchItem<'_>],
+    root: &Path,
+) {
+    let now = SystemTime::now();
+    let notes = items.iter().filter(|item| !item.notes.is_empty());
+    match format {
+        ReportFormat::Text => {
+            println!("{package}");
+            for change in changes {
+                let lines = change
+                    .lines
+                    .map(|(added, removed)| format!(" (+{added} -{removed})"))
+                    .unwrap_or_default();
+                println!("    {}{lines} from {}", change.file, change.source);
+            }
+            for item in notes {
+                println!("    {}: {}", item.label(root), item.notes.summary(now));
+            }
+        }
+        ReportFormat::Markdown => {
+            println!("## {package}");
+            println!();
+            println!("| File | Added | Removed | Patch |");
+            println!("| --- | ---: | ---: | --- |");
+            for change in changes {
+                let (added, removed) = change.lines.map_or_else(
+                    || (String::new(), String::new()),
+                    |(added, removed)| (added.to_string(), removed.to_string()),
+                );
+                let source = if change.lines.is_some() {
+                    format!("`{}`", change.source)
+                } else {
+                    change.source.clone()
+                };
+                println!(
+                    "| `{}` | {added} | {removed} | {} |",
+                    change.file,
+                    source.replace('|', "\\|")
+                );
+            }
+            println!();
+            let mut notes = notes.peekable();
+            if notes.peek().is_some() {
+                for item in notes {
+                    println!(
+                        "- `{}`: {}",
+                        item.label(root),
+                        item.notes.summary(now)
+                    );
+                }
+                println!();
+            }
+        }
+    }
+}
+
+/// Prints, for each patched package, the modified files together with the
+/// added and removed line counts and the patch files, sed edits or snippets
+/// responsible.
+/// Nothing is copied or written.
+///
+/// Descriptions, issues and expiry dates of the patch files follow.
+pub fn report(format: ReportFormat, options: &Options) -> Result<(), Error> {
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+    let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+    let ids = resolve_patches(&workspace, &pkg_set, &resolve, &config)?;
+
+    if ids.is_empty() {
+        println!("No patches found");
+        return Ok(());
+    }
+
+    for (patch, id) in ids {
+        let changes = entry_changes(&patch, &config)?;
+        print_package(
+            format,
+            &format!("{} v{}", id.name(), id.version()),
+            &changes,
+            &patch.patches,
+            &config.root,
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+//! SBOM fragments describing patched dependencies.
+
+use crate::{
+    orchestrator::{
+        get_config, load_workspace, lock_package_cache, resolve_patches, resolve_ws,
+        setup_gctx, suffixed_version, Options, PatchConfig, PatchEntry,
+    },
+    Error,
+};
+use anyhow::Result;
+use cargo::core::PackageId;
+use serde_json::{json, Value};
+use std::time::SystemTime;
+
+/// Output format of [`sbom`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SbomFormat {
+    #[default]
+    CycloneDx,
+    Spdx,
+}
+
+/// Percent-encodes `value` for a package URL, keeping the unreserved
+/// characters and `keep`.
+fn encode(value: &str, keep: &[char]) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-._~".contains(c) || keep.contains(&c) {
+                return c.to_string();
+            }
+            let mut buf = [0; 4];
+            c.encode_utf8(&mut buf)
+                .bytes()
+                .map(|byte| format!("%{byte:02X}"))
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the package URL of `id` with `version`. Packages from git get a
+/// `vcs_url` and those from other registries than crates.io a
+/// `repository_url` qualifier.
+fn purl(id: PackageId, version: &str) -> String {
+    let source = id.source_id();
+    let qualifier = if source.is_git() {
+        let url = source.url();
+        let vcs_url = source
+            .precise_git_fragment()
+            .map_or_else(|| format!("git+{url}"), |rev| format!("git+{url}@{rev}"));
+        Some(("vcs_url", vcs_url))
+    } else if source.is_registry() && !source.is_crates_io() {
+        Some(("repository_url", source.url().to_string()))
+    } else {
+        None
+    };
+    let purl = format!(
+        "pkg:cargo/{}@{}",
+        encode(&id.name(), &[]),
+        encode(version, &[])
+    );
+    match qualifier {
+        Some((key, value)) => {
+            format!("{purl}?{key}={}", encode(&value, &[':', '/']))
+        }
+        None => purl,
+    }
+}
+
+fn patched_version(patch: &PatchEntry<'_>, id: PackageId) -> Result<String> {
+    Ok(match &patch.version_suffix {
+        Some(suffix) => suffixed_version(id.version(), suffix)?.to_string(),
+        None => id.version().to_string(),
+    })
+}
+
+fn cyclonedx_component(
+    patch: &PatchEntry<'_>,
+    id: PackageId,
+    config: &PatchConfig,
+) -> Result<Value> {
+    let name = id.name();
+    let upstream = id.version().to_string();
+    let version = patched_version(patch, id)?;
+    let patches = patch
+        .patches
+        .iter()
+        .map(|item| {
+            json!({
+                "type": "unofficial",
+                "diff": { "url": item.label(&config.root) },
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "type": "library",
+        "name": name.as_str(),
+        "version": version,
+        "purl": purl(id, &version),
+        "pedigree": {
+            "ancestors": [{
+                "type": "library",
+                "name": name.as_str(),
+                "version": upstream,
+                "purl": purl(id, &upstream),
+            }],
+            "patches": patches,
+        },
+    }))
+}
+
+fn spdx_package(
+    patch: &PatchEntry<'_>,
+    id: PackageId,
+    config: &PatchConfig,
+    date: &str,
+) -> Result<Value> {
+    let name = id.name();
+    let version = patched_version(patch, id)?;
+    let annotations = patch
+        .patches
+        .iter()
+        .map(|item| {
+            json!({
+                "annotationType": "OTHER",
+                "annotator": format!("Tool: cargo-patch-{}", env!("CARGO_PKG_VERSION")),
+                "annotationDate": date,
+                "comment": format!(
+                    "Modified from upstream {name} {} by applying {}",
+                    id.version(),
+                    item.label(&config.root)
+                ),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(json!({
+        "SPDXID": format!("SPDXRef-Package-{name}-{version}").replace(['+', '_'], "-"),
+        "name": name.as_str(),
+        "versionInfo": version,
+        "externalRefs": [{
+            "referenceCategory": "PACKAGE-MANAGER",
+            "referenceType": "purl",
+            "referenceLocator": purl(id, &version),
+        }],
+        "annotations": annotations,
+    }))
+}
+
+/// Prints an SBOM fragment describing each patched dependency, its upstream
+/// version and the patches applied to it.
+pub fn sbom(format: SbomFormat, options: &Options) -> Result<(), Error> {
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+    let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+    let ids = resolve_patches(&workspace, &pkg_set, &resolve, &config)?;
+
+    let fragment = match format {
+        SbomFormat::CycloneDx => {
+            let components = ids
+                .iter()
+                .map(|(patch, id)| cyclonedx_component(patch, *id, &config))
+                .collect::<Result<Vec<_>>>()?;
+            json!({
+                "bomFormat": "CycloneDX",
+                "specVersion": "1.5",
+                "components": components,
+            })
+        }
+        SbomFormat::Spdx => {
+            let date =
+                humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+            let packages = ids
+                .iter()
+                .map(|(patch, id)| spdx_package(patch, *id, &config, &date))
+                .collect::<Result<Vec<_>>>()?;
+            json!({
+                "spdxVersion": "SPDX-2.3",
+                "packages": packages,
+            })
+        }
+    };
+    println!("{fragment:#}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::purl;
+    use cargo::core::{PackageId, SourceId};
+
+    fn id(source: &str) -> PackageId {
+        let source = SourceId::from_url(source).expect("Invalid source");
+        PackageId::try_new("serde", "1.0.110", source).expect("Invalid package id")
+    }
+
+    #[test]
+    fn purl_encodes_version_and_qualifies_source() {
+        let crates_io = id("registry+https://github.com/rust-lang/crates.io-index");
+        assert_eq!(
+            purl(crates_io, "1.0.110+patched"),
+            "pkg:cargo/serde@1.0.110%2Bpatched"
+        );
+        let registry = id("sparse+https://registry.example.com/index/");
+        assert_eq!(
+            purl(registry, "1.0.110"),
+            "pkg:cargo/serde@1.0.110?repository_url=sparse%2Bhttps://registry.example.com/index/"
+        );
+        let git = id("git+https://github.com/serde-rs/serde#0123abc");
+        assert_eq!(
+            purl(git, "1.0.110"),
+            "pkg:cargo/serde@1.0.110?vcs_url=git%2Bhttps://github.com/serde-rs/serde%400123abc"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Regex substitutions across the files of a package.
+
+use crate::{permissions, status};
+use anyhow::{anyhow, Result};
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml::Value;
+
+/// An item of the `sed` array of an entry, replacing every match of
+/// `pattern` in the files matching `glob`. Meant for mechanical renames
+/// which are too large to maintain as a diff.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SedEdit<'a> {
+    /// Files relative to the patched package, e.g. `src/**/*.rs`.
+    pub glob: &'a str,
+    pub pattern: &'a str,
+    /// Replacement, which may refer to capture groups like `$1`.
+    pub replace: &'a str,
+    /// Number of matches expected in all files together. Without it at
+    /// least one match is required.
+    pub count: Option<usize>,
+    /// Run before the patch files instead of after them.
+    pub before: bool,
+}
+
+impl<'a> SedEdit<'a> {
+    /// Parses an item of the `sed` array.
+    pub fn parse(item: &'a Value) -> Option<Self> {
+        let table = item.as_table()?;
+        let text = |key| table.get(key).and_then(Value::as_str);
+        let count = match table.get("count") {
+            Some(count) => Some(usize::try_from(count.as_integer()?).ok()?),
+            None => None,
+        };
+        let before = match table.get("when").map(Value::as_str) {
+            None | Some(Some("after")) => false,
+            Some(Some("before")) => true,
+            Some(_) => return None,
+        };
+        Some(Self {
+            glob: text("glob")?,
+            pattern: text("pattern")?,
+            replace: text("replace")?,
+            count,
+            before,
+        })
+    }
+
+    /// Applies the substitution to the package `name` at `path`. Fails if
+    /// the number of matches isn't the expected one.
+    pub fn apply(&self, name: &str, path: &Path) -> Result<()> {
+        let loc = format!("{name}: {}", self.glob);
+        let glob = Glob::new(self.glob)
+            .map_err(|err| anyhow!("Invalid sed glob ({loc}): {err}"))?
+            .compile_matcher();
+        let regex = Regex::new(self.pattern)
+            .map_err(|err| anyhow!("Invalid sed pattern ({loc}): {err}"))?;
+        let mut files = vec![];
+        matching_files(path, Path::new(""), &glob, &mut files)?;
+        files.sort();
+
+        let mut found = 0;
+        let mut changed = 0;
+        for file in &files {
+            let full = path.join(file);
+            // binary files can't contain the pattern
+            let Ok(old) = fs::read_to_string(&full) else {
+                continue;
+            };
+            let matches = regex.find_iter(&old).count();
+            if matches == 0 {
+                continue;
+            }
+            found += matches;
+            changed += 1;
+            permissions::write(
+                &full,
+                regex.replace_all(&old, self.replace).as_bytes(),
+            )?;
+        }
+        match self.count {
+            Some(count) if count != found => {
+                return Err(anyhow!(
+                    "Expected {count} match(es) of `{}` but found {found} ({loc})",
+                    self.pattern
+                ));
+            }
+            None if found == 0 => {
+                return Err(anyhow!("No match of `{}` found ({loc})", self.pattern));
+            }
+            Some(_) | None => {}
+        }
+        status::status(
+            "Replaced",
+            format!(
+                "{found} match(es) of `{}` in {changed} file(s) of {name}",
+                self.pattern
+            ),
+        );
+        Ok(())
+    }
+}
+
+/// Adds the files below `dir` matching `glob` relative to `root`.
+fn matching_files(
+    root: &Path,
+    dir: &Path,
+    glob: &GlobMatcher,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for entry in fs::read_dir(root.join(dir))? {
+        let entry = entry?;
+        let path = dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if entry.file_name() != ".git" {
+                matching_files(root, &path, glob, files)?;
+            }
+        } else if glob.is_match(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
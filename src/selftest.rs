@@ -0,0 +1,61 @@
+//! Runner for a corpus of real-world diffs, each stored as a triplet of the
+//! original file, the patch and the expected result.
+
+use crate::{engine::apply_single, Error};
+use anyhow::{anyhow, Result};
+use fs_extra::dir::get_dir_content;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Applies `<name>.patch` to `<name>.orig` and compares the result with
+/// `<name>.expected`.
+fn run_fixture(patch: &Path) -> Result<()> {
+    let read = |extension| {
+        let path = patch.with_extension(extension);
+        fs::read_to_string(&path)
+            .map_err(|err| anyhow!("Unable to read {}: {err}", path.display()))
+    };
+    let (original, data, expected) =
+        (read("orig")?, read("patch")?, read("expected")?);
+
+    let actual = apply_single(&data, &original, patch)?;
+    if actual != expected {
+        return Err(anyhow!("Result differs from the expected file"));
+    }
+    Ok(())
+}
+
+/// Runs every fixture below `dir` through the patch engine. A fixture
+/// consists of `<name>.orig`, `<name>.patch` and `<name>.expected`, where
+/// the patch contains the diff of a single file.
+///
+/// Prints the outcome of every fixture and fails if any of them failed.
+pub fn selftest(dir: &Path) -> Result<(), Error> {
+    let mut patches = get_dir_content(dir)
+        .map_err(anyhow::Error::from)?
+        .files
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|file| file.extension().is_some_and(|ext| ext == "patch"))
+        .collect::<Vec<_>>();
+    patches.sort();
+
+    let mut failed = 0;
+    for patch in &patches {
+        let name = patch.strip_prefix(dir).unwrap_or(patch).with_extension("");
+        match run_fixture(patch) {
+            Ok(()) => println!("ok     {}", name.display()),
+            Err(err) => {
+                failed += 1;
+                println!("FAILED {}: {err:#}", name.display());
+            }
+        }
+    }
+    println!("{} passed, {failed} failed", patches.len() - failed);
+    if failed > 0 {
+        return Err(anyhow!("{failed} fixture(s) failed").into());
+    }
+    Ok(())
+}
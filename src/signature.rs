@@ -0,0 +1,232 @@
+//! Verification of patch file signatures.
+
+use crate::orchestrator::{read_to_string, PatchConfig, PatchItem};
+use anyhow::{anyhow, Result};
+use minisign_verify::{PublicKey, Signature};
+use std::{
+    io::{ErrorKind, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Returns the normalized fingerprint if `key` is a GPG fingerprint, i.e.
+/// 40 or 64 hex digits, optionally with spaces and a `0x` prefix.
+fn gpg_fingerprint(key: &str) -> Option<String> {
+    let key = key.replace(' ', "");
+    let key = key.strip_prefix("0x").unwrap_or(&key);
+    (matches!(key.len(), 40 | 64) && key.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| key.to_ascii_uppercase())
+}
+
+fn verify_minisign(
+    data: &[u8],
+    signature: &Path,
+    config: &PatchConfig,
+) -> Result<()> {
+    let keys = config
+        .trusted_keys
+        .iter()
+        .filter(|key| gpg_fingerprint(key).is_none())
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        return Err(anyhow!(
+            "No trusted keys configured to verify signature {}",
+            signature.display()
+        ));
+    }
+    let sig = Signature::decode(&read_to_string(signature)?).map_err(|err| {
+        anyhow!("Invalid signature {}: {err}", signature.display())
+    })?;
+    let verified = keys.iter().any(|key| {
+        PublicKey::from_base64(key)
+            .map_err(|err| eprintln!("Invalid trusted key {key}: {err}"))
+            .is_ok_and(|key| key.verify(data, &sig, false).is_ok())
+    });
+    if verified {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Signature {} is not valid for any trusted key",
+            signature.display()
+        ))
+    }
+}
+
+/// Returns the fingerprints of the signing key and its primary key from the
+/// `VALIDSIG` lines of `gpg --status-fd` output.
+fn valid_fingerprints(status: &str) -> Vec<&str> {
+    status
+        .lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .flat_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            [fields.first().copied(), fields.get(9).copied()]
+        })
+        .flatten()
+        .collect()
+}
+
+#[allow(clippy::wildcard_enum_match_arm)]
+fn verify_gpg(data: &[u8], signature: &Path, config: &PatchConfig) -> Result<()> {
+    let trusted = config
+        .trusted_keys
+        .iter()
+        .filter_map(|key| gpg_fingerprint(key))
+        .collect::<Vec<_>>();
+    if trusted.is_empty() {
+        return Err(anyhow!(
+            "No trusted GPG fingerprints configured to verify signature {}",
+            signature.display()
+        ));
+    }
+    let mut child = Command::new("gpg")
+        .args(["--batch", "--status-fd", "1", "--verify"])
+        .arg(signature)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => anyhow!(
+                "gpg is required to verify signature {}",
+                signature.display()
+            ),
+            _ => err.into(),
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(data)?;
+    }
+    let output = child.wait_with_output()?;
+    let status = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Signature {} is not valid: {}",
+            signature.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    if valid_fingerprints(&status)
+        .iter()
+        .any(|fingerprint| trusted.contains(&fingerprint.to_ascii_uppercase()))
+    {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Signature {} is not made by a trusted key",
+            signature.display()
+        ))
+    }
+}
+
+/// Verifies the signature of the content `data` of a patch file. Signatures
+/// ending in `.minisig` are checked against the minisign keys of the
+/// trusted keys, all other signatures with `gpg` against their GPG
+/// fingerprints.
+pub fn verify_signature(
+    item: &PatchItem<'_>,
+    data: &[u8],
+    config: &PatchConfig,
+) -> Result<()> {
+    match item.signature.as_deref() {
+        Some(signature)
+            if signature.extension().is_some_and(|ext| ext == "minisig") =>
+        {
+            config.check_allowed(signature)?;
+            verify_minisign(data, signature, config)
+        }
+        Some(signature) => {
+            config.check_allowed(signature)?;
+            verify_gpg(data, signature, config)
+        }
+        None if config.require_signatures => {
+            Err(anyhow!("Patch file {} is not signed", item.path.display()))
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gpg_fingerprint, valid_fingerprints, verify_minisign};
+    use crate::orchestrator::PatchConfig;
+    use std::{fs, path::PathBuf};
+    use tempfile::TempDir;
+
+    const PUBLIC_KEY: &str =
+        "RWTAMGGyLwb3hW2d9UQ9eF4weXnD0c2eljpmumRow009MFb2SjtL6Vsf";
+    const PATCH: &str = "--- test\n+++ test\n@@ -1 +1 @@\n-old\n+new\n";
+    const SIGNATURE: &str = "untrusted comment: signature from rsign secret key
+RUTAMGGyLwb3hTlrcUyTgMrDIlavLenJsuPAgcvSzKt2tgLAN7qa5NH4ptb78MwCzkyiNvciY00w6nlK9s66auuH04+uX29XIQ8=
+trusted comment: test.patch
+prV9XgVF/lEtYS7RCy7G9dZksL5K6oIDgzSSTw1oDMFYXdt1ZBicKvZGVdJsTLPfc1M3PwfUQ8ygsrWq6HvUDQ==
+";
+
+    fn signature_file() -> (TempDir, PathBuf) {
+        let dir = tempfile::tempdir().expect("Unable to create temp dir");
+        let path = dir.path().join("test.patch.minisig");
+        fs::write(&path, SIGNATURE).expect("Unable to write signature");
+        (dir, path)
+    }
+
+    #[test]
+    fn verify_minisign_trusted() {
+        let (_dir, signature) = signature_file();
+        let config = PatchConfig {
+            trusted_keys: vec![PUBLIC_KEY.to_owned()],
+            ..PatchConfig::default()
+        };
+        assert!(verify_minisign(PATCH.as_bytes(), &signature, &config).is_ok());
+    }
+
+    #[test]
+    fn verify_minisign_tampered() {
+        let (_dir, signature) = signature_file();
+        let config = PatchConfig {
+            trusted_keys: vec![PUBLIC_KEY.to_owned()],
+            ..PatchConfig::default()
+        };
+        let tampered = PATCH.replace("new", "evil");
+        assert!(verify_minisign(tampered.as_bytes(), &signature, &config).is_err());
+    }
+
+    #[test]
+    fn verify_minisign_untrusted() {
+        let (_dir, signature) = signature_file();
+        assert!(verify_minisign(
+            PATCH.as_bytes(),
+            &signature,
+            &PatchConfig::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn verify_minisign_ignores_gpg_fingerprints() {
+        let (_dir, signature) = signature_file();
+        let config = PatchConfig {
+            trusted_keys: vec!["0123456789ABCDEF0123456789ABCDEF01234567".to_owned()],
+            ..PatchConfig::default()
+        };
+        assert!(verify_minisign(PATCH.as_bytes(), &signature, &config).is_err());
+    }
+
+    #[test]
+    fn parses_fingerprints() {
+        assert_eq!(
+            gpg_fingerprint("0x0123 4567 89ab cdef 0123 4567 89ab cdef 0123 4567"),
+            Some("0123456789ABCDEF0123456789ABCDEF01234567".to_owned())
+        );
+        assert_eq!(gpg_fingerprint(PUBLIC_KEY), None);
+        let status = "[GNUPG:] NEWSIG\n\
+[GNUPG:] GOODSIG 89ABCDEF01234567 Test <test@example.com>\n\
+[GNUPG:] VALIDSIG AAAA0123456789ABCDEF0123456789ABCDEF0123 2024-01-01 1704067200 0 4 0 22 10 00 0123456789ABCDEF0123456789ABCDEF01234567\n";
+        assert_eq!(
+            valid_fingerprints(status),
+            [
+                "AAAA0123456789ABCDEF0123456789ABCDEF0123",
+                "0123456789ABCDEF0123456789ABCDEF01234567"
+            ]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Replacing snippets of code found by content instead of line numbers.
+
+use crate::{engine::check_path, orchestrator::read_to_string, permissions, status};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::path::Path;
+use toml::Value;
+
+/// A `replace-snippet` patch item, replacing `find` in `file` with
+/// `replace`. Unlike hunks this survives upstream changes as long as the
+/// snippet stays unique.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Snippet<'a> {
+    /// File relative to the patched package.
+    pub file: &'a str,
+    /// Text, or regular expression with `regex = true`, to look for.
+    pub find: &'a str,
+    /// Replacement, which may refer to capture groups like `$1` with
+    /// `regex = true`.
+    pub replace: &'a str,
+    /// Number of matches expected, the item fails for any other count.
+    pub occurrences: usize,
+    pub regex: bool,
+}
+
+impl<'a> Snippet<'a> {
+    /// Parses the table of a `replace-snippet` item.
+    pub fn parse(item: &'a Value) -> Option<Self> {
+        let table = item.as_table()?;
+        let text = |key| table.get(key).and_then(Value::as_str);
+        let occurrences = match table.get("occurrences") {
+            Some(count) => usize::try_from(count.as_integer()?).ok()?,
+            None => 1,
+        };
+        Some(Self {
+            file: text("file")?,
+            find: text("find")?,
+            replace: text("replace")?,
+            occurrences,
+            regex: table
+                .get("regex")
+                .and_then(Value::as_bool)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Replaces the snippet in the package `name` at `path`. Fails unless the
+    /// snippet is found exactly [`Snippet::occurrences`] times.
+    pub fn apply(&self, name: &str, path: &Path) -> Result<()> {
+        let loc = format!("{name}: {}", self.file);
+        let file = check_path(path, self.file, &loc)?;
+        let old = read_to_string(&file)?;
+        let (found, new) = if self.regex {
+            let regex = Regex::new(self.find)
+                .map_err(|err| anyhow!("Invalid snippet pattern ({loc}): {err}"))?;
+            let found = regex.find_iter(&old).count();
+            (found, regex.replace_all(&old, self.replace).into_owned())
+        } else {
+            (
+                old.matches(self.find).count(),
+                old.replace(self.find, self.replace),
+            )
+        };
+        if found != self.occurrences {
+            return Err(anyhow!(
+                "Expected {} occurrence(s) of the snippet `{}` but found {found} ({loc})",
+                self.occurrences,
+                self.find
+            ));
+        }
+        permissions::write(&file, new)?;
+        status::status(
+            "Replaced",
+            format!("{found} occurrence(s) in {name}: {}", self.file),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snippet;
+    use toml::Value;
+
+    #[test]
+    fn parse_defaults() {
+        let item = toml::from_str::<Value>(
+            r#"
+            file = "src/lib.rs"
+            find = "a"
+            replace = "b"
+            "#,
+        )
+        .expect("Unable to parse item");
+        assert_eq!(
+            Snippet::parse(&item),
+            Some(Snippet {
+                file: "src/lib.rs",
+                find: "a",
+                replace: "b",
+                occurrences: 1,
+                regex: false,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_rejects_negative_occurrences() {
+        let item = toml::from_str::<Value>(
+            r#"
+            file = "src/lib.rs"
+            find = "a"
+            replace = "b"
+            occurrences = -1
+            "#,
+        )
+        .expect("Unable to parse item");
+        assert_eq!(Snippet::parse(&item), None);
+    }
+}
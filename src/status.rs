@@ -0,0 +1,139 @@
+//! Progress output, either as plain lines or as cargo-style status lines.
+
+use crate::{ci, orchestrator::Options, permissions, porcelain};
+use anyhow::Result;
+use cargo::core::Shell;
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    fs::{self, File},
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+
+/// How progress is printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputStyle {
+    /// Lines on stdout like `Patched serde: src/lib.rs`, stable for scripts
+    /// and tests.
+    Plain,
+    /// [`OutputStyle::Plain`] lines on stderr, keeping stdout free for
+    /// machine-readable output like the `cargo:` directives of build
+    /// scripts. The default of the library API.
+    #[default]
+    Stderr,
+    /// Right-aligned and colored status lines on stderr like cargo's.
+    Cargo,
+    /// [`OutputStyle::Cargo`] if stderr is a terminal, otherwise
+    /// [`OutputStyle::Plain`].
+    Auto,
+    /// Records of the stable porcelain format on stdout, like
+    /// `patched\tserde\tsrc/lib.rs\t2`, and [`OutputStyle::Plain`] lines on
+    /// stderr.
+    Porcelain,
+}
+
+thread_local! {
+    /// Shell used for cargo-style output, `None` in plain mode.
+    static SHELL: RefCell<Option<Shell>> = const { RefCell::new(None) };
+    /// Whether plain lines go to stderr, see [`OutputStyle::Stderr`].
+    static STDERR: Cell<bool> = const { Cell::new(false) };
+    /// Log of the package being patched, if logs are enabled, as the open
+    /// temporary file and the path it replaces once finished.
+    static LOG: RefCell<Option<(File, PathBuf)>> = const { RefCell::new(None) };
+}
+
+/// Selects the output style and colors for all following status lines.
+pub fn init(options: &Options) -> Result<()> {
+    let cargo_style = match options.output {
+        OutputStyle::Plain | OutputStyle::Stderr | OutputStyle::Porcelain => false,
+        OutputStyle::Cargo => true,
+        OutputStyle::Auto => io::stderr().is_terminal(),
+    };
+    let shell = if cargo_style {
+        let mut shell = Shell::new();
+        shell.set_color_choice(options.color.as_deref())?;
+        Some(shell)
+    } else {
+        None
+    };
+    SHELL.with_borrow_mut(|current| *current = shell);
+    STDERR.set(matches!(
+        options.output,
+        OutputStyle::Stderr | OutputStyle::Porcelain
+    ));
+    porcelain::init(options.output == OutputStyle::Porcelain);
+    Ok(())
+}
+
+/// Prints a status line, e.g. `Patched serde: src/lib.rs`.
+pub fn status(verb: &str, message: impl Display) {
+    let plain = format!("{verb} {message}");
+    status_or(verb, message, Some(plain));
+}
+
+/// Prints a status line which reads `plain` in plain mode or is left out
+/// there if `plain` is `None`.
+pub fn status_or(verb: &str, message: impl Display, plain: Option<String>) {
+    log(plain.as_deref().unwrap_or(&format!("{verb} {message}")));
+    SHELL.with_borrow_mut(|shell| match shell.as_mut() {
+        Some(shell) => {
+            let _ = shell.status(verb, message);
+        }
+        None => {
+            if let Some(plain) = plain {
+                line(plain);
+            }
+        }
+    });
+}
+
+/// Prints a line without a verb in any style, on stdout unless
+/// [`OutputStyle::Stderr`] is selected.
+pub fn line(message: impl Display) {
+    if STDERR.get() {
+        eprintln!("{message}");
+    } else {
+        println!("{message}");
+    }
+}
+
+/// Prints a warning.
+pub fn warning(message: impl Display) {
+    log(format!("warning: {message}"));
+    ci::warning(&message.to_string());
+    SHELL.with_borrow_mut(|shell| match shell.as_mut() {
+        Some(shell) => {
+            let _ = shell.warn(message);
+        }
+        None => eprintln!("warning: {message}"),
+    });
+}
+
+/// Starts writing all following status lines and details to a new log file
+/// at `path`. It is written to a temporary file until [`finish_log`].
+pub fn start_log(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = File::create(permissions::tmp_path(path)?)?;
+    LOG.with_borrow_mut(|log| *log = Some((file, path.to_path_buf())));
+    Ok(())
+}
+
+/// Stops writing to the log file and moves it to its final path.
+pub fn finish_log() -> Result<()> {
+    let Some((file, path)) = LOG.with_borrow_mut(Option::take) else {
+        return Ok(());
+    };
+    permissions::persist(file, &permissions::tmp_path(&path)?, &path)
+}
+
+/// Writes a line to the log file only.
+pub fn log(message: impl Display) {
+    LOG.with_borrow_mut(|log| {
+        if let Some((file, _)) = log.as_mut() {
+            let _ = writeln!(file, "{message}");
+        }
+    });
+}
@@ -0,0 +1,89 @@
+//! Restricting patching to the dependencies built for selected targets.
+
+use crate::{orchestrator::PatchEntry, status};
+use anyhow::Result;
+use cargo::core::{
+    compiler::{CompileKind, CompileTarget, RustcTargetData},
+    dependency::DepKind,
+    PackageId, Resolve, Workspace,
+};
+use std::collections::HashSet;
+
+/// Returns the packages built when building the workspace members for one
+/// of `targets`, like cargo with `--target`. Build dependencies and
+/// everything below them are built for the host.
+fn used_packages(
+    workspace: &Workspace<'_>,
+    resolve: &Resolve,
+    targets: &[String],
+) -> Result<HashSet<PackageId>> {
+    let kinds = targets
+        .iter()
+        .map(|target| CompileTarget::new(target).map(CompileKind::Target))
+        .collect::<Result<Vec<_>>>()?;
+    let target_data = RustcTargetData::new(workspace, &kinds)?;
+    let members = workspace
+        .members()
+        .map(|member| member.package_id())
+        .collect::<HashSet<_>>();
+
+    let mut visited = HashSet::new();
+    let mut queue = members
+        .iter()
+        .flat_map(|id| kinds.iter().map(move |kind| (*id, *kind)))
+        .collect::<Vec<_>>();
+    while let Some((id, kind)) = queue.pop() {
+        if !visited.insert((id, kind)) {
+            continue;
+        }
+        for (dep_id, deps) in resolve.deps(id) {
+            for dep in deps {
+                // dev-dependencies of dependencies are never built
+                if dep.kind() == DepKind::Development && !members.contains(&id) {
+                    continue;
+                }
+                let dep_kind = if dep.kind() == DepKind::Build {
+                    CompileKind::Host
+                } else {
+                    kind
+                };
+                if target_data.dep_platform_activated(dep, dep_kind) {
+                    queue.push((dep_id, dep_kind));
+                }
+            }
+        }
+    }
+    Ok(visited.into_iter().map(|(id, _)| id).collect())
+}
+
+/// Drops the packages of `ids` which aren't built for any of `targets`,
+/// see [`crate::Options::targets`].
+pub fn retain<'a>(
+    ids: Vec<(PatchEntry<'a>, PackageId)>,
+    workspace: &Workspace<'_>,
+    resolve: &Resolve,
+    targets: &[String],
+) -> Result<Vec<(PatchEntry<'a>, PackageId)>> {
+    if targets.is_empty() {
+        return Ok(ids);
+    }
+    let used = used_packages(workspace, resolve, targets)?;
+    Ok(ids
+        .into_iter()
+        .filter(|(_, id)| {
+            let keep = used.contains(id);
+            if !keep {
+                status::status(
+                    "Skipping",
+                    format!(
+                        "{} v{}, it isn't built for {}",
+                        id.name(),
+                        id.version(),
+                        targets.join(", ")
+                    ),
+                );
+            }
+            keep
+        })
+        .collect())
+}
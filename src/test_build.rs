@@ -0,0 +1,72 @@
+//! Checking that the patched packages and their users still compile.
+
+use crate::{
+    orchestrator::{in_build_script, Options, PatchEntry},
+    status,
+};
+use anyhow::{anyhow, Result};
+use cargo::core::Package;
+use std::{env, ffi::OsString, path::Path, process::Command};
+
+/// Returns a `cargo check` command with the flags of `options` which
+/// influence resolution and the targets to check for.
+fn cargo_check(options: &Options) -> Command {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo"));
+    let mut command = Command::new(cargo);
+    let _ = command.arg("check");
+    if options.offline || options.pure.is_some() {
+        let _ = command.arg("--offline");
+    }
+    for config in &options.config {
+        let _ = command.args(["--config", config]);
+    }
+    for target in &options.targets {
+        let _ = command.args(["--target", target]);
+    }
+    command
+}
+
+/// Runs `cargo check` for every patched package and, once they all compile,
+/// for the whole workspace. The patched copies are only built if `[patch]`
+/// points to them, as it has to for the build to use them.
+pub fn run(
+    packages: &[(PatchEntry<'_>, &Package)],
+    root: &Path,
+    options: &Options,
+) -> Result<()> {
+    if in_build_script() {
+        return Err(anyhow!("Test builds can't run from a build script"));
+    }
+    let mut failures = vec![];
+    for (patch, package) in packages {
+        let spec = format!("{}@{}", package.name(), package.version());
+        let status = cargo_check(options).args(["-p", &spec]).status()?;
+        if !status.success() {
+            let steps = patch.steps(root);
+            failures.push(format!(
+                "{} v{} with {}",
+                patch.name,
+                package.version(),
+                if steps.is_empty() {
+                    "its settings".to_owned()
+                } else {
+                    steps.join(", ")
+                }
+            ));
+        }
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} patched package(s) fail to build:\n    {}",
+            failures.len(),
+            failures.join("\n    ")
+        ));
+    }
+    if !cargo_check(options).arg("--workspace").status()?.success() {
+        return Err(anyhow!(
+            "The workspace fails to build with the patched packages"
+        ));
+    }
+    status::status("Checked", "the patched packages and the workspace");
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! Phase durations and copy sizes collected with `--timings`.
+
+use crate::{permissions, status};
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+use std::{
+    cell::RefCell,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Directory the timing reports are written to. It lives outside of
+/// `target/patch` so clearing the patched copies keeps earlier reports.
+const TIMINGS_DIR: &str = "target/cargo-patch-timings";
+
+/// Format of the timing report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimingsFormat {
+    /// A standalone page with tables, like cargo's `--timings`.
+    #[default]
+    Html,
+    /// Machine-readable durations in seconds and sizes in bytes.
+    Json,
+}
+
+/// A phase of patching the durations are summed up for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Loading the workspace, resolving dependencies and patch entries.
+    Resolution,
+    /// Copying pristine sources to the output directory.
+    Copy,
+    /// Reading and parsing patch files.
+    Parse,
+    /// Applying hunks and writing the patched files.
+    Apply,
+    /// Removing earlier copies.
+    Cleanup,
+}
+
+impl Phase {
+    const ALL: [Self; 5] = [
+        Self::Resolution,
+        Self::Copy,
+        Self::Parse,
+        Self::Apply,
+        Self::Cleanup,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Resolution => "resolution",
+            Self::Copy => "copy",
+            Self::Parse => "parse",
+            Self::Apply => "apply",
+            Self::Cleanup => "cleanup",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Durations([Duration; Phase::ALL.len()]);
+
+impl Durations {
+    fn add(&mut self, phase: Phase, duration: Duration) {
+        self.0[phase as usize] += duration;
+    }
+
+    fn to_json(&self) -> Value {
+        let phases = Phase::ALL
+            .iter()
+            .map(|phase| {
+                let secs = self.0[*phase as usize].as_secs_f64();
+                (phase.name().to_owned(), json!(secs))
+            })
+            .collect::<Map<_, _>>();
+        Value::Object(phases)
+    }
+}
+
+#[derive(Debug)]
+struct PackageTimings {
+    name: String,
+    version: String,
+    copy_size: u64,
+    durations: Durations,
+}
+
+#[derive(Debug)]
+struct Recorder {
+    started: Instant,
+    durations: Durations,
+    packages: Vec<PackageTimings>,
+    /// Time spent in nested phases of every running [`time`] call, which is
+    /// subtracted from the outer phase.
+    nested: Vec<Duration>,
+}
+
+thread_local! {
+    /// Timings of the current run, if enabled.
+    static TIMINGS: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+}
+
+/// Starts collecting timings for the following phases.
+pub fn start() {
+    TIMINGS.with_borrow_mut(|timings| {
+        *timings = Some(Recorder {
+            started: Instant::now(),
+            durations: Durations::default(),
+            packages: vec![],
+            nested: vec![],
+        });
+    });
+}
+
+/// Attributes the following phases to the package.
+pub fn package(name: &str, version: impl ToString) {
+    TIMINGS.with_borrow_mut(|timings| {
+        if let Some(timings) = timings {
+            timings.packages.push(PackageTimings {
+                name: name.to_owned(),
+                version: version.to_string(),
+                copy_size: 0,
+                durations: Durations::default(),
+            });
+        }
+    });
+}
+
+/// Runs `f` and adds its duration to `phase` of the run and the current
+/// package, if any. Time spent in nested calls only counts for their own
+/// phase. Does nothing but run `f` if timings aren't enabled.
+pub fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    TIMINGS.with_borrow_mut(|timings| {
+        if let Some(timings) = timings {
+            timings.nested.push(Duration::ZERO);
+        }
+    });
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+    TIMINGS.with_borrow_mut(|timings| {
+        let Some(timings) = timings else {
+            return;
+        };
+        let nested = timings.nested.pop().unwrap_or_default();
+        if let Some(outer) = timings.nested.last_mut() {
+            *outer += elapsed;
+        }
+        let duration = elapsed.saturating_sub(nested);
+        timings.durations.add(phase, duration);
+        if let Some(package) = timings.packages.last_mut() {
+            package.durations.add(phase, duration);
+        }
+    });
+    result
+}
+
+/// Records the size of the current package's copy at `path`.
+pub fn copy_size(path: &Path) {
+    TIMINGS.with_borrow_mut(|timings| {
+        if let Some(package) = timings.as_mut().and_then(|t| t.packages.last_mut()) {
+            package.copy_size = dir_size(path);
+        }
+    });
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => dir_size(&entry.path()),
+            _ => entry.metadata().map_or(0, |metadata| metadata.len()),
+        })
+        .sum()
+}
+
+/// Stops collecting timings and writes the report. Returns the path of the
+/// report or `None` if timings weren't enabled.
+pub fn finish(format: TimingsFormat) -> Result<Option<PathBuf>> {
+    let Some(timings) = TIMINGS.with_borrow_mut(Option::take) else {
+        return Ok(None);
+    };
+    let total = timings.started.elapsed();
+    let (file, contents) = match format {
+        TimingsFormat::Html => ("cargo-patch-timing.html", html(&timings, total)),
+        TimingsFormat::Json => ("cargo-patch-timing.json", json(&timings, total)),
+    };
+    fs::create_dir_all(TIMINGS_DIR)?;
+    let path = Path::new(TIMINGS_DIR).join(file);
+    permissions::write(&path, contents)?;
+    status::status("Timing", format!("report saved to {}", path.display()));
+    Ok(Some(path))
+}
+
+fn json(timings: &Recorder, total: Duration) -> String {
+    let packages = timings
+        .packages
+        .iter()
+        .map(|package| {
+            json!({
+                "name": package.name,
+                "version": package.version,
+                "copy-size": package.copy_size,
+                "phases": package.durations.to_json(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let report = json!({
+        "total": total.as_secs_f64(),
+        "phases": timings.durations.to_json(),
+        "packages": packages,
+    });
+    format!("{report:#}\n")
+}
+
+fn html(timings: &Recorder, total: Duration) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>cargo-patch timings</title>\n</head>\n<body>\n\
+         <h1>cargo-patch timings</h1>\n",
+    );
+    let _ = writeln!(html, "<p>Total time: {:.3}s</p>", total.as_secs_f64());
+    html.push_str("<table>\n<tr><th>Phase</th><th>Duration</th></tr>\n");
+    for phase in Phase::ALL {
+        let secs = timings.durations.0[phase as usize].as_secs_f64();
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{secs:.3}s</td></tr>",
+            phase.name()
+        );
+    }
+    html.push_str("</table>\n<table>\n<tr><th>Package</th><th>Copy size</th>");
+    for phase in Phase::ALL {
+        let _ = write!(html, "<th>{}</th>", phase.name());
+    }
+    html.push_str("</tr>\n");
+    for package in &timings.packages {
+        let _ = write!(
+            html,
+            "<tr><td>{} v{}</td><td>{} bytes</td>",
+            package.name, package.version, package.copy_size
+        );
+        for phase in Phase::ALL {
+            let secs = package.durations.0[phase as usize].as_secs_f64();
+            let _ = write!(html, "<td>{secs:.3}s</td>");
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
@@ -0,0 +1,93 @@
+//! Converting patch files exported by other tools before parsing them.
+
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+/// Character encoding of a patch file, set with `encoding`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// ISO 8859-1, where every byte is the character of the same code point.
+    Latin1,
+}
+
+impl Encoding {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Self::Utf8,
+            "latin1" | "latin-1" | "iso-8859-1" => Self::Latin1,
+            _ => {
+                eprintln!("Unknown patch encoding: {s}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Decodes the contents of the patch file at `path`.
+    pub fn decode(self, data: Vec<u8>, path: &Path) -> Result<String> {
+        match self {
+            Self::Utf8 => String::from_utf8(data).map_err(|_| {
+                anyhow!(
+                    "Patch file {} is not valid UTF-8, set its `encoding`",
+                    path.display()
+                )
+            }),
+            Self::Latin1 => Ok(data.into_iter().map(char::from).collect()),
+        }
+    }
+}
+
+/// Conversion of a decoded patch file, listed in `transform`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transform {
+    /// Replace CRLF line endings with LF.
+    Dos2Unix,
+    /// Replace LF line endings with CRLF.
+    Unix2Dos,
+}
+
+impl Transform {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "dos2unix" => Some(Self::Dos2Unix),
+            "unix2dos" => Some(Self::Unix2Dos),
+            _ => {
+                eprintln!("Unknown patch transform: {s}");
+                None
+            }
+        }
+    }
+
+    pub fn apply(self, data: &str) -> String {
+        let unix = data.replace("\r\n", "\n");
+        match self {
+            Self::Dos2Unix => unix,
+            Self::Unix2Dos => unix.replace('\n', "\r\n"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Encoding, Transform};
+    use std::path::Path;
+
+    #[test]
+    fn decode_latin1() {
+        let data = b"+caf\xe9\n".to_vec();
+        assert!(Encoding::Utf8.decode(data.clone(), Path::new("a")).is_err());
+        assert_eq!(
+            Encoding::Latin1
+                .decode(data, Path::new("a"))
+                .expect("Unable to decode"),
+            "+caf\u{e9}\n"
+        );
+    }
+
+    #[test]
+    fn line_endings() {
+        assert_eq!(Transform::Dos2Unix.apply("-a\r\n+b\r\n"), "-a\n+b\n");
+        assert_eq!(Transform::Unix2Dos.apply("-a\r\n+b\n"), "-a\r\n+b\r\n");
+    }
+}
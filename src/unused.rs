@@ -0,0 +1,114 @@
+//! Detection of patch configuration which has no effect anymore, e.g. after
+//! a dependency was removed.
+
+use crate::{
+    orchestrator::{
+        active_set_patches, custom_metadata, get_entries, get_patch_sets,
+        get_patches, matching_ids, Options, PatchConfig, PatchEntry,
+    },
+    status,
+};
+use anyhow::{anyhow, Result};
+use cargo::core::{Resolve, Workspace};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Extensions of files considered to be patch files.
+const PATCH_EXTENSIONS: [&str; 2] = ["patch", "diff"];
+
+/// Returns the patch files next to the local patch files of `entries` which
+/// none of them references. Directories outside of the workspace are
+/// skipped as they belong to dependencies.
+fn unused_files(entries: &[PatchEntry<'_>], root: &Path) -> Result<Vec<PathBuf>> {
+    let used = entries
+        .iter()
+        .flat_map(|entry| &entry.patches)
+        .filter(|item| item.remote.is_none())
+        .filter_map(|item| item.path.canonicalize().ok())
+        .collect::<BTreeSet<_>>();
+    let dirs = used
+        .iter()
+        .filter_map(|path| path.parent())
+        .filter(|dir| dir.starts_with(root))
+        .collect::<BTreeSet<_>>();
+
+    let mut unused = vec![];
+    for dir in dirs {
+        for file in fs::read_dir(dir)? {
+            let path = file?.path();
+            let is_patch = path.extension().is_some_and(|ext| {
+                PATCH_EXTENSIONS.iter().any(|patch| ext == *patch)
+            });
+            if is_patch && path.is_file() && !used.contains(&path) {
+                unused.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+            }
+        }
+    }
+    Ok(unused)
+}
+
+/// Warns, or fails in strict mode, about patch entries of the workspace
+/// which match no dependency and patch files which no entry references.
+/// Entries restricted to other profiles or of inactive patch sets are not
+/// reported, but their patch files count as used.
+pub fn check(
+    workspace: &Workspace<'_>,
+    resolve: &Resolve,
+    config: &PatchConfig,
+    options: &Options,
+) -> Result<()> {
+    let root = workspace.root().canonicalize()?;
+    let entries = custom_metadata(workspace)
+        .flat_map(get_patches)
+        .chain(active_set_patches(workspace, config)?)
+        .collect::<Vec<_>>();
+    let all_entries = custom_metadata(workspace)
+        .flat_map(get_patches)
+        .chain(
+            custom_metadata(workspace)
+                .flat_map(get_patch_sets)
+                .flat_map(|(_, entries)| get_entries(entries)),
+        )
+        .collect::<Vec<_>>();
+
+    let mut unused = entries
+        .iter()
+        .filter(|entry| entry.in_profile(&config.profile))
+        .filter(|entry| {
+            matching_ids(
+                entry.name,
+                &entry.version,
+                entry.package_source.as_ref(),
+                resolve,
+            )
+            .is_empty()
+        })
+        .map(|entry| {
+            let name = entry.version.as_ref().map_or_else(
+                || entry.name.to_owned(),
+                |version| format!("{} {version}", entry.name),
+            );
+            format!("patch entry for {name} matches no dependency")
+        })
+        .collect::<Vec<_>>();
+    unused.extend(unused_files(&all_entries, &root)?.iter().map(|file| {
+        format!(
+            "patch file {} isn't used by any patch entry",
+            file.display()
+        )
+    }));
+
+    if options.strict && !unused.is_empty() {
+        return Err(anyhow!(
+            "Found unused patch configuration:\n    {}",
+            unused.join("\n    ")
+        ));
+    }
+    for message in unused {
+        status::warning(message);
+    }
+    Ok(())
+}
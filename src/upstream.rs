@@ -0,0 +1,45 @@
+//! Looking up the newest releases of patched packages in their registries.
+
+use anyhow::Result;
+use cargo::{
+    core::{registry::PackageRegistry, Dependency, PackageId, Registry},
+    sources::{source::QueryKind, IndexSummary, SourceConfigMap},
+    GlobalContext,
+};
+use semver::Version;
+use std::{collections::HashMap, task::Poll};
+
+/// Returns the newest release which isn't yanked of every package in `ids`
+/// from a registry. Pre-releases are only considered for packages which are
+/// pre-releases themselves.
+pub fn latest_versions(
+    gctx: &GlobalContext,
+    ids: impl IntoIterator<Item = PackageId>,
+) -> Result<HashMap<PackageId, Version>> {
+    let scm = SourceConfigMap::new(gctx)?;
+    let mut registry = PackageRegistry::new_with_source_config(gctx, scm)?;
+    registry.lock_patches();
+    let mut latest = HashMap::new();
+    for id in ids {
+        if !id.source_id().is_registry() {
+            continue;
+        }
+        let dep = Dependency::parse(id.name(), None, id.source_id())?;
+        let summaries = loop {
+            match registry.query_vec(&dep, QueryKind::Exact) {
+                Poll::Ready(summaries) => break summaries?,
+                Poll::Pending => registry.block_until_ready()?,
+            }
+        };
+        let newest = summaries
+            .iter()
+            .filter(|summary| matches!(summary, IndexSummary::Candidate(_)))
+            .map(|summary| summary.as_summary().version())
+            .filter(|version| version.pre.is_empty() || !id.version().pre.is_empty())
+            .max();
+        if let Some(version) = newest {
+            let _ = latest.insert(id, version.clone());
+        }
+    }
+    Ok(latest)
+}
@@ -0,0 +1,120 @@
+//! Check that patched copies contain nothing but the configured patches.
+
+use crate::{
+    long_path::long_path,
+    orchestrator::{
+        copy_root, get_config, load_workspace, lock_package_cache, patch_package,
+        resolve_patches, resolve_ws, setup_gctx, Options, PatchConfig,
+    },
+    order,
+    provenance::PROVENANCE_FILE,
+    Error,
+};
+use anyhow::{anyhow, Result};
+use fs_extra::dir::get_dir_content;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Directory the expected copies are created in.
+const VERIFY_DIR: &str = "target/patch-verify";
+
+/// Returns the files below `root` relative to it, except for the
+/// provenance file and repositories created by `git-init`, which differ on
+/// every run.
+fn files(root: &Path) -> Result<BTreeSet<PathBuf>> {
+    Ok(get_dir_content(root)?
+        .files
+        .iter()
+        .filter_map(|file| Path::new(file).strip_prefix(root).ok())
+        .filter(|file| *file != Path::new(PROVENANCE_FILE))
+        .filter(|file| !file.components().any(|c| c.as_os_str() == ".git"))
+        .map(Path::to_path_buf)
+        .collect())
+}
+
+/// Compares the expected with the actual copy and returns a description of
+/// every difference.
+fn differences(expected: &Path, actual: &Path) -> Result<Vec<String>> {
+    let expected_files = files(expected)?;
+    let actual_files = files(actual)?;
+    let mut differences = vec![];
+    for file in expected_files.union(&actual_files) {
+        let change =
+            match (expected_files.contains(file), actual_files.contains(file)) {
+                (true, false) => "missing",
+                (false, true) => "added",
+                _ if fs::read(expected.join(file))?
+                    != fs::read(actual.join(file))? =>
+                {
+                    "modified"
+                }
+                _ => continue,
+            };
+        differences.push(format!("{change} {}", file.display()));
+    }
+    Ok(differences)
+}
+
+/// Re-creates every patched copy from the pristine sources and fails if the
+/// existing copy in the output directory differs from it, e.g. because it was
+/// edited by hand without updating the patch files.
+pub fn verify(options: &Options) -> Result<(), Error> {
+    let gctx = setup_gctx(options)?;
+    let _lock = lock_package_cache(&gctx, options)?;
+    let workspace = load_workspace(&gctx)?;
+    let (pkg_set, resolve) = resolve_ws(&workspace)?;
+    let config = get_config(&gctx, &workspace, &pkg_set, &resolve, options)?;
+    let ids = resolve_patches(&workspace, &pkg_set, &resolve, &config)?;
+    let ids = order::sort(ids, &resolve);
+    let ids = order::link(ids, &pkg_set, &resolve, &config)?;
+
+    if ids.is_empty() {
+        println!("No patches found");
+        return Ok(());
+    }
+
+    let expected_config = PatchConfig {
+        persist: Some(PathBuf::from(VERIFY_DIR)),
+        ..config.clone()
+    };
+    if Path::new(VERIFY_DIR).exists() {
+        fs::remove_dir_all(VERIFY_DIR)?;
+    }
+    let mut mismatched = 0;
+    for (patch, id) in ids {
+        let package = pkg_set.get_one(id)?;
+        let actual = copy_root(package, &config);
+        if !actual.exists() {
+            println!(
+                "{} v{}: {} is missing",
+                id.name(),
+                id.version(),
+                actual.display()
+            );
+            mismatched += 1;
+            continue;
+        }
+        patch_package(&patch, package, &expected_config, options)?;
+        let expected = copy_root(package, &expected_config);
+        let differences = differences(&long_path(&expected)?, &long_path(&actual)?)?;
+        if !differences.is_empty() {
+            mismatched += 1;
+        }
+        for difference in differences {
+            println!("{} v{}: {difference}", id.name(), id.version());
+        }
+    }
+    fs::remove_dir_all(VERIFY_DIR)?;
+
+    if mismatched > 0 {
+        return Err(anyhow!(
+            "{mismatched} patched package(s) don't match their patches"
+        )
+        .into());
+    }
+    println!("All patched packages match their patches");
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Re-applying patches whenever a patch file changes.
+
+use crate::{
+    interrupt,
+    long_path::long_path,
+    orchestrator::{copy_root, patch_package, Options, PatchConfig, PatchEntry},
+    status,
+};
+use anyhow::Result;
+use cargo::core::package::Package;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    fs, iter,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+/// Time to wait for further events, as editors tend to write files in
+/// several steps.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Returns the canonical path of `path`. Files which don't exist anymore,
+/// e.g. as an editor replaces them, get the canonical path of their
+/// directory, which is the path they were watched under.
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    let path = std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .unwrap_or_else(|_| path.to_path_buf());
+    match (path.parent().map(Path::canonicalize), path.file_name()) {
+        (Some(Ok(dir)), Some(name)) => dir.join(name),
+        _ => path,
+    }
+}
+
+/// Returns the local files whose changes affect the copy of a package: its
+/// patch files, their signatures and the copy-ignore file.
+fn watched_files(patch: &PatchEntry<'_>) -> Vec<PathBuf> {
+    patch
+        .patches
+        .iter()
+        .filter(|item| item.remote.is_none())
+        .flat_map(|item| {
+            iter::once(item.path.as_ref()).chain(item.signature.as_deref())
+        })
+        .chain(patch.copy_ignore)
+        .map(absolute)
+        .collect()
+}
+
+/// Waits for the next batch of changed files. Returns `None` once watching
+/// stopped or was interrupted.
+fn changed_files(
+    rx: &Receiver<notify::Result<notify::Event>>,
+) -> Option<HashSet<PathBuf>> {
+    let mut changed = HashSet::new();
+    let mut event = loop {
+        if interrupt::interrupted() {
+            return None;
+        }
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(event) => break event,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    };
+    loop {
+        match event {
+            Ok(event)
+                if matches!(
+                    event.kind,
+                    EventKind::Create(_)
+                        | EventKind::Modify(_)
+                        | EventKind::Remove(_)
+                ) =>
+            {
+                changed.extend(event.paths.iter().map(|path| absolute(path)));
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("Error: {err}"),
+        }
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(next) => event = next,
+            Err(_) => return Some(changed),
+        }
+    }
+}
+
+fn repatch(
+    patch: &PatchEntry<'_>,
+    package: &Package,
+    config: &PatchConfig,
+    options: &Options,
+) -> Result<()> {
+    let root = long_path(&copy_root(package, config))?;
+    if root.exists() {
+        fs::remove_dir_all(root)?;
+    }
+    patch_package(patch, package, config, options)
+}
+
+/// Watches the patch files of all packages and re-copies and re-patches a
+/// package as soon as one of its files changes. Returns `true` once one of
+/// the `manifests`, which hold the configuration including `sed` and
+/// `replace-snippet` items, changed and everything has to be patched again.
+/// Otherwise never returns unless watching fails or is interrupted.
+pub fn watch(
+    packages: &[(PatchEntry<'_>, &Package)],
+    manifests: &[PathBuf],
+    config: &PatchConfig,
+    options: &Options,
+) -> Result<bool> {
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+
+    let patch_files = packages
+        .iter()
+        .map(|(patch, _)| watched_files(patch))
+        .collect::<Vec<_>>();
+    let manifests = manifests
+        .iter()
+        .map(|path| absolute(path))
+        .collect::<Vec<_>>();
+
+    // editors often replace files, so watch the directories instead
+    let dirs = patch_files
+        .iter()
+        .flatten()
+        .chain(&manifests)
+        .filter_map(|file| file.parent())
+        .collect::<HashSet<_>>();
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    status::status("Watching", "patch files for changes");
+
+    while let Some(changed) = changed_files(&rx) {
+        if manifests.iter().any(|manifest| changed.contains(manifest)) {
+            return Ok(true);
+        }
+        for ((patch, package), files) in packages.iter().zip(&patch_files) {
+            if !files.iter().any(|file| changed.contains(file)) {
+                continue;
+            }
+            status::status("Re-applying", format!("patches of {}", patch.name));
+            if let Err(err) = repatch(patch, package, config, options) {
+                eprintln!("Error: {err:#}");
+            }
+        }
+    }
+    interrupt::check()?;
+    Ok(false)
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_all_versions() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bitflags = "=1.3.2"
+        bitflags2 = { package = "bitflags", version = "=2.4.0" }
+
+        [package.metadata.patch.bitflags]
+        all-versions = true
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = r#"--- /dev/null
++++ PATCHED.txt
+@@ -0,0 +1 @@
++PATCHED
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched bitflags: /dev/null -> PATCHED.txt\nPatched bitflags: /dev/null -> PATCHED.txt\n",
+        )
+        .run();
+
+    for dir in ["bitflags-1.3.2", "bitflags-2.4.0"] {
+        let patched = p.build_dir().join("patch").join(dir).join("PATCHED.txt");
+        assert!(patched.exists(), "{dir} was not patched");
+    }
+}
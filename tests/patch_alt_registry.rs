@@ -0,0 +1,62 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{
+    main_file, project,
+    registry::{Package, RegistryBuilder},
+};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_alt_registry_with_auth() {
+    let _registry = RegistryBuilder::new()
+        .alternative()
+        .auth_required()
+        .credential_provider(&["cargo:token"])
+        .http_index()
+        .build();
+    Package::new("bar", "0.1.0")
+        .alternative(true)
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = { version = "0.1.0", registry = "alternative" }
+
+        [package.metadata.patch.bar]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched bar: /dev/null -> PATCHED.txt\n")
+        .run();
+    assert!(p
+        .build_dir()
+        .join("patch")
+        .join("bar-0.1.0")
+        .join("PATCHED.txt")
+        .exists());
+
+    p.process(common::cargo_patch_exe())
+        .args(&["paths", "--toml"])
+        .with_stdout(
+            "[patch.alternative]\nbar = { path = \"./target/patch/bar-0.1.0\" }\n",
+        )
+        .run();
+}
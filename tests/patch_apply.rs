@@ -0,0 +1,88 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+const PATCH: &str = r#"--- a/LICENSE-MIT	2020-05-20 18:44:09.709027472 +0200
++++ b/LICENSE-MIT	2020-05-20 18:58:46.253762666 +0200
+@@ -8,9 +8,7 @@
+ is furnished to do so, subject to the following
+ conditions:
+ 
+-The above copyright notice and this permission notice
+-shall be included in all copies or substantial portions
+-of the Software.
++PATCHED
+ 
+ THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+ ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+"#;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_apply_stdin() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["apply", "serde", "-", "--source", "GithubPrDiff"])
+        .with_stdin(PATCH)
+        .with_stdout("Patched serde: LICENSE-MIT\n")
+        .run();
+
+    let license_mit = p
+        .build_dir()
+        .join("patch")
+        .join("serde-1.0.110")
+        .join("LICENSE-MIT");
+    let licenses =
+        std::fs::read_to_string(license_mit).expect("Unable to read license file");
+    assert!(licenses.contains("PATCHED"));
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_apply_replaces_copy() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["apply", "serde", "-", "--source", "GithubPrDiff"])
+        .with_stdin(PATCH)
+        .with_stdout("Patched serde: LICENSE-MIT\n")
+        .run();
+    let copy = p.build_dir().join("patch").join("serde-1.0.110");
+    std::fs::write(copy.join("stale.txt"), "").expect("Unable to write");
+
+    // the same diff applies again as the copy starts out pristine
+    p.process(common::cargo_patch_exe())
+        .args(&["apply", "serde", "-", "--source", "GithubPrDiff"])
+        .with_stdin(PATCH)
+        .with_stdout("Patched serde: LICENSE-MIT\n")
+        .run();
+    assert!(!copy.join("stale.txt").exists());
+}
@@ -0,0 +1,61 @@
+use cargo_patch::{apply_to_dir, Error, PatchSpec};
+use cargo_test_macro::cargo_test;
+use cargo_test_support::project;
+use std::path::PathBuf;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_apply_to_dir() {
+    let patch = r#"--- config.h
++++ config.h
+@@ -1 +1 @@
+-#define ENABLED 0
++#define ENABLED 1
+--- /dev/null
++++ extra.c
+@@ -0,0 +1 @@
++int extra;
+"#;
+    let p = project()
+        .file("vendor/lib/config.h", "#define ENABLED 0\n")
+        .file("fix.patch", patch)
+        .build();
+
+    let report = apply_to_dir(
+        &p.root().join("vendor/lib"),
+        &[PatchSpec::new(p.root().join("fix.patch"))],
+    )
+    .expect("Unable to apply patch");
+    assert_eq!(
+        report.files,
+        [PathBuf::from("config.h"), PathBuf::from("extra.c")]
+    );
+    let config = std::fs::read_to_string(p.root().join("vendor/lib/config.h"))
+        .expect("Unable to read patched file");
+    assert_eq!(config, "#define ENABLED 1\n");
+    assert!(p.root().join("vendor/lib/extra.c").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_apply_to_dir_hunk_failed() {
+    let patch = "--- config.h\n+++ config.h\n@@ -1 +1 @@\n-missing\n+ENABLED\n";
+    let p = project()
+        .file("vendor/lib/config.h", "#define ENABLED 0\n")
+        .file("fix.patch", patch)
+        .build();
+
+    let err = apply_to_dir(
+        &p.root().join("vendor/lib"),
+        &[PatchSpec::new(p.root().join("fix.patch"))],
+    )
+    .expect_err("Patch must not apply");
+    assert!(matches!(
+        err,
+        Error::HunkFailed {
+            line: 1,
+            hunk: 1,
+            ..
+        }
+    ));
+}
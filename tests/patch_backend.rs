@@ -0,0 +1,125 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+const PATCH: &str = r#"--- a/LICENSE-MIT
++++ b/LICENSE-MIT
+@@ -8,9 +8,7 @@
+ is furnished to do so, subject to the following
+ conditions:
+ 
+-The above copyright notice and this permission notice
+-shall be included in all copies or substantial portions
+-of the Software.
++PATCHED
+ 
+ THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+ ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+"#;
+
+#[allow(deprecated)]
+fn patch_with_backend(backend: &str, tool: &str) {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        backend = "{backend}"
+        source = "GitDiff"
+        patches = [
+            "test.patch"
+        ]
+    "#
+    );
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(format!("Patched serde: test.patch using {tool}\n"))
+        .run();
+
+    let license_mit = p
+        .build_dir()
+        .join("patch")
+        .join("serde-1.0.110")
+        .join("LICENSE-MIT");
+    let licenses =
+        std::fs::read_to_string(license_mit).expect("Unable to read license file");
+    assert!(licenses.contains("PATCHED"));
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_backend_git_apply() {
+    patch_with_backend("git-apply", "git apply");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_backend_gnu_patch() {
+    patch_with_backend("gnu-patch", "patch");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_backend_unsupported_options() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        backend = "gnu-patch"
+        final-newline = "always"
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "[..]final-newline is not supported by the patch backend of dep[..]",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        &manifest.replace("final-newline = \"always\"\n", ""),
+    );
+    p.process(common::cargo_patch_exe())
+        .arg("--show-diff")
+        .with_status(1)
+        .with_stderr_contains(
+            "[..]--show-diff is not supported by the patch backend of dep[..]",
+        )
+        .run();
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_build_script_offline() {
+    let manifest = |patches: &str| {
+        format!(
+            r#"
+            [package]
+            name = "example"
+            version = "0.1.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+            serde = "=1.0.110"
+
+            [package.metadata.patch.serde]
+            patches = [{patches}]
+        "#
+        )
+    };
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", &manifest(r#""test.patch""#))
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    // download the dependency like cargo does before running build scripts
+    p.process(common::cargo_patch_exe()).run();
+
+    // resolution and copying work without network access
+    p.process(common::cargo_patch_exe())
+        .env("OUT_DIR", p.build_dir())
+        .env("CARGO_MANIFEST_DIR", p.root())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+
+    let url = format!("file://{}", p.root().join("remote.patch").display());
+    p.change_file("Cargo.toml", &manifest(&format!(r#"{{ url = "{url}" }}"#)));
+    p.process(common::cargo_patch_exe())
+        .env("OUT_DIR", p.build_dir())
+        .env("CARGO_MANIFEST_DIR", p.root())
+        .with_stderr_contains(format!(
+            "Error: Unable to download patch {url} without network access"
+        ))
+        .with_status(1)
+        .run();
+}
@@ -0,0 +1,83 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{basic_lib_manifest, main_file, project, Project};
+
+const PATCH: &str = "--- helper.rs\n+++ helper.rs\n@@ -1 +1 @@\n-pub fn run() {}\n+pub fn run() { std::process::exit(1) }\n";
+
+fn setup(entry: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch.dep]
+        {entry}
+        patches = ["test.patch"]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("dep/Cargo.toml", &basic_lib_manifest("dep"))
+        .file("dep/src/lib.rs", "")
+        .file(
+            "dep/build.rs",
+            "mod helper;\nfn main() { helper::run(); }\n",
+        )
+        .file("dep/helper.rs", "pub fn run() {}\n")
+        .file("test.patch", PATCH)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_build_surface_module() {
+    let p = setup("");
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout_contains("Patched dep: helper.rs")
+        .with_stderr(
+            "warning: patches change the build script of dep. Set \
+             `allow-build-script-changes = true` to allow this\n",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_build_surface_strict() {
+    let p = setup("");
+
+    p.process(common::cargo_patch_exe())
+        .arg("--strict")
+        .with_stderr(
+            "Error: patches change the build script of dep. Set \
+             `allow-build-script-changes = true` to allow this\n",
+        )
+        .with_status(1)
+        .run();
+    assert!(!p.build_dir().join("patch/dep-0.5.0").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_build_surface_allowed() {
+    let p = setup("allow-build-script-changes = true");
+
+    p.process(common::cargo_patch_exe())
+        .arg("--strict")
+        .with_stdout_contains("Patched dep: helper.rs")
+        .with_stderr("")
+        .run();
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::project;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_ci() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        good = { path = "good" }
+        bad = { path = "bad" }
+
+        [package.metadata.patch.good]
+        patches = ["good.patch"]
+
+        [package.metadata.patch.bad]
+        patches = ["bad.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "good/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("good"),
+        )
+        .file("good/src/lib.rs", "")
+        .file(
+            "bad/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("bad"),
+        )
+        .file("bad/src/lib.rs", "pub fn bad() {}\n")
+        .file(
+            "good.patch",
+            "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n",
+        )
+        .file(
+            "bad.patch",
+            "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn other() {}\n+pub fn patched() {}\n",
+        )
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--ci")
+        .with_status(1)
+        .with_stderr_contains(
+            "bad.patch:3: error: failed to apply patch to lib.rs on line 1[..]",
+        )
+        .with_stderr_contains("Error: Failed to patch 1 package(s):[..]")
+        .run();
+
+    let summary =
+        std::fs::read_to_string(p.build_dir().join("cargo-patch-summary.json"))
+            .expect("Missing summary");
+    let summary = serde_json::from_str::<serde_json::Value>(&summary)
+        .expect("Invalid summary");
+    assert_eq!(summary["success"], false);
+    assert_eq!(summary["patched"][0]["name"], "good");
+    assert_eq!(summary["failed"][0]["name"], "bad");
+    assert_eq!(summary["failed"][0]["patch"], "bad.patch");
+    assert_eq!(summary["failed"][0]["line"], 3);
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--ci", "--watch"])
+        .with_status(2)
+        .with_stderr_contains("[..]cannot be used with[..]")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_ci_member_dir() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/lib.rs", "")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .cwd(p.root().join("src"))
+        .arg("--ci")
+        .run();
+
+    // the summary is written to the target directory of the workspace
+    assert!(p.build_dir().join("cargo-patch-summary.json").exists());
+    assert!(!p.root().join("src/target").exists());
+}
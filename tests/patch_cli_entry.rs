@@ -0,0 +1,80 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+fn dep_project() -> Project {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["missing.patch"]
+    "#;
+    let first = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn first() {}\n";
+    let second = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("fix.patch", first)
+        .file("other.diff", second)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_cli_entry() {
+    let p = dep_project();
+
+    // the configured entry with its missing patch file is ignored
+    p.process(common::cargo_patch_exe())
+        .args(&[
+            "--crate",
+            "dep",
+            "--version",
+            "0.5",
+            "--patch",
+            "fix.patch",
+            "--patch",
+            "other.diff",
+        ])
+        .with_stdout(
+            "Patched dep: src/lib.rs\nPatched dep: /dev/null -> PATCHED.txt\n",
+        )
+        .run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    assert_eq!(
+        std::fs::read_to_string(copy.join("src/lib.rs"))
+            .expect("Unable to read patched file"),
+        "pub fn first() {}\n"
+    );
+    assert!(copy.join("PATCHED.txt").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_cli_entry_unknown_crate() {
+    let p = dep_project();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--crate", "serde", "--patch", "fix.patch"])
+        .with_status(1)
+        .with_stderr_contains("Error: Unable to find package serde in dependencies")
+        .run();
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_copy_ignore() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        copy-ignore = ".patchignore"
+        patches = ["test.patch"]
+    "#;
+    let ignore = "# generated bindings\n/data/\n*.bin\n!keep.bin\n";
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("dep/data/huge.txt", "")
+        .file("dep/src/data/kept.txt", "")
+        .file("dep/blob.bin", "")
+        .file("dep/src/keep.bin", "")
+        .file(".patchignore", ignore)
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Removed 2 ignored path(s) from dep\nPatched dep: src/lib.rs\n")
+        .run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    assert!(!copy.join("data").exists());
+    assert!(!copy.join("blob.bin").exists());
+    assert!(copy.join("src/data/kept.txt").exists());
+    assert!(copy.join("src/keep.bin").exists());
+    assert!(copy.join("Cargo.toml").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_copy_ignore_missing_file() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        copy-ignore = "missing.patchignore"
+        patches = []
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: Unable to read copy-ignore file missing.patchignore: [..]",
+        )
+        .run();
+}
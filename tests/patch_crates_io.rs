@@ -1,3 +1,5 @@
+#![cfg(feature = "orchestrator")]
+
 mod common;
 
 use cargo_test_macro::cargo_test;
@@ -87,7 +89,7 @@ fn patch_crates_io_invalid_patch() {
         .build();
 
     p.process(common::cargo_patch_exe())
-        .with_stderr("Error: Unable to parse patch file\n")
+        .with_stderr("Error: Unable to parse patch file test.patch\n")
         .with_status(1)
         .run();
 }
@@ -313,3 +315,152 @@ fn patch_git_workspace_metadata() {
         std::fs::read_to_string(license_mit).expect("Unable to read license file");
     assert!(licenses.contains("PATCHED"));
 }
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_crates_io_version_suffix() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        version-suffix = "patched.1"
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = r#"--- LICENSE-MIT	2020-05-20 18:44:09.709027472 +0200
++++ LICENSE-MIT	2020-05-20 18:58:46.253762666 +0200
+@@ -8,9 +8,7 @@
+ is furnished to do so, subject to the following
+ conditions:
+ 
+-The above copyright notice and this permission notice
+-shall be included in all copies or substantial portions
+-of the Software.
++PATCHED
+ 
+ THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+ ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .cwd(p.root())
+        .with_stdout(
+            "Patched serde: LICENSE-MIT\nSet serde version to 1.0.110+patched.1\n",
+        )
+        .run();
+
+    let cargo_toml = p
+        .build_dir()
+        .join("patch")
+        .join("serde-1.0.110")
+        .join("Cargo.toml");
+    let cargo_toml =
+        std::fs::read_to_string(cargo_toml).expect("Unable to read manifest");
+    assert!(cargo_toml.contains(r#"version = "1.0.110+patched.1""#));
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_crates_io_show_diff() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = r#"--- LICENSE-MIT	2020-05-20 18:44:09.709027472 +0200
++++ LICENSE-MIT	2020-05-20 18:58:46.253762666 +0200
+@@ -8,9 +8,7 @@
+ is furnished to do so, subject to the following
+ conditions:
+ 
+-The above copyright notice and this permission notice
+-shall be included in all copies or substantial portions
+-of the Software.
++PATCHED
+ 
+ THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+ ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--show-diff")
+        .with_stdout_contains("--- LICENSE-MIT")
+        .with_stdout_contains("+++ LICENSE-MIT")
+        .with_stdout_contains("-of the Software.")
+        .with_stdout_contains("+PATCHED")
+        .with_stdout_contains("Patched serde: LICENSE-MIT")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_crates_io_provenance() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe()).run();
+
+    let info = p
+        .build_dir()
+        .join("patch")
+        .join("serde-1.0.110")
+        .join(".cargo-patch.json");
+    let info = std::fs::read_to_string(info).expect("Unable to read provenance");
+    let info: serde_json::Value =
+        serde_json::from_str(&info).expect("Invalid provenance");
+    assert!(info["package"]
+        .as_str()
+        .is_some_and(|id| id.starts_with("serde v1.0.110")));
+    assert!(info["pkgid"]
+        .as_str()
+        .is_some_and(|id| id.ends_with("#serde@1.0.110")));
+    assert_eq!(info["patches"][0]["file"], "test.patch");
+    assert_eq!(
+        info["patches"][0]["sha256"],
+        "c3110026f583d4868e1071dfe9be59c3b72aaa4cb51ba0e52a9fd58c903b4fde"
+    );
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "orchestrator")]
+
 use cargo_test_macro::cargo_test;
 use cargo_test_support::{main_file, project, Execs, Project};
 
@@ -7,7 +9,8 @@ static TEST_CONTENT: &str = r#"first
 
 second
 
-third"#;
+third
+"#;
 
 fn gen_execs(patch: &str) -> (Execs, Project) {
     static MANIFEST: &str = r#"
@@ -49,7 +52,11 @@ fn patch_create_file() {
 
     e.with_stdout("Patched serde: /dev/null -> test.txt").run();
 
-    let file = p.build_dir().join("patch").join("serde").join("test.txt");
+    let file = p
+        .build_dir()
+        .join("patch")
+        .join("serde-1.0.110")
+        .join("test.txt");
 
     let content = std::fs::read_to_string(file).expect("Unable to read test file");
     assert_eq!(content.as_str(), TEST_CONTENT);
@@ -83,7 +90,11 @@ fn patch_delete_file() {
     )
     .run();
 
-    let file = p.build_dir().join("patch").join("serde").join("test.txt");
+    let file = p
+        .build_dir()
+        .join("patch")
+        .join("serde-1.0.110")
+        .join("test.txt");
     assert!(!file.exists())
 }
 
@@ -0,0 +1,105 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_dependency_metadata() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["bundle"]
+
+        [workspace.metadata.patch-config]
+        dependency-metadata = ["bundle"]
+
+        [dependencies]
+        bundle = { path = "bundle" }
+        serde = "=1.0.110"
+    "#;
+    let bundle_manifest = r#"
+        [package]
+        name = "bundle"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [package.metadata.patch.serde]
+        patches = [
+            "patches/test.patch"
+        ]
+    "#;
+    let patch = r#"--- /dev/null
++++ PATCHED.txt
+@@ -0,0 +1 @@
++PATCHED
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("bundle/Cargo.toml", bundle_manifest)
+        .file("bundle/src/lib.rs", "")
+        .file("bundle/patches/test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+
+    let patched = p.build_dir().join("patch/serde-1.0.110/PATCHED.txt");
+    assert!(patched.exists());
+
+    // the lock names the patch relative to the package providing it
+    p.process(common::cargo_patch_exe())
+        .arg("--write-lock")
+        .with_stdout_contains("Locked 1 package(s) in cargo-patch.lock")
+        .run();
+    let lock = std::fs::read_to_string(p.root().join("cargo-patch.lock"))
+        .expect("Missing lock");
+    assert!(lock.contains(r#"steps = ["bundle-0.1.0/patches/test.patch"]"#));
+    assert!(lock.contains(r#"file = "bundle-0.1.0/patches/test.patch""#));
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_dependency_metadata_not_listed() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["bundle"]
+
+        [dependencies]
+        bundle = { path = "bundle" }
+    "#;
+    let bundle_manifest = r#"
+        [package]
+        name = "bundle"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [package.metadata.patch.bundle]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("bundle/Cargo.toml", bundle_manifest)
+        .file("bundle/src/lib.rs", "")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("No patches found\n")
+        .run();
+}
@@ -0,0 +1,138 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_diff_dirs() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    // output of `diff -ruN dep-0.5.0 dep-patched`
+    let patch = r#"diff -ruN dep-0.5.0/gone.txt dep-patched/gone.txt
+--- dep-0.5.0/gone.txt	2024-03-01 12:00:00.000000000 +0100
++++ dep-patched/gone.txt	1970-01-01 01:00:00.000000000 +0100
+@@ -1 +0,0 @@
+-gone
+diff -ruN dep-0.5.0/src/added.rs dep-patched/src/added.rs
+--- dep-0.5.0/src/added.rs	1970-01-01 01:00:00.000000000 +0100
++++ dep-patched/src/added.rs	2024-03-01 12:00:00.000000000 +0100
+@@ -0,0 +1 @@
++pub fn added() {}
+diff -ruN dep-0.5.0/src/lib.rs dep-patched/src/lib.rs
+--- dep-0.5.0/src/lib.rs	2024-03-01 12:00:00.000000000 +0100
++++ dep-patched/src/lib.rs	2024-03-01 12:00:00.000000000 +0100
+@@ -1 +1,2 @@
+ pub fn value() -> u32 { 1 }
++pub mod added;
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn value() -> u32 { 1 }\n")
+        .file("dep/gone.txt", "gone\n")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: gone.txt -> gone.txt\n\
+             Patched dep: src/added.rs -> src/added.rs\n\
+             Patched dep: src/lib.rs\n",
+        )
+        .run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    assert!(!copy.join("gone.txt").exists());
+    let added = std::fs::read_to_string(copy.join("src/added.rs"))
+        .expect("Unable to read created file");
+    assert_eq!(added, "pub fn added() {}\n");
+    let lib = std::fs::read_to_string(copy.join("src/lib.rs"))
+        .expect("Unable to read patched file");
+    assert_eq!(lib, "pub fn value() -> u32 { 1 }\npub mod added;\n");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_diff_dirs_round_trip() {
+    let p = project()
+        .file("old/src/lib.rs", "pub fn value() -> u32 { 1 }\n")
+        .file("old/gone.txt", "gone\n")
+        .file("old/same.txt", "same\n")
+        .file("old/no-newline.txt", "first\nsecond")
+        .file("old/empty-gone.txt", "")
+        .file(
+            "new/src/lib.rs",
+            "pub fn value() -> u32 { 2 }\npub mod added;\n",
+        )
+        .file("new/src/added.rs", "pub fn added() {}\n")
+        .file("new/same.txt", "same\n")
+        .file("new/no-newline.txt", "first\npatched")
+        .file("new/src/empty.rs", "")
+        .build();
+
+    let diff = cargo_patch::diff_dirs(&p.root().join("old"), &p.root().join("new"));
+    assert_eq!(
+        diff,
+        "--- gone.txt\n\
+         +++ /dev/null\n\
+         @@ -1 +0,0 @@\n\
+         -gone\n\
+         --- no-newline.txt\n\
+         +++ no-newline.txt\n\
+         @@ -1,2 +1,2 @@\n \
+         first\n\
+         -second\n\
+         \\ No newline at end of file\n\
+         +patched\n\
+         \\ No newline at end of file\n\
+         --- /dev/null\n\
+         +++ src/added.rs\n\
+         @@ -0,0 +1 @@\n\
+         +pub fn added() {}\n\
+         --- src/lib.rs\n\
+         +++ src/lib.rs\n\
+         @@ -1 +1,2 @@\n\
+         -pub fn value() -> u32 { 1 }\n\
+         +pub fn value() -> u32 { 2 }\n\
+         +pub mod added;\n\
+         diff --git empty-gone.txt empty-gone.txt\n\
+         deleted file mode 100644\n\
+         diff --git src/empty.rs src/empty.rs\n\
+         new file mode 100644\n"
+    );
+
+    std::fs::write(p.root().join("round-trip.patch"), diff).unwrap();
+    let _ = cargo_patch::apply_to_dir(
+        &p.root().join("old"),
+        &[cargo_patch::PatchSpec::new(
+            p.root().join("round-trip.patch"),
+        )],
+    )
+    .expect("Unable to apply generated patch");
+    assert_eq!(
+        cargo_patch::diff_dirs(&p.root().join("old"), &p.root().join("new")),
+        ""
+    );
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "orchestrator")]
+
+use cargo::{core::Workspace, util::cache_lock::CacheLockMode, GlobalContext};
+use cargo_patch::{patch_with_config, patch_workspace, Options};
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, paths, project};
+use std::{env, fs};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_preloaded_workspace() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    // like an xtask, which set up cargo itself
+    env::set_current_dir(p.root()).expect("Unable to enter project");
+    env::set_var("CARGO_HOME", paths::home().join(".cargo"));
+    let mut gctx = GlobalContext::default().expect("Unable to create context");
+    gctx.configure(0, true, None, false, false, true, &None, &[], &[])
+        .expect("Unable to configure context");
+    let patched = p.build_dir().join("patch/dep-0.5.0/src/lib.rs");
+
+    patch_with_config(&gctx, &Options::default()).expect("Failed while patching");
+    assert_eq!(
+        fs::read_to_string(&patched).expect("Unable to read patched file"),
+        "pub fn patched() {}\n"
+    );
+
+    let workspace = Workspace::new(&p.root().join("Cargo.toml"), &gctx)
+        .expect("Unable to load workspace");
+    // the lock held by the caller is shared
+    let _lock = gctx
+        .acquire_package_cache_lock(CacheLockMode::DownloadExclusive)
+        .expect("Unable to lock package cache");
+    fs::remove_dir_all(p.build_dir().join("patch")).expect("Unable to remove copy");
+    patch_workspace(&workspace, &Options::default()).expect("Failed while patching");
+    assert_eq!(
+        fs::read_to_string(&patched).expect("Unable to read patched file"),
+        "pub fn patched() {}\n"
+    );
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package};
+use serde_json::Value;
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_emit_hashes() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    let read = || {
+        let hashes = fs::read_to_string(p.root().join("hashes.json"))
+            .expect("Missing hashes");
+        serde_json::from_str::<Value>(&hashes).expect("Invalid hashes")
+    };
+    p.process(common::cargo_patch_exe())
+        .args(&["--emit-hashes", "hashes.json"])
+        .run();
+    let hashes = read();
+    assert_eq!(hashes["version"], 1);
+    assert_eq!(hashes["packages"][0]["name"], "bar");
+    assert_eq!(hashes["packages"][0]["path"], "target/patch/bar-0.1.0");
+    let hash = hashes["packages"][0]["hash"].clone();
+    assert!(hash
+        .as_str()
+        .is_some_and(|hash| hash.starts_with("sha256:")));
+
+    // patching again from scratch gives the same hash
+    fs::remove_dir_all(p.build_dir().join("patch")).expect("Unable to remove copy");
+    p.process(common::cargo_patch_exe())
+        .args(&["--emit-hashes", "hashes.json"])
+        .run();
+    assert_eq!(read()["packages"][0]["hash"], hash);
+
+    fs::write(
+        p.root().join("test.patch"),
+        patch.replace("+PATCHED", "+CHANGED"),
+    )
+    .expect("Unable to write patch");
+    p.process(common::cargo_patch_exe())
+        .args(&["--emit-hashes", "hashes.json"])
+        .run();
+    assert_ne!(read()["packages"][0]["hash"], hash);
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "orchestrator")]
+
 mod common;
 
 use cargo_test_macro::cargo_test;
@@ -68,6 +70,9 @@ fn patch_empty_missing_dependency() {
         .build();
 
     p.process(common::cargo_patch_exe())
-        .with_stderr("Unable to find package serde in dependencies\n")
+        .with_stderr(
+            "Unable to find package serde in dependencies\n\
+             warning: patch entry for serde matches no dependency\n",
+        )
         .run();
 }
@@ -0,0 +1,60 @@
+use cargo_patch::{apply_to_dir, Error, PatchSource, PatchSpec};
+use cargo_test_macro::cargo_test;
+use cargo_test_support::project;
+use std::path::PathBuf;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_engine_apply_to_dir() {
+    let diff = r#"diff --git a/config.h b/config.h
+--- a/config.h
++++ b/config.h
+@@ -1 +1 @@
+-#define ENABLED 0
++#define ENABLED 1
+diff --git a/old.c b/new.c
+similarity index 100%
+rename from old.c
+rename to new.c
+"#;
+    let p = project()
+        .file("vendor/lib/config.h", "#define ENABLED 0\n")
+        .file("vendor/lib/old.c", "int old;\n")
+        .file("fix.patch", diff)
+        .build();
+
+    let mut patch = PatchSpec::new(p.root().join("fix.patch"));
+    patch.source = PatchSource::GitDiff;
+    let report = apply_to_dir(&p.root().join("vendor/lib"), &[patch])
+        .expect("Unable to apply diff");
+    assert_eq!(
+        report.files,
+        [PathBuf::from("config.h"), PathBuf::from("new.c")]
+    );
+    let config = std::fs::read_to_string(p.root().join("vendor/lib/config.h"))
+        .expect("Unable to read patched file");
+    assert_eq!(config, "#define ENABLED 1\n");
+    assert!(!p.root().join("vendor/lib/old.c").exists());
+    assert!(p.root().join("vendor/lib/new.c").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_engine_apply_to_dir_escape() {
+    let diff = "--- /dev/null\n+++ ../escape.c\n@@ -0,0 +1 @@\n+int escape;\n";
+    let p = project()
+        .file("vendor/lib/config.h", "")
+        .file("escape.patch", diff)
+        .build();
+
+    let err = apply_to_dir(
+        &p.root().join("vendor/lib"),
+        &[PatchSpec::new(p.root().join("escape.patch"))],
+    )
+    .expect_err("Diff must not escape the directory");
+    assert!(matches!(
+        err,
+        Error::PathEscape { ref path, .. } if path == &PathBuf::from("../escape.c")
+    ));
+    assert!(!p.root().join("vendor/escape.c").exists());
+}
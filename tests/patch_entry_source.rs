@@ -0,0 +1,57 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_entry_source() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .file("second", "old\n")
+        .publish();
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        source = "GitDiff"
+        patches = [
+            "first.patch",
+            { path = "second.patch", source = "Default" },
+        ]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "first.patch",
+            "diff --git a/first b/first\nnew file mode 100644\n--- /dev/null\n+++ b/first\n@@ -0,0 +1 @@\n+new\n",
+        )
+        .file(
+            "second.patch",
+            "--- second\n+++ second\n@@ -1 +1 @@\n-old\n+new\n",
+        )
+        .build();
+
+    // the string item is read as a git diff without its `b/` prefix, the
+    // table item as a plain diff
+    p.process(common::cargo_patch_exe())
+        .with_stdout_contains("Patched bar: /dev/null -> first")
+        .with_stdout_contains("Patched bar: second")
+        .run();
+    for file in ["first", "second"] {
+        let patched =
+            fs::read_to_string(p.build_dir().join("patch/bar-0.1.0").join(file))
+                .expect("Unable to read patched file");
+        assert_eq!(patched, "new\n");
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "orchestrator")]
+
 mod common;
 
 use cargo_test_macro::cargo_test;
@@ -40,7 +42,10 @@ fn patch_context_mismatch() {
         .build();
 
     p.process(common::cargo_patch_exe())
-        .with_stderr("Error: failed to apply patch to LICENSE-MIT on line 8")
+        .with_stderr(
+            "Error: failed to apply patch to LICENSE-MIT on line 8 (line 8 after \
+             patching) in hunk 1 `@@ -8,9 +8,7 @@` of test.patch",
+        )
         .with_status(1)
         .run();
 }
@@ -69,7 +74,10 @@ fn patch_deleted_mismatch() {
         .build();
 
     p.process(common::cargo_patch_exe())
-        .with_stderr("Error: failed to apply patch to LICENSE-MIT on line 12")
+        .with_stderr(
+            "Error: failed to apply patch to LICENSE-MIT on line 12 (line 11 after \
+             patching) in hunk 1 `@@ -8,9 +8,7 @@` of test.patch",
+        )
         .with_status(1)
         .run();
 }
@@ -0,0 +1,90 @@
+#![cfg(feature = "orchestrator")]
+
+use cargo_patch::{Event, Options, Patcher};
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, paths, project};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_events() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch", "broken.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    let broken = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn missing() {}\n+pub fn other() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .file("broken.patch", broken)
+        .build();
+
+    // the library works on the current directory like a build script
+    env::set_current_dir(p.root()).expect("Unable to enter project");
+    env::set_var("CARGO_HOME", paths::home().join(".cargo"));
+    let mut options = Options::default();
+    options.offline = true;
+    let events = Arc::new(Mutex::new(vec![]));
+    let collected = Arc::clone(&events);
+    let result = Patcher::new(options)
+        .on_event(move |event| collected.lock().expect("poisoned").push(event))
+        .patch();
+    assert!(result.is_err());
+
+    let events = events.lock().expect("poisoned");
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    assert!(matches!(
+        &events[0],
+        Event::PackageResolved { name, version } if name == "dep" && version.to_string() == "0.5.0"
+    ));
+    assert!(matches!(
+        &events[1],
+        Event::PackageCopied { name, path }
+            if name == "dep" && path.canonicalize().ok() == copy.canonicalize().ok()
+    ));
+    assert_eq!(
+        events[2],
+        Event::HunkApplied {
+            name: "dep".to_owned(),
+            file: PathBuf::from("src/lib.rs"),
+            line: 1,
+            offset: 0,
+            fuzz: 0,
+        }
+    );
+    assert_eq!(
+        events[3],
+        Event::PatchApplied {
+            name: "dep".to_owned(),
+            patch: PathBuf::from("test.patch"),
+        }
+    );
+    assert!(matches!(
+        &events[4],
+        Event::PatchFailed { name, patch, error }
+            if name == "dep" && patch.as_path() == Path::new("broken.patch") && error.contains("changed by test.patch")
+    ));
+    assert_eq!(events.len(), 5);
+}
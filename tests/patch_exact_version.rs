@@ -0,0 +1,79 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+const PATCH: &str = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+
+fn workspace(entry: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch.dep]
+        {entry}
+        patches = ["test.patch"]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_manifest("dep", "1.0.0-alpha.1+build5"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("test.patch", PATCH)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_pre_release_requirement() {
+    let p = workspace(r#"version = "1.0""#);
+    p.process(common::cargo_patch_exe())
+        .with_stdout("No patches found\n")
+        .with_stderr_contains(
+            "Unable to find package dep in dependencies, found version(s) 1.0.0-alpha.1+build5 [..]",
+        )
+        .run();
+
+    let p = workspace(r#"version = "=1.0.0-alpha.1""#);
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_exact_version() {
+    let p = workspace("version = \"1.0.0-alpha.1+build5\"\nexact-version = true");
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+        .run();
+
+    // build metadata is part of the comparison
+    let p = workspace("version = \"1.0.0-alpha.1+build6\"\nexact-version = true");
+    p.process(common::cargo_patch_exe())
+        .with_stdout("No patches found\n")
+        .with_stderr_contains("Unable to find package dep in dependencies[..]")
+        .run();
+
+    let p = workspace("version = \"^1.0\"\nexact-version = true");
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Version must be a semver version with `exact-version = true`: \"^1.0\"",
+        )
+        .run();
+}
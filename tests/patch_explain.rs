@@ -0,0 +1,79 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::project;
+
+fn create(file: &str) -> String {
+    format!("--- /dev/null\n+++ {file}\n@@ -0,0 +1 @@\n+PATCHED\n")
+}
+
+fn member(name: &str, patches: &str) -> String {
+    format!(
+        r#"
+        [package]
+        name = "{name}"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        dep = {{ path = "../dep" }}
+
+        [package.metadata.patch.dep]
+        patches = [{patches}]
+    "#
+    )
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_explain() {
+    // members are listed out of order, the workspace entry comes first
+    let manifest = r#"
+        [workspace]
+        members = ["member-b", "member-a"]
+        exclude = ["dep"]
+
+        [workspace.metadata.patch.dep]
+        patches = ["a.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file(
+            "member-b/Cargo.toml",
+            &member("member-b", r#""b.patch", "a.patch""#),
+        )
+        .file("member-b/src/lib.rs", "")
+        .file("member-a/Cargo.toml", &member("member-a", r#""c.patch""#))
+        .file("member-a/src/lib.rs", "")
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("a.patch", &create("A.txt"))
+        .file("b.patch", &create("B.txt"))
+        .file("c.patch", &create("C.txt"))
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--explain")
+        .with_stdout(
+            "dep v0.5.0 ([..])\n    \
+             1. a.patch\n    \
+             2. c.patch\n    \
+             3. b.patch\n",
+        )
+        .run();
+    assert!(!p.build_dir().join("patch").exists());
+
+    // a.patch creates a file and would fail if it was applied twice
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: /dev/null -> A.txt\n\
+             Patched dep: /dev/null -> C.txt\n\
+             Patched dep: /dev/null -> B.txt\n",
+        )
+        .run();
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+use std::{fs, process::Command};
+
+const FIRST: &str =
+    "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn first() {}\n";
+
+fn dep_project(git_init: bool) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch.dep]
+        git-init = {git_init}
+        version-suffix = true
+        patches = ["first.patch"]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("first.patch", FIRST)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_export() {
+    let p = dep_project(false);
+
+    p.process(common::cargo_patch_exe())
+        .arg("export")
+        .arg("dep")
+        .arg("--out")
+        .arg("exported")
+        .with_stdout_contains("Exported 1 patch(es) of dep v0.5.0 to exported")
+        .run();
+
+    let exported =
+        fs::read_to_string(p.root().join("exported/0001-Apply-first.patch.patch"))
+            .expect("Unable to read exported patch");
+    assert!(exported.starts_with(
+        "From 0000000000000000000000000000000000000000 Mon Sep 17 00:00:00 2001\n\
+         From: cargo-patch <cargo-patch@localhost>\n"
+    ));
+    assert!(exported.contains("Subject: [PATCH] Apply first.patch\n"));
+    assert!(exported.contains("-pub fn dep() {}\n+pub fn first() {}\n"));
+    // the scratch copy is removed and the manifest adjustment isn't exported
+    assert!(!p.build_dir().join("patch/.export").exists());
+    assert_eq!(
+        fs::read_dir(p.root().join("exported"))
+            .expect("Unable to list exported patches")
+            .count(),
+        1
+    );
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_export_git_init() {
+    let p = dep_project(true);
+    p.process(common::cargo_patch_exe()).run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    fs::write(
+        copy.join("src/lib.rs"),
+        "pub fn first() {}\npub fn second() {}\n",
+    )
+    .expect("Unable to write to the copy");
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=Jane Doe",
+            "-c",
+            "user.email=jane@example.com",
+            "commit",
+            "--quiet",
+            "--all",
+            "-m",
+            "Add a second function",
+        ])
+        .current_dir(&copy)
+        .status()
+        .expect("Unable to run git");
+    assert!(status.success());
+
+    p.process(common::cargo_patch_exe())
+        .arg("export")
+        .arg("dep")
+        .arg("--out")
+        .arg("exported")
+        .with_stdout_contains("Exported 2 patch(es) of dep v0.5.0 to exported")
+        .run();
+
+    let mut files = fs::read_dir(p.root().join("exported"))
+        .expect("Unable to list exported patches")
+        .map(|entry| entry.expect("Unable to read entry").file_name())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            "0001-Apply-first.patch.patch",
+            "0002-Add-a-second-function.patch"
+        ]
+    );
+    let second = fs::read_to_string(
+        p.root().join("exported/0002-Add-a-second-function.patch"),
+    )
+    .expect("Unable to read exported patch");
+    assert!(second.contains("From: Jane Doe <jane@example.com>\n"));
+    assert!(second.contains("+pub fn second() {}\n"));
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_extends() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["recipe"]
+
+        [workspace.metadata.patch-config]
+        extends = "recipe/recipes/patches.toml"
+
+        [dependencies]
+        recipe = { path = "recipe" }
+        serde = "=1.0.110"
+    "#;
+    let recipe_manifest = r#"
+        [package]
+        name = "recipe"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+    "#;
+    let recipe = r#"
+        [patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("recipe/Cargo.toml", recipe_manifest)
+        .file("recipe/src/lib.rs", "")
+        .file("recipe/recipes/patches.toml", recipe)
+        .file("recipe/recipes/test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+    let patched = p
+        .build_dir()
+        .join("patch")
+        .join("serde-1.0.110")
+        .join("PATCHED.txt");
+    assert!(patched.exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_extends_missing_crate() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace.metadata.patch-config]
+        extends = "recipe/patches.toml"
+
+        [dependencies]
+        serde = "=1.0.110"
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains("Error: Unable to find package recipe in dependencies")
+        .with_status(1)
+        .run();
+}
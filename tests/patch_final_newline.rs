@@ -0,0 +1,59 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_final_newline() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        final-newline = "never"
+        patches = [
+            "never.patch",
+            { path = "always.patch", final-newline = "always" },
+            { path = "preserve.patch", final-newline = "preserve" },
+        ]
+    "#;
+    let never = "--- never.txt\n+++ never.txt\n@@ -1 +1 @@\n-old\n+new\n";
+    let always = "--- always.txt\n+++ always.txt\n@@ -1 +1 @@\n-old\n\\ No newline at end of file\n+new\n\\ No newline at end of file\n";
+    // the marker removes the newline the original file ends with
+    let preserve = "--- preserve.txt\n+++ preserve.txt\n@@ -1,2 +1,2 @@\n first\n-old\n+new\n\\ No newline at end of file\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("dep/never.txt", "old\n")
+        .file("dep/always.txt", "old")
+        .file("dep/preserve.txt", "first\nold\n")
+        .file("never.patch", never)
+        .file("always.patch", always)
+        .file("preserve.patch", preserve)
+        .build();
+
+    p.process(common::cargo_patch_exe()).run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    let read = |file| fs::read_to_string(copy.join(file)).expect("Unable to read");
+    assert_eq!(read("never.txt"), "new");
+    assert_eq!(read("always.txt"), "new\n");
+    assert_eq!(read("preserve.txt"), "first\nnew");
+}
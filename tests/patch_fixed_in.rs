@@ -0,0 +1,67 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{project, registry::Package, Project};
+
+fn gen_project(fixed_in: &str) -> Project {
+    for version in ["0.1.0", "0.1.1", "0.2.0"] {
+        Package::new("bar", version)
+            .file("src/lib.rs", "pub fn bar() {}\n")
+            .publish();
+    }
+
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        bar = "0.1"
+
+        [package.metadata.patch.bar]
+        patches = [
+            {{ path = "test.patch", description = "Fixes bar", fixed-in = "{fixed_in}" }},
+        ]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "test.patch",
+            "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n",
+        )
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_fixed_in_resolved() {
+    let p = gen_project(">=0.1.1");
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "warning: Patch test.patch of bar is fixed in >=0.1.1, which v0.1.1 matches, so it is no longer needed (Fixes bar)",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_fixed_in_upstream() {
+    let p = gen_project(">=0.2.0");
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_does_not_contain("[..]fixed in[..]")
+        .run();
+    p.process(common::cargo_patch_exe())
+        .arg("--check-upstream")
+        .with_stderr_contains(
+            "warning: Patch test.patch of bar is fixed in >=0.2.0, which the release v0.2.0 matches. Update bar and drop the patch (Fixes bar)",
+        )
+        .run();
+}
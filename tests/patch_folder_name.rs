@@ -0,0 +1,131 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+const PATCH: &str = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_folder_name() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        folder-name = "dep-patched"
+        patches = ["test.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+        .run();
+
+    let patch = p.build_dir().join("patch");
+    assert!(patch.join("dep-patched/PATCHED.txt").exists());
+    assert!(!patch.join("dep-0.5.0").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_folder_name_conflict() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep", "other"]
+
+        [dependencies]
+        dep = { path = "dep" }
+        other = { path = "other" }
+
+        [package.metadata.patch.dep]
+        folder-name = "patched"
+        patches = []
+
+        [package.metadata.patch.other]
+        folder-name = "patched"
+        patches = []
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file(
+            "other/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("other"),
+        )
+        .file("other/src/lib.rs", "")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: Folder name patched is used by both dep [..] and other [..]",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_folder_name_invalid() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        folder-name = "../escape"
+        patches = []
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Folder name must be a directory name not starting with a dot: \"../escape\"",
+        )
+        .run();
+    assert!(!p.build_dir().join("escape").exists());
+}
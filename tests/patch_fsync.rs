@@ -0,0 +1,77 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+use std::fs;
+
+fn fsync_project(patch_config: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [workspace.metadata.patch-config]
+        {patch_config}
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#
+    );
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build()
+}
+
+fn assert_patched(p: &Project) {
+    let dir = p.build_dir().join("patch/dep-0.5.0/src");
+    assert_eq!(
+        fs::read_to_string(dir.join("lib.rs")).expect("Unable to read lib.rs"),
+        "pub fn patched() {}\n"
+    );
+    let files = fs::read_dir(&dir)
+        .expect("Unable to list copy")
+        .map(|entry| entry.expect("Unable to read entry").file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(files, ["lib.rs"]);
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_fsync() {
+    let p = fsync_project("fsync = true");
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+    assert_patched(&p);
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_fsync_cargo_config() {
+    let p = fsync_project("");
+
+    p.process(common::cargo_patch_exe())
+        .env("CARGO_CARGO_PATCH_FSYNC", "true")
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+    assert_patched(&p);
+}
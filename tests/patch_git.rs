@@ -1,3 +1,5 @@
+#![cfg(feature = "orchestrator")]
+
 mod common;
 
 use cargo_test_macro::cargo_test;
@@ -135,7 +137,7 @@ fn patch_git_detailed() {
     let license_mit = p
         .build_dir()
         .join("patch")
-        .join("serde")
+        .join("serde-1.0.110")
         .join("LICENSE-MIT");
     let licenses =
         std::fs::read_to_string(license_mit).expect("Unable to read license file");
@@ -197,7 +199,7 @@ fn patch_git_workspace_root() {
     let license_mit = p
         .build_dir()
         .join("patch")
-        .join("serde")
+        .join("serde-1.0.110")
         .join("LICENSE-MIT");
     let licenses =
         std::fs::read_to_string(license_mit).expect("Unable to read license file");
@@ -253,7 +255,7 @@ fn patch_git_workspace_metadata() {
     let license_mit = p
         .build_dir()
         .join("patch")
-        .join("serde")
+        .join("serde-1.0.110")
         .join("LICENSE-MIT");
     let licenses =
         std::fs::read_to_string(license_mit).expect("Unable to read license file");
@@ -0,0 +1,108 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{git, main_file, paths, project, Project};
+use std::{fs, path::Path, process::Command};
+
+fn git_cmd(repo: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .current_dir(repo)
+        .args([
+            "-c",
+            "user.name=upstream",
+            "-c",
+            "user.email=upstream@example.com",
+        ])
+        .args(args)
+        .output()
+        .expect("Unable to run git");
+    assert!(output.status.success(), "git {args:?} failed");
+    String::from_utf8_lossy(&output.stdout).trim().to_owned()
+}
+
+/// Returns a project depending on `dep` and patching it with the fix of an
+/// upstream commit, which is followed by another commit.
+fn project_with_upstream_fix(config: &str) -> Project {
+    let upstream = git::repo(&paths::root().join("upstream"))
+        .file("Cargo.toml", &cargo_test_support::basic_lib_manifest("dep"))
+        .file("src/lib.rs", "pub fn dep() {}\n")
+        .build();
+    fs::write(upstream.root().join("src/lib.rs"), "pub fn fixed() {}\n")
+        .expect("Unable to write fix");
+    git_cmd(upstream.root(), &["commit", "-qam", "Fix dep"]);
+    let rev = git_cmd(upstream.root(), &["rev-parse", "HEAD"]);
+    fs::write(upstream.root().join("README.md"), "unreleased\n")
+        .expect("Unable to write readme");
+    git_cmd(upstream.root(), &["add", "README.md"]);
+    git_cmd(upstream.root(), &["commit", "-qm", "Add readme"]);
+
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch.dep]
+        patches = [{{ git = "{}", rev = "{rev}" }}]
+
+        {config}
+    "#,
+        upstream.url()
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .build()
+}
+
+/// Returns whether the repository commits were fetched into is shallow.
+fn fetched_shallow(p: &Project) -> bool {
+    let repos = fs::read_dir(p.root().join("target/patch-cache/git"))
+        .expect("Missing fetched repository")
+        .map(|entry| entry.expect("Unable to read repository").path())
+        .collect::<Vec<_>>();
+    assert_eq!(repos.len(), 1);
+    repos[0].join("shallow").exists()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_git_commit_shallow() {
+    let p = project_with_upstream_fix("");
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+    let lib = fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/lib.rs"))
+        .expect("Unable to read patched file");
+    assert_eq!(lib, "pub fn fixed() {}\n");
+    assert!(!p.build_dir().join("patch/dep-0.5.0/README.md").exists());
+    assert!(fetched_shallow(&p));
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_git_commit_full_fetch() {
+    let p = project_with_upstream_fix(
+        "[package.metadata.patch-config]\ngit-shallow = false",
+    );
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+    assert!(!fetched_shallow(&p));
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{git, main_file, paths, project, Project};
+use std::fs;
+
+/// Returns the provenance file of the only patched copy. Git dependencies
+/// are copied to a directory named after the checked out commit.
+fn provenance(p: &Project) -> String {
+    let copy = fs::read_dir(p.build_dir().join("patch"))
+        .expect("Unable to read patch folder")
+        .next()
+        .expect("Missing patched copy")
+        .expect("Unable to read patch folder");
+    fs::read_to_string(copy.path().join(".cargo-patch.json"))
+        .expect("Unable to read provenance")
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_git_drift() {
+    let repo = git::repo(&paths::root().join("dep"))
+        .file("Cargo.toml", &cargo_test_support::basic_lib_manifest("dep"))
+        .file("src/lib.rs", "pub fn value() -> u32 {\n    1\n}\n")
+        .build();
+    let first = repo.revparse_head();
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        dep = {{ git = "{}" }}
+
+        [package.metadata.patch.dep]
+        patches = [
+            "test.patch"
+        ]
+    "#,
+        repo.url()
+    );
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+        .with_stderr("")
+        .run();
+    assert!(provenance(&p).contains(&format!(r#""git-commit": "{first}""#)));
+
+    fs::write(repo.root().join("README.md"), "moved").expect("Unable to write");
+    let git_repo = git::init(repo.root());
+    git::add(&git_repo);
+    let second = git::commit(&git_repo).to_string();
+    let message = format!(
+        "dep v0.5.0 now resolves to commit {second}, but the patched copy was \
+         created from commit {first}. Check that the patches still match"
+    );
+
+    p.process(common::cargo_patch_exe())
+        .arg("--frozen-copy")
+        .with_stderr(format!("Error: {message}\n"))
+        .with_status(1)
+        .run();
+    assert!(provenance(&p).contains(&first));
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+        .with_stderr(format!("warning: {message}\n"))
+        .run();
+    assert!(provenance(&p).contains(&format!(r#""git-commit": "{second}""#)));
+}
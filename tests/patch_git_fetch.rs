@@ -0,0 +1,75 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{git, main_file, paths, project};
+use std::{env, fs};
+
+#[cfg(unix)]
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_git_fetch_with_cli() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let repo = git::repo(&paths::root().join("repo"))
+        .file(
+            "Cargo.toml",
+            &cargo_test_support::basic_manifest("dep", "0.6.0"),
+        )
+        .file("src/lib.rs", "pub fn dep() {}\n")
+        .build();
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        dep = {{ git = "{}" }}
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#,
+        repo.url()
+    );
+    let patch =
+        "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn cli() {}\n";
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(".cargo/config.toml", "[net]\ngit-fetch-with-cli = true\n")
+        .file("test.patch", patch)
+        .build();
+
+    // a git wrapper recording that cargo fetched with the command line tool
+    let bin = paths::root().join("bin");
+    let log = paths::root().join("git.log");
+    fs::create_dir_all(&bin).expect("Unable to create bin directory");
+    let git = bin.join("git");
+    fs::write(
+        &git,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> {}\nPATH={} exec git \"$@\"\n",
+            log.display(),
+            env::var("PATH").expect("PATH not set")
+        ),
+    )
+    .expect("Unable to write git wrapper");
+    fs::set_permissions(&git, fs::Permissions::from_mode(0o755))
+        .expect("Unable to make git wrapper executable");
+    let path = env::join_paths(
+        std::iter::once(bin)
+            .chain(env::split_paths(&env::var_os("PATH").unwrap_or_default())),
+    )
+    .expect("Unable to build PATH");
+
+    p.process(common::cargo_patch_exe())
+        .env("PATH", path)
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+
+    let log = fs::read_to_string(log).expect("git was not invoked");
+    assert!(log.lines().any(|line| line.starts_with("fetch")), "{log}");
+}
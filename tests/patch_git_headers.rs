@@ -0,0 +1,61 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_git_extended_headers() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            { path = "test.patch", source = "GitDiff" }
+        ]
+    "#;
+    let patch = r#"diff --git a/EMPTY b/EMPTY
+new file mode 100644
+index 0000000..e69de29
+diff --git a/README.md b/README.txt
+similarity index 100%
+rename from README.md
+rename to README.txt
+diff --git a/LICENSE-MIT b/LICENSE-MIT
+index 31aa793..8bd9e3e 100644
+--- a/LICENSE-MIT
++++ b/LICENSE-MIT
+@@ -1,3 +1,3 @@
+-Permission is hereby granted, free of charge, to any
++PATCHED
+ person obtaining a copy of this software and associated
+ documentation files (the "Software"), to deal in the
+ Software without restriction, including without
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched serde: LICENSE-MIT\n\
+             Patched serde: /dev/null -> EMPTY\n\
+             Patched serde: README.md -> README.txt\n",
+        )
+        .run();
+
+    let serde = p.build_dir().join("patch").join("serde-1.0.110");
+    assert!(serde.join("EMPTY").exists());
+    assert!(serde.join("README.txt").exists());
+    assert!(!serde.join("README.md").exists());
+}
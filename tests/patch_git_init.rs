@@ -0,0 +1,87 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+use std::process::Command;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_git_init() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        git-init = true
+        patches = [
+            "first.patch",
+            { path = "second.patch", source = "GitDiff" },
+        ]
+    "#;
+    let first = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn first() {}\n";
+    // output of `git format-patch`
+    let second = concat!(
+        r#"From 0123456789abcdef0123456789abcdef01234567 Mon Sep 17 00:00:00 2001
+From: Jane Doe <jane@example.com>
+Date: Fri, 1 Mar 2024 12:00:00 +0100
+Subject: [PATCH] Add a second function
+
+Needed by the example.
+---
+ src/lib.rs | 1 +
+ 1 file changed, 1 insertion(+)
+
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1,2 @@
+ pub fn first() {}
++pub fn second() {}
+"#,
+        "-- \n2.44.0\n",
+    );
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("first.patch", first)
+        .file("second.patch", second)
+        .build();
+
+    p.process(common::cargo_patch_exe()).run();
+
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(p.build_dir().join("patch/dep-0.5.0"))
+            .output()
+            .expect("Unable to run git");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    assert_eq!(
+        git(&["log", "--format=%an|%s|%b"]),
+        "Jane Doe|Add a second function|Needed by the example.\n\
+         \n\
+         cargo-patch|Apply first.patch|\n\
+         cargo-patch|Pristine sources of dep v0.5.0|\n"
+    );
+    assert_eq!(
+        git(&["log", "-1", "--format=%ad", "--date=iso"]),
+        "2024-03-01 12:00:00 +0100\n"
+    );
+    assert_eq!(git(&["status", "--porcelain"]), "?? .cargo-patch.json\n");
+}
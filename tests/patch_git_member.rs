@@ -0,0 +1,120 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{git, main_file, paths, project};
+
+#[allow(deprecated)]
+fn repo() -> git::Repository {
+    git::repo(&paths::root().join("repo"))
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["member"]
+
+            [workspace.package]
+            version = "0.1.0"
+            license = "MIT"
+        "#,
+        )
+        .file("LICENSE", "MIT")
+        .file("docs/notes.txt", "notes\n")
+        .file(
+            "member/Cargo.toml",
+            r#"
+            [package]
+            name = "member"
+            version.workspace = true
+            license.workspace = true
+        "#,
+        )
+        .file("member/src/lib.rs", "pub fn value() -> u32 {\n    1\n}\n")
+        .build()
+}
+
+fn manifest(url: &str, copy_repo_root: bool) -> String {
+    format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        member = {{ git = "{url}" }}
+
+        [package.metadata.patch.member]
+        copy-repo-root = {copy_repo_root}
+        patches = [
+            "test.patch"
+        ]
+    "#
+    )
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_git_member() {
+    let repo = repo();
+    let url = repo.url().to_string();
+    let patch = r#"--- src/lib.rs
++++ src/lib.rs
+@@ -1,3 +1,3 @@
+ pub fn value() -> u32 {
+-    1
++    2
+ }
+"#;
+    let p = project()
+        .file("Cargo.toml", &manifest(&url, false))
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched member: src/lib.rs\n")
+        .run();
+    let copy = p.root().join("target/patch/member-0.1.0");
+    assert!(copy.join("LICENSE").exists());
+    assert!(copy.join("Cargo.toml").exists());
+    assert!(!copy.join("docs").exists());
+    let lib = std::fs::read_to_string(copy.join("member/src/lib.rs"))
+        .expect("Unable to read patched file");
+    assert!(lib.contains("    2"));
+
+    let mut manifest = manifest(&url, false);
+    manifest.push_str(&format!(
+        "\n[patch.\"{url}\"]\nmember = {{ path = \"target/patch/member-0.1.0/member\" }}\n"
+    ));
+    p.change_file("Cargo.toml", &manifest);
+    p.process("cargo").arg("build").run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_git_member_repo_root() {
+    let repo = repo();
+    let url = repo.url().to_string();
+    let patch = r#"--- docs/notes.txt
++++ docs/notes.txt
+@@ -1 +1 @@
+-notes
++patched notes
+"#;
+    let p = project()
+        .file("Cargo.toml", &manifest(&url, true))
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched member: docs/notes.txt\n")
+        .run();
+    let copy = p.root().join("target/patch/member-0.1.0");
+    let notes = std::fs::read_to_string(copy.join("docs/notes.txt"))
+        .expect("Unable to read patched file");
+    assert_eq!(notes, "patched notes\n");
+    assert!(copy.join("member/src/lib.rs").exists());
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "orchestrator")]
+
 use cargo_test_macro::cargo_test;
 use cargo_test_support::{main_file, project, Execs, Project};
 
@@ -53,7 +55,7 @@ fn patch_file() {
     let license_mit = p
         .build_dir()
         .join("patch")
-        .join("serde")
+        .join("serde-1.0.110")
         .join("LICENSE-MIT");
     let license =
         std::fs::read_to_string(license_mit).expect("Unable to read license file");
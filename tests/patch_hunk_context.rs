@@ -0,0 +1,102 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+const LIB: &str = r#"pub struct Größe;
+
+impl Größe {
+    pub fn first(&self) -> &str {
+        "@@ one @@"
+    }
+
+    pub fn second(&self) -> &str {
+        "two"
+    }
+}
+"#;
+
+fn project_with(patch: &str) -> Project {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", LIB)
+        .file("test.patch", patch)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_hunk_context() {
+    let patch = r#"--- src/lib.rs
++++ src/lib.rs
+@@ -4,3 +4,3 @@ impl Größe {
+     pub fn first(&self) -> &str {
+-        "@@ one @@"
++        "@@ ONE @@"
+     }
+@@ -8,3 +8,3 @@ impl Größe { // "@@ -8,3 +8,3 @@"
+     pub fn second(&self) -> &str {
+-        "two"
++        "TWO"
+     }
+"#;
+    let p = project_with(patch);
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: src/lib.rs (impl Größe {, impl Größe { // \"@@ -8,3 +8,3 @@\")\n",
+        )
+        .run();
+
+    let lib =
+        std::fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/lib.rs"))
+            .expect("Unable to read patched file");
+    assert!(lib.contains("\"@@ ONE @@\""));
+    assert!(lib.contains("\"TWO\""));
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_hunk_context_failed() {
+    let patch = r#"--- src/lib.rs
++++ src/lib.rs
+@@ -8,3 +8,3 @@ impl Größe {
+     pub fn second(&self) -> &str {
+-        "three"
++        "THREE"
+     }
+"#;
+    let p = project_with(patch);
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr(
+            "Error: failed to apply patch to lib.rs on line 9 (line 9 after \
+             patching) in hunk 1 `@@ -8,3 +8,3 @@ impl Größe {` of test.patch\n",
+        )
+        .with_status(1)
+        .run();
+}
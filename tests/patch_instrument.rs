@@ -0,0 +1,76 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_instrument() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+    "#;
+    let value = r#"pub struct Value(u32);
+
+impl Value {
+    pub fn new(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+pub fn new() -> Value {
+    Value::new(0)
+}
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub mod value;\n")
+        .file("dep/src/value.rs", value)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["instrument", "dep", "--fn", "new"])
+        .with_stderr_contains("Error: Function new is ambiguous, use a longer path:")
+        .with_stderr_contains("    value::Value::new in src/value.rs")
+        .with_stderr_contains("    value::new in src/value.rs")
+        .with_status(1)
+        .run();
+    p.process(common::cargo_patch_exe())
+        .args(&["instrument", "dep", "--fn", "missing"])
+        .with_stderr_contains("Error: Unable to find function missing in dep")
+        .with_status(1)
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["instrument", "dep", "--fn", "dep::Value::new"])
+        .with_stdout("Patched dep: src/value.rs\n")
+        .run();
+    let patched =
+        std::fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/value.rs"))
+            .expect("Unable to read instrumented file");
+    assert_eq!(
+        patched,
+        value.replace(
+            "    pub fn new(value: u32) -> Self {\n",
+            "    pub fn new(value: u32) -> Self {\n        \
+             let _cargo_patch_guard = { struct Guard; impl Drop for Guard { \
+             fn drop(&mut self) { println!(\"[cargo-patch] exit dep::value::Value::new\"); } } \
+             println!(\"[cargo-patch] enter dep::value::Value::new\"); Guard };\n",
+        )
+    );
+}
@@ -0,0 +1,192 @@
+#![cfg(all(unix, feature = "orchestrator"))]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+use std::{
+    io::{BufRead, BufReader, Read},
+    process::Stdio,
+};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_interrupt_watch() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    let mut child = p
+        .process(common::cargo_patch_exe())
+        .arg("--watch")
+        .build_command()
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Unable to start cargo-patch");
+    let mut stdout = BufReader::new(child.stdout.take().expect("Missing stdout"));
+    let mut line = String::new();
+    while !line.starts_with("Watching") {
+        line.clear();
+        let read = stdout.read_line(&mut line).expect("Unable to read stdout");
+        assert!(read > 0, "cargo-patch stopped before watching");
+    }
+
+    let pid = i32::try_from(child.id()).expect("Invalid pid");
+    // SAFETY: sends a signal to the child started above
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGINT) }, 0);
+    let status = child.wait().expect("Unable to wait for cargo-patch");
+    let mut stderr = String::new();
+    child
+        .stderr
+        .take()
+        .expect("Missing stderr")
+        .read_to_string(&mut stderr)
+        .expect("Unable to read stderr");
+
+    assert_eq!(status.code(), Some(130), "{stderr}");
+    assert_eq!(stderr, "Error: Interrupted\n");
+    // the copy was complete before the interrupt
+    assert!(p
+        .build_dir()
+        .join("patch/dep-0.5.0/.cargo-patch.json")
+        .exists());
+}
+
+/// Waits until the process `pid` handles SIGINT itself.
+fn wait_for_handler(pid: u32) {
+    let status = format!("/proc/{pid}/status");
+    if !std::path::Path::new(&status).exists() {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        return;
+    }
+    for _ in 0..100 {
+        let caught = std::fs::read_to_string(&status)
+            .ok()
+            .and_then(|status| {
+                let mask = status
+                    .lines()
+                    .find_map(|line| line.strip_prefix("SigCgt:"))?;
+                u64::from_str_radix(mask.trim(), 16).ok()
+            })
+            .is_some_and(|mask| mask & (1 << (libc::SIGINT - 1)) != 0);
+        if caught {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    panic!("cargo-patch didn't install its signal handler");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_interrupt_apply() {
+    let p = project()
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+
+    let mut child = p
+        .process(common::cargo_patch_exe())
+        .args(&["apply", "dep", "-"])
+        .build_command()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Unable to start cargo-patch");
+    wait_for_handler(child.id());
+    let pid = i32::try_from(child.id()).expect("Invalid pid");
+    // SAFETY: sends a signal to the child started above
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGINT) }, 0);
+    // the diff is read completely before the interrupt is noticed
+    drop(child.stdin.take());
+    let output = child
+        .wait_with_output()
+        .expect("Unable to wait for cargo-patch");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(130), "{stderr}");
+    assert_eq!(stderr, "Error: Interrupted\n");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_interrupt_lock() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = []
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .build();
+
+    // lock the package cache like another cargo process would
+    let cargo_home = cargo_test_support::paths::home().join(".cargo");
+    std::fs::create_dir_all(&cargo_home).expect("Unable to create cargo home");
+    let lock = std::fs::File::create(cargo_home.join(".package-cache"))
+        .expect("Unable to create lock file");
+    lock.lock().expect("Unable to lock package cache");
+
+    let mut child = p
+        .process(common::cargo_patch_exe())
+        .build_command()
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Unable to start cargo-patch");
+    let mut stdout = BufReader::new(child.stdout.take().expect("Missing stdout"));
+    let mut line = String::new();
+    let _ = stdout.read_line(&mut line).expect("Unable to read stdout");
+    assert!(line.starts_with("Blocking"), "{line}");
+
+    let pid = i32::try_from(child.id()).expect("Invalid pid");
+    // SAFETY: sends a signal to the child started above
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGINT) }, 0);
+    // a single Ctrl-C stops waiting for the lock
+    let output = child
+        .wait_with_output()
+        .expect("Unable to wait for cargo-patch");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(130), "{stderr}");
+    assert_eq!(stderr, "Error: Interrupted\n");
+}
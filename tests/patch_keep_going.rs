@@ -0,0 +1,89 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+const BROKEN_PATCH: &str = r#"--- LICENSE-MIT
++++ LICENSE-MIT
+@@ -1,1 +1,1 @@
+-this line doesn't exist
++PATCHED
+"#;
+
+#[allow(deprecated)]
+fn project_with_broken_patch() -> Project {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["tail"]
+
+        [dependencies]
+        serde = "=1.0.110"
+        tail = { path = "tail" }
+
+        [package.metadata.patch.serde]
+        patches = [
+            "broken.patch"
+        ]
+
+        [package.metadata.patch.tail]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let tail_manifest = r#"
+        [package]
+        name = "tail"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("tail/Cargo.toml", tail_manifest)
+        .file("tail/src/lib.rs", "")
+        .file("broken.patch", BROKEN_PATCH)
+        .file("test.patch", patch)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_stops_on_failure() {
+    let p = project_with_broken_patch();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains("Error: failed to apply patch to LICENSE-MIT [..]")
+        .with_status(1)
+        .run();
+    assert!(!p.build_dir().join("patch").join("tail-0.1.0").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_keep_going() {
+    let p = project_with_broken_patch();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--keep-going")
+        .with_stdout("Patched tail: /dev/null -> PATCHED.txt\n")
+        .with_stderr_contains("Error: Failed to patch 1 package(s):")
+        .with_stderr_contains(
+            "    serde v1.0.110: failed to apply patch to LICENSE-MIT [..]",
+        )
+        .with_status(1)
+        .run();
+    assert!(p
+        .build_dir()
+        .join("patch")
+        .join("tail-0.1.0")
+        .join("PATCHED.txt")
+        .exists());
+}
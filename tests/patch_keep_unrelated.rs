@@ -0,0 +1,94 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_keep_unrelated() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [workspace.metadata.patch-config]
+        keep-unrelated = true
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .file("target/patch/foreign/keep.txt", "")
+        .file("target/patch/stale-1.0.0/.cargo-patch.json", "{}")
+        .file("target/patch/serde-1.0.110/OUTDATED.txt", "")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+    let patch_dir = p.build_dir().join("patch");
+    assert!(patch_dir.join("foreign").join("keep.txt").exists());
+    assert!(!patch_dir.join("stale-1.0.0").exists());
+    assert!(!patch_dir
+        .join("serde-1.0.110")
+        .join("OUTDATED.txt")
+        .exists());
+    assert!(patch_dir.join("serde-1.0.110").join("PATCHED.txt").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_keep_unrelated_repo_copy() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [workspace.metadata.patch-config]
+        keep-unrelated = true
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("test.patch", patch)
+        .file("target/patch/foreign/nested/keep.txt", "")
+        .file("target/patch/repo-1234/member/.cargo-patch.json", "{}")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+        .run();
+    // copies of whole repositories keep their provenance file further down
+    let patch_dir = p.build_dir().join("patch");
+    assert!(patch_dir.join("foreign/nested/keep.txt").exists());
+    assert!(!patch_dir.join("repo-1234").exists());
+    assert!(patch_dir.join("dep-0.5.0/PATCHED.txt").exists());
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_large_file() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let generated = (0..200_000)
+        .map(|line| format!("pub const C{line}: u32 = {line};\n"))
+        .collect::<String>();
+    let patch = "--- src/generated.rs\n+++ src/generated.rs\n@@ -100000,3 +100000,3 @@\n pub const C99999: u32 = 99999;\n-pub const C100000: u32 = 100000;\n+pub const C100000: u32 = 0;\n pub const C100001: u32 = 100001;\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "mod generated;\n")
+        .file("dep/src/generated.rs", &generated)
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: src/generated.rs\n")
+        .run();
+
+    let dir = p.build_dir().join("patch/dep-0.5.0/src");
+    let patched = fs::read_to_string(dir.join("generated.rs"))
+        .expect("Unable to read patched file");
+    assert_eq!(
+        patched,
+        generated.replace(
+            "pub const C100000: u32 = 100000;\n",
+            "pub const C100000: u32 = 0;\n"
+        )
+    );
+    let mut files = fs::read_dir(&dir)
+        .expect("Unable to list copy")
+        .map(|entry| entry.expect("Unable to read entry").file_name())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["generated.rs", "lib.rs"]);
+}
+
+#[cfg(unix)]
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_large_file_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch", "broken.patch"]
+    "#;
+    let patch =
+        "--- run.sh\n+++ run.sh\n@@ -1,2 +1,2 @@\n #!/bin/sh\n-echo old\n+echo new\n";
+    let broken = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn other() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("dep/run.sh", "#!/bin/sh\necho old\n")
+        .file("test.patch", patch)
+        .file("broken.patch", broken)
+        .build();
+    fs::set_permissions(
+        p.root().join("dep/run.sh"),
+        fs::Permissions::from_mode(0o755),
+    )
+    .expect("Unable to make script executable");
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: failed to apply patch to lib.rs on line 1 [..]",
+        )
+        .run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    let mode = fs::metadata(copy.join("run.sh"))
+        .expect("Unable to read script metadata")
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o755);
+    assert_eq!(
+        fs::read_to_string(copy.join("run.sh")).expect("Unable to read script"),
+        "#!/bin/sh\necho new\n"
+    );
+    // the failed file is left untouched without a temporary file
+    assert_eq!(
+        fs::read_to_string(copy.join("src/lib.rs")).expect("Unable to read lib.rs"),
+        "pub fn dep() {}\n"
+    );
+    assert!(!copy.join("src/.lib.rs.cargo-patch.tmp").exists());
+}
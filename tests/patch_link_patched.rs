@@ -0,0 +1,151 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package};
+use std::fs;
+
+const PATCH: &str = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+
+fn publish() {
+    Package::new("inner", "0.1.0")
+        .file("src/lib.rs", "pub fn inner() {}\n")
+        .publish();
+    Package::new("outer", "0.1.0")
+        .dep("inner", "0.1")
+        .file("src/lib.rs", "pub fn outer() {}\n")
+        .publish();
+}
+
+fn manifest(link: bool) -> String {
+    // the dependent package is configured first
+    format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        outer = "0.1.0"
+
+        [package.metadata.patch-config]
+        link-patched = {link}
+
+        [package.metadata.patch]
+        outer = {{ patches = ["test.patch"] }}
+        inner = {{ patches = ["test.patch"] }}
+    "#
+    )
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_dependencies_first() {
+    publish();
+    let p = project()
+        .file("Cargo.toml", &manifest(false))
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched inner: /dev/null -> PATCHED.txt\n\
+             Patched outer: /dev/null -> PATCHED.txt\n",
+        )
+        .run();
+    let outer =
+        fs::read_to_string(p.build_dir().join("patch/outer-0.1.0/Cargo.toml"))
+            .expect("Unable to read manifest");
+    assert!(!outer.contains("path ="), "{outer}");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_link_patched_dependencies() {
+    publish();
+    let p = project()
+        .file("Cargo.toml", &manifest(true))
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched inner: /dev/null -> PATCHED.txt\n\
+             Patched outer: /dev/null -> PATCHED.txt\n\
+             Linked outer to the patched inner\n",
+        )
+        .run();
+    let outer =
+        fs::read_to_string(p.build_dir().join("patch/outer-0.1.0/Cargo.toml"))
+            .expect("Unable to read manifest");
+    let inner = p.build_dir().join("patch/inner-0.1.0");
+    assert!(
+        outer.contains(&format!("path = \"{}\"", inner.display())),
+        "{outer}"
+    );
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_link_deps() {
+    publish();
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        outer = "0.1.0"
+
+        [package.metadata.patch]
+        outer = { patches = ["test.patch"], link-deps = { inner = "patched", log = "patched" } }
+        inner = { patches = ["test.patch"] }
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout_contains("Linked outer to the patched inner")
+        .with_stderr_contains(
+            "warning: `link-deps` of outer names log, which isn't a patched dependency of it",
+        )
+        .run();
+    let outer =
+        fs::read_to_string(p.build_dir().join("patch/outer-0.1.0/Cargo.toml"))
+            .expect("Unable to read manifest");
+    assert!(outer.contains("patch/inner-0.1.0\""), "{outer}");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_link_deps_pristine() {
+    publish();
+    let manifest = manifest(true).replace(
+        r#"outer = { patches = ["test.patch"] }"#,
+        r#"outer = { patches = ["test.patch"], link-deps = { inner = "pristine" } }"#,
+    );
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched inner: /dev/null -> PATCHED.txt\n\
+             Patched outer: /dev/null -> PATCHED.txt\n",
+        )
+        .run();
+    let outer =
+        fs::read_to_string(p.build_dir().join("patch/outer-0.1.0/Cargo.toml"))
+            .expect("Unable to read manifest");
+    assert!(!outer.contains("path ="), "{outer}");
+}
@@ -0,0 +1,152 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+const MANIFEST: &str = r#"
+    [package]
+    name = "example"
+    version = "0.1.0"
+    authors = ["wycats@example.com"]
+
+    [workspace]
+    exclude = ["foo-sys", "bar-sys"]
+
+    [dependencies]
+    foo-sys = { path = "foo-sys" }
+    bar-sys = { path = "bar-sys" }
+"#;
+
+fn sys_manifest(name: &str, links: &str) -> String {
+    format!(
+        r#"
+        [package]
+        name = "{name}"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+        links = "{links}"
+        "#
+    )
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_links_override() {
+    let manifest = format!(
+        r#"{MANIFEST}
+        [package.metadata.patch.bar-sys]
+        links = "bar-patched"
+        patches = [
+            "test.patch"
+        ]
+        "#
+    );
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("foo-sys/Cargo.toml", &sys_manifest("foo-sys", "foo"))
+        .file("foo-sys/src/lib.rs", "")
+        .file("foo-sys/build.rs", "fn main() {}")
+        .file("bar-sys/Cargo.toml", &sys_manifest("bar-sys", "bar"))
+        .file("bar-sys/src/lib.rs", "")
+        .file("bar-sys/build.rs", "fn main() {}")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched bar-sys: /dev/null -> PATCHED.txt\n\
+             Set bar-sys links to `bar-patched`\n",
+        )
+        .with_stderr("")
+        .run();
+
+    let manifest = p.build_dir().join("patch/bar-sys-0.1.0/Cargo.toml");
+    let manifest = std::fs::read_to_string(manifest).expect("Unable to read");
+    assert!(manifest.contains(r#"links = "bar-patched""#));
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_links_conflict() {
+    let manifest = format!(
+        r#"{MANIFEST}
+        [package.metadata.patch.bar-sys]
+        links = "foo"
+        patches = [
+            "test.patch"
+        ]
+        "#
+    );
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("foo-sys/Cargo.toml", &sys_manifest("foo-sys", "foo"))
+        .file("foo-sys/src/lib.rs", "")
+        .file("foo-sys/build.rs", "fn main() {}")
+        .file("bar-sys/Cargo.toml", &sys_manifest("bar-sys", "bar"))
+        .file("bar-sys/src/lib.rs", "")
+        .file("bar-sys/build.rs", "fn main() {}")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr(
+            "warning: bar-sys v0.1.0 now links to `foo` like foo-sys v0.1.0, \
+             but only one package may link to a native library\n",
+        )
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--strict")
+        .with_stderr(
+            "Error: bar-sys v0.1.0 now links to `foo` like foo-sys v0.1.0, \
+             but only one package may link to a native library\n",
+        )
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_links_changed_by_patch() {
+    let manifest = format!(
+        r#"{MANIFEST}
+        [package.metadata.patch.bar-sys]
+        patches = [
+            "test.patch"
+        ]
+        "#
+    );
+    let patch = r#"--- Cargo.toml
++++ Cargo.toml
+@@ -3,4 +3,4 @@
+         name = "bar-sys"
+         version = "0.1.0"
+         authors = ["wycats@example.com"]
+-        links = "bar"
++        links = "baz"
+"#;
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("foo-sys/Cargo.toml", &sys_manifest("foo-sys", "foo"))
+        .file("foo-sys/src/lib.rs", "")
+        .file("foo-sys/build.rs", "fn main() {}")
+        .file("bar-sys/Cargo.toml", &sys_manifest("bar-sys", "bar"))
+        .file("bar-sys/src/lib.rs", "")
+        .file("bar-sys/build.rs", "fn main() {}")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr(
+            "warning: patches change the `links` key of bar-sys. Set \
+             `allow-build-script-changes = true` to allow this\n",
+        )
+        .run();
+}
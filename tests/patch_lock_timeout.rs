@@ -0,0 +1,58 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, paths, project};
+use std::fs::{self, File};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_lock_timeout() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = []
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .build();
+
+    // lock the package cache like another cargo process would
+    let cargo_home = paths::home().join(".cargo");
+    fs::create_dir_all(&cargo_home).expect("Unable to create cargo home");
+    let lock = File::create(cargo_home.join(".package-cache"))
+        .expect("Unable to create lock file");
+    lock.lock().expect("Unable to lock package cache");
+
+    p.process(common::cargo_patch_exe())
+        .arg("--lock-timeout=1")
+        .with_status(1)
+        .with_stdout("Blocking waiting for file lock on package cache\n")
+        .with_stderr_contains(
+            "Error: Timed out after 1s waiting for the package cache lock held by another cargo process",
+        )
+        .run();
+
+    lock.unlock().expect("Unable to unlock package cache");
+    p.process(common::cargo_patch_exe())
+        .arg("--lock-timeout=1")
+        .with_stdout("")
+        .run();
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_locked_metadata() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--locked-metadata")
+        .with_status(1)
+        .with_stderr_contains("Error: Unable to read cargo-patch.lock, run `cargo patch --write-lock` to create it")
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--write-lock")
+        .with_stdout_contains("Locked 1 package(s) in cargo-patch.lock")
+        .run();
+    let lock =
+        fs::read_to_string(p.root().join("cargo-patch.lock")).expect("Missing lock");
+    assert!(lock.contains("name = \"bar\""));
+    assert!(lock.contains("version = \"0.1.0\""));
+    assert!(lock.contains("file = \"test.patch\""));
+
+    p.process(common::cargo_patch_exe())
+        .arg("--locked-metadata")
+        .with_stdout_contains("Patched bar: /dev/null -> PATCHED.txt")
+        .run();
+
+    fs::write(
+        p.root().join("test.patch"),
+        patch.replace("+PATCHED", "+CHANGED"),
+    )
+    .expect("Unable to write patch");
+    p.process(common::cargo_patch_exe())
+        .arg("--locked-metadata")
+        .with_status(1)
+        .with_stderr_contains("    test.patch of bar v0.1.0 changed")
+        .run();
+
+    fs::write(
+        p.root().join("Cargo.toml"),
+        manifest.replace("patches = [\"test.patch\"]", "patches = []"),
+    )
+    .expect("Unable to write manifest");
+    p.process(common::cargo_patch_exe())
+        .arg("--locked-metadata")
+        .with_status(1)
+        .with_stderr_contains("    bar v0.1.0 is patched with other steps")
+        .run();
+}
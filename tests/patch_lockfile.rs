@@ -0,0 +1,54 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::project;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_allow_lock_update() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("test.patch", patch)
+        .build();
+    let lockfile = p.root().join("Cargo.lock");
+
+    // without the flag the workspace is resolved without writing a lockfile
+    p.process(common::cargo_patch_exe()).run();
+    assert!(!lockfile.exists());
+
+    p.process(common::cargo_patch_exe())
+        .arg("--allow-lock-update")
+        .with_stdout_contains("Generated Cargo.lock")
+        .run();
+    assert!(lockfile.exists());
+    let lock =
+        std::fs::read_to_string(&lockfile).expect("Unable to read Cargo.lock");
+    assert!(lock.contains("name = \"dep\""));
+
+    // an existing lockfile is used as is
+    p.process(common::cargo_patch_exe())
+        .arg("--allow-lock-update")
+        .with_stdout_does_not_contain("Generated Cargo.lock")
+        .run();
+}
@@ -0,0 +1,170 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_logs() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["good", "bad"]
+
+        [dependencies]
+        good = { path = "good" }
+        bad = { path = "bad" }
+
+        [package.metadata.patch.good]
+        patches = ["good.patch"]
+
+        [package.metadata.patch.bad]
+        patches = ["bad.patch"]
+    "#;
+    let good = r#"--- src/lib.rs
++++ src/lib.rs
+@@ -1,2 +1,3 @@ mod first
+ pub fn first() {}
+-pub fn second() {}
++pub fn second() { first() }
++pub fn third() {}
+"#;
+    let bad = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-missing\n+found\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "good/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("good"),
+        )
+        .file("good/src/lib.rs", "pub fn first() {}\npub fn second() {}\n")
+        .file(
+            "bad/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("bad"),
+        )
+        .file("bad/src/lib.rs", "pub fn first() {}\n")
+        .file("good.patch", good)
+        .file("bad.patch", bad)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--logs", "--keep-going"])
+        .with_stdout("Patched good: src/lib.rs (mod first)\n")
+        .with_status(1)
+        .run();
+
+    let logs = p.build_dir().join("patch/.logs");
+    let good = fs::read_to_string(logs.join("good-0.5.0.log")).expect("Missing log");
+    assert_eq!(
+        good,
+        "Patching good v0.5.0\n\
+         Applying good.patch\n\
+         Patched good: src/lib.rs (mod first)\n    \
+         hunk 1 `@@ -1,2 +1,3 @@ mod first`: 2 added, 1 removed\n"
+    );
+    let bad = fs::read_to_string(logs.join("bad-0.5.0.log")).expect("Missing log");
+    assert!(bad.contains("Applying bad.patch\n"));
+    assert!(bad.contains(
+        "error: failed to apply patch to lib.rs on line 1 (line 1 after patching) \
+         in hunk 1 `@@ -1,1 +1,1 @@` of bad.patch\n"
+    ));
+    // the temporary files the logs are written to are gone
+    assert_eq!(fs::read_dir(&logs).expect("Missing logs").count(), 2);
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_logs_persist() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["good"]
+
+        [workspace.metadata.patch-config]
+        persist = true
+        dir = "patched"
+
+        [dependencies]
+        good = { path = "good" }
+
+        [package.metadata.patch.good]
+        patches = ["good.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "good/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("good"),
+        )
+        .file("good/src/lib.rs", "pub fn first() {}\n")
+        .file(
+            "good.patch",
+            "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn first() {}\n+pub fn second() {}\n",
+        )
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--logs")
+        .with_stdout("Patched good: src/lib.rs\n")
+        .run();
+
+    // logs are kept next to the persisted copies
+    assert!(p.root().join("patched/.logs/good-0.5.0.log").exists());
+    assert!(!p.build_dir().join("patch/.logs").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_logs_folder_name() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["good"]
+
+        [dependencies]
+        good = { path = "good" }
+
+        [package.metadata.patch.good]
+        folder-name = "good-patched"
+        patches = ["good.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "good/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("good"),
+        )
+        .file("good/src/lib.rs", "pub fn first() {}\n")
+        .file(
+            "good.patch",
+            "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn first() {}\n+pub fn second() {}\n",
+        )
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--logs")
+        .with_stdout("Patched good: src/lib.rs\n")
+        .run();
+
+    // logs are named like the copies
+    let logs = p.build_dir().join("patch/.logs");
+    assert!(logs.join("good-patched.log").exists());
+    assert!(!logs.join("good-0.5.0.log").exists());
+}
@@ -0,0 +1,58 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_long_paths() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    // exceeds MAX_PATH on Windows once below target/patch
+    let module = ["deeply_nested_module_directory"; 10].join("/");
+    let file = format!("src/{module}/mod.rs");
+    let patch = format!(
+        "--- {file}\n+++ {file}\n@@ -1 +1 @@\n-pub fn nested() {{}}\n+pub fn patched() {{}}\n"
+    );
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file(format!("dep/{file}"), "pub fn nested() {}\n")
+        .file("test.patch", &patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(format!("Patched dep: {file}\n"))
+        .run();
+
+    // canonical paths have the extended-length prefix on Windows
+    let copy = p
+        .build_dir()
+        .canonicalize()
+        .expect("Unable to canonicalize target directory")
+        .join("patch/dep-0.5.0")
+        .join(&file);
+    let content =
+        std::fs::read_to_string(copy).expect("Unable to read patched file");
+    assert_eq!(content, "pub fn patched() {}\n");
+}
@@ -0,0 +1,138 @@
+#![cfg(feature = "metadata-backend")]
+
+mod common;
+
+use cargo_patch::{patch_from_metadata, MetadataOptions, MetadataSource};
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package, Project};
+use std::fs;
+
+const PATCH: &str = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+
+fn setup() -> Project {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = ["test.patch"]
+    "#;
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build()
+}
+
+/// Writes the output of `cargo metadata` to `metadata.json`.
+fn write_metadata(p: &Project) {
+    let output = p
+        .process("cargo")
+        .args(&["metadata", "--format-version", "1"])
+        .exec_with_output()
+        .expect("cargo metadata failed");
+    fs::write(p.root().join("metadata.json"), output.stdout)
+        .expect("Unable to write metadata");
+}
+
+#[cfg(feature = "orchestrator")]
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_metadata_backend_run() {
+    let p = setup();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--metadata")
+        .with_stdout("Patched bar: /dev/null -> PATCHED.txt\n")
+        .run();
+    assert!(p.build_dir().join("patch/bar-0.1.0/PATCHED.txt").exists());
+}
+
+#[cfg(feature = "orchestrator")]
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_metadata_backend_file() {
+    let p = setup();
+    write_metadata(&p);
+
+    p.process(common::cargo_patch_exe())
+        .arg("--metadata=metadata.json")
+        .with_stdout("Patched bar: /dev/null -> PATCHED.txt\n")
+        .run();
+    assert!(p.build_dir().join("patch/bar-0.1.0/PATCHED.txt").exists());
+
+    p.process(common::cargo_patch_exe())
+        .arg("--metadata=missing.json")
+        .with_stderr_contains("Error: Unable to read missing.json")
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_metadata_backend_library() {
+    let p = setup();
+    write_metadata(&p);
+    let stale = p.build_dir().join("patch/stale-1.0.0");
+    fs::create_dir_all(&stale).expect("Unable to create stale copy");
+
+    patch_from_metadata(
+        &MetadataSource::File(p.root().join("metadata.json")),
+        &MetadataOptions::default(),
+    )
+    .expect("Unable to patch");
+    assert!(p.build_dir().join("patch/bar-0.1.0/PATCHED.txt").exists());
+    assert!(!stale.exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_metadata_backend_unsupported_key() {
+    let p = setup();
+    let manifest = fs::read_to_string(p.root().join("Cargo.toml"))
+        .expect("Unable to read manifest");
+    fs::write(
+        p.root().join("Cargo.toml"),
+        manifest
+            .replace("patches = [", "version-suffix = true\n        patches = ["),
+    )
+    .expect("Unable to write manifest");
+    write_metadata(&p);
+
+    let err = patch_from_metadata(
+        &MetadataSource::File(p.root().join("metadata.json")),
+        &MetadataOptions::default(),
+    )
+    .expect_err("Unsupported keys must be rejected");
+    assert_eq!(
+        err.to_string(),
+        "`version-suffix` of the patch entry bar isn't supported by the metadata backend"
+    );
+    assert!(!p.build_dir().join("patch/bar-0.1.0").exists());
+}
+
+#[cfg(feature = "orchestrator")]
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_metadata_backend_unsupported_flag() {
+    let p = setup();
+
+    for flag in ["--pure=pure", "--watch", "--keep-going", "--write-lock"] {
+        p.process(common::cargo_patch_exe())
+            .arg("--metadata")
+            .arg(flag)
+            .with_stderr_contains(
+                "error: the argument '--metadata[=<FILE>]' cannot be used with [..]",
+            )
+            .with_status(2)
+            .run();
+    }
+}
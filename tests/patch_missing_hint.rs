@@ -0,0 +1,110 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+fn workspace(dependencies: &str, patch: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["serde_json", "extra"]
+
+        {dependencies}
+
+        [package.metadata.patch.{patch}]
+        patches = []
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "serde_json/Cargo.toml",
+            r#"
+            [package]
+            name = "serde_json"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            extra = { path = "../extra", optional = true }
+        "#,
+        )
+        .file("serde_json/src/lib.rs", "")
+        .file(
+            "extra/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("extra"),
+        )
+        .file("extra/src/lib.rs", "")
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_missing_hint_typo() {
+    let p = workspace(
+        "[dependencies]\nserde_json = { path = \"serde_json\" }",
+        "serde-json",
+    );
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Unable to find package serde-json in dependencies, did you mean `serde_json`?",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_missing_hint_optional() {
+    let p = workspace(
+        "[dependencies]\nserde_json = { path = \"serde_json\" }",
+        "extra",
+    );
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Unable to find package extra in dependencies, it is an optional dependency of serde_json 0.5.0 which none of its enabled features activate",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_missing_hint_version() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        version = "2"
+        patches = []
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .build();
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Unable to find package dep in dependencies, found version(s) 0.5.0 which don't match the configured version or source",
+        )
+        .run();
+}
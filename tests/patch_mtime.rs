@@ -0,0 +1,116 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+use std::{
+    fs::{self, File},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// only the first file diff carries a timestamp
+const PATCH: &str = "--- src/lib.rs\t2023-07-30 15:38:00.598467733 +0200
++++ src/lib.rs\t2023-07-30 15:39:01.284727222 +0200
+@@ -1 +1 @@
+-pub fn dep() {}
++pub fn patched() {}
+--- /dev/null
++++ PATCHED.txt
+@@ -0,0 +1 @@
++PATCHED
+";
+
+fn gen_project(mtime: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch-config]
+        mtime = {mtime}
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", PATCH)
+        .build()
+}
+
+fn mtime(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .expect("Unable to read mtime")
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_mtime_fixed() {
+    let p = gen_project("1000000000");
+
+    p.process(common::cargo_patch_exe()).run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    let fixed = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    assert_eq!(mtime(&copy.join("Cargo.toml")), fixed);
+    assert_eq!(mtime(&copy.join("PATCHED.txt")), fixed);
+    if cfg!(unix) {
+        assert_eq!(mtime(&copy.join("src")), fixed);
+    }
+    // 2023-07-30 13:39:01.284727222 UTC
+    let header = UNIX_EPOCH + Duration::new(1_690_724_341, 284_727_222);
+    assert_eq!(mtime(&copy.join("src/lib.rs")), header);
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_mtime_lockfile() {
+    let p = gen_project(r#""lockfile""#);
+    p.process("cargo")
+        .arg("generate-lockfile")
+        .arg("--offline")
+        .run();
+    let lockfile = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+    File::options()
+        .write(true)
+        .open(p.root().join("Cargo.lock"))
+        .and_then(|file| file.set_modified(lockfile))
+        .expect("Unable to set mtime of Cargo.lock");
+
+    p.process(common::cargo_patch_exe()).run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    assert_eq!(mtime(&copy.join("PATCHED.txt")), lockfile);
+    assert_eq!(mtime(&copy.join("Cargo.toml")), lockfile);
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_mtime_invalid() {
+    let p = gen_project(r#""yesterday""#);
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: Mtime must be seconds since the epoch or \"lockfile\": \"yesterday\"",
+        )
+        .run();
+}
@@ -0,0 +1,188 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{git, main_file, paths, project, registry::Package};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_multi_source() {
+    let repo = git::repo(&paths::root().join("repo"))
+        .file(
+            "Cargo.toml",
+            &cargo_test_support::basic_manifest("dep", "0.6.0"),
+        )
+        .file("src/lib.rs", "pub fn dep() {}\n")
+        .build();
+    let url = repo.url().to_string();
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+        dep-git = {{ package = "dep", git = "{url}" }}
+
+        [package.metadata.patch.dep]
+        source-git = "{url}.git"
+        patches = ["test.patch"]
+    "#
+    );
+    let patch =
+        "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn git() {}\n";
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+
+    let copies = fs::read_dir(p.build_dir().join("patch"))
+        .expect("Unable to list copies")
+        .map(|entry| {
+            entry
+                .expect("Unable to read entry")
+                .file_name()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(copies.len(), 1);
+    // copies of dependencies from several sources get a hash of the source
+    assert!(copies[0].starts_with("dep-0.6.0-"), "{copies:?}");
+    assert_eq!(
+        fs::read_to_string(
+            p.build_dir()
+                .join("patch")
+                .join(&copies[0])
+                .join("src/lib.rs")
+        )
+        .expect("Unable to read patched file"),
+        "pub fn git() {}\n"
+    );
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_multi_source_ambiguous() {
+    let repo = git::repo(&paths::root().join("repo"))
+        .file(
+            "Cargo.toml",
+            &cargo_test_support::basic_manifest("dep", "0.6.0"),
+        )
+        .file("src/lib.rs", "pub fn dep() {}\n")
+        .build();
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+        dep-git = {{ package = "dep", git = "{}" }}
+
+        [package.metadata.patch.dep]
+        patches = []
+    "#,
+        repo.url()
+    );
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "dep is available from multiple sources. Try setting `registry = \"crates-io\"` or `source-git = \"<url>\"`.",
+        )
+        .run();
+    // the path dependency is picked first
+    let copies = fs::read_dir(p.build_dir().join("patch"))
+        .expect("Unable to list copies")
+        .map(|entry| {
+            entry
+                .expect("Unable to read entry")
+                .file_name()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(copies.len(), 1);
+    assert!(copies[0].starts_with("dep-0.5.0-"), "{copies:?}");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_multi_source_registry() {
+    Package::new("dep", "0.5.0")
+        .file("src/lib.rs", "pub fn dep() {}\n")
+        .publish();
+    let repo = git::repo(&paths::root().join("repo"))
+        .file(
+            "Cargo.toml",
+            &cargo_test_support::basic_manifest("dep", "0.6.0"),
+        )
+        .file("src/lib.rs", "pub fn dep() {}\n")
+        .build();
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        dep = "0.5.0"
+        dep-git = {{ package = "dep", git = "{}" }}
+
+        [package.metadata.patch.dep]
+        registry = "crates-io"
+        source = "GitDiff"
+        patches = ["test.patch"]
+    "#,
+        repo.url()
+    );
+    let patch = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn registry() {}\n";
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+
+    // the copy from crates.io keeps its plain name
+    assert_eq!(
+        fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/lib.rs"))
+            .expect("Unable to read patched file"),
+        "pub fn registry() {}\n"
+    );
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::project;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_with_comments_and_prose() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "# Backport from the mailing list\n\
+                 Hi all, the fix below renames the function:\n\
+                 \n\
+                 Index: src/lib.rs\n\
+                 ===================================================================\n\
+                 --- src/lib.rs\n\
+                 +++ src/lib.rs\n\
+                 @@ -1 +1 @@\n\
+                 -pub fn dep() {}\n\
+                 +pub fn patched() {}\n\
+                 \n\
+                 # and adds a file\n\
+                 Index: NEW.txt\n\
+                 --- /dev/null\n\
+                 +++ NEW.txt\n\
+                 @@ -0,0 +1 @@\n\
+                 +NEW\n\
+                 \n\
+                 Thanks!\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: src/lib.rs\n\
+             Patched dep: /dev/null -> NEW.txt\n",
+        )
+        .run();
+    let lib =
+        std::fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/lib.rs"))
+            .expect("Unable to read patched file");
+    assert_eq!(lib, "pub fn patched() {}\n");
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{project, Project};
+
+fn gen_project() -> Project {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [
+            { path = "old.patch", description = "Backport of dep#12", issue = "https://example.com/dep/pull/12", expires = "2020-01-31" },
+            { path = "new.patch", description = "Faster parsing", expires = "2999-12-31" },
+            "plain.patch",
+        ]
+    "#;
+    let create =
+        |file| format!("--- /dev/null\n+++ {file}\n@@ -0,0 +1 @@\n+PATCHED\n");
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("old.patch", &create("OLD.txt"))
+        .file("new.patch", &create("NEW.txt"))
+        .file("plain.patch", &create("PLAIN.txt"))
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_notes_expired() {
+    let p = gen_project();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "warning: Patch old.patch of dep expired on 2020-01-31, check whether it is still needed (Backport of dep#12, https://example.com/dep/pull/12)",
+        )
+        .with_stderr_does_not_contain("[..]new.patch[..]")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_notes_report() {
+    let p = gen_project();
+
+    p.process(common::cargo_patch_exe())
+        .arg("report")
+        .with_stdout(
+            "dep v0.5.0\n    \
+             OLD.txt (+1 -0) from old.patch\n    \
+             NEW.txt (+1 -0) from new.patch\n    \
+             PLAIN.txt (+1 -0) from plain.patch\n    \
+             old.patch: Backport of dep#12, https://example.com/dep/pull/12, expires 2020-01-31 (expired)\n    \
+             new.patch: Faster parsing, expires 2999-12-31\n",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_notes_invalid_date() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [{ path = "test.patch", expires = "31.12.2025" }]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file(
+            "test.patch",
+            "--- /dev/null\n+++ A.txt\n@@ -0,0 +1 @@\n+A\n",
+        )
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains("Expires must be a date like 2025-12-31: 31.12.2025")
+        .run();
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_open() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["open", "dep", "--print"])
+        .with_stderr_contains(
+            "Error: dep v0.5.0 is not patched yet, run `cargo patch` or pass --pristine",
+        )
+        .with_status(1)
+        .run();
+    p.process(common::cargo_patch_exe())
+        .args(&["open", "dep", "--pristine", "--print"])
+        .with_stdout("/[..]/foo/dep\n")
+        .run();
+
+    p.process(common::cargo_patch_exe()).run();
+    p.process(common::cargo_patch_exe())
+        .args(&["open", "dep", "--print"])
+        .with_stdout("/[..]/foo/target/patch/dep-0.5.0\n")
+        .run();
+    // the editor receives the directory as last argument
+    p.process(common::cargo_patch_exe())
+        .args(&["open", "dep"])
+        .env_remove("VISUAL")
+        .env("EDITOR", "echo --editor")
+        .with_stdout("--editor /[..]/foo/target/patch/dep-0.5.0\n")
+        .run();
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+const PATCH: &str = r#"--- Cargo.toml
++++ Cargo.toml
+@@ -1,3 +1,3 @@
+ [package]
+ name = "serde"
+-version = "1.0.110" # remember to update html_root_url and serde_derive dependency
++version = "1.0.110" # patched
+"#;
+
+fn manifest(use_orig_manifest: bool) -> String {
+    format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        use-orig-manifest = {use_orig_manifest}
+        patches = [
+            "test.patch"
+        ]
+    "#
+    )
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_orig_manifest_hint() {
+    let p = project()
+        .file("Cargo.toml", &manifest(false))
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains("[..]set `use-orig-manifest = true` on the entry[..]")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_orig_manifest() {
+    let p = project()
+        .file("Cargo.toml", &manifest(true))
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: Cargo.toml.orig -> Cargo.toml\n")
+        .run();
+    let manifest = std::fs::read_to_string(
+        p.root()
+            .join("target/patch/serde-1.0.110")
+            .join("Cargo.toml"),
+    )
+    .expect("Unable to read manifest");
+    assert!(manifest.contains("# patched"));
+}
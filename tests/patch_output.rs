@@ -0,0 +1,94 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_output_cargo() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = r#"--- /dev/null
++++ PATCHED.txt
+@@ -0,0 +1 @@
++PATCHED
+--- LICENSE-MIT
++++ LICENSE-MIT
+@@ -1,1 +1,1 @@
+-Permission is hereby granted, free of charge, to any
++PATCHED
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--output")
+        .arg("cargo")
+        .arg("--color")
+        .arg("never")
+        .with_stdout("")
+        .with_stderr(
+            "    Patching serde v1.0.110\n     \
+             Created PATCHED.txt\n     \
+             Applied 1 hunk(s) to LICENSE-MIT\n",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_output_stderr() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("test.patch", patch)
+        .build();
+
+    // stdout stays free for `cargo:` directives of build scripts
+    p.process(common::cargo_patch_exe())
+        .arg("--output")
+        .arg("stderr")
+        .with_stdout("")
+        .with_stderr("Patched dep: /dev/null -> PATCHED.txt\n")
+        .run();
+}
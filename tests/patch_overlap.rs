@@ -0,0 +1,105 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+const LINES: &str = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+
+const FIRST: &str = r#"--- lines.txt
++++ lines.txt
+@@ -3,5 +3,5 @@
+ three
+ four
+-five
++FIVE
+ six
+ seven
+"#;
+
+fn project_with(second: &str) -> Project {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [
+            "first.patch",
+            "second.patch"
+        ]
+    "#;
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("dep/lines.txt", LINES)
+        .file("first.patch", FIRST)
+        .file("second.patch", second)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_overlap_failed() {
+    let second = r#"--- lines.txt
++++ lines.txt
+@@ -4,3 +4,3 @@
+ four
+-five
++5
+ six
+"#;
+    let p = project_with(second);
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr(
+            "Error: failed to apply patch to lines.txt on line 5 (line 5 after \
+             patching) in hunk 1 `@@ -4,3 +4,3 @@` of second.patch. The lines \
+             of the hunk were changed by first.patch before\n",
+        )
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_overlap_applied() {
+    let second = r#"--- lines.txt
++++ lines.txt
+@@ -4,3 +4,4 @@
+ four
+-FIVE
++5
++5.5
+ six
+@@ -9,2 +10,2 @@
+ nine
+-ten
++TEN
+"#;
+    let p = project_with(second);
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: lines.txt\n\
+             Patched dep: lines.txt\n",
+        )
+        .with_stderr(
+            "warning: hunk 1 of second.patch changes line 5 of lines.txt \
+             which first.patch changed before\n",
+        )
+        .run();
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_excluded_file() {
+    Package::new("bar", "0.1.0")
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            exclude = ["tests/"]
+        "#,
+        )
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = ["test.patch"]
+    "#;
+    let patch = r#"--- src/lib.rs
++++ src/lib.rs
+@@ -1 +1 @@
+-pub fn bar() {}
++pub fn bar() -> u32 { 1 }
+--- tests/it.rs
++++ tests/it.rs
+@@ -1 +1 @@
+-fn it() {}
++fn it() { bar::bar(); }
+"#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "warning: bar: tests/it.rs is excluded from the published package by `include`/`exclude`, so patching it has no effect on the registry source. Depend on the git repository of bar instead to patch it",
+        )
+        .with_stderr_does_not_contain("[..]src/lib.rs is excluded[..]")
+        .run();
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{git, main_file, paths, project, registry::Package};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_paths_json() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["paths", "--json"])
+        .with_stdout_contains(r#"    "exists": false,"#)
+        .with_stdout_contains(r#"    "id": "registry+[..]#serde@1.0.110","#)
+        .with_stdout_contains(r#"    "name": "serde","#)
+        .with_stdout_contains(r#"    "path": "[..]/target/patch/serde-1.0.110","#)
+        .with_stdout_contains(r#"    "version": "1.0.110""#)
+        .run();
+
+    assert!(!p.build_dir().join("patch").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_paths_toml_absolute() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["paths", "--toml"])
+        .with_stdout_contains(r#"bar = { path = "./target/patch/bar-0.1.0" }"#)
+        .run();
+    p.process(common::cargo_patch_exe())
+        .args(&["paths", "--toml", "--override-path-style", "absolute"])
+        .with_stdout_contains(r#"bar = { path = "/[..]/target/patch/bar-0.1.0" }"#)
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--override-path-style", "absolute"])
+        .run();
+    let provenance = std::fs::read_to_string(
+        p.build_dir().join("patch/bar-0.1.0/.cargo-patch.json"),
+    )
+    .unwrap();
+    let expected = format!(
+        r#""override-path": "{}""#,
+        p.build_dir()
+            .join("patch/bar-0.1.0")
+            .canonicalize()
+            .unwrap()
+            .display()
+    );
+    assert!(provenance.contains(&expected), "{provenance}");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_paths_toml_groups_sources() {
+    let repo = git::repo(&paths::root().join("Dep.git"))
+        .file(
+            "Cargo.toml",
+            &cargo_test_support::basic_manifest("dep", "0.6.0"),
+        )
+        .file("src/lib.rs", "pub fn dep() {}\n")
+        .build();
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+        dep = {{ git = "{}" }}
+
+        [package.metadata.patch]
+        bar = {{ patches = ["test.patch"] }}
+        dep = {{ patches = ["test.patch"] }}
+    "#,
+        repo.url()
+    );
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    // git URLs are written the way cargo compares them, without `.git`
+    p.process(common::cargo_patch_exe())
+        .args(&["paths", "--toml"])
+        .with_stdout(
+            "[patch.crates-io]\n\
+             bar = { path = \"./target/patch/bar-0.1.0\" }\n\
+             \n\
+             [patch.\"file:///[..]/Dep\"]\n\
+             dep = { path = \"./target/patch/dep-0.6.0\" }\n",
+        )
+        .run();
+}
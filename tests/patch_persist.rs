@@ -0,0 +1,245 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_persist() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [workspace.metadata.patch-config]
+        persist = true
+        dir = "patched"
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+    let copy = p.root().join("patched").join("serde-1.0.110");
+    assert!(copy.join("PATCHED.txt").exists());
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("serde is up to date\n")
+        .run();
+
+    std::fs::write(
+        p.root().join("test.patch"),
+        "--- /dev/null\n+++ UPDATED.txt\n@@ -0,0 +1 @@\n+UPDATED\n",
+    )
+    .expect("Unable to write patch");
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> UPDATED.txt\n")
+        .run();
+    assert!(copy.join("UPDATED.txt").exists());
+    assert!(!copy.join("PATCHED.txt").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_persist_remote() {
+    let p = project()
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "remote.patch",
+            "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n",
+        )
+        .build();
+    let url = format!("file://{}", p.root().join("remote.patch").display());
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+            [package]
+            name = "example"
+            version = "0.1.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+            serde = "=1.0.110"
+
+            [workspace.metadata.patch-config]
+            persist = true
+            dir = "patched"
+
+            [package.metadata.patch.serde]
+            patches = [
+                {{ url = "{url}", cache = "forever" }}
+            ]
+        "#
+        ),
+    );
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+    p.process(common::cargo_patch_exe())
+        .with_stdout("serde is up to date\n")
+        .run();
+
+    // a changed download makes the copy stale
+    std::fs::write(
+        p.root().join("remote.patch"),
+        "--- /dev/null\n+++ UPDATED.txt\n@@ -0,0 +1 @@\n+UPDATED\n",
+    )
+    .expect("Unable to write patch");
+    p.process(common::cargo_patch_exe())
+        .arg("--refresh-patches")
+        .with_stdout("Patched serde: /dev/null -> UPDATED.txt\n")
+        .run();
+    assert!(p.root().join("patched/serde-1.0.110/UPDATED.txt").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_persist_prune() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [workspace.metadata.patch-config]
+        persist = true
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .file("patched/serde-1.0.100/.cargo-patch.json", "{}")
+        .file("patched/notes/README.md", "kept")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Removed unused copy serde-1.0.100\n\
+             Patched serde: /dev/null -> PATCHED.txt\n",
+        )
+        .run();
+    assert!(!p.root().join("patched/serde-1.0.100").exists());
+    assert!(p.root().join("patched/notes/README.md").exists());
+    assert!(p.root().join("patched/serde-1.0.110/PATCHED.txt").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_persist_outside_workspace() {
+    for dir in ["../patched", "/tmp/patched"] {
+        let manifest = format!(
+            r#"
+            [package]
+            name = "example"
+            version = "0.1.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+            serde = "=1.0.110"
+
+            [workspace.metadata.patch-config]
+            persist = true
+            dir = "{dir}"
+
+            [package.metadata.patch.serde]
+            patches = [
+                "test.patch"
+            ]
+        "#
+        );
+        let p = project()
+            .file("Cargo.toml", &manifest)
+            .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+            .file("test.patch", "")
+            .build();
+
+        p.process(common::cargo_patch_exe())
+            .with_status(1)
+            .with_stderr(format!(
+                "Error: Persist directory must be a relative path below the workspace root: {dir}\n"
+            ))
+            .run();
+    }
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_persist_settings() {
+    let manifest = |settings: &str| {
+        format!(
+            r#"
+            [package]
+            name = "example"
+            version = "0.1.0"
+            authors = ["wycats@example.com"]
+
+            [workspace]
+            exclude = ["dep"]
+
+            [dependencies]
+            dep = {{ path = "dep" }}
+
+            [workspace.metadata.patch-config]
+            persist = true
+            {settings}
+
+            [package.metadata.patch.dep]
+            patches = ["test.patch"]
+        "#
+        )
+    };
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", &manifest(""))
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+        .run();
+
+    // settings changing the metadata of the copy re-create it
+    for settings in ["mtime = 0", "preserve-permissions = true"] {
+        std::fs::write(p.root().join("Cargo.toml"), manifest(settings))
+            .expect("Unable to write manifest");
+        p.process(common::cargo_patch_exe())
+            .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+            .run();
+        p.process(common::cargo_patch_exe())
+            .with_stdout("dep is up to date\n")
+            .run();
+    }
+}
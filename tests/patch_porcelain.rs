@@ -0,0 +1,81 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+const PATCH: &str = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1,2 +1,2 @@\n-pub fn first() {}\n+pub fn one() {}\n pub fn second() {}\n@@ -5,2 +5,2 @@\n pub fn fifth() {}\n-pub fn sixth() {}\n+pub fn six() {}\n--- /dev/null\n+++ NEW.txt\n@@ -0,0 +1 @@\n+new\n";
+
+fn dep_project(backend: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch.dep]
+        {backend}
+        patches = ["test.patch"]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file(
+            "dep/src/lib.rs",
+            "pub fn first() {}\npub fn second() {}\npub fn third() {}\npub fn fourth() {}\npub fn fifth() {}\npub fn sixth() {}\n",
+        )
+        .file("test.patch", PATCH)
+        .build()
+}
+
+// the test support shows tabs as `<tab>`
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_porcelain() {
+    let p = dep_project("");
+    p.process(common::cargo_patch_exe())
+        .arg("--porcelain")
+        .with_stdout(
+            "patched<tab>dep<tab>src/lib.rs<tab>2\n\
+             patched<tab>dep<tab>NEW.txt<tab>1\n",
+        )
+        .with_stderr_contains("Patched dep: src/lib.rs")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_porcelain_backend() {
+    let p = dep_project("backend = \"gnu-patch\"");
+    p.process(common::cargo_patch_exe())
+        .arg("--porcelain=v1")
+        .with_stdout(
+            "patched<tab>dep<tab>src/lib.rs<tab>2\n\
+             patched<tab>dep<tab>NEW.txt<tab>1\n",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_porcelain_unknown_version() {
+    let p = dep_project("");
+    p.process(common::cargo_patch_exe())
+        .arg("--porcelain=v2")
+        .with_status(2)
+        .with_stderr_contains("[..]invalid value 'v2' for '--porcelain[..]")
+        .run();
+}
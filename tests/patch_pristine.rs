@@ -0,0 +1,64 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, paths, project, registry::Package};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_modified_pristine_source() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn bar() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_does_not_contain("[..]modified locally[..]")
+        .run();
+
+    // edit the extracted sources in the cargo cache
+    let index = fs::read_dir(paths::home().join(".cargo/registry/src"))
+        .expect("Unable to read registry sources")
+        .next()
+        .expect("Missing registry index")
+        .expect("Unable to read registry index")
+        .path();
+    let source = index.join("bar-0.1.0");
+    fs::write(source.join("src/extra.rs"), "").expect("Unable to add file");
+    fs::write(
+        source.join("src/lib.rs"),
+        "pub fn bar() {}\npub fn edit() {}\n",
+    )
+    .expect("Unable to edit file");
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "warning: The pristine source of bar v0.1.0 at [..]bar-0.1.0 was modified locally (src/lib.rs changed, src/extra.rs added), so the patched copy contains these edits. Delete it to have cargo extract it again",
+        )
+        .run();
+    p.process(common::cargo_patch_exe())
+        .arg("--strict")
+        .with_status(1)
+        .with_stderr_contains("Error: The pristine source of bar v0.1.0 [..]")
+        .run();
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_profile() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        profile = "debug-tracing"
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ TRACING.txt\n@@ -0,0 +1 @@\n+TRACING\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("No patches found\n")
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--profile")
+        .arg("debug-tracing")
+        .with_stdout("Patched serde: /dev/null -> TRACING.txt\n")
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .env("CARGO_PATCH_PROFILE", "debug-tracing")
+        .with_stdout("Patched serde: /dev/null -> TRACING.txt\n")
+        .run();
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package};
+use std::{
+    fs,
+    time::{Duration, UNIX_EPOCH},
+};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_pure() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    // only dependencies which are locked and downloaded already can be used
+    p.process(common::cargo_patch_exe())
+        .args(&["--pure", "out"])
+        .with_stderr_contains("Error: no matching package named `bar` found")
+        .with_status(1)
+        .run();
+    p.process(common::cargo_patch_exe()).run();
+    fs::remove_dir_all(p.build_dir().join("patch")).expect("Unable to remove copy");
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--pure", "out"])
+        .env("SOURCE_DATE_EPOCH", "1700000000")
+        .with_stdout("Patched bar: /dev/null -> PATCHED.txt\n")
+        .run();
+    assert!(!p.build_dir().join("patch").exists());
+    let file = p.root().join("out/bar-0.1.0/PATCHED.txt");
+    let metadata = fs::metadata(&file).expect("Missing patched file");
+    assert_eq!(
+        metadata.modified().expect("No mtime"),
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o644);
+    }
+    let provenance =
+        fs::read_to_string(p.root().join("out/bar-0.1.0/.cargo-patch.json"))
+            .expect("Missing provenance");
+    assert!(
+        provenance.contains(r#""patched-at": "2023-11-14T22:13:20Z""#),
+        "{provenance}"
+    );
+}
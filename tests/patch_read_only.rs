@@ -0,0 +1,74 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+use std::fs;
+
+fn gen_project(config: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch-config]
+        {config}
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#
+    );
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+    let lib = p.root().join("dep/src/lib.rs");
+    let mut permissions = fs::metadata(&lib).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&lib, permissions).unwrap();
+    p
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_read_only_normalized() {
+    let p = gen_project("");
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+    let lib = p.build_dir().join("patch/dep-0.5.0/src/lib.rs");
+    assert_eq!(fs::read_to_string(&lib).unwrap(), "pub fn patched() {}\n");
+    assert!(!fs::metadata(&lib).unwrap().permissions().readonly());
+    let manifest = p.build_dir().join("patch/dep-0.5.0/Cargo.toml");
+    assert!(!fs::metadata(manifest).unwrap().permissions().readonly());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_read_only_preserved() {
+    let p = gen_project("preserve-permissions = true");
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+    let lib = p.build_dir().join("patch/dep-0.5.0/src/lib.rs");
+    assert_eq!(fs::read_to_string(&lib).unwrap(), "pub fn patched() {}\n");
+    assert!(fs::metadata(&lib).unwrap().permissions().readonly());
+}
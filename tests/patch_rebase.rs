@@ -0,0 +1,247 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+const MANIFEST: &str = r#"
+    [package]
+    name = "example"
+    version = "0.1.0"
+    authors = ["wycats@example.com"]
+
+    [dependencies]
+    serde = "=1.0.110"
+
+    [package.metadata.patch.serde]
+    patches = [
+        "test.patch"
+    ]
+"#;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_rebase_moved_hunk() {
+    // the hunk is located at line 8 of the license
+    let patch = r#"--- LICENSE-MIT
++++ LICENSE-MIT
+@@ -2,9 +2,7 @@
+ is furnished to do so, subject to the following
+ conditions:
+ 
+-The above copyright notice and this permission notice
+-shall be included in all copies or substantial portions
+-of the Software.
++PATCHED
+ 
+ THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+ ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+"#;
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("rebase")
+        .arg("serde")
+        .with_stdout("Rebasing patches of serde v1.0.110\nUpdated test.patch\n")
+        .run();
+
+    let rebased = std::fs::read_to_string(p.root().join("test.patch"))
+        .expect("Unable to read");
+    assert!(rebased.contains("@@ -8,9 +8,7 @@"));
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: LICENSE-MIT\n")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_rebase_manual_attention() {
+    let patch = r#"--- LICENSE-MIT
++++ LICENSE-MIT
+@@ -1 +1 @@
+-This line does not exist
++PATCHED
+"#;
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("rebase")
+        .arg("serde")
+        .with_status(1)
+        .with_stdout(
+            "Rebasing patches of serde v1.0.110\n\
+             Hunk 1 of LICENSE-MIT in test.patch needs manual attention\n\
+             Unable to rebase test.patch\n",
+        )
+        .with_stderr("Error: 1 hunk(s) need manual attention\n")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_rebase_path_escape() {
+    let patch = r#"--- /dev/null
++++ ../../escaped.txt
+@@ -0,0 +1 @@
++ESCAPED
+"#;
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("rebase")
+        .arg("serde")
+        .with_status(1)
+        .with_stdout("Rebasing patches of serde v1.0.110\n")
+        .with_stderr(
+            "Error: Patch file tried to escape dependency folder (test.patch)\n",
+        )
+        .run();
+
+    assert!(!p.root().join("target/escaped.txt").exists());
+    assert!(!p.root().join("escaped.txt").exists());
+}
+
+/// Patch whose hunk is located 2 lines further down in `dep/src/lib.rs`.
+const MOVED: &str = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1,3 +1,3 @@\n pub fn one() {}\n-pub fn two() {}\n+pub fn patched() {}\n pub fn three() {}\n";
+
+fn moved_project(items: &str, patch: &str) -> cargo_test_support::Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep", "bundle"]
+
+        [workspace.metadata.patch-config]
+        dependency-metadata = ["bundle"]
+
+        [dependencies]
+        bundle = {{ path = "bundle" }}
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch.dep]
+        patches = [{items}]
+    "#
+    );
+    let bundle_manifest = r#"
+        [package]
+        name = "bundle"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [package.metadata.patch.dep]
+        patches = ["bundled.patch"]
+    "#;
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file(
+            "dep/src/lib.rs",
+            "// one\n// two\npub fn one() {}\npub fn two() {}\npub fn three() {}\n",
+        )
+        .file("bundle/Cargo.toml", bundle_manifest)
+        .file("bundle/src/lib.rs", "")
+        .file(
+            "bundle/bundled.patch",
+            "--- /dev/null\n+++ BUNDLED.txt\n@@ -0,0 +1 @@\n+BUNDLED\n",
+        )
+        .file("test.patch", patch)
+        .file("test.patch.minisig", "")
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_rebase_other_package() {
+    let p = moved_project("", MOVED);
+
+    p.process(common::cargo_patch_exe())
+        .arg("rebase")
+        .arg("dep")
+        .with_stdout_contains("Skipping patch [..]bundled.patch of bundle-0.1.0")
+        .run();
+    assert!(p.root().join("bundle/bundled.patch").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_rebase_signed() {
+    let p = moved_project(
+        r#"{ path = "test.patch", signature = "test.patch.minisig" }"#,
+        MOVED,
+    );
+
+    p.process(common::cargo_patch_exe())
+        .arg("rebase")
+        .arg("dep")
+        .with_stderr_contains(
+            "warning: test.patch applies with offsets, update and sign it again manually",
+        )
+        .run();
+    let patch = std::fs::read_to_string(p.root().join("test.patch"))
+        .expect("Unable to read patch");
+    assert_eq!(patch, MOVED);
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_rebase_surrounding_text() {
+    let patch = format!("Renames two.\n\n{MOVED}");
+    let p = moved_project(r#""test.patch""#, &patch);
+
+    p.process(common::cargo_patch_exe())
+        .arg("rebase")
+        .arg("dep")
+        .with_stdout_contains(
+            "test.patch applies with offsets, update it manually to keep the text around its diffs",
+        )
+        .run();
+    let rebased = std::fs::read_to_string(p.root().join("test.patch"))
+        .expect("Unable to read patch");
+    assert_eq!(rebased, patch);
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_rebase_encoding() {
+    let p = moved_project(r#"{ path = "test.patch", encoding = "latin1" }"#, "");
+    let patch = MOVED
+        .replace("patched() {}", "patched() {} // caf\u{e9}")
+        .chars()
+        .map(|char| u8::try_from(char).expect("Not latin1"))
+        .collect::<Vec<_>>();
+    std::fs::write(p.root().join("test.patch"), &patch)
+        .expect("Unable to write patch");
+
+    p.process(common::cargo_patch_exe())
+        .arg("rebase")
+        .arg("dep")
+        .with_stdout_contains(
+            "test.patch applies with offsets, update it manually to keep its encoding",
+        )
+        .run();
+    let rebased =
+        std::fs::read(p.root().join("test.patch")).expect("Unable to read patch");
+    assert_eq!(rebased, patch);
+}
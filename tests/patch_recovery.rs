@@ -0,0 +1,80 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{project, registry::Package, Project};
+use std::fs;
+
+const MANIFEST: &str = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = ["test.patch"]
+
+        [patch.crates-io]
+        bar = { path = "./target/patch/bar-0.1.0" }
+    "#;
+
+fn project_with_broken_patch() -> Project {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn missing() {}\n+pub fn bar() {}\n";
+    project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/main.rs", "fn main() { bar::bar(); }\n")
+        .file("test.patch", patch)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_recovery_guidance() {
+    let p = project_with_broken_patch();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "help: bar v0.1.0 failed to patch, but `[patch]` in Cargo.toml still points to its copy, so builds use the broken copy. Next steps:",
+        )
+        .with_stderr_contains("    1. Fix its patches, or run `cargo patch rebase bar` if it was updated, and run `cargo patch` again")
+        .run();
+    let manifest = fs::read_to_string(p.root().join("Cargo.toml"))
+        .expect("Unable to read manifest");
+    assert_eq!(manifest, MANIFEST);
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_recovery_disable_override() {
+    let p = project_with_broken_patch();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--disable-failed-overrides")
+        .with_status(1)
+        .with_stdout_contains(
+            "Disabled the override of bar in Cargo.toml, builds use the pristine crate",
+        )
+        .with_stderr_contains("help: bar v0.1.0 failed to patch. Next steps:")
+        .run();
+    let manifest = fs::read_to_string(p.root().join("Cargo.toml"))
+        .expect("Unable to read manifest");
+    assert!(manifest.contains(
+        "# Disabled by cargo-patch as patching failed: bar v0.1.0\n\
+         #         bar = { path = \"./target/patch/bar-0.1.0\" }\n"
+    ));
+
+    // the workspace resolves without the override
+    p.process(common::cargo_patch_exe())
+        .arg("paths")
+        .with_stdout_contains("bar 0.1.0 [..]")
+        .run();
+}
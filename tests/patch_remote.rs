@@ -0,0 +1,128 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{basic_manifest, main_file, project};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_remote_cache() {
+    let p = project()
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "remote.patch",
+            "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n",
+        )
+        .build();
+    let url = format!("file://{}", p.root().join("remote.patch").display());
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            {{ url = "{url}", cache = "forever" }}
+        ]
+    "#
+    );
+    fs::write(p.root().join("Cargo.toml"), manifest).expect("Unable to write");
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+
+    fs::write(
+        p.root().join("remote.patch"),
+        "--- /dev/null\n+++ UPDATED.txt\n@@ -0,0 +1 @@\n+UPDATED\n",
+    )
+    .expect("Unable to write");
+
+    // the cached patch is used until it is refreshed
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+    p.process(common::cargo_patch_exe())
+        .arg("--refresh-patches")
+        .with_stdout("Patched serde: /dev/null -> UPDATED.txt\n")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_remote_proxy_error() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [
+            { url = "https://example.com/fix.patch" }
+        ]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("dep/Cargo.toml", &basic_manifest("dep", "0.1.0"))
+        .file("dep/src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            "[http]\nproxy = \"http://127.0.0.1:1\"\n",
+        )
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains("help: unable to use the proxy http://127.0.0.1:1[..]")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_remote_too_large() {
+    let p = project()
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+    fs::write(p.root().join("remote.patch"), vec![b'+'; 17 * 1024 * 1024])
+        .expect("Unable to write");
+    let url = format!("file://{}", p.root().join("remote.patch").display());
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            {{ url = "{url}" }}
+        ]
+    "#
+    );
+    fs::write(p.root().join("Cargo.toml"), manifest).expect("Unable to write");
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Error: Unable to download patch file://[..]remote.patch: larger than 16 MiB",
+        )
+        .with_status(1)
+        .run();
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+fn gen_project() -> Project {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = r#"--- LICENSE-MIT	2020-05-20 18:44:09.709027472 +0200
++++ LICENSE-MIT	2020-05-20 18:58:46.253762666 +0200
+@@ -8,9 +8,7 @@
+ is furnished to do so, subject to the following
+ conditions:
+ 
+-The above copyright notice and this permission notice
+-shall be included in all copies or substantial portions
+-of the Software.
++PATCHED
+ 
+ THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+ ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+"#;
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_report_text() {
+    let p = gen_project();
+
+    p.process(common::cargo_patch_exe())
+        .arg("report")
+        .with_stdout("serde v1.0.110\n    LICENSE-MIT (+1 -3) from test.patch\n")
+        .run();
+
+    assert!(!p.build_dir().join("patch").exists());
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_report_markdown() {
+    let p = gen_project();
+
+    p.process(common::cargo_patch_exe())
+        .arg("patch")
+        .arg("report")
+        .arg("--format")
+        .arg("markdown")
+        .with_stdout(
+            "## serde v1.0.110
+
+| File | Added | Removed | Patch |
+| --- | ---: | ---: | --- |
+| `LICENSE-MIT` | 1 | 3 | `test.patch` |
+
+",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_report_all_changes() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [
+            "patches/fix.patch",
+            { replace-snippet = { file = "src/lib.rs", find = "fixed", replace = "done" } },
+        ]
+        sed = [{ glob = "src/*.rs", pattern = "a|b", replace = "c" }]
+    "#;
+    let fix = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn fixed() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("patches/fix.patch", fix)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("report")
+        .with_stdout(
+            "dep v0.5.0\n    \
+             src/lib.rs (+1 -1) from patches/fix.patch\n    \
+             src/*.rs from sed `a|b`\n    \
+             src/lib.rs from replace-snippet `fixed`\n",
+        )
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["report", "--format", "markdown"])
+        .with_stdout(
+            "## dep v0.5.0
+
+| File | Added | Removed | Patch |
+| --- | ---: | ---: | --- |
+| `src/lib.rs` | 1 | 1 | `patches/fix.patch` |
+| `src/*.rs` |  |  | sed `a\\|b` |
+| `src/lib.rs` |  |  | replace-snippet `fixed` |
+
+",
+        )
+        .run();
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+fn required_project(required: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace.metadata.patch-config]
+        required-version = "{required}"
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_required_version_too_old() {
+    let p = required_project(">=99");
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: This workspace requires cargo-patch >=99, but [..] is running. \
+             Upgrade with `cargo install cargo-patch`",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_required_version_satisfied() {
+    let p = required_project(concat!("=", env!("CARGO_PKG_VERSION")));
+
+    p.process(common::cargo_patch_exe()).run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_required_version_invalid() {
+    let p = required_project("latest");
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: Required version must be a semver requirement: \"latest\"",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_required_version_before_resolution() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        missing = { path = "missing" }
+
+        [workspace.metadata.patch-config]
+        required-version = ">=99"
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: This workspace requires cargo-patch >=99, but [..] is running. \
+             Upgrade with `cargo install cargo-patch`",
+        )
+        .run();
+}
@@ -0,0 +1,287 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    thread,
+};
+
+const MANIFEST: &str = r#"
+    [package]
+    name = "example"
+    version = "0.1.0"
+    authors = ["wycats@example.com"]
+
+    [dependencies]
+    serde = "=1.0.110"
+
+    [package.metadata.patch.serde]
+    patches = [
+        "../outside.patch"
+    ]
+"#;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_restrict_outside_workspace() {
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .build();
+    std::fs::write(p.root().join("../outside.patch"), "")
+        .expect("Unable to write patch");
+
+    // populate the package cache as restricted mode is offline
+    p.process(common::cargo_patch_exe())
+        .with_stderr("Error: Unable to parse patch file ../outside.patch\n")
+        .with_status(1)
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--restrict")
+        .with_stderr_contains(
+            "Error: Patch file [..]outside.patch is outside of the allowed patch roots",
+        )
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_restrict_allowed_roots() {
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [cargo-patch]
+                allowed-roots = ["patches"]
+            "#,
+        )
+        .file("patches/.keep", "")
+        .build();
+    std::fs::write(p.root().join("../outside.patch"), "")
+        .expect("Unable to write patch");
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Error: Patch file [..]outside.patch is outside of the allowed patch roots",
+        )
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_restrict_cached_remote() {
+    let p = project()
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "remote.patch",
+            "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n",
+        )
+        .build();
+    let url = format!("file://{}", p.root().join("remote.patch").display());
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+            [package]
+            name = "example"
+            version = "0.1.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+            serde = "=1.0.110"
+
+            [package.metadata.patch.serde]
+            patches = [
+                {{ url = "{url}", cache = "forever" }}
+            ]
+        "#
+        ),
+    );
+
+    // populates the patch cache
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--restrict")
+        .with_stderr_contains(
+            "Error: Remote patch file://[..]remote.patch is not allowed in restricted mode",
+        )
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_restrict_allowed_urls() {
+    let p = project()
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "patches/remote.patch",
+            "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n",
+        )
+        .build();
+    let url = format!("file://{}", p.root().join("patches/remote.patch").display());
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+            [package]
+            name = "example"
+            version = "0.1.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+            serde = "=1.0.110"
+
+            [package.metadata.patch.serde]
+            patches = [
+                {{ url = "{url}" }}
+            ]
+        "#
+        ),
+    );
+    let config = "[cargo-patch]\nallowed-roots = [\"patches\"]\n";
+    p.change_file(".cargo/config.toml", config);
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Error: Remote patch file://[..]remote.patch is outside of the allowed patch URLs",
+        )
+        .with_status(1)
+        .run();
+
+    // a sibling directory sharing the beginning of the name isn't allowed
+    let sibling = format!("file://{}", p.root().join("patch").display());
+    p.change_file(
+        ".cargo/config.toml",
+        &format!("{config}allowed-urls = [\"{sibling}\"]\n"),
+    );
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Error: Remote patch file://[..]remote.patch is outside of the allowed patch URLs",
+        )
+        .with_status(1)
+        .run();
+
+    let prefix = format!("file://{}", p.root().join("patches").display());
+    p.change_file(
+        ".cargo/config.toml",
+        &format!("{config}allowed-urls = [\"{prefix}\"]\n"),
+    );
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_restrict_redirect() {
+    // answers every request with a redirect to another host
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to listen");
+    let port = listener.local_addr().expect("Missing address").port();
+    let _ = thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(
+                b"HTTP/1.1 302 Found\r\nLocation: http://localhost:1/evil.patch\r\n\
+                  Content-Length: 0\r\nConnection: close\r\n\r\n",
+            );
+        }
+    });
+    let p = project()
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "example"
+                version = "0.1.0"
+                authors = ["wycats@example.com"]
+
+                [dependencies]
+                serde = "=1.0.110"
+
+                [package.metadata.patch.serde]
+                patches = [
+                    {{ url = "http://127.0.0.1:{port}/fix.patch" }}
+                ]
+            "#
+            ),
+        )
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                "[cargo-patch]\nallowed-roots = [\"patches\"]\n\
+                 allowed-urls = [\"http://127.0.0.1:{port}/\"]\n"
+            ),
+        )
+        .file("patches/.keep", "")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .env_remove("http_proxy")
+        .env_remove("HTTP_PROXY")
+        .with_stderr_contains(
+            "Error: Unable to download patch http://127.0.0.1:[..]/fix.patch: redirected to \
+             http://localhost:1/evil.patch, which is outside of the allowed patch URLs",
+        )
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_restrict_missing_outside() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["../missing/test.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [cargo-patch]
+                allowed-roots = ["patches"]
+            "#,
+        )
+        .file("patches/.keep", "")
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Error: Patch file [..]missing/test.patch is outside of the allowed patch roots",
+        )
+        .with_status(1)
+        .run();
+}
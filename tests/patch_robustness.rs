@@ -0,0 +1,123 @@
+use cargo_patch::apply_diff;
+use similar::TextDiff;
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+};
+
+/// Lines the property tests build files and diffs from. Few distinct lines
+/// make context lines ambiguous and hunks overlap.
+const LINES: &[&str] = &["a", "b", "", " ", "fn main() {}", "@@", "+", "-"];
+
+/// Inputs which once crashed the patch engine, see `fuzz/README.md`. Every
+/// `<name>.patch` is applied to `<name>.orig` or an empty file and has to
+/// fail with an error unless `<name>.expected` holds the result.
+#[test]
+fn patch_robustness_fuzz_regressions() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/regressions");
+    let read = |path: &Path| fs::read_to_string(path).unwrap_or_default();
+    let mut patches = fs::read_dir(&dir)
+        .expect("Unable to list regressions")
+        .map(|entry| entry.expect("Unable to read entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "patch"))
+        .collect::<Vec<_>>();
+    patches.sort();
+    assert!(!patches.is_empty());
+
+    for patch in patches {
+        let diff = read(&patch);
+        let original = read(&patch.with_extension("orig"));
+        let result = panic::catch_unwind(|| apply_diff(&diff, &original))
+            .unwrap_or_else(|_| panic!("{} panicked", patch.display()));
+        let expected = patch.with_extension("expected");
+        if expected.exists() {
+            assert_eq!(result.ok(), Some(read(&expected)), "{}", patch.display());
+        } else {
+            assert!(result.is_err(), "{} applied", patch.display());
+        }
+    }
+}
+
+fn random_text(rng: &mut fastrand::Rng) -> String {
+    let mut text = (0..rng.usize(0..8))
+        .map(|_| LINES[rng.usize(..LINES.len())])
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !text.is_empty() && rng.bool() {
+        text.push('\n');
+    }
+    text
+}
+
+fn random_number(rng: &mut fastrand::Rng) -> String {
+    match rng.u8(0..5) {
+        0 => u64::MAX.to_string(),
+        1 => "18446744073709551616".to_owned(),
+        _ => rng.u64(0..10).to_string(),
+    }
+}
+
+/// Builds a diff with random hunk ranges and lines which are mostly invalid.
+fn random_diff(rng: &mut fastrand::Rng) -> String {
+    let mut diff = String::from("--- f\n+++ f\n");
+    for _ in 0..rng.usize(0..4) {
+        let mut range = || {
+            let start = random_number(rng);
+            if rng.bool() {
+                format!("{start},{}", random_number(rng))
+            } else {
+                start
+            }
+        };
+        let (old, new) = (range(), range());
+        diff.push_str(&format!("@@ -{old} +{new} @@\n"));
+        for _ in 0..rng.usize(0..6) {
+            diff.push_str(["", " ", "+", "-", "\\ "][rng.usize(..5)]);
+            diff.push_str(LINES[rng.usize(..LINES.len())]);
+            if rng.u8(0..8) > 0 {
+                diff.push('\n');
+            }
+        }
+    }
+    diff
+}
+
+/// Malformed diffs never make the engine panic.
+#[test]
+fn patch_robustness_random_diffs() {
+    for seed in 0..20_000 {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let diff = random_diff(&mut rng);
+        let original = random_text(&mut rng);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let _ = apply_diff(&diff, &original);
+        }));
+        assert!(
+            result.is_ok(),
+            "seed {seed} panicked with {diff:?} on {original:?}"
+        );
+    }
+}
+
+/// Diffs between random files turn the old file into the new one.
+#[test]
+fn patch_robustness_round_trip() {
+    for seed in 0..5_000 {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let (old, new) = (random_text(&mut rng), random_text(&mut rng));
+        if old == new {
+            continue;
+        }
+        let diff = TextDiff::from_lines(&old, &new)
+            .unified_diff()
+            .context_radius(rng.usize(0..4))
+            .header("f", "f")
+            .to_string();
+        assert_eq!(
+            apply_diff(&diff, &old).ok().as_deref(),
+            Some(new.as_str()),
+            "seed {seed} failed with {diff:?} on {old:?}"
+        );
+    }
+}
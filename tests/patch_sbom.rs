@@ -0,0 +1,40 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_sbom_cyclonedx() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        version-suffix = true
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", "")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("sbom")
+        .with_stdout_contains(
+            r#"      "purl": "pkg:cargo/serde@1.0.110%2Bpatched","#,
+        )
+        .with_stdout_contains(r#"            "purl": "pkg:cargo/serde@1.0.110","#)
+        .with_stdout_contains(r#"              "url": "test.patch""#)
+        .run();
+}
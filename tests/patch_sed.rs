@@ -0,0 +1,137 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_sed() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+        sed = [
+            { glob = "src/**/*.rs", pattern = "old_api\\(", replace = "new_api(", count = 3 },
+            { glob = "src/*.rs", pattern = "fn (\\w+)_v1", replace = "fn ${1}_v2", when = "before" },
+        ]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1,2 +1,2 @@\n-pub fn run_v2() { old_api(); }\n+pub fn run_v2() { old_api(); old_api(); }\n pub mod util;\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file(
+            "dep/src/lib.rs",
+            "pub fn run_v1() { old_api(); }\npub mod util;\n",
+        )
+        .file("dep/src/util/mod.rs", "pub fn old_api() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Replaced 1 match(es) of `fn (\\w+)_v1` in 1 file(s) of dep\n\
+             Patched dep: src/lib.rs\n\
+             Replaced 3 match(es) of `old_api\\(` in 2 file(s) of dep\n",
+        )
+        .run();
+
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    assert_eq!(
+        fs::read_to_string(copy.join("src/lib.rs")).expect("Unable to read lib.rs"),
+        "pub fn run_v2() { new_api(); new_api(); }\npub mod util;\n"
+    );
+    assert_eq!(
+        fs::read_to_string(copy.join("src/util/mod.rs"))
+            .expect("Unable to read mod.rs"),
+        "pub fn new_api() {}\n"
+    );
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_sed_count_mismatch() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = []
+        sed = [{ glob = "src/**/*.rs", pattern = "dep", replace = "renamed", count = 2 }]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: Expected 2 match(es) of `dep` but found 1 (dep: src/**/*.rs)",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_sed_no_match() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = []
+        sed = [{ glob = "*.md", pattern = "dep", replace = "renamed" }]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains("Error: No match of `dep` found (dep: *.md)")
+        .run();
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::project;
+use std::path::Path;
+
+/// Runs the fixtures in `tests/corpus`. Add a triplet of `<name>.orig`,
+/// `<name>.patch` and `<name>.expected` there to cover a real-world diff.
+#[test]
+fn patch_selftest_corpus() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    cargo_patch::selftest(&corpus).expect("Corpus fixtures failed");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_selftest_failed() {
+    let patch = "--- a.txt\n+++ a.txt\n@@ -1,2 +1,2 @@\n one\n-two\n+TWO\n";
+    let p = project()
+        .file("fixtures/good.orig", "one\ntwo\n")
+        .file("fixtures/good.patch", patch)
+        .file("fixtures/good.expected", "one\nTWO\n")
+        .file("fixtures/nested/context.orig", "one\nzwei\n")
+        .file("fixtures/nested/context.patch", patch)
+        .file("fixtures/nested/context.expected", "one\nTWO\n")
+        .file("fixtures/result.orig", "one\ntwo\n")
+        .file("fixtures/result.patch", patch)
+        .file("fixtures/result.expected", "one\ntwo\n")
+        .file("fixtures/missing.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["selftest", "fixtures"])
+        .with_stdout(
+            "ok     good\n\
+             FAILED missing: Unable to read fixtures/missing.orig: No such file or directory (os error 2)\n\
+             FAILED nested/context: hunk 1 `@@ -1,2 +1,2 @@` failed on line 2\n\
+             FAILED result: Result differs from the expected file\n\
+             1 passed, 3 failed\n",
+        )
+        .with_stderr("Error: 3 fixture(s) failed\n")
+        .with_status(1)
+        .run();
+}
@@ -0,0 +1,162 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_sets() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["fix.patch"]
+
+        [workspace.metadata.patch-profiles.debug-dep.dep]
+        patches = ["debug.patch"]
+    "#;
+    let fix = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn fixed() {}\n";
+    let debug = "--- /dev/null\n+++ DEBUG.txt\n@@ -0,0 +1 @@\n+DEBUG\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("fix.patch", fix)
+        .file("debug.patch", debug)
+        .build();
+
+    // the patch file of the inactive set is not reported as unused
+    p.process(common::cargo_patch_exe())
+        .arg("--strict")
+        .with_stdout("Patched dep: src/lib.rs\n")
+        .run();
+    assert!(!p.build_dir().join("patch/dep-0.5.0/DEBUG.txt").exists());
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--set", "debug-dep"])
+        .with_stdout(
+            "Patched dep: src/lib.rs\n\
+             Patched dep: /dev/null -> DEBUG.txt\n",
+        )
+        .run();
+    assert!(p.build_dir().join("patch/dep-0.5.0/DEBUG.txt").exists());
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--set", "missing"])
+        .with_stderr_contains(
+            "Error: Patch set missing is not defined in any `patch-profiles` table",
+        )
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_sets_sed() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["fix.patch"]
+
+        [workspace.metadata.patch-profiles.debug-dep.dep]
+        sed = [{ glob = "src/*.rs", pattern = "fixed", replace = "debug" }]
+    "#;
+    let fix = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn fixed() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("fix.patch", fix)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--set", "debug-dep"])
+        .with_stdout(
+            "Patched dep: src/lib.rs\n\
+             Replaced 1 match(es) of `fixed` in 1 file(s) of dep\n",
+        )
+        .run();
+    assert_eq!(
+        std::fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/lib.rs"))
+            .expect("Unable to read lib.rs"),
+        "pub fn debug() {}\n"
+    );
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_sets_snippet() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["fix.patch"]
+
+        [workspace.metadata.patch-profiles.debug-dep.dep]
+        patches = [
+            { replace-snippet = { file = "src/lib.rs", find = "fixed", replace = "debug" } },
+        ]
+    "#;
+    let fix = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn fixed() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("fix.patch", fix)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .args(&["--set", "debug-dep"])
+        .with_stdout(
+            "Patched dep: src/lib.rs\n\
+             Replaced 1 occurrence(s) in dep: src/lib.rs\n",
+        )
+        .run();
+    assert_eq!(
+        std::fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/lib.rs"))
+            .expect("Unable to read lib.rs"),
+        "pub fn debug() {}\n"
+    );
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+const PATCH: &str =
+    "--- /dev/null\n+++ HEADER.txt\n@@ -0,0 +1 @@\n+Licensed under MIT\n";
+
+fn gen_project(entry: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep", "other"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+        other = {{ path = "other" }}
+
+        [package.metadata.patch.license-header]
+        {entry}
+        patches = ["header.patch"]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file(
+            "other/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("other"),
+        )
+        .file("other/src/lib.rs", "")
+        .file("header.patch", PATCH)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_shared_entry() {
+    let p = gen_project(r#"packages = ["dep", "other"]"#);
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: /dev/null -> HEADER.txt\n\
+             Patched other: /dev/null -> HEADER.txt\n",
+        )
+        .run();
+    for copy in ["dep-0.5.0", "other-0.5.0"] {
+        let header = std::fs::read_to_string(
+            p.build_dir().join("patch").join(copy).join("HEADER.txt"),
+        )
+        .expect("Unable to read patched file");
+        assert_eq!(header, "Licensed under MIT\n");
+    }
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_shared_entry_invalid() {
+    let p = gen_project(r#"packages = "dep""#);
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("No patches found\n")
+        .with_stderr_contains("Packages must be an array of strings: \"dep\"")
+        .run();
+}
@@ -0,0 +1,191 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package, Project};
+use std::{fs, path::Path, process::Command};
+
+const PUBLIC_KEY: &str = "RWTAMGGyLwb3hW2d9UQ9eF4weXnD0c2eljpmumRow009MFb2SjtL6Vsf";
+const PATCH: &str = "--- test\n+++ test\n@@ -1 +1 @@\n-old\n+new\n";
+const SIGNATURE: &str = "untrusted comment: signature from rsign secret key
+RUTAMGGyLwb3hTlrcUyTgMrDIlavLenJsuPAgcvSzKt2tgLAN7qa5NH4ptb78MwCzkyiNvciY00w6nlK9s66auuH04+uX29XIQ8=
+trusted comment: test.patch
+prV9XgVF/lEtYS7RCy7G9dZksL5K6oIDgzSSTw1oDMFYXdt1ZBicKvZGVdJsTLPfc1M3PwfUQ8ygsrWq6HvUDQ==
+";
+
+fn setup(config: &str, patches: &str) -> Project {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .file("test", "old\n")
+        .publish();
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch-config]
+        {config}
+
+        [package.metadata.patch.bar]
+        patches = [{patches}]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .file("test.patch.minisig", SIGNATURE)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_signature_minisign() {
+    let p = setup(
+        &format!(r#"trusted-keys = ["{PUBLIC_KEY}"]"#),
+        r#"{ path = "test.patch", signature = "test.patch.minisig" }"#,
+    );
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout_contains("Patched bar: test")
+        .run();
+    let patched = fs::read_to_string(p.build_dir().join("patch/bar-0.1.0/test"))
+        .expect("Unable to read patched file");
+    assert_eq!(patched, "new\n");
+
+    fs::write(p.root().join("test.patch"), PATCH.replace("+new", "+evil"))
+        .expect("Unable to write patch");
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Error: Signature test.patch.minisig is not valid for any trusted key",
+        )
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_signature_untrusted_key() {
+    let p = setup(
+        r#"trusted-keys = ["RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3"]"#,
+        r#"{ path = "test.patch", signature = "test.patch.minisig" }"#,
+    );
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains(
+            "Error: Signature test.patch.minisig is not valid for any trusted key",
+        )
+        .with_status(1)
+        .run();
+    let copy = fs::read_to_string(p.build_dir().join("patch/bar-0.1.0/test"))
+        .expect("Unable to read copied file");
+    assert_eq!(copy, "old\n");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_signature_required() {
+    let p = setup(
+        &format!(
+            "trusted-keys = [\"{PUBLIC_KEY}\"]\n        require-signatures = true"
+        ),
+        r#""test.patch""#,
+    );
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains("Error: Patch file test.patch is not signed")
+        .with_status(1)
+        .run();
+}
+
+/// Runs gpg with the keyring in `home`.
+fn gpg(home: &Path, args: &[&str]) -> String {
+    let output = Command::new("gpg")
+        .env("GNUPGHOME", home)
+        .args(["--batch", "--yes", "--passphrase", ""])
+        .args(args)
+        .output()
+        .expect("Unable to run gpg");
+    assert!(output.status.success(), "gpg {args:?} failed");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_signature_gpg() {
+    if Command::new("gpg").arg("--version").output().is_err() {
+        eprintln!("gpg is not installed, skipping");
+        return;
+    }
+    let p = setup(
+        "trusted-keys = [\"FINGERPRINT\"]",
+        r#"{ path = "test.patch", signature = "test.patch.sig" }"#,
+    );
+    let home = p.root().join("gnupg");
+    fs::create_dir(&home).expect("Unable to create gpg home");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&home, fs::Permissions::from_mode(0o700))
+            .expect("Unable to restrict gpg home");
+    }
+    let _ = gpg(
+        &home,
+        &[
+            "--quick-gen-key",
+            "Test <test@example.com>",
+            "ed25519",
+            "sign",
+            "never",
+        ],
+    );
+    let fingerprint = gpg(&home, &["--with-colons", "--list-keys"])
+        .lines()
+        .find_map(|line| line.strip_prefix("fpr:"))
+        .map(|line| line.trim_matches(':').to_owned())
+        .expect("Missing fingerprint");
+    let patch = p.root().join("test.patch");
+    let signature = p.root().join("test.patch.sig");
+    let _ = gpg(
+        &home,
+        &[
+            "--detach-sign",
+            "--output",
+            signature.to_str().unwrap(),
+            patch.to_str().unwrap(),
+        ],
+    );
+    let manifest = p.root().join("Cargo.toml");
+    let template = fs::read_to_string(&manifest).expect("Unable to read manifest");
+
+    fs::write(&manifest, template.replace("FINGERPRINT", &fingerprint))
+        .expect("Unable to write manifest");
+    p.process(common::cargo_patch_exe())
+        .env("GNUPGHOME", &home)
+        .with_stdout_contains("Patched bar: test")
+        .run();
+
+    fs::write(
+        &manifest,
+        template.replace("FINGERPRINT", "0123456789ABCDEF0123456789ABCDEF01234567"),
+    )
+    .expect("Unable to write manifest");
+    p.process(common::cargo_patch_exe())
+        .env("GNUPGHOME", &home)
+        .with_stderr_contains(
+            "Error: Signature test.patch.sig is not made by a trusted key",
+        )
+        .with_status(1)
+        .run();
+
+    let _ = Command::new("gpgconf")
+        .env("GNUPGHOME", &home)
+        .args(["--kill", "gpg-agent"])
+        .status();
+}
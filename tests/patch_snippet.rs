@@ -0,0 +1,80 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+fn snippet_project(items: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch.dep]
+        patches = [
+            "test.patch",
+            {items}
+        ]
+    "#
+    );
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1,2 @@\n pub const LIMIT: u32 = 16;\n+pub const OTHER: u32 = 16;\n";
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub const LIMIT: u32 = 16;\n")
+        .file("test.patch", patch)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_snippet() {
+    let p = snippet_project(
+        r#"{ replace-snippet = { file = "src/lib.rs", find = "LIMIT: u32 = 16", replace = "LIMIT: u32 = 64" } },
+            { replace-snippet = { file = "src/lib.rs", find = "(\\w+): u32", replace = "$1: u64", occurrences = 2, regex = true } },"#,
+    );
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: src/lib.rs\n\
+             Replaced 1 occurrence(s) in dep: src/lib.rs\n\
+             Replaced 2 occurrence(s) in dep: src/lib.rs\n",
+        )
+        .run();
+
+    let lib =
+        std::fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/lib.rs"))
+            .expect("Unable to read patched file");
+    assert_eq!(
+        lib,
+        "pub const LIMIT: u64 = 64;\npub const OTHER: u64 = 16;\n"
+    );
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_snippet_wrong_count() {
+    let p = snippet_project(
+        r#"{ replace-snippet = { file = "src/lib.rs", find = "= 16", replace = "= 64" } },"#,
+    );
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: Expected 1 occurrence(s) of the snippet `= 16` but found 2 (dep: src/lib.rs)",
+        )
+        .run();
+}
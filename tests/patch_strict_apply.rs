@@ -0,0 +1,83 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+// the hunk expects `a` at line 1, but two lines were added upstream
+const PATCH: &str = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1,3 +1,3 @@\n // a\n-// b\n+// patched\n // c\n";
+
+fn gen_project(config: &str) -> Project {
+    let manifest = format!(
+        r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = {{ path = "dep" }}
+
+        [package.metadata.patch-config]
+        {config}
+
+        [package.metadata.patch.dep]
+        backend = "gnu-patch"
+        patches = ["test.patch"]
+    "#
+    );
+    project()
+        .file("Cargo.toml", &manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "// x\n// y\n// a\n// b\n// c\n")
+        .file("test.patch", PATCH)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_strict_apply_offset_tolerated() {
+    let p = gen_project("");
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: test.patch using patch\n")
+        .run();
+    let lib =
+        std::fs::read_to_string(p.build_dir().join("patch/dep-0.5.0/src/lib.rs"))
+            .expect("Unable to read patched file");
+    assert_eq!(lib, "// x\n// y\n// a\n// patched\n// c\n");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_strict_apply_no_fuzz_tolerance() {
+    let p = gen_project("");
+
+    p.process(common::cargo_patch_exe())
+        .arg("--no-fuzz-tolerance")
+        .with_stderr_contains(
+            "Error: patch applied test.patch inexactly, regenerate it against the current sources:",
+        )
+        .with_stderr_contains("    Hunk #1 succeeded at 3 (offset 2 lines).")
+        .with_status(1)
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_strict_apply_config() {
+    let p = gen_project("strict-apply = true");
+
+    p.process(common::cargo_patch_exe())
+        .with_stderr_contains("Error: patch applied test.patch inexactly, [..]")
+        .with_status(1)
+        .run();
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_strip_dev_targets() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        strip-dev-targets = true
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let dep_manifest = r#"
+        [package]
+        name = "dep"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [[bench]]
+        name = "heavy"
+        harness = false
+
+        [[example]]
+        name = "demo"
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("dep/Cargo.toml", dep_manifest)
+        .file("dep/src/lib.rs", "")
+        .file("dep/benches/heavy.rs", "fn main() {}")
+        .file("dep/examples/demo.rs", "fn main() {}")
+        .file("dep/tests/it.rs", "")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: /dev/null -> PATCHED.txt\nStripped dev targets of dep\n",
+        )
+        .run();
+
+    let copy = p.build_dir().join("patch").join("dep-0.1.0");
+    let manifest = std::fs::read_to_string(copy.join("Cargo.toml"))
+        .expect("Unable to read manifest");
+    assert!(!manifest.contains("[[bench]]"));
+    assert!(!manifest.contains("[[example]]"));
+    assert!(manifest.contains("autotests = false"));
+    assert!(!copy.join("benches").exists());
+    assert!(!copy.join("examples").exists());
+    assert!(!copy.join("tests").exists());
+    assert!(copy.join("src/lib.rs").exists());
+}
@@ -0,0 +1,60 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package, Project};
+
+fn windows_only_dependency() -> Project {
+    Package::new("deep", "0.1.0")
+        .file("src/lib.rs", "pub fn deep() {}\n")
+        .publish();
+    Package::new("middle", "0.1.0")
+        .target_dep("deep", "0.1", "cfg(windows)")
+        .publish();
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        middle = "0.1.0"
+
+        [package.metadata.patch.deep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn deep() {}\n+pub fn patched() {}\n";
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_target_dependency() {
+    let p = windows_only_dependency();
+    p.process(common::cargo_patch_exe())
+        .arg("--target")
+        .arg("x86_64-pc-windows-msvc")
+        .with_stdout("Patched deep: src/lib.rs\n")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_target_skips_other_targets() {
+    let p = windows_only_dependency();
+    p.process(common::cargo_patch_exe())
+        .arg("--target")
+        .arg("x86_64-unknown-linux-gnu")
+        .with_stdout(
+            "Skipping deep v0.1.0, it isn't built for x86_64-unknown-linux-gnu\n\
+             No patches found\n",
+        )
+        .run();
+    assert!(!p.build_dir().join("patch/deep-0.1.0").exists());
+}
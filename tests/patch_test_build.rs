@@ -0,0 +1,77 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{project, registry::Package, Project};
+
+fn project_with_patch(patch: &str) -> Project {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() {}\n")
+        .publish();
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        bar = "0.1.0"
+
+        [package.metadata.patch.bar]
+        patches = ["test.patch"]
+
+        [patch.crates-io]
+        bar = { path = "./target/patch/bar-0.1.0" }
+    "#;
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", "fn main() { bar::bar(); }\n")
+        .file("test.patch", patch)
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_test_build_ok() {
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn bar() {}\n+pub fn bar() { println!(\"patched\"); }\n";
+    let p = project_with_patch(patch);
+
+    p.process(common::cargo_patch_exe())
+        .arg("--test-build")
+        .with_stdout_contains("Checked the patched packages and the workspace")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_test_build_broken_package() {
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn bar() {}\n+pub fn bar() { undefined(); }\n";
+    let p = project_with_patch(patch);
+
+    p.process(common::cargo_patch_exe())
+        .arg("--test-build")
+        .with_status(1)
+        .with_stderr_contains("[..]cannot find function `undefined`[..]")
+        .with_stderr_contains(
+            "Error: 1 patched package(s) fail to build:\n    bar v0.1.0 with test.patch",
+        )
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_test_build_broken_user() {
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn bar() {}\n+pub fn baz() {}\n";
+    let p = project_with_patch(patch);
+
+    p.process(common::cargo_patch_exe())
+        .arg("--test-build")
+        .with_status(1)
+        .with_stderr_contains("[..]cannot find function `bar`[..]")
+        .with_stderr_contains(
+            "Error: The workspace fails to build with the patched packages",
+        )
+        .run();
+}
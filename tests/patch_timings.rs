@@ -0,0 +1,122 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+use std::fs;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_timings() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--timings=json")
+        .with_stdout(
+            "Patched dep: src/lib.rs\n\
+             Timing report saved to target/cargo-patch-timings/cargo-patch-timing.json\n",
+        )
+        .run();
+    let report: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(
+            p.build_dir()
+                .join("cargo-patch-timings/cargo-patch-timing.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    for phase in ["resolution", "copy", "parse", "apply", "cleanup"] {
+        assert!(report["phases"][phase].is_f64(), "{report:#}");
+    }
+    let package = &report["packages"][0];
+    assert_eq!(package["name"], "dep");
+    assert_eq!(package["version"], "0.5.0");
+    assert!(package["copy-size"].as_u64().unwrap() > 0, "{report:#}");
+
+    p.process(common::cargo_patch_exe())
+        .arg("--timings")
+        .with_stdout_contains(
+            "Timing report saved to target/cargo-patch-timings/cargo-patch-timing.html",
+        )
+        .run();
+    let html = fs::read_to_string(
+        p.build_dir()
+            .join("cargo-patch-timings/cargo-patch-timing.html"),
+    )
+    .unwrap();
+    assert!(html.contains("<td>dep v0.5.0</td>"), "{html}");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_timings_failure() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn other() {}\n+pub fn patched() {}\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--timings=json")
+        .with_status(1)
+        .with_stdout_contains(
+            "Timing report saved to target/cargo-patch-timings/cargo-patch-timing.json",
+        )
+        .run();
+    let report: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(
+            p.build_dir()
+                .join("cargo-patch-timings/cargo-patch-timing.json"),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(report["phases"]["resolution"].is_f64(), "{report:#}");
+}
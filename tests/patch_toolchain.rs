@@ -0,0 +1,65 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, Project};
+
+#[allow(deprecated)]
+fn example() -> Project {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .file(
+            "nightly-cargo",
+            "#!/bin/sh\necho 'cargo 1.90.0-nightly (0000000 2025-01-01)'\n",
+        )
+        .build()
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_unstable_flags_on_stable() {
+    let p = example();
+
+    p.process(common::cargo_patch_exe())
+        .arg("-Zunstable-options")
+        .env_remove("CARGO")
+        .with_stderr_contains("[..]only accepted on the nightly channel[..]")
+        .with_status(1)
+        .run();
+}
+
+#[cfg(unix)]
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_unstable_flags_with_nightly_cargo() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let p = example();
+    let cargo = p.root().join("nightly-cargo");
+    std::fs::set_permissions(&cargo, std::fs::Permissions::from_mode(0o755))
+        .expect("Unable to make script executable");
+
+    p.process(common::cargo_patch_exe())
+        .arg("-Zunstable-options")
+        .env("CARGO", &cargo)
+        .with_stdout("Patched serde: /dev/null -> PATCHED.txt\n")
+        .run();
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::project;
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_transform_and_encoding() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = [
+            { path = "crlf.patch", transform = ["dos2unix"] },
+            { path = "latin1.patch", encoding = "latin1" },
+        ]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file(
+            "crlf.patch",
+            "--- src/lib.rs\r\n+++ src/lib.rs\r\n@@ -1 +1 @@\r\n-pub fn dep() {}\r\n+pub fn patched() {}\r\n",
+        )
+        .build();
+    std::fs::write(
+        p.root().join("latin1.patch"),
+        b"--- /dev/null\n+++ NAME.txt\n@@ -0,0 +1 @@\n+Jos\xe9\n",
+    )
+    .expect("Unable to write patch");
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout(
+            "Patched dep: src/lib.rs\n\
+             Patched dep: /dev/null -> NAME.txt\n",
+        )
+        .run();
+    let copy = p.build_dir().join("patch/dep-0.5.0");
+    let lib = std::fs::read_to_string(copy.join("src/lib.rs"))
+        .expect("Unable to read patched file");
+    assert_eq!(lib, "pub fn patched() {}\n");
+    let name = std::fs::read_to_string(copy.join("NAME.txt"))
+        .expect("Unable to read created file");
+    assert_eq!(name, "Jos\u{e9}\n");
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_invalid_utf8() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["latin1.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .build();
+    std::fs::write(
+        p.root().join("latin1.patch"),
+        b"--- /dev/null\n+++ NAME.txt\n@@ -0,0 +1 @@\n+Jos\xe9\n",
+    )
+    .expect("Unable to write patch");
+
+    p.process(common::cargo_patch_exe())
+        .with_status(1)
+        .with_stderr_contains(
+            "Error: Patch file latin1.patch is not valid UTF-8, set its `encoding`",
+        )
+        .run();
+}
@@ -0,0 +1,106 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project, registry::Package};
+
+const PATCH: &str = "--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn deep() {}\n+pub fn patched() {}\n";
+
+fn publish_deep() {
+    Package::new("deep", "0.1.0")
+        .file("src/lib.rs", "pub fn deep() {}\n")
+        .publish();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_transitive_dependency() {
+    publish_deep();
+    Package::new("middle", "0.1.0").dep("deep", "0.1").publish();
+    Package::new("top", "0.1.0").dep("middle", "0.1").publish();
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        top = "0.1.0"
+
+        [package.metadata.patch.deep]
+        patches = ["test.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched deep: src/lib.rs\n")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_foreign_target_dependency() {
+    publish_deep();
+    // only used on a target other than the host
+    let target = if cfg!(windows) { "unix" } else { "windows" };
+    Package::new("middle", "0.1.0")
+        .target_dep("deep", "0.1", &format!("cfg({target})"))
+        .publish();
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        middle = "0.1.0"
+
+        [package.metadata.patch.deep]
+        patches = ["test.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched deep: src/lib.rs\n")
+        .run();
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_dev_dependency() {
+    publish_deep();
+    Package::new("middle", "0.1.0").dep("deep", "0.1").publish();
+
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dev-dependencies]
+        middle = "0.1.0"
+
+        [package.metadata.patch.deep]
+        patches = ["test.patch"]
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", PATCH)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched deep: src/lib.rs\n")
+        .run();
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+const MANIFEST: &str = r#"
+    [package]
+    name = "example"
+    version = "0.1.0"
+    authors = ["wycats@example.com"]
+
+    [workspace]
+    exclude = ["dep"]
+
+    [dependencies]
+    dep = { path = "dep" }
+
+    [package.metadata.patch.dep]
+    patches = [
+        "patches/dep.patch"
+    ]
+
+    [package.metadata.patch.removed]
+    version = "1.0"
+    patches = [
+        "patches/removed.patch"
+    ]
+
+    [package.metadata.patch.release-only]
+    profile = "release"
+    patches = []
+"#;
+
+const PATCH: &str = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_unused() {
+    let p = project()
+        .file("Cargo.toml", MANIFEST)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file(
+            "dep/Cargo.toml",
+            &cargo_test_support::basic_lib_manifest("dep"),
+        )
+        .file("dep/src/lib.rs", "")
+        .file("patches/dep.patch", PATCH)
+        .file("patches/removed.patch", PATCH)
+        .file("patches/stale.patch", PATCH)
+        .file("patches/README.md", "")
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .with_stdout("Patched dep: /dev/null -> PATCHED.txt\n")
+        .with_stderr(
+            "Unable to find package removed in dependencies\n\
+             warning: patch entry for removed ^1.0 matches no dependency\n\
+             warning: patch file patches/stale.patch isn't used by any patch entry\n",
+        )
+        .run();
+
+    p.process(common::cargo_patch_exe())
+        .arg("--strict")
+        .with_stderr(
+            "Unable to find package removed in dependencies\n\
+             Error: Found unused patch configuration:\n    \
+             patch entry for removed ^1.0 matches no dependency\n    \
+             patch file patches/stale.patch isn't used by any patch entry\n",
+        )
+        .with_status(1)
+        .run();
+}
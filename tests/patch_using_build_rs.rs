@@ -1,3 +1,5 @@
+#![cfg(feature = "orchestrator")]
+
 mod common;
 
 use cargo_test_macro::cargo_test;
@@ -0,0 +1,57 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{main_file, project};
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_verify() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies]
+        serde = "=1.0.110"
+
+        [package.metadata.patch.serde]
+        patches = [
+            "test.patch"
+        ]
+    "#;
+    let patch = "--- /dev/null\n+++ PATCHED.txt\n@@ -0,0 +1 @@\n+PATCHED\n";
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("test.patch", patch)
+        .build();
+
+    p.process(common::cargo_patch_exe())
+        .arg("verify")
+        .with_stdout_contains("serde v1.0.110: [..]serde-1.0.110 is missing")
+        .with_status(1)
+        .run();
+
+    p.process(common::cargo_patch_exe()).run();
+    p.process(common::cargo_patch_exe())
+        .arg("verify")
+        .with_stdout_contains("All patched packages match their patches")
+        .run();
+
+    let copy = p.root().join("target/patch/serde-1.0.110");
+    std::fs::write(copy.join("PATCHED.txt"), "HACKED\n")
+        .expect("Unable to write file");
+    std::fs::write(copy.join("EXTRA.txt"), "EXTRA\n").expect("Unable to write file");
+    p.process(common::cargo_patch_exe())
+        .arg("verify")
+        .with_stdout_contains("serde v1.0.110: added EXTRA.txt")
+        .with_stdout_contains("serde v1.0.110: modified PATCHED.txt")
+        .with_stderr_contains(
+            "Error: 1 patched package(s) don't match their patches",
+        )
+        .with_status(1)
+        .run();
+}
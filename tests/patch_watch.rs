@@ -0,0 +1,96 @@
+#![cfg(feature = "orchestrator")]
+
+mod common;
+
+use cargo_test_macro::cargo_test;
+use cargo_test_support::{basic_lib_manifest, main_file, project};
+use std::{
+    fs,
+    io::{BufRead, BufReader},
+    process::Stdio,
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::Duration,
+};
+
+/// Waits until cargo-patch printed a line starting with `prefix`.
+fn wait_for(lines: &Receiver<String>, prefix: &str) {
+    loop {
+        let line = lines
+            .recv_timeout(Duration::from_secs(60))
+            .unwrap_or_else(|_| panic!("cargo-patch didn't print {prefix:?}"));
+        if line.starts_with(prefix) {
+            return;
+        }
+    }
+}
+
+#[allow(deprecated)]
+#[cargo_test]
+fn patch_watch() {
+    let manifest = r#"
+        [package]
+        name = "example"
+        version = "0.1.0"
+        authors = ["wycats@example.com"]
+
+        [workspace]
+        exclude = ["dep"]
+
+        [dependencies]
+        dep = { path = "dep" }
+
+        [package.metadata.patch.dep]
+        patches = ["test.patch"]
+    "#;
+    let patch = |name| {
+        format!("--- src/lib.rs\n+++ src/lib.rs\n@@ -1 +1 @@\n-pub fn dep() {{}}\n+pub fn {name}() {{}}\n")
+    };
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/main.rs", &main_file(r#""i am foo""#, &[]))
+        .file("dep/Cargo.toml", &basic_lib_manifest("dep"))
+        .file("dep/src/lib.rs", "pub fn dep() {}\n")
+        .file("test.patch", &patch("first"))
+        .build();
+    let lib = p.build_dir().join("patch/dep-0.5.0/src/lib.rs");
+
+    let mut child = p
+        .process(common::cargo_patch_exe())
+        .arg("--watch")
+        .build_command()
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Unable to start cargo-patch");
+    let stdout = BufReader::new(child.stdout.take().expect("Missing stdout"));
+    let (tx, lines) = channel();
+    let _ = thread::spawn(move || {
+        for line in stdout.lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    wait_for(&lines, "Watching");
+    assert_eq!(fs::read_to_string(&lib).unwrap(), "pub fn first() {}\n");
+
+    fs::write(p.root().join("test.patch"), patch("second"))
+        .expect("Unable to write");
+    wait_for(&lines, "Re-applying patches of dep");
+    wait_for(&lines, "Patched dep: src/lib.rs");
+    assert_eq!(fs::read_to_string(&lib).unwrap(), "pub fn second() {}\n");
+
+    // a changed configuration is read again
+    fs::write(
+        p.root().join("Cargo.toml"),
+        manifest.replace(r#"["test.patch"]"#, "[]"),
+    )
+    .expect("Unable to write");
+    wait_for(&lines, "Reloading the patch configuration");
+    wait_for(&lines, "Watching");
+    assert_eq!(fs::read_to_string(&lib).unwrap(), "pub fn dep() {}\n");
+
+    child.kill().expect("Unable to stop cargo-patch");
+    let _ = child.wait();
+}